@@ -0,0 +1,488 @@
+use crate::elf::{self, ELF};
+use crate::error::{get_bytes, Error, Result};
+use std::convert::TryInto;
+
+// Static libraries (.a) in the ar format used by the GNU and BSD tools. Members
+// are usually relocatable objects, each can be loaded as an ELF.
+
+const ARCHIVE_MAGIC: [u8; 8] = *b"!<arch>\n";
+const MEMBER_HEADER_SIZE: u64 = 60;
+const MEMBER_HEADER_END: [u8; 2] = *b"`\n";
+
+// Names of the special members
+const GNU_SYMBOL_TABLE: &str = "/";
+const GNU_SYMBOL_TABLE_64: &str = "/SYM64/";
+const GNU_NAME_TABLE: &str = "//";
+const BSD_SYMBOL_TABLE: &str = "__.SYMDEF";
+const BSD_SYMBOL_TABLE_SORTED: &str = "__.SYMDEF SORTED";
+const BSD_SYMBOL_TABLE_64: &str = "__.SYMDEF_64";
+const BSD_SYMBOL_TABLE_64_SORTED: &str = "__.SYMDEF_64 SORTED";
+const BSD_LONG_NAME_PREFIX: &str = "#1/";
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ArchiveKind {
+    GNU, // names end in '/', long names are kept in the "//" member
+    BSD, // long names are stored at the start of the member data
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Member {
+    pub name: String,
+    pub date: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub mode: u32,
+    pub header_offset: u64, // The symbol index refers to members by this
+    pub offset: u64,        // Start of the member data, after any BSD long name
+    pub size: u64,
+}
+
+// An entry in the symbol index, the ranlib table the linker uses to find which
+// member defines a symbol without reading them all
+#[derive(Debug, Eq, PartialEq)]
+pub struct ArchiveSymbol {
+    pub name: String,
+    pub member_offset: u64, // header_offset of the member that defines it
+}
+
+pub struct Archive {
+    pub kind: ArchiveKind,
+    pub members: Vec<Member>, // Without the symbol index and long name table
+    pub symbol_index: Vec<ArchiveSymbol>,
+    pub data: Vec<u8>,
+}
+
+impl Archive {
+    pub fn get_member_data(&self, member: &Member) -> &[u8] {
+        &self.data[member.offset as usize..(member.offset + member.size) as usize]
+    }
+
+    pub fn get_member_by_name(&self, name: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.name == name)
+    }
+
+    // The member that defines the symbol, according to the symbol index
+    pub fn get_member_for_symbol(&self, name: &str) -> Option<&Member> {
+        let symbol = self.symbol_index.iter().find(|s| s.name == name)?;
+        self.members
+            .iter()
+            .find(|m| m.header_offset == symbol.member_offset)
+    }
+
+    // The member data is copied so the ELF can own it
+    pub fn load_elf_member(&self, member: &Member) -> Result<ELF> {
+        elf::load_elf_from_vec(self.get_member_data(member).to_vec())
+    }
+
+    // Loads each ELF member in turn. Members that aren't ELF files (e.g. the
+    // metadata in an rlib) are skipped.
+    pub fn elf_members(&self) -> impl Iterator<Item = (&Member, Result<ELF>)> {
+        self.members
+            .iter()
+            .filter(move |m| elf::is_elf(self.get_member_data(m)))
+            .map(move |m| (m, self.load_elf_member(m)))
+    }
+}
+
+pub fn is_archive(binary: &[u8]) -> bool {
+    binary.get(0..8) == Some(&ARCHIVE_MAGIC)
+}
+
+// The header fields are space padded ASCII
+fn get_field(raw: &[u8]) -> &str {
+    std::str::from_utf8(raw).unwrap_or("").trim_end_matches(' ')
+}
+
+// Tools that write deterministic archives leave the date, ids and mode blank or
+// zero, anything unreadable is treated the same way
+fn parse_field(raw: &[u8], radix: u32) -> u64 {
+    u64::from_str_radix(get_field(raw), radix).unwrap_or(0)
+}
+
+fn parse_size(raw: &[u8], offset: u64, context: &str) -> Result<u64> {
+    let field = get_field(raw);
+    field
+        .parse::<u64>()
+        .map_err(|_| Error::malformed(offset, format!("{} {:?} isn't a number", context, field)))
+}
+
+fn get_null_terminated_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+// GNU long names are "/" followed by an offset into the "//" member, where each
+// name ends with "/\n"
+fn get_gnu_long_name(name: &str, name_table: Option<&[u8]>, offset: u64) -> Result<String> {
+    let index = name[1..]
+        .parse::<usize>()
+        .map_err(|_| Error::malformed(offset, format!("bad long member name {:?}", name)))?;
+    let table = name_table
+        .ok_or_else(|| Error::malformed(offset, format!("{:?} with no long name table", name)))?;
+    let rest = table.get(index..).ok_or_else(|| {
+        Error::malformed(
+            offset,
+            format!("{:?} is past the end of the name table", name),
+        )
+    })?;
+    let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let long_name = String::from_utf8_lossy(&rest[..end]);
+    Ok(long_name.trim_end_matches('/').to_string())
+}
+
+// Big endian count, that many member offsets, then the null terminated names
+fn parse_gnu_symbol_table(
+    data: &[u8],
+    offset: u64,
+    word_size: usize,
+) -> Result<Vec<ArchiveSymbol>> {
+    let read_word = |at: usize| -> Result<u64> {
+        let raw = get_bytes(data, at as u64, word_size as u64, || {
+            String::from("archive symbol table")
+        })
+        .map_err(|_| {
+            Error::truncated(offset + at as u64, word_size as u64, "archive symbol table")
+        })?;
+        Ok(match word_size {
+            4 => u64::from(u32::from_be_bytes(raw.try_into().unwrap())),
+            _ => u64::from_be_bytes(raw.try_into().unwrap()),
+        })
+    };
+    let count = read_word(0)?;
+    let names_start = count
+        .checked_add(1)
+        .and_then(|v| v.checked_mul(word_size as u64))
+        .filter(|&v| v <= data.len() as u64)
+        .ok_or_else(|| Error::invalid_value(offset, count, "archive symbol count"))?;
+
+    let mut symbols: Vec<ArchiveSymbol> = vec![];
+    let mut names = &data[names_start as usize..];
+    for i in 0..count as usize {
+        if names.is_empty() {
+            return Err(Error::truncated(
+                offset + data.len() as u64,
+                0,
+                "archive symbol names",
+            ));
+        }
+        let name = get_null_terminated_string(names);
+        names = names.get(name.len() + 1..).unwrap_or(&[]);
+        symbols.push(ArchiveSymbol {
+            name,
+            member_offset: read_word((i + 1) * word_size)?,
+        });
+    }
+    Ok(symbols)
+}
+
+// The ranlib table: the size in bytes of the (name offset, member offset) pairs,
+// the pairs, then the size of the string table and the strings. It is written in
+// the byte order of the target, so whichever order gives sizes that fit is used.
+fn parse_bsd_symbol_table(
+    data: &[u8],
+    offset: u64,
+    word_size: usize,
+) -> Result<Vec<ArchiveSymbol>> {
+    let read_word = |at: usize, big_endian: bool| -> Option<u64> {
+        let raw = data.get(at..at.checked_add(word_size)?)?;
+        Some(match (word_size, big_endian) {
+            (4, false) => u64::from(u32::from_le_bytes(raw.try_into().unwrap())),
+            (4, true) => u64::from(u32::from_be_bytes(raw.try_into().unwrap())),
+            (_, false) => u64::from_le_bytes(raw.try_into().unwrap()),
+            (_, true) => u64::from_be_bytes(raw.try_into().unwrap()),
+        })
+    };
+    let fits = |big_endian: bool| -> Option<(usize, usize)> {
+        let ranlib_size = read_word(0, big_endian)? as usize;
+        let strings_start = word_size.checked_add(ranlib_size)?.checked_add(word_size)?;
+        let strings_size = read_word(strings_start - word_size, big_endian)? as usize;
+        if strings_start.checked_add(strings_size)? > data.len() {
+            return None;
+        }
+        Some((ranlib_size, strings_start))
+    };
+    let (big_endian, (ranlib_size, strings_start)) = match fits(false) {
+        Some(v) => (false, v),
+        None => match fits(true) {
+            Some(v) => (true, v),
+            None => return Err(Error::malformed(offset, "archive ranlib table sizes")),
+        },
+    };
+
+    let strings = &data[strings_start..];
+    let mut symbols: Vec<ArchiveSymbol> = vec![];
+    for i in 0..ranlib_size / (word_size * 2) {
+        let entry = word_size + i * word_size * 2;
+        let name_offset = read_word(entry, big_endian).unwrap() as usize;
+        let member_offset = read_word(entry + word_size, big_endian).unwrap();
+        symbols.push(ArchiveSymbol {
+            name: get_null_terminated_string(strings.get(name_offset..).unwrap_or(&[])),
+            member_offset,
+        });
+    }
+    Ok(symbols)
+}
+
+pub fn load_archive_from_vec(data: Vec<u8>) -> Result<Archive> {
+    if !is_archive(&data) {
+        return Err(Error::BadMagic {
+            format: "ar archive",
+            magic: data.iter().take(8).cloned().collect(),
+        });
+    }
+
+    let mut members: Vec<Member> = vec![];
+    let mut symbol_index: Vec<ArchiveSymbol> = vec![];
+    let mut name_table: Option<&[u8]> = None;
+    let mut is_gnu = false;
+    let mut is_bsd = false;
+    let mut offset = ARCHIVE_MAGIC.len() as u64;
+    while offset < data.len() as u64 {
+        // Some tools pad the end of the archive with newlines
+        let rest = &data[offset as usize..];
+        if rest.len() < MEMBER_HEADER_SIZE as usize && rest.iter().all(|&b| b == b'\n') {
+            break;
+        }
+        let header = get_bytes(&data, offset, MEMBER_HEADER_SIZE, || {
+            String::from("archive member header")
+        })?;
+        if header[58..60] != MEMBER_HEADER_END {
+            return Err(Error::malformed(offset, "archive member header terminator"));
+        }
+        let size = parse_size(&header[48..58], offset + 48, "archive member size")?;
+        let data_offset = offset + MEMBER_HEADER_SIZE;
+        let member_data = get_bytes(&data, data_offset, size, || {
+            String::from("archive member data")
+        })?;
+
+        let raw_name = get_field(&header[0..16]);
+        let (mut name, name_size) =
+            if let Some(length) = raw_name.strip_prefix(BSD_LONG_NAME_PREFIX) {
+                is_bsd = true;
+                let length = parse_size(length.as_bytes(), offset, "archive member name length")?;
+                if length > size {
+                    return Err(Error::malformed(
+                        offset,
+                        "archive member name is longer than the member",
+                    ));
+                }
+                (
+                    get_null_terminated_string(&member_data[..length as usize]),
+                    length,
+                )
+            } else {
+                (raw_name.to_string(), 0)
+            };
+
+        match name.as_str() {
+            GNU_SYMBOL_TABLE => {
+                is_gnu = true;
+                symbol_index = parse_gnu_symbol_table(member_data, data_offset, 4)?;
+            }
+            GNU_SYMBOL_TABLE_64 => {
+                is_gnu = true;
+                symbol_index = parse_gnu_symbol_table(member_data, data_offset, 8)?;
+            }
+            GNU_NAME_TABLE => {
+                is_gnu = true;
+                name_table = Some(member_data);
+            }
+            BSD_SYMBOL_TABLE
+            | BSD_SYMBOL_TABLE_SORTED
+            | BSD_SYMBOL_TABLE_64
+            | BSD_SYMBOL_TABLE_64_SORTED => {
+                is_bsd = true;
+                let word_size = if name.starts_with(BSD_SYMBOL_TABLE_64) {
+                    8
+                } else {
+                    4
+                };
+                symbol_index = parse_bsd_symbol_table(
+                    &member_data[name_size as usize..],
+                    data_offset + name_size,
+                    word_size,
+                )?;
+            }
+            _ => {
+                if name.len() > 1 && name.starts_with('/') {
+                    name = get_gnu_long_name(&name, name_table, offset)?;
+                    is_gnu = true;
+                } else if name.len() > 1 && name.ends_with('/') {
+                    name.pop();
+                    is_gnu = true;
+                }
+                members.push(Member {
+                    name,
+                    date: parse_field(&header[16..28], 10),
+                    uid: parse_field(&header[28..34], 10) as u32,
+                    gid: parse_field(&header[34..40], 10) as u32,
+                    mode: parse_field(&header[40..48], 8) as u32,
+                    header_offset: offset,
+                    offset: data_offset + name_size,
+                    size: size - name_size,
+                });
+            }
+        }
+
+        // Members start on an even offset
+        offset = data_offset + size + (size & 1);
+    }
+
+    // Archives with only short names and no symbol index look the same in both
+    // formats, the names don't end in '/' so they are counted as BSD
+    let kind = if is_bsd || (!is_gnu && !members.is_empty()) {
+        ArchiveKind::BSD
+    } else {
+        ArchiveKind::GNU
+    };
+    log::trace!("{:?} archive with {} members", kind, members.len());
+
+    Ok(Archive {
+        kind,
+        members,
+        symbol_index,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+
+    fn get_member_header(name: &str, size: usize) -> Vec<u8> {
+        format!(
+            "{:16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            name, 1600000000, 1000, 1000, 100644, size
+        )
+        .into_bytes()
+    }
+
+    fn push_member(archive: &mut Vec<u8>, name: &str, data: &[u8]) {
+        archive.extend(get_member_header(name, data.len()));
+        archive.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            archive.push(b'\n');
+        }
+    }
+
+    fn get_gnu_archive() -> Vec<u8> {
+        let names = b"a_rather_long_member_name.o/\n";
+        let symbol_names = b"first\0second\0\0";
+        // Offsets of the member headers after the magic, symbol table and the
+        // padded name table
+        let first = 8 + 60 + 12 + symbol_names.len() as u32 + 60 + names.len() as u32 + 1;
+        let second = first + 60 + 4;
+        let mut symbols: Vec<u8> = vec![];
+        symbols.extend(&2u32.to_be_bytes());
+        symbols.extend(&first.to_be_bytes());
+        symbols.extend(&second.to_be_bytes());
+        symbols.extend(symbol_names);
+
+        let mut archive = ARCHIVE_MAGIC.to_vec();
+        push_member(&mut archive, "/", &symbols);
+        push_member(&mut archive, "//", names);
+        push_member(&mut archive, "short.o/", b"abcd");
+        push_member(&mut archive, "/0", b"efg");
+        archive
+    }
+
+    #[test]
+    fn can_load_gnu_archive() {
+        let archive = load_archive_from_vec(get_gnu_archive()).unwrap();
+        assert_eq!(archive.kind, ArchiveKind::GNU);
+        assert_eq!(archive.members.len(), 2);
+        let short = &archive.members[0];
+        assert_eq!(short.name, "short.o");
+        assert_eq!(short.date, 1600000000);
+        assert_eq!(short.mode, 0o100644);
+        assert_eq!(archive.get_member_data(short), b"abcd");
+        let long = archive
+            .get_member_by_name("a_rather_long_member_name.o")
+            .unwrap();
+        assert_eq!(archive.get_member_data(long), b"efg");
+
+        assert_eq!(archive.symbol_index.len(), 2);
+        assert_eq!(
+            archive.get_member_for_symbol("first").unwrap().name,
+            "short.o"
+        );
+        assert_eq!(archive.get_member_for_symbol("second"), Some(long));
+        assert_eq!(archive.get_member_for_symbol("third"), None);
+        assert_eq!(archive.elf_members().count(), 0);
+    }
+
+    #[test]
+    fn can_load_bsd_archive() {
+        let name = b"__.SYMDEF SORTED\0\0\0\0";
+        let member_name = b"long_object_name.o\0\0";
+        let first = 8 + 60 + name.len() as u32 + 20;
+        let mut symbols: Vec<u8> = name.to_vec();
+        symbols.extend(&8u32.to_le_bytes());
+        symbols.extend(&0u32.to_le_bytes());
+        symbols.extend(&first.to_le_bytes());
+        symbols.extend(&4u32.to_le_bytes());
+        symbols.extend(b"sym\0");
+
+        let mut member = member_name.to_vec();
+        member.extend(b"data");
+        let mut raw = ARCHIVE_MAGIC.to_vec();
+        push_member(&mut raw, &format!("#1/{}", name.len()), &symbols);
+        push_member(&mut raw, &format!("#1/{}", member_name.len()), &member);
+        push_member(&mut raw, "plain.o", b"xy");
+
+        let archive = load_archive_from_vec(raw).unwrap();
+        assert_eq!(archive.kind, ArchiveKind::BSD);
+        assert_eq!(archive.members.len(), 2);
+        let long = &archive.members[0];
+        assert_eq!(long.name, "long_object_name.o");
+        assert_eq!(archive.get_member_data(long), b"data");
+        assert_eq!(archive.members[1].name, "plain.o");
+        assert_eq!(
+            archive.symbol_index,
+            vec![ArchiveSymbol {
+                name: String::from("sym"),
+                member_offset: u64::from(first),
+            }]
+        );
+        assert_eq!(archive.get_member_for_symbol("sym"), Some(long));
+    }
+
+    #[test]
+    fn can_load_elf_members() {
+        // An x86_64 object file with only the ELF header
+        let elf_data = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let mut raw = ARCHIVE_MAGIC.to_vec();
+        push_member(&mut raw, "notes.txt/", b"not an object");
+        push_member(&mut raw, "test.o/", &elf_data);
+
+        let archive = load_archive_from_vec(raw).unwrap();
+        let members: Vec<_> = archive.elf_members().collect();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].0.name, "test.o");
+        let elf = members[0].1.as_ref().unwrap();
+        assert_eq!(elf.data, elf_data);
+        assert!(elf.is_relocatable());
+        assert!(elf.symbol_table.is_empty());
+    }
+
+    #[test]
+    fn can_reject_bad_archives() {
+        assert!(!is_archive(b"!<thin>\n"));
+        assert!(load_archive_from_vec(b"!<thin>\n".to_vec()).is_err());
+
+        let mut truncated = get_gnu_archive();
+        truncated.truncate(truncated.len() - 10);
+        assert!(load_archive_from_vec(truncated).is_err());
+
+        let mut bad_name = get_gnu_archive();
+        let long_name = bad_name.len() - 64;
+        bad_name[long_name + 1..long_name + 3].copy_from_slice(b"99");
+        assert!(load_archive_from_vec(bad_name).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use super::{from_elf, from_macho, Permissions, Segment};
+use crate::elf::elf_header::E_Type;
+use crate::elf::ELF;
+use crate::error::{Error, Result};
+use crate::macho::header::{FileType, Flags};
+use crate::macho::MACHO;
+
+// A mapped range of the address space. The first file_size bytes come from the
+// file, the rest up to vm_size are zero filled (e.g. .bss).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Region {
+    pub vm_address: u64,
+    pub vm_size: u64,
+    pub offset: u64,
+    pub file_size: u64,
+    pub permissions: Permissions,
+}
+
+impl Region {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.vm_address && address - self.vm_address < self.vm_size
+    }
+
+    fn contains_offset(&self, offset: u64) -> bool {
+        offset >= self.offset && offset - self.offset < self.file_size
+    }
+}
+
+// The file laid out the way the loader maps it, for translating between virtual
+// addresses and file offsets. Nothing is copied, reads come straight from the
+// file data.
+pub struct AddressSpace<'a> {
+    pub regions: Vec<Region>,
+    pub relocatable: bool, // PIE executables and shared libraries can be rebased
+    slide: u64,
+    data: &'a [u8],
+}
+
+impl<'a> AddressSpace<'a> {
+    pub fn new(data: &'a [u8], segments: &[Segment], relocatable: bool) -> AddressSpace<'a> {
+        let regions = segments
+            .iter()
+            // __PAGEZERO only reserves the bottom of the address space
+            .filter(|s| s.vm_size > 0 && !s.permissions.is_empty())
+            .filter(|s| s.vm_address.checked_add(s.vm_size).is_some())
+            .map(|s| Region {
+                vm_address: s.vm_address,
+                vm_size: s.vm_size,
+                offset: s.offset,
+                // Anything in the file past the end of the segment isn't mapped
+                file_size: std::cmp::min(s.file_size, s.vm_size),
+                permissions: s.permissions,
+            })
+            .collect();
+        AddressSpace {
+            regions,
+            relocatable,
+            slide: 0,
+            data,
+        }
+    }
+
+    pub fn from_elf(elf: &'a ELF) -> AddressSpace<'a> {
+        AddressSpace::new(
+            &elf.data,
+            &from_elf::get_elf_segments(elf),
+            elf.elf_header.e_type == E_Type::DYN || elf.is_relocatable(),
+        )
+    }
+
+    pub fn from_macho(macho: &'a MACHO) -> AddressSpace<'a> {
+        let relocatable = match macho.header.file_type {
+            FileType::Execute => macho.header.flags.contains(Flags::PIE),
+            FileType::DynamicLibrary | FileType::Bundle => true,
+            _ => false,
+        };
+        AddressSpace::new(
+            &macho.data,
+            &from_macho::get_segments(&macho.segments),
+            relocatable,
+        )
+    }
+
+    // The lowest mapped address
+    pub fn get_base_address(&self) -> u64 {
+        self.regions.iter().map(|r| r.vm_address).min().unwrap_or(0)
+    }
+
+    // How far the image has been moved from the addresses in the file, add it to
+    // an address from the file (e.g. a symbol) to get the address in this space
+    pub fn get_slide(&self) -> u64 {
+        self.slide
+    }
+
+    // Moves the image so it starts at base_address. Only addresses change, data
+    // that holds pointers isn't relocated.
+    pub fn rebase(&mut self, base_address: u64) -> Result<()> {
+        if !self.relocatable {
+            return Err(Error::unsupported(
+                0,
+                "rebasing an image that isn't position independent",
+            ));
+        }
+        let current_base = self.get_base_address();
+        let size = self
+            .regions
+            .iter()
+            .map(|r| r.vm_address - current_base + r.vm_size)
+            .max()
+            .unwrap_or(0);
+        if base_address.checked_add(size).is_none() {
+            return Err(Error::unsupported(
+                0,
+                format!(
+                    "rebasing to {:#x} wraps around the address space",
+                    base_address
+                ),
+            ));
+        }
+        let delta = base_address.wrapping_sub(current_base);
+        for region in self.regions.iter_mut() {
+            region.vm_address = region.vm_address.wrapping_add(delta);
+        }
+        self.slide = self.slide.wrapping_add(delta);
+        Ok(())
+    }
+
+    pub fn get_region(&self, address: u64) -> Option<&Region> {
+        self.regions.iter().find(|r| r.contains(address))
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        self.get_region(address).is_some()
+    }
+
+    pub fn get_permissions(&self, address: u64) -> Option<Permissions> {
+        self.get_region(address).map(|r| r.permissions)
+    }
+
+    pub fn is_readable(&self, address: u64) -> bool {
+        self.has_permissions(address, Permissions::READ)
+    }
+
+    pub fn is_writable(&self, address: u64) -> bool {
+        self.has_permissions(address, Permissions::WRITE)
+    }
+
+    pub fn is_executable(&self, address: u64) -> bool {
+        self.has_permissions(address, Permissions::EXECUTE)
+    }
+
+    fn has_permissions(&self, address: u64, permissions: Permissions) -> bool {
+        match self.get_permissions(address) {
+            Some(v) => v.contains(permissions),
+            None => false,
+        }
+    }
+
+    // None for addresses that are zero filled rather than backed by the file
+    pub fn offset_of(&self, address: u64) -> Option<u64> {
+        let region = self.get_region(address)?;
+        let delta = address - region.vm_address;
+        if delta < region.file_size {
+            region.offset.checked_add(delta)
+        } else {
+            None
+        }
+    }
+
+    pub fn vaddr_of(&self, offset: u64) -> Option<u64> {
+        let region = self.regions.iter().find(|r| r.contains_offset(offset))?;
+        Some(region.vm_address + (offset - region.offset))
+    }
+
+    // The file backed bytes from address to the end of its region, without copying
+    pub fn get_file_data(&self, address: u64) -> Option<&'a [u8]> {
+        let region = self.get_region(address)?;
+        let delta = address - region.vm_address;
+        if delta >= region.file_size {
+            return None;
+        }
+        let start = region.offset.checked_add(delta)? as usize;
+        let end = region.offset.saturating_add(region.file_size) as usize;
+        self.data.get(start..std::cmp::min(end, self.data.len()))
+    }
+
+    // Reads size bytes, which can span adjacent regions. Zero filled parts read as
+    // zeros. None if any of the range isn't mapped.
+    pub fn read(&self, address: u64, size: u64) -> Option<Vec<u8>> {
+        address.checked_add(size)?;
+        let mut result: Vec<u8> = Vec::with_capacity(std::cmp::min(size, 1 << 20) as usize);
+        let mut current = address;
+        let end = address + size;
+        while current < end {
+            let region = self.get_region(current)?;
+            let region_end = region.vm_address + region.vm_size;
+            let chunk_end = std::cmp::min(end, region_end);
+            let file_end = region.vm_address + region.file_size;
+            if current < file_end {
+                let file_data = self.get_file_data(current).unwrap_or(&[]);
+                let wanted = (std::cmp::min(chunk_end, file_end) - current) as usize;
+                let available = std::cmp::min(wanted, file_data.len());
+                result.extend_from_slice(&file_data[..available]);
+                // Segments that claim more file data than the file has
+                result.resize(result.len() + wanted - available, 0);
+                current += wanted as u64;
+            }
+            result.resize(result.len() + (chunk_end - current) as usize, 0);
+            current = chunk_end;
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod address_space_tests {
+    use super::*;
+
+    fn get_segment(
+        vm_address: u64,
+        vm_size: u64,
+        offset: u64,
+        file_size: u64,
+        permissions: Permissions,
+    ) -> Segment {
+        Segment {
+            name: String::new(),
+            vm_address,
+            vm_size,
+            offset,
+            file_size,
+            permissions,
+        }
+    }
+
+    #[test]
+    fn can_translate_addresses() {
+        let data: Vec<u8> = (0..=0xff).collect();
+        let segments = [
+            get_segment(0, 0x1000, 0, 0, Permissions::empty()),
+            get_segment(
+                0x1000,
+                0x80,
+                0,
+                0x80,
+                Permissions::READ | Permissions::EXECUTE,
+            ),
+            get_segment(
+                0x1080,
+                0x100,
+                0x80,
+                0x80,
+                Permissions::READ | Permissions::WRITE,
+            ),
+        ];
+        let space = AddressSpace::new(&data, &segments, true);
+
+        assert_eq!(space.regions.len(), 2);
+        assert!(!space.contains(0x10));
+        assert!(space.is_executable(0x1010));
+        assert!(!space.is_executable(0x1090));
+        assert!(space.is_writable(0x1170));
+        assert_eq!(space.offset_of(0x1090), Some(0x90));
+        assert_eq!(space.offset_of(0x1110), None);
+        assert_eq!(space.vaddr_of(0x90), Some(0x1090));
+        assert_eq!(space.vaddr_of(0x100), None);
+
+        assert_eq!(space.read(0x107e, 4), Some(vec![0x7e, 0x7f, 0x80, 0x81]));
+        assert_eq!(space.read(0x10fe, 4), Some(vec![0xfe, 0xff, 0, 0]));
+        assert_eq!(space.read(0x117e, 4), None);
+        assert_eq!(space.get_file_data(0x10fe), Some(&data[0xfe..]));
+    }
+
+    #[test]
+    fn can_rebase() {
+        let data = [0u8; 0x100];
+        let segments = [get_segment(0, 0x100, 0, 0x100, Permissions::READ)];
+        let mut space = AddressSpace::new(&data, &segments, true);
+
+        space.rebase(0x555555554000).unwrap();
+        assert_eq!(space.get_base_address(), 0x555555554000);
+        assert_eq!(space.get_slide(), 0x555555554000);
+        assert_eq!(space.vaddr_of(0x10), Some(0x555555554010));
+        assert!(space.rebase(u64::MAX).is_err());
+
+        let mut fixed = AddressSpace::new(&data, &segments, false);
+        assert!(fixed.rebase(0x1000).is_err());
+    }
+
+    #[test]
+    fn can_build_from_elf() {
+        use crate::elf::load_elf_from_vec;
+
+        // An x86_64 executable with no section headers, code at 0x4000b0 and data
+        // at 0x4010c0 followed by .bss
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x02, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_EXEC, x86_64, version 1
+            0xb0, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x02, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_load = |flags: u32, offset: u64, address: u64, size: u64, memory_size: u64| {
+            push_u32(&mut raw, 1);
+            push_u32(&mut raw, flags);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, address);
+            push_u64(&mut raw, address);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, memory_size);
+            push_u64(&mut raw, 0x1000);
+        };
+        push_load(5, 0, 0x40_0000, 0xc0, 0xc0);
+        push_load(6, 0xc0, 0x40_10c0, 0x10, 0x30);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0xc0, 0xcc);
+        raw.extend_from_slice(b"hello, world\0\0\0\0");
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        let space = AddressSpace::from_elf(&elf);
+        assert_eq!(space.regions.len(), 2);
+        assert!(space.is_executable(0x40_00b0));
+        assert!(!space.is_executable(0x40_10c0));
+        assert!(space.is_writable(0x40_10e0));
+        assert!(!space.contains(0x40_00c0));
+        assert!(!space.contains(0x40_10f0));
+        assert_eq!(space.offset_of(0x40_00b0), Some(0xb0));
+        assert_eq!(space.vaddr_of(0xc4), Some(0x40_10c4));
+        // .bss isn't in the file
+        assert_eq!(space.offset_of(0x40_10d0), None);
+        // endbr64 at the entry point
+        assert_eq!(space.read(0x40_00b0, 4), Some(vec![0xf3, 0x0f, 0x1e, 0xfa]));
+        // The end of the data runs into the zeroes of .bss
+        assert_eq!(
+            space.read(0x40_10c8, 0x10).unwrap(),
+            b"orld\0\0\0\0\0\0\0\0\0\0\0\0"
+        );
+    }
+}
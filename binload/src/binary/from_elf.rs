@@ -0,0 +1,379 @@
+use super::{Arch, Binary, BinaryType, Endian, Permissions, Section, SectionType, Segment};
+use super::{Symbol, SymbolScope, SymbolType};
+use crate::elf::elf_header::{EI_Class, EI_Data, E_Machine};
+use crate::elf::program_header::{ProgramHeader, ProgramHeaderType};
+use crate::elf::section::{SectionFlags, SectionType as ELFSectionType};
+use crate::elf::symbol::{
+    Bind, SectionIndex, Symbol as ELFSymbol, SymbolType as ELFSymbolType, Visibility,
+};
+use crate::elf::{relocatable, ELF};
+use std::collections::HashSet;
+
+// p_flags bits
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+const PF_R: u32 = 0x4;
+
+fn get_arch(machine: &E_Machine) -> Arch {
+    match machine {
+        E_Machine::X86 => Arch::X86,
+        E_Machine::X86_64 => Arch::X86_64,
+        E_Machine::ARM => Arch::ARM,
+        E_Machine::ARM_AARCH64 => Arch::AARCH64,
+        E_Machine::MIPS_R3000_BE | E_Machine::MIPS_R3000_LE => Arch::MIPS,
+        E_Machine::PowerPC => Arch::PPC,
+        E_Machine::PowerPC_64 => Arch::PPC64,
+        E_Machine::Sparc | E_Machine::SPARC_V9 => Arch::SPARC,
+        E_Machine::RiscV => Arch::RISCV,
+        _ => Arch::UNKNOWN,
+    }
+}
+
+fn get_segment_permissions(flags: u32) -> Permissions {
+    let mut permissions = Permissions::empty();
+    permissions.set(Permissions::READ, flags & PF_R != 0);
+    permissions.set(Permissions::WRITE, flags & PF_W != 0);
+    permissions.set(Permissions::EXECUTE, flags & PF_X != 0);
+    permissions
+}
+
+// Only the PT_LOAD segments are mapped
+fn get_segments(program_headers: &[ProgramHeader]) -> Vec<Segment> {
+    program_headers
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.header_type == ProgramHeaderType::Load)
+        .map(|(i, p)| Segment {
+            name: format!("segment {}", i),
+            vm_address: p.virtual_address,
+            vm_size: p.memory_size,
+            offset: p.offset,
+            file_size: p.file_size,
+            permissions: get_segment_permissions(p.flags),
+        })
+        .collect()
+}
+
+// Relocatable objects have no program headers, so each loaded section is treated
+// as a segment at the address it was given by relocatable::get_section_layout
+fn get_relocatable_segments(elf: &ELF) -> Vec<Segment> {
+    elf.section_headers
+        .iter()
+        .zip(elf.get_section_addresses())
+        .filter(|(_, address)| *address != 0)
+        .map(|(s, address)| Segment {
+            name: s.name_string.clone(),
+            vm_address: address,
+            vm_size: s.size,
+            offset: s.offset,
+            file_size: if s.section_type == ELFSectionType::NoBits {
+                0
+            } else {
+                s.size
+            },
+            permissions: get_section_permissions(&s.flags),
+        })
+        .collect()
+}
+
+pub(super) fn get_elf_segments(elf: &ELF) -> Vec<Segment> {
+    if elf.is_relocatable() {
+        get_relocatable_segments(elf)
+    } else {
+        get_segments(&elf.program_headers)
+    }
+}
+
+fn get_section_permissions(flags: &[SectionFlags]) -> Permissions {
+    let mut permissions = Permissions::empty();
+    for flag in flags {
+        match flag {
+            SectionFlags::Alloc => permissions |= Permissions::READ,
+            SectionFlags::Write => permissions |= Permissions::WRITE,
+            SectionFlags::ExecInstr => permissions |= Permissions::EXECUTE,
+            _ => {}
+        }
+    }
+    permissions
+}
+
+fn get_symbol_type(symbol_type: ELFSymbolType) -> SymbolType {
+    match symbol_type {
+        ELFSymbolType::Function | ELFSymbolType::GNUIFunct => SymbolType::FUNCTION,
+        ELFSymbolType::Object | ELFSymbolType::Common | ELFSymbolType::TLS => SymbolType::OBJECT,
+        ELFSymbolType::Section => SymbolType::SECTION,
+        ELFSymbolType::File => SymbolType::FILE,
+        _ => SymbolType::UNKNOWN,
+    }
+}
+
+fn get_symbol_scope(symbol: &ELFSymbol) -> SymbolScope {
+    if symbol.section_index == SectionIndex::Undefined {
+        return SymbolScope::IMPORT;
+    }
+    let is_global = matches!(symbol.bind, Bind::Global | Bind::Weak | Bind::GNUUnique);
+    let is_visible = matches!(
+        symbol.visibility,
+        Visibility::Default | Visibility::Protected
+    );
+    if is_global && is_visible {
+        SymbolScope::EXPORT
+    } else {
+        SymbolScope::LOCAL
+    }
+}
+
+impl Binary {
+    pub fn from_elf(elf: &ELF) -> Binary {
+        let header = &elf.elf_header;
+        let section_addresses = elf.get_section_addresses();
+
+        let sections = elf
+            .section_headers
+            .iter()
+            .zip(section_addresses.iter())
+            .filter(|(s, _)| s.section_type != ELFSectionType::Null)
+            .map(|(s, address)| {
+                let permissions = get_section_permissions(&s.flags);
+                let is_nobits = s.section_type == ELFSectionType::NoBits;
+                let section_type = if !permissions.contains(Permissions::READ) {
+                    SectionType::NONE
+                } else if permissions.contains(Permissions::EXECUTE) {
+                    SectionType::CODE
+                } else if is_nobits {
+                    SectionType::BSS
+                } else {
+                    SectionType::DATA
+                };
+                Section {
+                    name: s.name_string.clone(),
+                    section_type,
+                    vm_address: *address,
+                    size: s.size,
+                    offset: s.offset,
+                    permissions,
+                    bytes: if is_nobits {
+                        vec![]
+                    } else {
+                        s.get_data(&elf.data).to_vec()
+                    },
+                }
+            })
+            .collect();
+
+        // The dynamic symbols are usually repeated in .symtab, when it is there
+        let mut seen: HashSet<(&str, u64)> = HashSet::new();
+        let mut symbols: Vec<Symbol> = vec![];
+        for symbol in elf
+            .symbol_table
+            .iter()
+            .chain(elf.dynamic_symbol_table.iter())
+        {
+            // Symbols in relocatable objects are moved to where their section was put
+            let address = if elf.is_relocatable() {
+                relocatable::get_symbol_address(symbol, &section_addresses).unwrap_or(0)
+            } else {
+                symbol.address
+            };
+            if symbol.name_string.is_empty() || !seen.insert((symbol.name_string.as_str(), address))
+            {
+                continue;
+            }
+            symbols.push(Symbol {
+                symbol_type: get_symbol_type(symbol.symbol_type),
+                scope: get_symbol_scope(symbol),
+                name: symbol.name_string.clone(),
+                address,
+                size: symbol.size,
+            });
+        }
+
+        Binary {
+            binary_type: BinaryType::ELF,
+            arch: get_arch(&header.e_machine),
+            bits: match header.ident.ei_class {
+                EI_Class::ELF32 => 32,
+                EI_Class::ELF64 => 64,
+            },
+            endian: match header.ident.ei_data {
+                EI_Data::LittleEndian => Endian::LITTLE,
+                EI_Data::BigEndian => Endian::BIG,
+            },
+            entry_point: header.e_entry,
+            segments: get_elf_segments(elf),
+            sections,
+            symbols,
+        }
+    }
+}
+
+#[cfg(test)]
+mod binary_from_elf_tests {
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    fn push_symbol(raw: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64, size: u64) {
+        raw.extend_from_slice(&name.to_le_bytes());
+        raw.extend_from_slice(&[info, 0]);
+        raw.extend_from_slice(&shndx.to_le_bytes());
+        raw.extend_from_slice(&value.to_le_bytes());
+        raw.extend_from_slice(&size.to_le_bytes());
+    }
+
+    // A 64 bit x86_64 executable with read only, code and data PT_LOADs. puts is
+    // imported in both .dynsym and .symtab, main and counter are exported.
+    fn get_test_binary() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x02, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_EXEC, x86_64, version 1
+            0x20, 0x11, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x28, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x03, 0x00, 0x40, 0x00, 0x0a, 0x00, 0x09, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_load = |flags: u32, offset: u64, address: u64, size: u64, memory_size: u64| {
+            push_u32(&mut raw, 1);
+            push_u32(&mut raw, flags);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, address);
+            push_u64(&mut raw, address);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, memory_size);
+            push_u64(&mut raw, 0x1000);
+        };
+        push_load(4, 0, 0x40_0000, 0x120, 0x120);
+        push_load(5, 0x120, 0x40_1120, 0x10, 0x10);
+        push_load(6, 0x130, 0x40_2130, 0x10, 0x20);
+
+        // .dynsym and .dynstr
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 1, 0x12, 0, 0, 0);
+        raw.extend_from_slice(b"\0puts\0");
+        // .text and .data
+        raw.resize(0x120, 0);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0x130, 0xcc);
+        raw.extend_from_slice(b"hello, world\0\0\0\0");
+        // .comment
+        raw.extend_from_slice(b"GCC: (GNU) 13\0");
+        // .symtab and .strtab
+        raw.resize(0x150, 0);
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 1, 0x04, 0xfff1, 0, 0);
+        push_symbol(&mut raw, 8, 0x12, 3, 0x40_1120, 5);
+        push_symbol(&mut raw, 13, 0x11, 5, 0x40_2140, 4);
+        push_symbol(&mut raw, 21, 0x12, 0, 0, 0);
+        raw.extend_from_slice(b"\0test.c\0main\0counter\0puts\0");
+        // .shstrtab
+        raw.extend_from_slice(
+            b"\0.dynsym\0.dynstr\0.text\0.data\0.bss\0.comment\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x228, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 11, 2, 0x40_00e8, 0xe8, 0x30, 2, 1, 8, 24]);
+        push_section_header(&mut raw, [9, 3, 2, 0x40_0118, 0x118, 6, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [17, 1, 6, 0x40_1120, 0x120, 0x10, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [23, 1, 3, 0x40_2130, 0x130, 0x10, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [29, 8, 3, 0x40_2140, 0x140, 0x10, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [34, 1, 0x30, 0, 0x140, 14, 0, 0, 1, 1]);
+        push_section_header(&mut raw, [43, 2, 0, 0, 0x150, 0x78, 8, 3, 8, 24]);
+        push_section_header(&mut raw, [51, 3, 0, 0, 0x1c8, 0x1a, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [59, 3, 0, 0, 0x1e2, 0x45, 0, 0, 1, 0]);
+        raw
+    }
+
+    #[test]
+    fn can_convert_elf() {
+        let elf = load_elf_from_vec(get_test_binary()).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        let binary = Binary::from_elf(&elf);
+
+        assert_eq!(binary.binary_type, BinaryType::ELF);
+        assert_eq!(binary.arch, Arch::X86_64);
+        assert_eq!(binary.bits, 64);
+        assert_eq!(binary.endian, Endian::LITTLE);
+        assert_eq!(binary.entry_point, 0x40_1120);
+
+        let segments: Vec<(u64, u64, u64, Permissions)> = binary
+            .segments
+            .iter()
+            .map(|s| (s.vm_address, s.vm_size, s.file_size, s.permissions))
+            .collect();
+        assert_eq!(
+            segments,
+            vec![
+                (0x40_0000, 0x120, 0x120, Permissions::READ),
+                (
+                    0x40_1120,
+                    0x10,
+                    0x10,
+                    Permissions::READ | Permissions::EXECUTE
+                ),
+                (
+                    0x40_2130,
+                    0x20,
+                    0x10,
+                    Permissions::READ | Permissions::WRITE
+                ),
+            ]
+        );
+
+        // Section 0 is left out
+        assert_eq!(binary.sections.len(), 9);
+        let text = binary.get_section_by_name(".text").unwrap();
+        assert_eq!(text.section_type, SectionType::CODE);
+        assert_eq!(text.vm_address, 0x40_1120);
+        assert_eq!(text.bytes[..5], [0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        assert_eq!(
+            binary.get_section_for_address(0x40_1124).unwrap().name,
+            ".text"
+        );
+        let bss = binary.get_section_by_name(".bss").unwrap();
+        assert_eq!(bss.section_type, SectionType::BSS);
+        assert_eq!(bss.size, 0x10);
+        assert!(bss.bytes.is_empty());
+        let data = binary.get_section_by_name(".data").unwrap();
+        assert_eq!(data.section_type, SectionType::DATA);
+        assert_eq!(data.bytes, b"hello, world\0\0\0\0");
+        assert_eq!(
+            binary.get_section_by_name(".comment").unwrap().section_type,
+            SectionType::NONE
+        );
+
+        // puts is in both symbol tables but only listed once
+        let imports: Vec<&str> = binary.imports().map(|s| s.name.as_str()).collect();
+        assert_eq!(imports, vec!["puts"]);
+        let exports: Vec<&str> = binary.exports().map(|s| s.name.as_str()).collect();
+        assert_eq!(exports, vec!["main", "counter"]);
+        let main = binary.get_symbol_by_name("main").unwrap();
+        assert_eq!(main.symbol_type, SymbolType::FUNCTION);
+        assert_eq!(main.size, 5);
+        assert_eq!(
+            binary.get_symbol_for_address(0x40_1122).unwrap().name,
+            "main"
+        );
+        assert_eq!(
+            binary.get_symbol_by_name("counter").unwrap().symbol_type,
+            SymbolType::OBJECT
+        );
+        let file = binary.get_symbol_by_name("test.c").unwrap();
+        assert_eq!(file.symbol_type, SymbolType::FILE);
+        assert_eq!(file.scope, SymbolScope::LOCAL);
+    }
+}
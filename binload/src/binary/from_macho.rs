@@ -0,0 +1,159 @@
+use super::{Arch, Binary, BinaryType, Endian, Permissions, Section, SectionType, Segment};
+use super::{Symbol, SymbolScope, SymbolType};
+use crate::macho::header::{ArchSize, CpuType, Endian as MachOEndian};
+use crate::macho::load_command::{Section as MachOSection, Segment as MachOSegment, VmProtection};
+use crate::macho::MACHO;
+
+fn get_arch(cpu_type: &CpuType) -> Arch {
+    match cpu_type {
+        CpuType::X86 => Arch::X86,
+        CpuType::X86_64 => Arch::X86_64,
+        CpuType::ARM => Arch::ARM,
+        CpuType::ARM64 | CpuType::ARM64_32 => Arch::AARCH64,
+        CpuType::PowerPC => Arch::PPC,
+        CpuType::PowerPC64 => Arch::PPC64,
+        CpuType::Sparc => Arch::SPARC,
+        _ => Arch::UNKNOWN,
+    }
+}
+
+fn get_permissions(protection: VmProtection) -> Permissions {
+    let mut permissions = Permissions::empty();
+    permissions.set(Permissions::READ, protection.contains(VmProtection::READ));
+    permissions.set(Permissions::WRITE, protection.contains(VmProtection::WRITE));
+    permissions.set(
+        Permissions::EXECUTE,
+        protection.contains(VmProtection::EXECUTE),
+    );
+    permissions
+}
+
+pub(super) fn get_segments(segments: &[MachOSegment]) -> Vec<Segment> {
+    segments
+        .iter()
+        .map(|s| Segment {
+            name: s.name.clone(),
+            vm_address: s.vm_address,
+            vm_size: s.vm_size,
+            offset: s.file_offset,
+            file_size: s.file_size,
+            permissions: get_permissions(s.initial_protection),
+        })
+        .collect()
+}
+
+impl Binary {
+    pub fn from_macho(macho: &MACHO) -> Binary {
+        let header = &macho.header;
+
+        // Symbols refer to sections by their 1 based position across all segments
+        let macho_sections: Vec<(&MachOSection, Permissions)> = macho
+            .segments
+            .iter()
+            .flat_map(|segment| {
+                let permissions = get_permissions(segment.initial_protection);
+                segment.sections.iter().map(move |s| (s, permissions))
+            })
+            .collect();
+        let sections = macho_sections
+            .iter()
+            .map(|(s, permissions)| Section {
+                name: s.name.clone(),
+                section_type: if s.has_instructions() {
+                    SectionType::CODE
+                } else if s.is_zero_fill() {
+                    SectionType::BSS
+                } else {
+                    SectionType::DATA
+                },
+                vm_address: s.address,
+                size: s.size,
+                offset: u64::from(s.offset),
+                permissions: *permissions,
+                bytes: s.get_data(&macho.data).to_vec(),
+            })
+            .collect();
+
+        let symbols = macho
+            .symbols
+            .iter()
+            .filter(|s| !s.is_debug())
+            .map(|s| {
+                let section = match s.section_index {
+                    0 => None,
+                    i => macho_sections.get(i as usize - 1),
+                };
+                Symbol {
+                    symbol_type: match section {
+                        Some((section, _)) if section.has_instructions() => SymbolType::FUNCTION,
+                        Some(_) => SymbolType::OBJECT,
+                        None => SymbolType::UNKNOWN,
+                    },
+                    scope: if s.is_undefined() {
+                        SymbolScope::IMPORT
+                    } else if s.is_external() {
+                        SymbolScope::EXPORT
+                    } else {
+                        SymbolScope::LOCAL
+                    },
+                    name: s.name.clone(),
+                    address: s.value,
+                    size: 0,
+                }
+            })
+            .collect();
+
+        Binary {
+            binary_type: BinaryType::MACHO,
+            arch: get_arch(&header.cpu_type),
+            bits: match header.arch_size {
+                ArchSize::_32 => 32,
+                ArchSize::_64 => 64,
+            },
+            endian: match header.endian {
+                MachOEndian::LittleEndian => Endian::LITTLE,
+                MachOEndian::BigEndian => Endian::BIG,
+            },
+            entry_point: macho.entry_point.unwrap_or(0),
+            segments: get_segments(&macho.segments),
+            sections,
+            symbols,
+        }
+    }
+}
+
+#[cfg(test)]
+mod binary_from_macho_tests {
+    use super::*;
+    use crate::macho::load_command::macho_load_command_tests::get_test_binary;
+    use crate::macho::load_macho_from_slice;
+
+    #[test]
+    fn can_convert_macho() {
+        let macho = load_macho_from_slice(&get_test_binary()).unwrap();
+        let binary = Binary::from_macho(&macho);
+
+        assert_eq!(binary.binary_type, BinaryType::MACHO);
+        assert_eq!(binary.arch, Arch::X86_64);
+        assert_eq!(binary.bits, 64);
+        assert_eq!(binary.entry_point, 0x100000180);
+        assert_eq!(binary.segments.len(), 2);
+        assert_eq!(
+            binary
+                .get_segment_for_address(0x100000180)
+                .unwrap()
+                .permissions,
+            Permissions::READ | Permissions::EXECUTE
+        );
+
+        let text = binary.get_section_by_name("__text").unwrap();
+        assert_eq!(text.section_type, SectionType::CODE);
+        assert_eq!(text.bytes.len(), 0x10);
+
+        let main = binary.get_symbol_by_name("_main").unwrap();
+        assert_eq!(main.symbol_type, SymbolType::FUNCTION);
+        assert_eq!(main.scope, SymbolScope::EXPORT);
+        let imports: Vec<&str> = binary.imports().map(|s| s.name.as_str()).collect();
+        assert_eq!(imports, vec!["_puts"]);
+    }
+}
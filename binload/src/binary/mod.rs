@@ -1,28 +1,100 @@
-// TODO - remove allow dead code
-#![allow(dead_code)]
+// A format independent view of a binary, built from one of the format specific
+// loaders, so analysis code doesn't need to know which format it is looking at
+pub mod address_space;
+mod from_elf;
+mod from_macho;
+pub mod section;
+pub mod segment;
+pub mod symbol;
 
-mod section;
-mod symbol;
+pub use address_space::{AddressSpace, Region};
+pub use section::{Section, SectionType};
+pub use segment::{Permissions, Segment};
+pub use symbol::{Symbol, SymbolScope, SymbolType};
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Arch {
     UNKNOWN,
     X86,
     X86_64,
+    ARM,
+    AARCH64,
+    MIPS,
+    PPC,
+    PPC64,
+    SPARC,
+    RISCV,
 }
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum BinaryType {
     ELF,
+    MACHO,
     PE,
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Endian {
+    LITTLE,
+    BIG,
+}
+
 pub struct Binary {
-    pub filename: std::string::String,
     pub binary_type: BinaryType,
     pub arch: Arch,
     pub bits: u8,
+    pub endian: Endian,
     pub entry_point: u64,
-    pub sections: Vec<section::Section>,
-    pub symbols: Vec<symbol::Symbol>,
+    pub segments: Vec<Segment>,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Binary {
+    pub fn get_section_by_name(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    pub fn get_section_for_address(&self, address: u64) -> Option<&Section> {
+        self.sections.iter().find(|s| s.contains(address))
+    }
+
+    pub fn get_segment_for_address(&self, address: u64) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.contains(address))
+    }
+
+    pub fn get_symbol_by_name(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.name == name)
+    }
+
+    // The defined symbol starting at the address, or failing that the one whose
+    // extent covers it
+    pub fn get_symbol_for_address(&self, address: u64) -> Option<&Symbol> {
+        let defined = || {
+            self.symbols
+                .iter()
+                .filter(|s| s.scope != SymbolScope::IMPORT)
+        };
+        defined()
+            .find(|s| s.address == address)
+            .or_else(|| defined().find(|s| s.contains(address)))
+    }
+
+    pub fn imports(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.scope == SymbolScope::IMPORT)
+    }
+
+    pub fn exports(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.scope == SymbolScope::EXPORT)
+    }
+
+    pub fn locals(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .filter(|s| s.scope == SymbolScope::LOCAL)
+    }
 }
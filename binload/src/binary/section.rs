@@ -1,24 +1,25 @@
-// TODO - remove allow dead code
-#![allow(dead_code)]
+use super::segment::Permissions;
 
 pub struct Section {
-//    binary: Binary
     pub name: std::string::String,
     pub section_type: SectionType,
-    pub vm_address: u64,
+    pub vm_address: u64, // 0 for sections that aren't loaded
     pub size: u64,
-    pub bytes: Vec<u8>,
+    pub offset: u64,
+    pub permissions: Permissions,
+    pub bytes: Vec<u8>, // Empty for sections that take no space in the file
 }
 
-#[allow(dead_code)]
+impl Section {
+    pub fn contains(&self, address: u64) -> bool {
+        self.vm_address != 0 && address >= self.vm_address && address - self.vm_address < self.size
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum SectionType {
-    NONE,
+    NONE, // not loaded, e.g. debug information or string tables
     CODE,
     DATA,
-}
-
-impl SectionType {
-    pub fn contains(_address: u64) -> bool {
-        true
-    }
+    BSS, // zero filled in memory, nothing in the file
 }
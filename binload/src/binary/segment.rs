@@ -0,0 +1,25 @@
+use bitflags::bitflags;
+
+bitflags! {
+    pub struct Permissions: u8 {
+        const READ = 0x01;
+        const WRITE = 0x02;
+        const EXECUTE = 0x04;
+    }
+}
+
+// A range of the file mapped into memory
+pub struct Segment {
+    pub name: std::string::String,
+    pub vm_address: u64,
+    pub vm_size: u64,
+    pub offset: u64,
+    pub file_size: u64,
+    pub permissions: Permissions,
+}
+
+impl Segment {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.vm_address && address - self.vm_address < self.vm_size
+    }
+}
@@ -1,10 +1,29 @@
 pub struct Symbol {
     pub symbol_type: SymbolType,
+    pub scope: SymbolScope,
     pub name: std::string::String,
     pub address: u64,
+    pub size: u64, // 0 when the format doesn't record it
 }
 
+impl Symbol {
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.size
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum SymbolType {
     UNKNOWN,
     FUNCTION,
+    OBJECT,
+    SECTION,
+    FILE,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SymbolScope {
+    LOCAL,  // only visible inside the binary
+    EXPORT, // defined here and visible to other binaries
+    IMPORT, // defined in another binary
 }
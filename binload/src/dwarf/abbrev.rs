@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use super::constants::{DwarfAttribute, DwarfTag, DW_FORM_IMPLICIT_CONST};
+use super::reader::Reader;
+use crate::error::Result;
+
+pub struct AttributeSpecification {
+    pub name: DwarfAttribute,
+    pub form: u64,
+    pub implicit_const: i64, // Only used with DW_FORM_implicit_const
+}
+
+// An entry in .debug_abbrev describing the layout of DIEs with this code
+pub struct Abbreviation {
+    pub code: u64,
+    pub tag: DwarfTag,
+    pub has_children: bool,
+    pub attributes: Vec<AttributeSpecification>,
+}
+
+// Parses the abbreviation table starting at the reader's offset, up to the
+// terminating zero code
+pub fn parse_abbreviations(reader: &mut Reader) -> Result<HashMap<u64, Abbreviation>> {
+    let mut abbreviations: HashMap<u64, Abbreviation> = HashMap::new();
+    loop {
+        let code = reader.get_uleb128()?;
+        if code == 0 {
+            break;
+        }
+        let tag = DwarfTag::from_u64(reader.get_uleb128()?);
+        let has_children = reader.get_u8()? != 0;
+        let mut attributes: Vec<AttributeSpecification> = vec![];
+        loop {
+            let name = reader.get_uleb128()?;
+            let form = reader.get_uleb128()?;
+            if name == 0 && form == 0 {
+                break;
+            }
+            let implicit_const = if form == DW_FORM_IMPLICIT_CONST {
+                reader.get_sleb128()?
+            } else {
+                0
+            };
+            attributes.push(AttributeSpecification {
+                name: DwarfAttribute::from_u64(name),
+                form,
+                implicit_const,
+            });
+        }
+        abbreviations.insert(
+            code,
+            Abbreviation {
+                code,
+                tag,
+                has_children,
+                attributes,
+            },
+        );
+    }
+    Ok(abbreviations)
+}
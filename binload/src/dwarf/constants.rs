@@ -0,0 +1,220 @@
+use std::fmt;
+
+// DW_TAG_* values, only the tags we do something with are named
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DwarfTag {
+    ArrayType,         // 0x01
+    ClassType,         // 0x02
+    EnumerationType,   // 0x04
+    FormalParameter,   // 0x05
+    LexicalBlock,      // 0x0b
+    Member,            // 0x0d
+    PointerType,       // 0x0f
+    CompileUnit,       // 0x11
+    StructureType,     // 0x13
+    SubroutineType,    // 0x15
+    Typedef,           // 0x16
+    UnionType,         // 0x17
+    InlinedSubroutine, // 0x1d
+    BaseType,          // 0x24
+    ConstType,         // 0x26
+    Subprogram,        // 0x2e
+    Variable,          // 0x34
+    Namespace,         // 0x39
+    PartialUnit,       // 0x3c
+    TypeUnit,          // 0x41
+    SkeletonUnit,      // 0x4a
+    CallSite,          // 0x48
+    GNUCallSite,       // 0x4109
+    Other(u64),
+}
+impl DwarfTag {
+    pub fn from_u64(value: u64) -> DwarfTag {
+        match value {
+            0x01 => DwarfTag::ArrayType,
+            0x02 => DwarfTag::ClassType,
+            0x04 => DwarfTag::EnumerationType,
+            0x05 => DwarfTag::FormalParameter,
+            0x0b => DwarfTag::LexicalBlock,
+            0x0d => DwarfTag::Member,
+            0x0f => DwarfTag::PointerType,
+            0x11 => DwarfTag::CompileUnit,
+            0x13 => DwarfTag::StructureType,
+            0x15 => DwarfTag::SubroutineType,
+            0x16 => DwarfTag::Typedef,
+            0x17 => DwarfTag::UnionType,
+            0x1d => DwarfTag::InlinedSubroutine,
+            0x24 => DwarfTag::BaseType,
+            0x26 => DwarfTag::ConstType,
+            0x2e => DwarfTag::Subprogram,
+            0x34 => DwarfTag::Variable,
+            0x39 => DwarfTag::Namespace,
+            0x3c => DwarfTag::PartialUnit,
+            0x41 => DwarfTag::TypeUnit,
+            0x4a => DwarfTag::SkeletonUnit,
+            0x48 => DwarfTag::CallSite,
+            0x4109 => DwarfTag::GNUCallSite,
+            _ => DwarfTag::Other(value),
+        }
+    }
+}
+
+impl fmt::Display for DwarfTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DwarfTag::Other(v) => write!(f, "DW_TAG_{:#x}", v),
+            v => write!(f, "{:?}", v),
+        }
+    }
+}
+
+// DW_AT_* values, again only the ones we look at are named
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DwarfAttribute {
+    Sibling,         // 0x01
+    Name,            // 0x03
+    ByteSize,        // 0x0b
+    StmtList,        // 0x10
+    LowPc,           // 0x11
+    HighPc,          // 0x12
+    Language,        // 0x13
+    CompDir,         // 0x1b
+    Inline,          // 0x20
+    Producer,        // 0x25
+    AbstractOrigin,  // 0x31
+    DeclFile,        // 0x3a
+    DeclLine,        // 0x3b
+    Declaration,     // 0x3c
+    External,        // 0x3f
+    FrameBase,       // 0x40
+    Specification,   // 0x47
+    Type,            // 0x49
+    EntryPc,         // 0x52
+    Ranges,          // 0x55
+    CallFile,        // 0x58
+    CallLine,        // 0x59
+    LinkageName,     // 0x6e
+    StrOffsetsBase,  // 0x72
+    AddrBase,        // 0x73
+    RnglistsBase,    // 0x74
+    DwoName,         // 0x76
+    MIPSLinkageName, // 0x2007
+    GNUDwoName,      // 0x2130
+    GNUAddrBase,     // 0x2133
+    GNURangesBase,   // 0x2132
+    Other(u64),
+}
+impl DwarfAttribute {
+    pub fn from_u64(value: u64) -> DwarfAttribute {
+        match value {
+            0x01 => DwarfAttribute::Sibling,
+            0x03 => DwarfAttribute::Name,
+            0x0b => DwarfAttribute::ByteSize,
+            0x10 => DwarfAttribute::StmtList,
+            0x11 => DwarfAttribute::LowPc,
+            0x12 => DwarfAttribute::HighPc,
+            0x13 => DwarfAttribute::Language,
+            0x1b => DwarfAttribute::CompDir,
+            0x20 => DwarfAttribute::Inline,
+            0x25 => DwarfAttribute::Producer,
+            0x31 => DwarfAttribute::AbstractOrigin,
+            0x3a => DwarfAttribute::DeclFile,
+            0x3b => DwarfAttribute::DeclLine,
+            0x3c => DwarfAttribute::Declaration,
+            0x3f => DwarfAttribute::External,
+            0x40 => DwarfAttribute::FrameBase,
+            0x47 => DwarfAttribute::Specification,
+            0x49 => DwarfAttribute::Type,
+            0x52 => DwarfAttribute::EntryPc,
+            0x55 => DwarfAttribute::Ranges,
+            0x58 => DwarfAttribute::CallFile,
+            0x59 => DwarfAttribute::CallLine,
+            0x6e => DwarfAttribute::LinkageName,
+            0x72 => DwarfAttribute::StrOffsetsBase,
+            0x73 => DwarfAttribute::AddrBase,
+            0x74 => DwarfAttribute::RnglistsBase,
+            0x76 => DwarfAttribute::DwoName,
+            0x2007 => DwarfAttribute::MIPSLinkageName,
+            0x2130 => DwarfAttribute::GNUDwoName,
+            0x2132 => DwarfAttribute::GNURangesBase,
+            0x2133 => DwarfAttribute::GNUAddrBase,
+            _ => DwarfAttribute::Other(value),
+        }
+    }
+}
+
+impl fmt::Display for DwarfAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DwarfAttribute::Other(v) => write!(f, "DW_AT_{:#x}", v),
+            v => write!(f, "{:?}", v),
+        }
+    }
+}
+
+// DW_FORM_* values
+pub const DW_FORM_ADDR: u64 = 0x01;
+pub const DW_FORM_BLOCK2: u64 = 0x03;
+pub const DW_FORM_BLOCK4: u64 = 0x04;
+pub const DW_FORM_DATA2: u64 = 0x05;
+pub const DW_FORM_DATA4: u64 = 0x06;
+pub const DW_FORM_DATA8: u64 = 0x07;
+pub const DW_FORM_STRING: u64 = 0x08;
+pub const DW_FORM_BLOCK: u64 = 0x09;
+pub const DW_FORM_BLOCK1: u64 = 0x0a;
+pub const DW_FORM_DATA1: u64 = 0x0b;
+pub const DW_FORM_FLAG: u64 = 0x0c;
+pub const DW_FORM_SDATA: u64 = 0x0d;
+pub const DW_FORM_STRP: u64 = 0x0e;
+pub const DW_FORM_UDATA: u64 = 0x0f;
+pub const DW_FORM_REF_ADDR: u64 = 0x10;
+pub const DW_FORM_REF1: u64 = 0x11;
+pub const DW_FORM_REF2: u64 = 0x12;
+pub const DW_FORM_REF4: u64 = 0x13;
+pub const DW_FORM_REF8: u64 = 0x14;
+pub const DW_FORM_REF_UDATA: u64 = 0x15;
+pub const DW_FORM_INDIRECT: u64 = 0x16;
+pub const DW_FORM_SEC_OFFSET: u64 = 0x17;
+pub const DW_FORM_EXPRLOC: u64 = 0x18;
+pub const DW_FORM_FLAG_PRESENT: u64 = 0x19;
+pub const DW_FORM_STRX: u64 = 0x1a;
+pub const DW_FORM_ADDRX: u64 = 0x1b;
+pub const DW_FORM_REF_SUP4: u64 = 0x1c;
+pub const DW_FORM_STRP_SUP: u64 = 0x1d;
+pub const DW_FORM_DATA16: u64 = 0x1e;
+pub const DW_FORM_LINE_STRP: u64 = 0x1f;
+pub const DW_FORM_REF_SIG8: u64 = 0x20;
+pub const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+pub const DW_FORM_LOCLISTX: u64 = 0x22;
+pub const DW_FORM_RNGLISTX: u64 = 0x23;
+pub const DW_FORM_REF_SUP8: u64 = 0x24;
+pub const DW_FORM_STRX1: u64 = 0x25;
+pub const DW_FORM_STRX2: u64 = 0x26;
+pub const DW_FORM_STRX3: u64 = 0x27;
+pub const DW_FORM_STRX4: u64 = 0x28;
+pub const DW_FORM_ADDRX1: u64 = 0x29;
+pub const DW_FORM_ADDRX2: u64 = 0x2a;
+pub const DW_FORM_ADDRX3: u64 = 0x2b;
+pub const DW_FORM_ADDRX4: u64 = 0x2c;
+pub const DW_FORM_GNU_ADDR_INDEX: u64 = 0x1f01;
+pub const DW_FORM_GNU_STR_INDEX: u64 = 0x1f02;
+pub const DW_FORM_GNU_REF_ALT: u64 = 0x1f20;
+pub const DW_FORM_GNU_STRP_ALT: u64 = 0x1f21;
+
+// DW_UT_* unit types from the DWARF 5 unit header
+pub const DW_UT_COMPILE: u8 = 0x01;
+pub const DW_UT_TYPE: u8 = 0x02;
+pub const DW_UT_PARTIAL: u8 = 0x03;
+pub const DW_UT_SKELETON: u8 = 0x04;
+pub const DW_UT_SPLIT_COMPILE: u8 = 0x05;
+pub const DW_UT_SPLIT_TYPE: u8 = 0x06;
+
+// DW_RLE_* entry kinds in .debug_rnglists
+pub const DW_RLE_END_OF_LIST: u8 = 0x00;
+pub const DW_RLE_BASE_ADDRESSX: u8 = 0x01;
+pub const DW_RLE_STARTX_ENDX: u8 = 0x02;
+pub const DW_RLE_STARTX_LENGTH: u8 = 0x03;
+pub const DW_RLE_OFFSET_PAIR: u8 = 0x04;
+pub const DW_RLE_BASE_ADDRESS: u8 = 0x05;
+pub const DW_RLE_START_END: u8 = 0x06;
+pub const DW_RLE_START_LENGTH: u8 = 0x07;
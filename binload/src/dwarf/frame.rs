@@ -0,0 +1,892 @@
+// Call frame information from .eh_frame and .eh_frame_hdr. The format is the
+// DWARF .debug_frame one with GNU extensions for pointer encodings and
+// augmentation data.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::reader::Reader;
+use crate::elf::elf_header::{EI_Class, EI_Data};
+use crate::elf::program_header::{get_data_for_address, ProgramHeaderType};
+use crate::elf::section::get_section_by_name;
+use crate::elf::ELF;
+use crate::error::{Error, Result};
+
+// DW_EH_PE_* pointer encodings, the low nibble is the format and the high
+// nibble is what the value is relative to
+pub const DW_EH_PE_ABSPTR: u8 = 0x00;
+pub const DW_EH_PE_ULEB128: u8 = 0x01;
+pub const DW_EH_PE_UDATA2: u8 = 0x02;
+pub const DW_EH_PE_UDATA4: u8 = 0x03;
+pub const DW_EH_PE_UDATA8: u8 = 0x04;
+pub const DW_EH_PE_SLEB128: u8 = 0x09;
+pub const DW_EH_PE_SDATA2: u8 = 0x0a;
+pub const DW_EH_PE_SDATA4: u8 = 0x0b;
+pub const DW_EH_PE_SDATA8: u8 = 0x0c;
+pub const DW_EH_PE_PCREL: u8 = 0x10;
+pub const DW_EH_PE_TEXTREL: u8 = 0x20;
+pub const DW_EH_PE_DATAREL: u8 = 0x30;
+pub const DW_EH_PE_FUNCREL: u8 = 0x40;
+pub const DW_EH_PE_ALIGNED: u8 = 0x50;
+pub const DW_EH_PE_INDIRECT: u8 = 0x80;
+pub const DW_EH_PE_OMIT: u8 = 0xff;
+
+// DW_CFA_* call frame instructions
+const DW_CFA_ADVANCE_LOC: u8 = 0x40; // High two bits, delta in the low six
+const DW_CFA_OFFSET: u8 = 0x80; // High two bits, register in the low six
+const DW_CFA_RESTORE: u8 = 0xc0; // High two bits, register in the low six
+const DW_CFA_NOP: u8 = 0x00;
+const DW_CFA_SET_LOC: u8 = 0x01;
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+const DW_CFA_OFFSET_EXTENDED: u8 = 0x05;
+const DW_CFA_RESTORE_EXTENDED: u8 = 0x06;
+const DW_CFA_UNDEFINED: u8 = 0x07;
+const DW_CFA_SAME_VALUE: u8 = 0x08;
+const DW_CFA_REGISTER: u8 = 0x09;
+const DW_CFA_REMEMBER_STATE: u8 = 0x0a;
+const DW_CFA_RESTORE_STATE: u8 = 0x0b;
+const DW_CFA_DEF_CFA: u8 = 0x0c;
+const DW_CFA_DEF_CFA_REGISTER: u8 = 0x0d;
+const DW_CFA_DEF_CFA_OFFSET: u8 = 0x0e;
+const DW_CFA_DEF_CFA_EXPRESSION: u8 = 0x0f;
+const DW_CFA_EXPRESSION: u8 = 0x10;
+const DW_CFA_OFFSET_EXTENDED_SF: u8 = 0x11;
+const DW_CFA_DEF_CFA_SF: u8 = 0x12;
+const DW_CFA_DEF_CFA_OFFSET_SF: u8 = 0x13;
+const DW_CFA_VAL_OFFSET: u8 = 0x14;
+const DW_CFA_VAL_OFFSET_SF: u8 = 0x15;
+const DW_CFA_VAL_EXPRESSION: u8 = 0x16;
+const DW_CFA_GNU_WINDOW_SAVE: u8 = 0x2d; // DW_CFA_AARCH64_negate_ra_state on AArch64
+const DW_CFA_GNU_ARGS_SIZE: u8 = 0x2e;
+const DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED: u8 = 0x2f;
+
+// Bases that DW_EH_PE_* relative pointers are resolved against
+#[derive(Clone, Copy, Default)]
+struct PointerBases {
+    section_address: u64, // Address of the start of the data being read, for pcrel
+    data_address: u64,    // For datarel, only used by .eh_frame_hdr
+    function_address: u64,
+}
+
+// Reads a pointer in one of the DW_EH_PE_* encodings
+fn get_encoded_pointer(
+    reader: &mut Reader,
+    encoding: u8,
+    address_size: u8,
+    bases: &PointerBases,
+) -> Result<u64> {
+    if encoding == DW_EH_PE_OMIT {
+        return Ok(0);
+    }
+    let position = bases.section_address.wrapping_add(reader.offset as u64);
+    let value = match encoding & 0x0f {
+        DW_EH_PE_ABSPTR => reader.get_sized(address_size)?,
+        DW_EH_PE_ULEB128 => reader.get_uleb128()?,
+        DW_EH_PE_UDATA2 => u64::from(reader.get_u16()?),
+        DW_EH_PE_UDATA4 => u64::from(reader.get_u32()?),
+        DW_EH_PE_UDATA8 => reader.get_u64()?,
+        DW_EH_PE_SLEB128 => reader.get_sleb128()? as u64,
+        DW_EH_PE_SDATA2 => reader.get_u16()? as i16 as i64 as u64,
+        DW_EH_PE_SDATA4 => reader.get_u32()? as i32 as i64 as u64,
+        DW_EH_PE_SDATA8 => reader.get_u64()?,
+        _ => {
+            return Err(Error::invalid_value(
+                reader.offset as u64,
+                u64::from(encoding),
+                "DW_EH_PE pointer format",
+            ))
+        }
+    };
+    // Indirect pointers point at the real value, which we don't follow as it
+    // needs the loaded image. They are only used for personality routines.
+    match encoding & 0x70 {
+        DW_EH_PE_ABSPTR => Ok(value),
+        DW_EH_PE_PCREL => Ok(position.wrapping_add(value)),
+        DW_EH_PE_DATAREL => Ok(bases.data_address.wrapping_add(value)),
+        DW_EH_PE_FUNCREL => Ok(bases.function_address.wrapping_add(value)),
+        DW_EH_PE_TEXTREL | DW_EH_PE_ALIGNED => Err(Error::unsupported(
+            reader.offset as u64,
+            "DW_EH_PE_textrel and DW_EH_PE_aligned pointers",
+        )),
+        _ => Err(Error::invalid_value(
+            reader.offset as u64,
+            u64::from(encoding),
+            "DW_EH_PE pointer application",
+        )),
+    }
+}
+
+// CIE, the information shared between a number of FDEs
+pub struct CommonInformationEntry {
+    pub offset: u64, // Offset of the entry in .eh_frame
+    pub version: u8,
+    pub augmentation: String,
+    pub code_alignment_factor: u64,
+    pub data_alignment_factor: i64,
+    pub return_address_register: u64,
+    pub fde_pointer_encoding: u8,
+    pub lsda_encoding: u8,
+    pub personality: Option<u64>,
+    pub is_signal_frame: bool,
+    pub initial_instructions: Vec<u8>,
+}
+
+// FDE, the unwind information for a single function
+pub struct FrameDescriptionEntry {
+    pub offset: u64,     // Offset of the entry in .eh_frame
+    pub cie_offset: u64, // Offset of the CIE this FDE uses
+    pub initial_location: u64,
+    pub address_range: u64,
+    pub lsda: Option<u64>, // Language specific data area, e.g. C++ exception tables
+    pub instructions: Vec<u8>,
+}
+
+impl FrameDescriptionEntry {
+    pub fn get_end_address(&self) -> u64 {
+        self.initial_location.wrapping_add(self.address_range)
+    }
+
+    pub fn contains_address(&self, address: u64) -> bool {
+        address >= self.initial_location && address < self.get_end_address()
+    }
+}
+
+impl fmt::Display for FrameDescriptionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "FDE {:#x} cie={:#x} pc={:#x}..{:#x}",
+            self.offset,
+            self.cie_offset,
+            self.initial_location,
+            self.get_end_address()
+        )
+    }
+}
+
+// How the canonical frame address is computed
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum CfaRule {
+    RegisterOffset(u64, i64), // register + offset
+    Expression(Vec<u8>),      // DWARF expression evaluating to the CFA
+}
+
+impl fmt::Display for CfaRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CfaRule::RegisterOffset(register, offset) => write!(f, "r{}{:+}", register, offset),
+            CfaRule::Expression(_) => write!(f, "exp"),
+        }
+    }
+}
+
+// How the caller's value of a register is recovered
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum RegisterRule {
+    Undefined,
+    SameValue,
+    Offset(i64),    // Saved at CFA + offset
+    ValOffset(i64), // The value is CFA + offset
+    Register(u64),  // Saved in another register
+    Expression(Vec<u8>),
+    ValExpression(Vec<u8>),
+}
+
+impl fmt::Display for RegisterRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegisterRule::Undefined => write!(f, "u"),
+            RegisterRule::SameValue => write!(f, "s"),
+            RegisterRule::Offset(v) => write!(f, "c{:+}", v),
+            RegisterRule::ValOffset(v) => write!(f, "v{:+}", v),
+            RegisterRule::Register(v) => write!(f, "r{}", v),
+            RegisterRule::Expression(_) => write!(f, "exp"),
+            RegisterRule::ValExpression(_) => write!(f, "vexp"),
+        }
+    }
+}
+
+// One row of the unwind table, the rules that apply for [start_address, end_address)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct UnwindRow {
+    pub start_address: u64,
+    pub end_address: u64,
+    pub cfa: CfaRule,
+    pub registers: BTreeMap<u64, RegisterRule>,
+}
+
+impl fmt::Display for UnwindRow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .map(|(register, rule)| format!("r{}={}", register, rule))
+            .collect();
+        write!(
+            f,
+            "{:#x}..{:#x} cfa={} {}",
+            self.start_address,
+            self.end_address,
+            self.cfa,
+            registers.join(" ")
+        )
+    }
+}
+
+pub struct EhFrame {
+    pub address: u64, // Virtual address of .eh_frame
+    pub ei_data: EI_Data,
+    pub cies: BTreeMap<u64, CommonInformationEntry>,
+    pub fdes: Vec<FrameDescriptionEntry>, // Sorted by initial location
+}
+
+impl EhFrame {
+    pub fn find_fde(&self, address: u64) -> Option<&FrameDescriptionEntry> {
+        let index = match self
+            .fdes
+            .binary_search_by_key(&address, |f| f.initial_location)
+        {
+            Ok(v) => v,
+            Err(0) => return None,
+            Err(v) => v - 1,
+        };
+        let fde = &self.fdes[index];
+        if fde.contains_address(address) {
+            Some(fde)
+        } else {
+            None
+        }
+    }
+
+    // Start addresses of every function with unwind information
+    pub fn get_function_starts(&self) -> Vec<u64> {
+        let mut starts: Vec<u64> = self
+            .fdes
+            .iter()
+            .filter(|f| f.address_range != 0)
+            .map(|f| f.initial_location)
+            .collect();
+        starts.dedup();
+        starts
+    }
+
+    pub fn get_unwind_rows(&self, fde: &FrameDescriptionEntry) -> Result<Vec<UnwindRow>> {
+        match self.cies.get(&fde.cie_offset) {
+            Some(cie) => get_unwind_rows(cie, fde, &self.ei_data),
+            None => Err(Error::malformed(fde.offset, "FDE refers to a missing CIE")),
+        }
+    }
+
+    // The rules in effect at an address
+    pub fn get_unwind_row(&self, address: u64) -> Option<UnwindRow> {
+        let fde = self.find_fde(address)?;
+        self.get_unwind_rows(fde)
+            .ok()?
+            .into_iter()
+            .find(|r| address >= r.start_address && address < r.end_address)
+    }
+}
+
+// Parses the CIEs and FDEs in .eh_frame, `address` is where the section is
+// loaded and is needed to resolve pc relative pointers
+pub fn parse_eh_frame(
+    data: &[u8],
+    address: u64,
+    address_size: u8,
+    ei_data: &EI_Data,
+) -> Result<EhFrame> {
+    let mut reader = Reader::new(data, 0, ei_data);
+    let bases = PointerBases {
+        section_address: address,
+        ..Default::default()
+    };
+    let mut cies: BTreeMap<u64, CommonInformationEntry> = BTreeMap::new();
+    let mut fdes: Vec<FrameDescriptionEntry> = vec![];
+
+    while !reader.is_empty() {
+        let offset = reader.offset as u64;
+        let (length, is_64_bit) = reader.get_initial_length()?;
+        if length == 0 {
+            // Zero terminator, gcc's crtend adds one at the end of .eh_frame
+            break;
+        }
+        let id_offset = reader.offset as u64;
+        let end = id_offset
+            .checked_add(length)
+            .filter(|v| *v <= data.len() as u64)
+            .ok_or_else(|| Error::truncated(offset, length, ".eh_frame entry"))?;
+        let id = reader.get_offset(is_64_bit)?;
+
+        if id == 0 {
+            let cie = parse_cie(&mut reader, offset, end, address_size, &bases)?;
+            cies.insert(offset, cie);
+        } else {
+            // The CIE pointer is relative to the position of the pointer itself
+            let cie_offset = id_offset.wrapping_sub(id);
+            if let Some(cie) = cies.get(&cie_offset) {
+                fdes.push(parse_fde(
+                    &mut reader,
+                    offset,
+                    end,
+                    cie,
+                    address_size,
+                    &bases,
+                )?);
+            }
+        }
+        reader.offset = end as usize;
+    }
+
+    fdes.sort_by_key(|f| f.initial_location);
+    Ok(EhFrame {
+        address,
+        ei_data: *ei_data,
+        cies,
+        fdes,
+    })
+}
+
+fn parse_cie(
+    reader: &mut Reader,
+    offset: u64,
+    end: u64,
+    address_size: u8,
+    bases: &PointerBases,
+) -> Result<CommonInformationEntry> {
+    let version = reader.get_u8()?;
+    if version != 1 && version != 3 {
+        return Err(Error::invalid_value(
+            offset,
+            u64::from(version),
+            ".eh_frame CIE version",
+        ));
+    }
+    let augmentation = reader.get_string()?;
+    if augmentation.contains("eh") {
+        // Old GCC eh_ptr, only present with the "eh" augmentation
+        reader.get_sized(address_size)?;
+    }
+    let code_alignment_factor = reader.get_uleb128()?;
+    let data_alignment_factor = reader.get_sleb128()?;
+    let return_address_register = if version == 1 {
+        u64::from(reader.get_u8()?)
+    } else {
+        reader.get_uleb128()?
+    };
+
+    let mut cie = CommonInformationEntry {
+        offset,
+        version,
+        augmentation: augmentation.clone(),
+        code_alignment_factor,
+        data_alignment_factor,
+        return_address_register,
+        fde_pointer_encoding: DW_EH_PE_ABSPTR,
+        lsda_encoding: DW_EH_PE_OMIT,
+        personality: None,
+        is_signal_frame: false,
+        initial_instructions: vec![],
+    };
+
+    if augmentation.starts_with('z') {
+        let length = reader.get_uleb128()?;
+        let augmentation_end = reader.offset + length as usize;
+        for c in augmentation.chars().skip(1) {
+            match c {
+                'L' => cie.lsda_encoding = reader.get_u8()?,
+                'R' => cie.fde_pointer_encoding = reader.get_u8()?,
+                'P' => {
+                    let encoding = reader.get_u8()?;
+                    cie.personality = Some(get_encoded_pointer(
+                        reader,
+                        encoding & !DW_EH_PE_INDIRECT,
+                        address_size,
+                        bases,
+                    )?);
+                }
+                'S' => cie.is_signal_frame = true,
+                // Anything else is unknown, the length lets us skip it
+                _ => break,
+            }
+        }
+        reader.offset = augmentation_end;
+    }
+
+    cie.initial_instructions = get_remaining_bytes(reader, end)?;
+    Ok(cie)
+}
+
+fn parse_fde(
+    reader: &mut Reader,
+    offset: u64,
+    end: u64,
+    cie: &CommonInformationEntry,
+    address_size: u8,
+    bases: &PointerBases,
+) -> Result<FrameDescriptionEntry> {
+    let initial_location =
+        get_encoded_pointer(reader, cie.fde_pointer_encoding, address_size, bases)?;
+    // The range uses the same format but is never relative to anything
+    let address_range =
+        get_encoded_pointer(reader, cie.fde_pointer_encoding & 0x0f, address_size, bases)?;
+
+    let mut lsda: Option<u64> = None;
+    if cie.augmentation.starts_with('z') {
+        let length = reader.get_uleb128()?;
+        let augmentation_end = reader.offset + length as usize;
+        if cie.lsda_encoding != DW_EH_PE_OMIT && length > 0 {
+            let function_bases = PointerBases {
+                function_address: initial_location,
+                ..*bases
+            };
+            let pointer = get_encoded_pointer(
+                reader,
+                cie.lsda_encoding & !DW_EH_PE_INDIRECT,
+                address_size,
+                &function_bases,
+            )?;
+            if pointer != 0 {
+                lsda = Some(pointer);
+            }
+        }
+        reader.offset = augmentation_end;
+    }
+
+    Ok(FrameDescriptionEntry {
+        offset,
+        cie_offset: cie.offset,
+        initial_location,
+        address_range,
+        lsda,
+        instructions: get_remaining_bytes(reader, end)?,
+    })
+}
+
+fn get_remaining_bytes(reader: &mut Reader, end: u64) -> Result<Vec<u8>> {
+    let length = (end as usize).checked_sub(reader.offset).ok_or_else(|| {
+        Error::malformed(
+            reader.offset as u64,
+            ".eh_frame augmentation data runs past the end of the entry",
+        )
+    })?;
+    Ok(reader.get_bytes(length)?.to_vec())
+}
+
+#[derive(Clone)]
+struct UnwindState {
+    cfa: CfaRule,
+    registers: BTreeMap<u64, RegisterRule>,
+}
+
+// Runs the CIE's initial instructions and then the FDE's to build the unwind table
+pub fn get_unwind_rows(
+    cie: &CommonInformationEntry,
+    fde: &FrameDescriptionEntry,
+    ei_data: &EI_Data,
+) -> Result<Vec<UnwindRow>> {
+    let mut state = UnwindState {
+        cfa: CfaRule::RegisterOffset(0, 0),
+        registers: BTreeMap::new(),
+    };
+    let mut rows: Vec<UnwindRow> = vec![];
+    let mut address = fde.initial_location;
+
+    run_cfa_instructions(
+        &cie.initial_instructions,
+        cie,
+        ei_data,
+        &mut state,
+        None,
+        &mut address,
+    )?;
+    let initial_registers = state.registers.clone();
+    run_cfa_instructions(
+        &fde.instructions,
+        cie,
+        ei_data,
+        &mut state,
+        Some((&initial_registers, &mut rows)),
+        &mut address,
+    )?;
+
+    let end_address = fde.get_end_address();
+    if address < end_address {
+        rows.push(UnwindRow {
+            start_address: address,
+            end_address,
+            cfa: state.cfa,
+            registers: state.registers,
+        });
+    }
+    Ok(rows)
+}
+
+fn run_cfa_instructions(
+    instructions: &[u8],
+    cie: &CommonInformationEntry,
+    ei_data: &EI_Data,
+    state: &mut UnwindState,
+    mut fde: Option<(&BTreeMap<u64, RegisterRule>, &mut Vec<UnwindRow>)>,
+    address: &mut u64,
+) -> Result<()> {
+    let mut reader = Reader::new(instructions, 0, ei_data);
+    let mut stack: Vec<UnwindState> = vec![];
+    let data_alignment = cie.data_alignment_factor;
+
+    while !reader.is_empty() {
+        let instruction = reader.get_u8()?;
+        let mut advance: Option<u64> = None;
+        match instruction & 0xc0 {
+            DW_CFA_ADVANCE_LOC => advance = Some(u64::from(instruction & 0x3f)),
+            DW_CFA_OFFSET => {
+                let offset = reader.get_uleb128()? as i64 * data_alignment;
+                state
+                    .registers
+                    .insert(u64::from(instruction & 0x3f), RegisterRule::Offset(offset));
+            }
+            DW_CFA_RESTORE => {
+                restore_register(state, u64::from(instruction & 0x3f), &fde);
+            }
+            _ => match instruction {
+                DW_CFA_NOP => {}
+                DW_CFA_SET_LOC => {
+                    if cie.fde_pointer_encoding & 0x70 != DW_EH_PE_ABSPTR {
+                        return Err(Error::unsupported(
+                            reader.offset as u64,
+                            "relative DW_CFA_set_loc",
+                        ));
+                    }
+                    let bases = PointerBases::default();
+                    let target =
+                        get_encoded_pointer(&mut reader, cie.fde_pointer_encoding, 8, &bases)?;
+                    advance =
+                        Some(target.wrapping_sub(*address) / cie.code_alignment_factor.max(1));
+                }
+                DW_CFA_ADVANCE_LOC1 => advance = Some(u64::from(reader.get_u8()?)),
+                DW_CFA_ADVANCE_LOC2 => advance = Some(u64::from(reader.get_u16()?)),
+                DW_CFA_ADVANCE_LOC4 => advance = Some(u64::from(reader.get_u32()?)),
+                DW_CFA_OFFSET_EXTENDED => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_uleb128()? as i64 * data_alignment;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::Offset(offset));
+                }
+                DW_CFA_RESTORE_EXTENDED => {
+                    let register = reader.get_uleb128()?;
+                    restore_register(state, register, &fde);
+                }
+                DW_CFA_UNDEFINED => {
+                    let register = reader.get_uleb128()?;
+                    state.registers.insert(register, RegisterRule::Undefined);
+                }
+                DW_CFA_SAME_VALUE => {
+                    let register = reader.get_uleb128()?;
+                    state.registers.insert(register, RegisterRule::SameValue);
+                }
+                DW_CFA_REGISTER => {
+                    let register = reader.get_uleb128()?;
+                    let other = reader.get_uleb128()?;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::Register(other));
+                }
+                DW_CFA_REMEMBER_STATE => stack.push(state.clone()),
+                DW_CFA_RESTORE_STATE => {
+                    // The CFA isn't part of the saved state in gcc's unwinder
+                    // but it is in the DWARF spec, follow the spec
+                    *state = stack.pop().ok_or_else(|| {
+                        Error::malformed(
+                            reader.offset as u64,
+                            "DW_CFA_restore_state with no saved state",
+                        )
+                    })?;
+                }
+                DW_CFA_DEF_CFA => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_uleb128()? as i64;
+                    state.cfa = CfaRule::RegisterOffset(register, offset);
+                }
+                DW_CFA_DEF_CFA_SF => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_sleb128()? * data_alignment;
+                    state.cfa = CfaRule::RegisterOffset(register, offset);
+                }
+                DW_CFA_DEF_CFA_REGISTER => {
+                    let register = reader.get_uleb128()?;
+                    if let CfaRule::RegisterOffset(_, offset) = state.cfa {
+                        state.cfa = CfaRule::RegisterOffset(register, offset);
+                    }
+                }
+                DW_CFA_DEF_CFA_OFFSET => {
+                    let offset = reader.get_uleb128()? as i64;
+                    if let CfaRule::RegisterOffset(register, _) = state.cfa {
+                        state.cfa = CfaRule::RegisterOffset(register, offset);
+                    }
+                }
+                DW_CFA_DEF_CFA_OFFSET_SF => {
+                    let offset = reader.get_sleb128()? * data_alignment;
+                    if let CfaRule::RegisterOffset(register, _) = state.cfa {
+                        state.cfa = CfaRule::RegisterOffset(register, offset);
+                    }
+                }
+                DW_CFA_DEF_CFA_EXPRESSION => {
+                    let length = reader.get_uleb128()?;
+                    state.cfa = CfaRule::Expression(reader.get_bytes(length as usize)?.to_vec());
+                }
+                DW_CFA_EXPRESSION | DW_CFA_VAL_EXPRESSION => {
+                    let register = reader.get_uleb128()?;
+                    let length = reader.get_uleb128()?;
+                    let expression = reader.get_bytes(length as usize)?.to_vec();
+                    let rule = if instruction == DW_CFA_EXPRESSION {
+                        RegisterRule::Expression(expression)
+                    } else {
+                        RegisterRule::ValExpression(expression)
+                    };
+                    state.registers.insert(register, rule);
+                }
+                DW_CFA_OFFSET_EXTENDED_SF => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_sleb128()? * data_alignment;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::Offset(offset));
+                }
+                DW_CFA_VAL_OFFSET => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_uleb128()? as i64 * data_alignment;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::ValOffset(offset));
+                }
+                DW_CFA_VAL_OFFSET_SF => {
+                    let register = reader.get_uleb128()?;
+                    let offset = reader.get_sleb128()? * data_alignment;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::ValOffset(offset));
+                }
+                DW_CFA_GNU_WINDOW_SAVE => {}
+                DW_CFA_GNU_ARGS_SIZE => {
+                    reader.get_uleb128()?;
+                }
+                DW_CFA_GNU_NEGATIVE_OFFSET_EXTENDED => {
+                    let register = reader.get_uleb128()?;
+                    let offset = -(reader.get_uleb128()? as i64) * data_alignment;
+                    state
+                        .registers
+                        .insert(register, RegisterRule::Offset(offset));
+                }
+                _ => {
+                    return Err(Error::invalid_value(
+                        reader.offset as u64 - 1,
+                        u64::from(instruction),
+                        "DW_CFA instruction",
+                    ))
+                }
+            },
+        }
+
+        if let Some(delta) = advance {
+            let next = address.wrapping_add(delta * cie.code_alignment_factor);
+            // The CIE's initial instructions have no rows of their own
+            if let Some((_, rows)) = fde.as_mut() {
+                if next > *address {
+                    rows.push(UnwindRow {
+                        start_address: *address,
+                        end_address: next,
+                        cfa: state.cfa.clone(),
+                        registers: state.registers.clone(),
+                    });
+                }
+            }
+            *address = next;
+        }
+    }
+    Ok(())
+}
+
+// DW_CFA_restore goes back to the rule set up by the CIE's initial instructions
+fn restore_register(
+    state: &mut UnwindState,
+    register: u64,
+    fde: &Option<(&BTreeMap<u64, RegisterRule>, &mut Vec<UnwindRow>)>,
+) {
+    let initial = fde.as_ref().and_then(|(initial, _)| initial.get(&register));
+    match initial {
+        Some(rule) => state.registers.insert(register, rule.clone()),
+        None => state.registers.remove(&register),
+    };
+}
+
+// .eh_frame_hdr, the sorted lookup table the runtime unwinder uses
+pub struct EhFrameHdr {
+    pub version: u8,
+    pub eh_frame_address: u64,
+    pub table: Vec<(u64, u64)>, // (initial location, FDE address) sorted by location
+}
+
+impl EhFrameHdr {
+    // Returns the address of the FDE that may cover the address
+    pub fn lookup(&self, address: u64) -> Option<u64> {
+        match self.table.binary_search_by_key(&address, |(l, _)| *l) {
+            Ok(v) => Some(self.table[v].1),
+            Err(0) => None,
+            Err(v) => Some(self.table[v - 1].1),
+        }
+    }
+}
+
+pub fn parse_eh_frame_hdr(
+    data: &[u8],
+    address: u64,
+    address_size: u8,
+    ei_data: &EI_Data,
+) -> Result<EhFrameHdr> {
+    let mut reader = Reader::new(data, 0, ei_data);
+    let bases = PointerBases {
+        section_address: address,
+        data_address: address,
+        ..Default::default()
+    };
+    let version = reader.get_u8()?;
+    if version != 1 {
+        return Err(Error::invalid_value(
+            0,
+            u64::from(version),
+            ".eh_frame_hdr version",
+        ));
+    }
+    let eh_frame_pointer_encoding = reader.get_u8()?;
+    let fde_count_encoding = reader.get_u8()?;
+    let table_encoding = reader.get_u8()?;
+    let eh_frame_address =
+        get_encoded_pointer(&mut reader, eh_frame_pointer_encoding, address_size, &bases)?;
+
+    let mut table: Vec<(u64, u64)> = vec![];
+    if fde_count_encoding != DW_EH_PE_OMIT && table_encoding != DW_EH_PE_OMIT {
+        let count = get_encoded_pointer(&mut reader, fde_count_encoding, address_size, &bases)?;
+        for _ in 0..count {
+            let location = get_encoded_pointer(&mut reader, table_encoding, address_size, &bases)?;
+            let fde = get_encoded_pointer(&mut reader, table_encoding, address_size, &bases)?;
+            table.push((location, fde));
+        }
+    }
+    Ok(EhFrameHdr {
+        version,
+        eh_frame_address,
+        table,
+    })
+}
+
+fn get_address_size(elf: &ELF) -> u8 {
+    match elf.elf_header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    }
+}
+
+pub fn load_eh_frame_hdr_from_elf(elf: &ELF) -> Result<EhFrameHdr> {
+    let (data, address) = match get_section_by_name(".eh_frame_hdr", &elf.section_headers) {
+        Some(v) => (v.get_data(&elf.data), v.address),
+        None => match elf
+            .program_headers
+            .iter()
+            .find(|ph| ph.header_type == ProgramHeaderType::GNU_EH_Frame)
+        {
+            Some(v) => (v.get_data(&elf.data), v.virtual_address),
+            None => return Err(Error::not_found(".eh_frame_hdr")),
+        },
+    };
+    parse_eh_frame_hdr(
+        data,
+        address,
+        get_address_size(elf),
+        &elf.elf_header.ident.ei_data,
+    )
+}
+
+// Uses the .eh_frame section, or finds it through PT_GNU_EH_FRAME when the
+// section headers are gone
+pub fn load_eh_frame_from_elf(elf: &ELF) -> Result<EhFrame> {
+    let address_size = get_address_size(elf);
+    let ei_data = &elf.elf_header.ident.ei_data;
+    if let Some(section) = get_section_by_name(".eh_frame", &elf.section_headers) {
+        return parse_eh_frame(
+            section.get_data(&elf.data),
+            section.address,
+            address_size,
+            ei_data,
+        );
+    }
+    let hdr = load_eh_frame_hdr_from_elf(elf)?;
+    // We don't know the size, the zero terminator marks the end
+    let data = get_data_for_address(
+        &elf.data,
+        hdr.eh_frame_address,
+        elf.data.len() as u64,
+        &elf.program_headers,
+    )
+    // eh_frame_ptr is 4 bytes into .eh_frame_hdr
+    .ok_or_else(|| Error::invalid_value(4, hdr.eh_frame_address, ".eh_frame address"))?;
+    parse_eh_frame(data, hdr.eh_frame_address, address_size, ei_data)
+}
+
+#[cfg(test)]
+mod frame_tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_eh_frame() {
+        let raw = [
+            // CIE
+            0x14, 0x00, 0x00, 0x00, // length
+            0x00, 0x00, 0x00, 0x00, // CIE id
+            0x01, // version
+            0x7a, 0x52, 0x00, // augmentation "zR"
+            0x01, // code alignment factor
+            0x78, // data alignment factor -8
+            0x10, // return address register 16
+            0x01, // augmentation length
+            0x1b, // FDE encoding pcrel sdata4
+            0x0c, 0x07, 0x08, // DW_CFA_def_cfa r7+8
+            0x90, 0x01, // DW_CFA_offset r16 at cfa-8
+            0x00, 0x00, // padding
+            // FDE at 0x18
+            0x1c, 0x00, 0x00, 0x00, // length
+            0x1c, 0x00, 0x00, 0x00, // CIE pointer, 0x1c back from here is 0
+            0xe0, 0x0f, 0x00, 0x00, // initial location, 0x1000 from 0x20
+            0x10, 0x00, 0x00, 0x00, // address range
+            0x00, // augmentation length
+            0x41, // DW_CFA_advance_loc 1
+            0x0e, 0x10, // DW_CFA_def_cfa_offset 16
+            0x86, 0x02, // DW_CFA_offset r6 at cfa-16
+            0x43, // DW_CFA_advance_loc 3
+            0x0d, 0x06, // DW_CFA_def_cfa_register r6
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x00, 0x00, 0x00, 0x00, // terminator
+        ];
+        let eh_frame = parse_eh_frame(&raw, 0x2000, 8, &EI_Data::LittleEndian).unwrap();
+        assert_eq!(eh_frame.cies.len(), 1);
+        assert_eq!(eh_frame.fdes.len(), 1);
+        assert_eq!(eh_frame.get_function_starts(), vec![0x3000]);
+
+        let fde = eh_frame.find_fde(0x3008).unwrap();
+        assert_eq!(fde.initial_location, 0x3000);
+        assert_eq!(fde.get_end_address(), 0x3010);
+        assert!(eh_frame.find_fde(0x3010).is_none());
+
+        let rows = eh_frame.get_unwind_rows(fde).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].start_address, 0x3000);
+        assert_eq!(rows[0].cfa, CfaRule::RegisterOffset(7, 8));
+        assert_eq!(rows[0].registers.get(&16), Some(&RegisterRule::Offset(-8)));
+        assert_eq!(rows[1].start_address, 0x3001);
+        assert_eq!(rows[1].cfa, CfaRule::RegisterOffset(7, 16));
+        assert_eq!(rows[1].registers.get(&6), Some(&RegisterRule::Offset(-16)));
+        assert_eq!(rows[2].start_address, 0x3004);
+        assert_eq!(rows[2].end_address, 0x3010);
+        assert_eq!(rows[2].cfa, CfaRule::RegisterOffset(6, 16));
+    }
+}
@@ -0,0 +1,393 @@
+use super::constants::{
+    DW_FORM_BLOCK, DW_FORM_DATA1, DW_FORM_DATA16, DW_FORM_DATA2, DW_FORM_DATA4, DW_FORM_DATA8,
+    DW_FORM_LINE_STRP, DW_FORM_STRING, DW_FORM_STRP, DW_FORM_STRX, DW_FORM_STRX1, DW_FORM_STRX2,
+    DW_FORM_STRX3, DW_FORM_STRX4, DW_FORM_UDATA,
+};
+use super::reader::{get_string_at, Reader};
+use super::DwarfSections;
+use crate::error::{Error, Result};
+
+// Standard opcodes
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_SET_BASIC_BLOCK: u8 = 0x07;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+const DW_LNS_SET_PROLOGUE_END: u8 = 0x0a;
+const DW_LNS_SET_EPILOGUE_BEGIN: u8 = 0x0b;
+const DW_LNS_SET_ISA: u8 = 0x0c;
+
+// Extended opcodes
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+const DW_LNE_DEFINE_FILE: u8 = 0x03;
+
+// Content types for the DWARF 5 directory and file entry formats
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FileEntry {
+    pub name: String,
+    pub directory_index: u64,
+}
+
+// A row of the line number matrix
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LineRow {
+    pub address: u64,
+    pub file: u64, // Index into LineProgram.files
+    pub line: u64,
+    pub column: u64,
+    pub is_stmt: bool,
+    pub end_sequence: bool, // First address past the end of a sequence
+}
+
+pub struct LineProgram {
+    pub offset: u64, // Offset in .debug_line
+    pub version: u16,
+    // Both lists are indexed the way the program refers to them. Before DWARF 5
+    // entry 0 is implicitly the compilation directory and unit source file, we
+    // fill those in so the indexes line up.
+    pub directories: Vec<String>,
+    pub files: Vec<FileEntry>,
+    pub rows: Vec<LineRow>,
+}
+
+impl LineProgram {
+    pub fn get_file_path(&self, index: u64) -> Option<String> {
+        let file = self.files.get(index as usize)?;
+        if file.name.starts_with('/') {
+            return Some(file.name.clone());
+        }
+        match self.directories.get(file.directory_index as usize) {
+            Some(directory) if !directory.is_empty() => {
+                Some(format!("{}/{}", directory.trim_end_matches('/'), file.name))
+            }
+            _ => Some(file.name.clone()),
+        }
+    }
+}
+
+struct LineProgramHeader {
+    minimum_instruction_length: u8,
+    default_is_stmt: bool,
+    line_base: i8,
+    line_range: u8,
+    opcode_base: u8,
+    standard_opcode_lengths: Vec<u8>,
+}
+
+// Reads the line number program at `offset` in .debug_line. The compilation
+// directory and unit name fill in entry 0 of the tables for older versions.
+pub fn parse_line_program(
+    sections: &DwarfSections,
+    offset: u64,
+    address_size: u8,
+    comp_dir: &str,
+    unit_name: &str,
+) -> Result<LineProgram> {
+    let mut reader = Reader::new(sections.debug_line, offset as usize, sections.ei_data);
+    let (length, is_64_bit) = reader.get_initial_length()?;
+    let mut reader = reader.get_sub_reader(length)?;
+
+    let version = reader.get_u16()?;
+    if !(2..=5).contains(&version) {
+        return Err(Error::invalid_value(
+            offset,
+            u64::from(version),
+            "DWARF line program version",
+        ));
+    }
+    let mut address_size = address_size;
+    if version >= 5 {
+        address_size = reader.get_u8()?;
+        reader.get_u8()?; // segment_selector_size
+    }
+    let header_length = reader.get_offset(is_64_bit)?;
+    let program_offset = reader.offset + header_length as usize;
+
+    let minimum_instruction_length = reader.get_u8()?;
+    if version >= 4 {
+        reader.get_u8()?; // maximum_operations_per_instruction, only used for VLIW
+    }
+    let default_is_stmt = reader.get_u8()? != 0;
+    let line_base = reader.get_i8()?;
+    let line_range = reader.get_u8()?;
+    let opcode_base = reader.get_u8()?;
+    let mut standard_opcode_lengths: Vec<u8> = vec![];
+    for _ in 1..opcode_base {
+        standard_opcode_lengths.push(reader.get_u8()?);
+    }
+    if line_range == 0 {
+        return Err(Error::malformed(
+            offset,
+            "DWARF line program has a line range of zero",
+        ));
+    }
+
+    let mut program = LineProgram {
+        offset,
+        version,
+        directories: vec![],
+        files: vec![],
+        rows: vec![],
+    };
+    if version >= 5 {
+        for entry in parse_entry_list(&mut reader, sections, is_64_bit)? {
+            program.directories.push(entry.name);
+        }
+        program.files = parse_entry_list(&mut reader, sections, is_64_bit)?;
+    } else {
+        program.directories.push(comp_dir.to_string());
+        loop {
+            let directory = reader.get_string()?;
+            if directory.is_empty() {
+                break;
+            }
+            program.directories.push(directory);
+        }
+        program.files.push(FileEntry {
+            name: unit_name.to_string(),
+            directory_index: 0,
+        });
+        loop {
+            let name = reader.get_string()?;
+            if name.is_empty() {
+                break;
+            }
+            let directory_index = reader.get_uleb128()?;
+            reader.get_uleb128()?; // modification time
+            reader.get_uleb128()?; // length
+            program.files.push(FileEntry {
+                name,
+                directory_index,
+            });
+        }
+    }
+
+    let header = LineProgramHeader {
+        minimum_instruction_length,
+        default_is_stmt,
+        line_base,
+        line_range,
+        opcode_base,
+        standard_opcode_lengths,
+    };
+    reader.offset = program_offset;
+    run_line_program(&mut reader, &header, address_size, &mut program)?;
+    Ok(program)
+}
+
+// DWARF 5 describes the layout of directory and file entries with a list of
+// content type and form pairs
+fn parse_entry_list(
+    reader: &mut Reader,
+    sections: &DwarfSections,
+    is_64_bit: bool,
+) -> Result<Vec<FileEntry>> {
+    let format_count = reader.get_u8()?;
+    let mut formats: Vec<(u64, u64)> = vec![];
+    for _ in 0..format_count {
+        formats.push((reader.get_uleb128()?, reader.get_uleb128()?));
+    }
+
+    let count = reader.get_uleb128()?;
+    let mut entries: Vec<FileEntry> = vec![];
+    for _ in 0..count {
+        let mut entry = FileEntry {
+            name: String::new(),
+            directory_index: 0,
+        };
+        for (content_type, form) in &formats {
+            let mut string: Option<String> = None;
+            let mut value: u64 = 0;
+            match *form {
+                DW_FORM_STRING => string = Some(reader.get_string()?),
+                DW_FORM_LINE_STRP => {
+                    let offset = reader.get_offset(is_64_bit)?;
+                    string = Some(get_string_at(sections.debug_line_str, offset)?);
+                }
+                DW_FORM_STRP => {
+                    let offset = reader.get_offset(is_64_bit)?;
+                    string = Some(get_string_at(sections.debug_str, offset)?);
+                }
+                DW_FORM_UDATA | DW_FORM_STRX => value = reader.get_uleb128()?,
+                DW_FORM_DATA1 | DW_FORM_STRX1 => value = u64::from(reader.get_u8()?),
+                DW_FORM_DATA2 | DW_FORM_STRX2 => value = u64::from(reader.get_u16()?),
+                DW_FORM_STRX3 => value = u64::from(reader.get_u24()?),
+                DW_FORM_DATA4 | DW_FORM_STRX4 => value = u64::from(reader.get_u32()?),
+                DW_FORM_DATA8 => value = reader.get_u64()?,
+                DW_FORM_DATA16 => reader.skip(16)?,
+                DW_FORM_BLOCK => {
+                    let length = reader.get_uleb128()? as usize;
+                    reader.skip(length)?;
+                }
+                _ => {
+                    return Err(Error::unsupported(
+                        reader.offset as u64,
+                        format!("form {:#x} in a DWARF line program entry", form),
+                    ))
+                }
+            }
+            match *content_type {
+                DW_LNCT_PATH => entry.name = string.unwrap_or_default(),
+                DW_LNCT_DIRECTORY_INDEX => entry.directory_index = value,
+                _ => {}
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// Runs the line number state machine, appending a row every time one is emitted
+fn run_line_program(
+    reader: &mut Reader,
+    header: &LineProgramHeader,
+    address_size: u8,
+    program: &mut LineProgram,
+) -> Result<()> {
+    let new_row = |header: &LineProgramHeader| LineRow {
+        address: 0,
+        file: 1,
+        line: 1,
+        column: 0,
+        is_stmt: header.default_is_stmt,
+        end_sequence: false,
+    };
+    let minimum_instruction_length = u64::from(header.minimum_instruction_length);
+    let mut row = new_row(header);
+
+    while !reader.is_empty() {
+        let opcode = reader.get_u8()?;
+        if opcode >= header.opcode_base {
+            // Special opcodes advance both the address and line then emit a row
+            let adjusted = opcode - header.opcode_base;
+            row.address = row
+                .address
+                .wrapping_add(u64::from(adjusted / header.line_range) * minimum_instruction_length);
+            row.line = (row.line as i64
+                + i64::from(header.line_base)
+                + i64::from(adjusted % header.line_range)) as u64;
+            program.rows.push(row.clone());
+            continue;
+        }
+        match opcode {
+            0 => {
+                let length = reader.get_uleb128()? as usize;
+                if length == 0 {
+                    continue;
+                }
+                let mut extended = reader.get_sub_reader(length as u64)?;
+                match extended.get_u8()? {
+                    DW_LNE_END_SEQUENCE => {
+                        row.end_sequence = true;
+                        program.rows.push(row.clone());
+                        row = new_row(header);
+                    }
+                    DW_LNE_SET_ADDRESS => {
+                        row.address = extended.get_sized(address_size)?;
+                    }
+                    DW_LNE_DEFINE_FILE => {
+                        let name = extended.get_string()?;
+                        let directory_index = extended.get_uleb128()?;
+                        program.files.push(FileEntry {
+                            name,
+                            directory_index,
+                        });
+                    }
+                    // DW_LNE_set_discriminator and vendor extensions are skipped
+                    // thanks to the length
+                    _ => {}
+                }
+            }
+            DW_LNS_COPY => program.rows.push(row.clone()),
+            DW_LNS_ADVANCE_PC => {
+                row.address = row
+                    .address
+                    .wrapping_add(reader.get_uleb128()? * minimum_instruction_length);
+            }
+            DW_LNS_ADVANCE_LINE => {
+                row.line = (row.line as i64).wrapping_add(reader.get_sleb128()?) as u64;
+            }
+            DW_LNS_SET_FILE => row.file = reader.get_uleb128()?,
+            DW_LNS_SET_COLUMN => row.column = reader.get_uleb128()?,
+            DW_LNS_NEGATE_STMT => row.is_stmt = !row.is_stmt,
+            DW_LNS_SET_BASIC_BLOCK | DW_LNS_SET_PROLOGUE_END | DW_LNS_SET_EPILOGUE_BEGIN => {}
+            DW_LNS_CONST_ADD_PC => {
+                let adjusted = 255 - header.opcode_base;
+                row.address = row.address.wrapping_add(
+                    u64::from(adjusted / header.line_range) * minimum_instruction_length,
+                );
+            }
+            DW_LNS_FIXED_ADVANCE_PC => {
+                row.address = row.address.wrapping_add(u64::from(reader.get_u16()?));
+            }
+            DW_LNS_SET_ISA => {
+                reader.get_uleb128()?;
+            }
+            _ => {
+                // Unknown standard opcodes are skipped using their operand count
+                let count = header.standard_opcode_lengths[opcode as usize - 1];
+                for _ in 0..count {
+                    reader.get_uleb128()?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod line_tests {
+    use super::super::DwarfSections;
+    use super::*;
+    use crate::elf::elf_header::EI_Data;
+
+    #[test]
+    fn can_run_dwarf_4_line_program() {
+        let raw = [
+            0x33, 0x00, 0x00, 0x00, // unit_length
+            0x04, 0x00, // version
+            0x1b, 0x00, 0x00, 0x00, // header_length
+            0x01, // minimum_instruction_length
+            0x01, // maximum_operations_per_instruction
+            0x01, // default_is_stmt
+            0xfb, // line_base -5
+            0x0e, // line_range 14
+            0x0d, // opcode_base 13
+            0x00, 0x01, 0x01, 0x01, 0x01, 0x00, // standard_opcode_lengths
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x01, // standard_opcode_lengths
+            0x00, // no include_directories
+            0x74, 0x2e, 0x63, 0x00, // file "t.c"
+            0x00, 0x00, 0x00, // directory, time, length
+            0x00, // end of file_names
+            0x00, 0x09, 0x02, // DW_LNE_set_address
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0x1000
+            0x14, // special: address +0, line +2
+            0x59, // special: address +5, line +1
+            0x02, 0x04, // DW_LNS_advance_pc 4
+            0x00, 0x01, 0x01, // DW_LNE_end_sequence
+        ];
+        let sections = DwarfSections {
+            debug_line: &raw,
+            ..DwarfSections::new(&EI_Data::LittleEndian)
+        };
+        let program = parse_line_program(&sections, 0, 8, "/src", "t.c").unwrap();
+        assert_eq!(program.get_file_path(1), Some("/src/t.c".to_string()));
+        let rows: Vec<(u64, u64, bool)> = program
+            .rows
+            .iter()
+            .map(|r| (r.address, r.line, r.end_sequence))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(0x1000, 3, false), (0x1005, 4, false), (0x1009, 4, true)]
+        );
+    }
+}
@@ -0,0 +1,649 @@
+pub mod abbrev;
+pub mod constants;
+pub mod frame;
+pub mod line;
+pub mod reader;
+pub mod unit;
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::elf::elf_header::EI_Data;
+use crate::elf::relocatable::get_symbol_address;
+use crate::elf::ELF;
+use crate::error::{Error, Result};
+use constants::*;
+use line::{parse_line_program, LineProgram};
+use reader::Reader;
+use unit::{parse_units, AttributeValue, CompilationUnit, DebuggingInformationEntry};
+
+// The raw contents of the debug sections, any that are missing are left empty
+pub struct DwarfSections<'a> {
+    pub ei_data: &'a EI_Data,
+    pub debug_info: &'a [u8],
+    pub debug_abbrev: &'a [u8],
+    pub debug_line: &'a [u8],
+    pub debug_str: &'a [u8],
+    pub debug_line_str: &'a [u8],
+    pub debug_str_offsets: &'a [u8],
+    pub debug_addr: &'a [u8],
+    pub debug_ranges: &'a [u8],
+    pub debug_rnglists: &'a [u8],
+}
+
+impl<'a> DwarfSections<'a> {
+    pub fn new(ei_data: &'a EI_Data) -> DwarfSections<'a> {
+        DwarfSections {
+            ei_data,
+            debug_info: &[],
+            debug_abbrev: &[],
+            debug_line: &[],
+            debug_str: &[],
+            debug_line_str: &[],
+            debug_str_offsets: &[],
+            debug_addr: &[],
+            debug_ranges: &[],
+            debug_rnglists: &[],
+        }
+    }
+}
+
+// A function from a DW_TAG_subprogram entry that has code
+pub struct Subprogram {
+    pub name: String,
+    pub linkage_name: Option<String>,
+    pub ranges: Vec<(u64, u64)>, // [start, end) address ranges
+    pub unit_index: usize,       // Index into Dwarf.units
+    pub offset: u64,             // Offset of the entry in .debug_info
+}
+
+impl Subprogram {
+    pub fn get_low_pc(&self) -> Option<u64> {
+        self.ranges.iter().map(|(start, _)| *start).min()
+    }
+
+    pub fn contains_address(&self, address: u64) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| address >= *start && address < *end)
+    }
+}
+
+impl fmt::Display for Subprogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|(start, end)| format!("{:#x}-{:#x}", start, end))
+            .collect();
+        write!(f, "{} [{}]", self.name, ranges.join(", "))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+// An address range covered by a single row of a line table
+struct LineRange {
+    start: u64,
+    end: u64,
+    unit_index: usize,
+    file: u64,
+    line: u64,
+    column: u64,
+}
+
+pub struct Dwarf {
+    pub units: Vec<CompilationUnit>,
+    pub line_programs: Vec<Option<LineProgram>>, // One per unit, None without DW_AT_stmt_list
+    pub subprograms: Vec<Subprogram>,            // Sorted by lowest address
+    line_ranges: Vec<LineRange>,                 // Sorted by start address
+}
+
+impl Dwarf {
+    pub fn parse(sections: &DwarfSections) -> Result<Dwarf> {
+        let units = parse_units(sections)?;
+
+        let mut line_programs: Vec<Option<LineProgram>> = vec![];
+        for unit in &units {
+            line_programs.push(match unit.get_stmt_list() {
+                Some(offset) => parse_line_program(
+                    sections,
+                    offset,
+                    unit.address_size,
+                    unit.get_comp_dir().unwrap_or(""),
+                    unit.get_name().unwrap_or(""),
+                )
+                .ok(),
+                None => None,
+            });
+        }
+
+        let mut dwarf = Dwarf {
+            subprograms: vec![],
+            line_ranges: get_line_ranges(&line_programs),
+            units,
+            line_programs,
+        };
+        dwarf.subprograms = dwarf.get_subprograms_from_units(sections);
+        Ok(dwarf)
+    }
+
+    fn get_subprograms_from_units(&self, sections: &DwarfSections) -> Vec<Subprogram> {
+        let mut subprograms: Vec<Subprogram> = vec![];
+        for (unit_index, unit) in self.units.iter().enumerate() {
+            for entry in &unit.entries {
+                if entry.tag != DwarfTag::Subprogram {
+                    continue;
+                }
+                let ranges = get_entry_ranges(entry, unit, sections);
+                if ranges.is_empty() {
+                    // Declarations and inlined-only functions have no code of their own
+                    continue;
+                }
+                subprograms.push(Subprogram {
+                    name: self
+                        .get_entry_name(entry, DwarfAttribute::Name)
+                        .unwrap_or_default(),
+                    linkage_name: self
+                        .get_entry_name(entry, DwarfAttribute::LinkageName)
+                        .or_else(|| self.get_entry_name(entry, DwarfAttribute::MIPSLinkageName)),
+                    ranges,
+                    unit_index,
+                    offset: entry.offset,
+                });
+            }
+        }
+        subprograms.sort_by_key(|s| s.get_low_pc());
+        subprograms
+    }
+
+    // Names often live on the declaration the entry points to with
+    // DW_AT_specification or DW_AT_abstract_origin, so those are followed
+    fn get_entry_name(
+        &self,
+        entry: &DebuggingInformationEntry,
+        name: DwarfAttribute,
+    ) -> Option<String> {
+        let mut entry = entry;
+        // Bound the number of references we follow in case of cycles
+        for _ in 0..8 {
+            if let Some(v) = entry.get_string(name) {
+                return Some(v.to_string());
+            }
+            let reference = match entry
+                .get_attribute(DwarfAttribute::Specification)
+                .or_else(|| entry.get_attribute(DwarfAttribute::AbstractOrigin))
+            {
+                Some(AttributeValue::Reference(v)) => *v,
+                _ => return None,
+            };
+            entry = self.get_entry_at(reference)?;
+        }
+        None
+    }
+
+    pub fn get_entry_at(&self, offset: u64) -> Option<&DebuggingInformationEntry> {
+        self.units
+            .iter()
+            .find(|u| u.contains_offset(offset))?
+            .get_entry_at(offset)
+    }
+
+    pub fn find_subprogram(&self, address: u64) -> Option<&Subprogram> {
+        self.subprograms
+            .iter()
+            .find(|s| s.contains_address(address))
+    }
+
+    pub fn find_location(&self, address: u64) -> Option<SourceLocation> {
+        let index = match self.line_ranges.binary_search_by_key(&address, |r| r.start) {
+            Ok(v) => v,
+            Err(0) => return None,
+            Err(v) => v - 1,
+        };
+        let range = &self.line_ranges[index];
+        if address >= range.end {
+            return None;
+        }
+        let program = self.line_programs[range.unit_index].as_ref()?;
+        Some(SourceLocation {
+            file: program.get_file_path(range.file)?,
+            line: range.line,
+            column: range.column,
+        })
+    }
+
+    // Returns the location only if the address is the first one for its row,
+    // which is what a disassembly listing wants to annotate
+    pub fn find_location_start(&self, address: u64) -> Option<SourceLocation> {
+        self.line_ranges
+            .binary_search_by_key(&address, |r| r.start)
+            .ok()?;
+        self.find_location(address)
+    }
+}
+
+fn get_line_ranges(line_programs: &[Option<LineProgram>]) -> Vec<LineRange> {
+    let mut ranges: Vec<LineRange> = vec![];
+    for (unit_index, program) in line_programs.iter().enumerate() {
+        let program = match program {
+            Some(v) => v,
+            None => continue,
+        };
+        for rows in program.rows.windows(2) {
+            let (row, next) = (&rows[0], &rows[1]);
+            if row.end_sequence || next.address <= row.address {
+                continue;
+            }
+            ranges.push(LineRange {
+                start: row.address,
+                end: next.address,
+                unit_index,
+                file: row.file,
+                line: row.line,
+                column: row.column,
+            });
+        }
+    }
+    // Sequences for code that was discarded by the linker start at 0, keep the
+    // first row for any address
+    ranges.sort_by_key(|r| r.start);
+    ranges.dedup_by_key(|r| r.start);
+    ranges
+}
+
+// Gets the address ranges covered by an entry from DW_AT_low_pc/DW_AT_high_pc or
+// from DW_AT_ranges
+pub fn get_entry_ranges(
+    entry: &DebuggingInformationEntry,
+    unit: &CompilationUnit,
+    sections: &DwarfSections,
+) -> Vec<(u64, u64)> {
+    if let (Some(low), Some(high)) = (entry.get_low_pc(), entry.get_high_pc()) {
+        if high > low {
+            return vec![(low, high)];
+        }
+        return vec![];
+    }
+    let base_address = unit.get_root().and_then(|r| r.get_low_pc()).unwrap_or(0);
+    let ranges = match entry.get_attribute(DwarfAttribute::Ranges) {
+        Some(AttributeValue::SectionOffset(offset)) if unit.version >= 5 => {
+            get_rnglist(sections, unit, *offset, base_address)
+        }
+        Some(AttributeValue::SectionOffset(offset)) => {
+            let offset = offset
+                + unit
+                    .get_root()
+                    .and_then(|r| r.get_u64(DwarfAttribute::GNURangesBase))
+                    .unwrap_or(0);
+            get_debug_ranges(sections, unit, offset, base_address)
+        }
+        Some(AttributeValue::RangeListIndex(index)) => {
+            // The index selects an offset from the table at DW_AT_rnglists_base
+            let offset_size: u64 = if unit.is_64_bit { 8 } else { 4 };
+            let base = unit
+                .get_root()
+                .and_then(|r| r.get_u64(DwarfAttribute::RnglistsBase))
+                .unwrap_or(offset_size * 2 + 4);
+            let mut reader = Reader::new(
+                sections.debug_rnglists,
+                (base + index * offset_size) as usize,
+                sections.ei_data,
+            );
+            match reader.get_offset(unit.is_64_bit) {
+                Ok(offset) => get_rnglist(sections, unit, base + offset, base_address),
+                Err(_) => Ok(vec![]),
+            }
+        }
+        _ => Ok(vec![]),
+    };
+    ranges
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(start, end)| end > start)
+        .collect()
+}
+
+// Pre DWARF 5 range lists are pairs of addresses relative to the base address,
+// ending with a pair of zeros. A start of all ones sets a new base address.
+fn get_debug_ranges(
+    sections: &DwarfSections,
+    unit: &CompilationUnit,
+    offset: u64,
+    base_address: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let mut reader = Reader::new(sections.debug_ranges, offset as usize, sections.ei_data);
+    let max_address = if unit.address_size == 4 {
+        0xffff_ffff
+    } else {
+        u64::MAX
+    };
+    let mut base_address = base_address;
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    loop {
+        let start = reader.get_sized(unit.address_size)?;
+        let end = reader.get_sized(unit.address_size)?;
+        if start == 0 && end == 0 {
+            break;
+        }
+        if start == max_address {
+            base_address = end;
+            continue;
+        }
+        ranges.push((
+            base_address.wrapping_add(start),
+            base_address.wrapping_add(end),
+        ));
+    }
+    Ok(ranges)
+}
+
+fn get_address_at_index(
+    sections: &DwarfSections,
+    unit: &CompilationUnit,
+    index: u64,
+) -> Result<u64> {
+    let base = unit
+        .get_root()
+        .and_then(|r| r.get_u64(DwarfAttribute::AddrBase))
+        .unwrap_or(8);
+    let mut reader = Reader::new(
+        sections.debug_addr,
+        (base + index * u64::from(unit.address_size)) as usize,
+        sections.ei_data,
+    );
+    reader.get_sized(unit.address_size)
+}
+
+// DWARF 5 range lists are a series of DW_RLE_* encoded entries
+fn get_rnglist(
+    sections: &DwarfSections,
+    unit: &CompilationUnit,
+    offset: u64,
+    base_address: u64,
+) -> Result<Vec<(u64, u64)>> {
+    let mut reader = Reader::new(sections.debug_rnglists, offset as usize, sections.ei_data);
+    let mut base_address = base_address;
+    let mut ranges: Vec<(u64, u64)> = vec![];
+    loop {
+        match reader.get_u8()? {
+            DW_RLE_END_OF_LIST => break,
+            DW_RLE_BASE_ADDRESSX => {
+                base_address = get_address_at_index(sections, unit, reader.get_uleb128()?)?;
+            }
+            DW_RLE_STARTX_ENDX => {
+                let start = get_address_at_index(sections, unit, reader.get_uleb128()?)?;
+                let end = get_address_at_index(sections, unit, reader.get_uleb128()?)?;
+                ranges.push((start, end));
+            }
+            DW_RLE_STARTX_LENGTH => {
+                let start = get_address_at_index(sections, unit, reader.get_uleb128()?)?;
+                let length = reader.get_uleb128()?;
+                ranges.push((start, start.wrapping_add(length)));
+            }
+            DW_RLE_OFFSET_PAIR => {
+                let start = reader.get_uleb128()?;
+                let end = reader.get_uleb128()?;
+                ranges.push((
+                    base_address.wrapping_add(start),
+                    base_address.wrapping_add(end),
+                ));
+            }
+            DW_RLE_BASE_ADDRESS => base_address = reader.get_sized(unit.address_size)?,
+            DW_RLE_START_END => {
+                let start = reader.get_sized(unit.address_size)?;
+                let end = reader.get_sized(unit.address_size)?;
+                ranges.push((start, end));
+            }
+            DW_RLE_START_LENGTH => {
+                let start = reader.get_sized(unit.address_size)?;
+                let length = reader.get_uleb128()?;
+                ranges.push((start, start.wrapping_add(length)));
+            }
+            entry => {
+                return Err(Error::invalid_value(
+                    reader.offset as u64 - 1,
+                    u64::from(entry),
+                    "DWARF range list entry",
+                ))
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+// Objects leave the section offsets and addresses in their debug sections to the
+// linker, apply the relocations against the section so it reads like a linked file's
+fn apply_relocations(elf: &ELF, section_index: usize, data: &mut [u8]) {
+    let ei_data = elf.elf_header.ident.ei_data;
+    let section_addresses = elf.get_section_addresses();
+    for relocation in elf
+        .relocation_tables
+        .iter()
+        .filter(|t| t.target_section_index as usize == section_index)
+        .flat_map(|t| &t.relocations)
+    {
+        let size = match relocation.relocation_type.get_data_size() {
+            Some(v) => v,
+            None => continue,
+        };
+        if relocation.offset.saturating_add(size as u64) > data.len() as u64 {
+            continue;
+        }
+        let start = relocation.offset as usize;
+        // Symbols in the sections that aren't loaded, like the debug sections
+        // themselves, keep their offset in the section
+        let symbol_value = match &relocation.symbol {
+            Some(symbol) => match get_symbol_address(symbol, &section_addresses) {
+                Some(v) => v,
+                None if symbol.section_index.get_index().is_some() => symbol.address,
+                None => continue,
+            },
+            None => 0,
+        };
+        // REL entries keep the addend in the location being patched
+        let addend = match relocation.addend {
+            Some(v) => v as u64,
+            None => match Reader::new(data, start, &ei_data).get_sized(size as u8) {
+                Ok(v) => v,
+                Err(_) => continue,
+            },
+        };
+        let relocated = symbol_value.wrapping_add(addend);
+        let slot = &mut data[start..start + size];
+        match (size, ei_data) {
+            (4, EI_Data::LittleEndian) => slot.copy_from_slice(&(relocated as u32).to_le_bytes()),
+            (4, EI_Data::BigEndian) => slot.copy_from_slice(&(relocated as u32).to_be_bytes()),
+            (_, EI_Data::LittleEndian) => slot.copy_from_slice(&relocated.to_le_bytes()),
+            (_, EI_Data::BigEndian) => slot.copy_from_slice(&relocated.to_be_bytes()),
+        }
+    }
+}
+
+// Gets a debug section's data, falling back to the legacy .zdebug_* name and then
+// to the separate debug file of a stripped binary. Sections that are missing or fail
+// to decompress are treated as empty.
+fn get_debug_section_data<'a>(elf: &'a ELF, name: &str) -> Cow<'a, [u8]> {
+    let zdebug_name = name.replacen(".debug", ".zdebug", 1);
+    let position = |name: &str| {
+        elf.section_headers
+            .iter()
+            .position(|s| s.name_string == name)
+    };
+    let index = position(name).or_else(|| position(&zdebug_name));
+    match (index, &elf.debug_file) {
+        (Some(index), _) => {
+            let data = elf.section_headers[index]
+                .get_decompressed_data(&elf.data, &elf.elf_header)
+                .unwrap_or_default();
+            if !elf.is_relocatable() {
+                return data;
+            }
+            let mut data = data.into_owned();
+            apply_relocations(elf, index, &mut data);
+            Cow::Owned(data)
+        }
+        (None, Some(debug_file)) => get_debug_section_data(debug_file, name),
+        (None, None) => Cow::Borrowed(&[]),
+    }
+}
+
+pub fn load_dwarf_from_elf(elf: &ELF) -> Result<Dwarf> {
+    let debug_info = get_debug_section_data(elf, ".debug_info");
+    if debug_info.is_empty() {
+        return Err(Error::not_found(".debug_info section"));
+    }
+    let debug_abbrev = get_debug_section_data(elf, ".debug_abbrev");
+    let debug_line = get_debug_section_data(elf, ".debug_line");
+    let debug_str = get_debug_section_data(elf, ".debug_str");
+    let debug_line_str = get_debug_section_data(elf, ".debug_line_str");
+    let debug_str_offsets = get_debug_section_data(elf, ".debug_str_offsets");
+    let debug_addr = get_debug_section_data(elf, ".debug_addr");
+    let debug_ranges = get_debug_section_data(elf, ".debug_ranges");
+    let debug_rnglists = get_debug_section_data(elf, ".debug_rnglists");
+
+    let sections = DwarfSections {
+        ei_data: &elf.elf_header.ident.ei_data,
+        debug_info: &debug_info,
+        debug_abbrev: &debug_abbrev,
+        debug_line: &debug_line,
+        debug_str: &debug_str,
+        debug_line_str: &debug_line_str,
+        debug_str_offsets: &debug_str_offsets,
+        debug_addr: &debug_addr,
+        debug_ranges: &debug_ranges,
+        debug_rnglists: &debug_rnglists,
+    };
+    Dwarf::parse(&sections)
+}
+
+#[cfg(test)]
+mod dwarf_tests {
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    fn push_symbol(raw: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64, size: u64) {
+        raw.extend_from_slice(&name.to_le_bytes());
+        raw.extend_from_slice(&[info, 0]);
+        raw.extend_from_slice(&shndx.to_le_bytes());
+        raw.extend_from_slice(&value.to_le_bytes());
+        raw.extend_from_slice(&size.to_le_bytes());
+    }
+
+    fn push_rela(raw: &mut Vec<u8>, offset: u64, symbol: u64, relocation_type: u64, addend: i64) {
+        raw.extend_from_slice(&offset.to_le_bytes());
+        raw.extend_from_slice(&((symbol << 32) | relocation_type).to_le_bytes());
+        raw.extend_from_slice(&addend.to_le_bytes());
+    }
+
+    #[test]
+    fn can_apply_relocations_in_objects() {
+        // An x86_64 object file for t.c with main at 0x10 in .text. The abbreviation
+        // offset, names and addresses in .debug_info are all left to .rela.debug_info.
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_REL, x86_64, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x10, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, // e_flags to e_phentsize
+            0x00, 0x00, 0x40, 0x00, 0x09, 0x00, 0x08, 0x00, // e_phnum to e_shstrndx
+        ];
+        // .text
+        raw.resize(0x60, 0xc3);
+        // .debug_abbrev
+        raw.extend_from_slice(&[
+            0x01, 0x11, 0x01, // 1: DW_TAG_compile_unit, has children
+            0x03, 0x0e, // DW_AT_name, DW_FORM_strp
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x07, // DW_AT_high_pc, DW_FORM_data8
+            0x00, 0x00, // end of attributes
+            0x02, 0x2e, 0x00, // 2: DW_TAG_subprogram, no children
+            0x03, 0x0e, // DW_AT_name, DW_FORM_strp
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0x00, 0x00, // end of attributes
+            0x00, // end of abbreviations
+        ]);
+        // .debug_info
+        raw.extend_from_slice(&[
+            0x2e, 0x00, 0x00, 0x00, // unit_length
+            0x04, 0x00, // version
+            0x00, 0x00, 0x00, 0x00, // debug_abbrev_offset
+            0x08, // address_size
+            0x01, 0x00, 0x00, 0x00, 0x00, // compile_unit, strp
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // low_pc
+            0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // high_pc +0x20
+            0x02, 0x00, 0x00, 0x00, 0x00, // subprogram, strp
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // low_pc
+            0x10, 0x00, 0x00, 0x00, // high_pc +0x10
+            0x00, // end of children
+        ]);
+        // .rela.debug_info, R_X86_64_32 against the .debug_abbrev and .debug_str
+        // section symbols and R_X86_64_64 against the .text one
+        raw.resize(0xb0, 0);
+        push_rela(&mut raw, 6, 2, 10, 0);
+        push_rela(&mut raw, 12, 3, 10, 0);
+        push_rela(&mut raw, 16, 1, 1, 0);
+        push_rela(&mut raw, 33, 3, 10, 4);
+        push_rela(&mut raw, 37, 1, 1, 0x10);
+        // .debug_str
+        raw.extend_from_slice(b"t.c\0main\0");
+        // .symtab, the section symbols are local
+        raw.resize(0x138, 0);
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 0, 0x03, 1, 0, 0);
+        push_symbol(&mut raw, 0, 0x03, 2, 0, 0);
+        push_symbol(&mut raw, 0, 0x03, 5, 0, 0);
+        push_symbol(&mut raw, 1, 0x12, 1, 0x10, 0x10);
+        raw.extend_from_slice(b"\0main\0");
+        raw.extend_from_slice(
+            b"\0.text\0.debug_abbrev\0.debug_info\0.rela.debug_info\0.debug_str\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x210, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 6, 0, 0x40, 0x20, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [7, 1, 0, 0, 0x60, 0x17, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [21, 1, 0, 0, 0x77, 0x32, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [33, 4, 0x40, 0, 0xb0, 0x78, 6, 3, 8, 24]);
+        push_section_header(&mut raw, [50, 1, 0x30, 0, 0x128, 9, 0, 0, 1, 1]);
+        push_section_header(&mut raw, [61, 2, 0, 0, 0x138, 0x78, 7, 4, 8, 24]);
+        push_section_header(&mut raw, [69, 3, 0, 0, 0x1b0, 6, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [77, 3, 0, 0, 0x1b6, 0x57, 0, 0, 1, 0]);
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+
+        let dwarf = load_dwarf_from_elf(&elf).unwrap();
+        assert_eq!(dwarf.units.len(), 1);
+        assert_eq!(dwarf.units[0].get_name(), Some("t.c"));
+        assert_eq!(dwarf.subprograms.len(), 1);
+        // main is at 0x10 in .text, which the layout puts at 0x1000
+        let main = &dwarf.subprograms[0];
+        assert_eq!(main.name, "main");
+        assert_eq!(main.ranges, vec![(0x1010, 0x1020)]);
+    }
+}
@@ -0,0 +1,229 @@
+use std::convert::TryInto;
+
+use crate::elf::elf_header::EI_Data;
+use crate::error::{Error, Result};
+
+// Cursor over a DWARF section. All reads are bounds checked and return an error
+// rather than panicking as the sections are often truncated or malformed.
+#[derive(Clone)]
+pub struct Reader<'a> {
+    pub data: &'a [u8],
+    pub offset: usize,
+    pub ei_data: &'a EI_Data,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8], offset: usize, ei_data: &'a EI_Data) -> Reader<'a> {
+        Reader {
+            data,
+            offset,
+            ei_data,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    pub fn get_bytes(&mut self, size: usize) -> Result<&'a [u8]> {
+        let end = match self.offset.checked_add(size) {
+            Some(v) => v,
+            None => {
+                return Err(Error::truncated(
+                    self.offset as u64,
+                    size as u64,
+                    "DWARF data",
+                ))
+            }
+        };
+        match self.data.get(self.offset..end) {
+            Some(v) => {
+                self.offset = end;
+                Ok(v)
+            }
+            None => Err(Error::truncated(
+                self.offset as u64,
+                size as u64,
+                "DWARF data",
+            )),
+        }
+    }
+
+    pub fn skip(&mut self, size: usize) -> Result<()> {
+        self.get_bytes(size).map(|_| ())
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8> {
+        Ok(self.get_bytes(1)?[0])
+    }
+
+    pub fn get_i8(&mut self) -> Result<i8> {
+        Ok(self.get_u8()? as i8)
+    }
+
+    pub fn get_u16(&mut self) -> Result<u16> {
+        let raw = self.get_bytes(2)?.try_into().unwrap();
+        Ok(match self.ei_data {
+            EI_Data::LittleEndian => u16::from_le_bytes(raw),
+            EI_Data::BigEndian => u16::from_be_bytes(raw),
+        })
+    }
+
+    pub fn get_u24(&mut self) -> Result<u32> {
+        let raw = self.get_bytes(3)?;
+        Ok(match self.ei_data {
+            EI_Data::LittleEndian => u32::from_le_bytes([raw[0], raw[1], raw[2], 0]),
+            EI_Data::BigEndian => u32::from_be_bytes([0, raw[0], raw[1], raw[2]]),
+        })
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32> {
+        let raw = self.get_bytes(4)?.try_into().unwrap();
+        Ok(match self.ei_data {
+            EI_Data::LittleEndian => u32::from_le_bytes(raw),
+            EI_Data::BigEndian => u32::from_be_bytes(raw),
+        })
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64> {
+        let raw = self.get_bytes(8)?.try_into().unwrap();
+        Ok(match self.ei_data {
+            EI_Data::LittleEndian => u64::from_le_bytes(raw),
+            EI_Data::BigEndian => u64::from_be_bytes(raw),
+        })
+    }
+
+    // Reads an unsigned value of 1, 2, 4 or 8 bytes
+    pub fn get_sized(&mut self, size: u8) -> Result<u64> {
+        match size {
+            1 => Ok(u64::from(self.get_u8()?)),
+            2 => Ok(u64::from(self.get_u16()?)),
+            4 => Ok(u64::from(self.get_u32()?)),
+            8 => self.get_u64(),
+            _ => Err(Error::invalid_value(
+                self.offset as u64,
+                u64::from(size),
+                "DWARF address or offset size",
+            )),
+        }
+    }
+
+    pub fn get_uleb128(&mut self) -> Result<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.get_u8()?;
+            if shift < 64 {
+                result |= u64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+
+    pub fn get_sleb128(&mut self) -> Result<i64> {
+        let mut result: i64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.get_u8()?;
+            if shift < 64 {
+                result |= i64::from(byte & 0x7f) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Ok(result);
+            }
+        }
+    }
+
+    pub fn get_string(&mut self) -> Result<String> {
+        let rest = match self.data.get(self.offset..) {
+            Some(v) => v,
+            None => return Err(Error::truncated(self.offset as u64, 1, "DWARF string")),
+        };
+        match rest.iter().position(|b| *b == 0) {
+            Some(length) => {
+                let string = String::from_utf8_lossy(&rest[..length]).into_owned();
+                self.offset += length + 1;
+                Ok(string)
+            }
+            None => Err(Error::malformed(
+                self.offset as u64,
+                "unterminated DWARF string",
+            )),
+        }
+    }
+
+    // Reads a unit length, returning the length and whether the 64-bit DWARF
+    // format is in use (offsets are then 8 bytes rather than 4)
+    pub fn get_initial_length(&mut self) -> Result<(u64, bool)> {
+        let length = self.get_u32()?;
+        if length == 0xffff_ffff {
+            Ok((self.get_u64()?, true))
+        } else if length >= 0xffff_fff0 {
+            Err(Error::invalid_value(
+                self.offset as u64 - 4,
+                u64::from(length),
+                "DWARF unit length",
+            ))
+        } else {
+            Ok((u64::from(length), false))
+        }
+    }
+
+    pub fn get_offset(&mut self, is_64_bit: bool) -> Result<u64> {
+        if is_64_bit {
+            self.get_u64()
+        } else {
+            Ok(u64::from(self.get_u32()?))
+        }
+    }
+
+    // Returns a reader over the next `length` bytes and moves past them
+    pub fn get_sub_reader(&mut self, length: u64) -> Result<Reader<'a>> {
+        let data = self.get_bytes(length as usize)?;
+        Ok(Reader::new(data, 0, self.ei_data))
+    }
+}
+
+// Reads a null terminated string at an offset into a string section
+pub fn get_string_at(data: &[u8], offset: u64) -> Result<String> {
+    let mut reader = Reader::new(data, offset as usize, &EI_Data::LittleEndian);
+    reader.get_string()
+}
+
+#[cfg(test)]
+mod reader_tests {
+    use super::*;
+
+    #[test]
+    fn can_read_leb128() {
+        let raw = [
+            0xe5, 0x8e, 0x26, // 624485
+            0x7f, // -1 signed
+            0x80, 0x7f, // -128 signed
+            0x02, // 2
+        ];
+        let mut reader = Reader::new(&raw, 0, &EI_Data::LittleEndian);
+        assert_eq!(reader.get_uleb128().unwrap(), 624_485);
+        assert_eq!(reader.get_sleb128().unwrap(), -1);
+        assert_eq!(reader.get_sleb128().unwrap(), -128);
+        assert_eq!(reader.get_sleb128().unwrap(), 2);
+        assert!(reader.get_u8().is_err());
+    }
+
+    #[test]
+    fn can_read_initial_length() {
+        let raw = [
+            0xff, 0xff, 0xff, 0xff, // 64-bit DWARF escape
+            0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // length
+        ];
+        let mut reader = Reader::new(&raw, 0, &EI_Data::LittleEndian);
+        assert_eq!(reader.get_initial_length().unwrap(), (0x10, true));
+    }
+}
@@ -0,0 +1,472 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::abbrev::{parse_abbreviations, Abbreviation};
+use super::constants::*;
+use super::reader::{get_string_at, Reader};
+use super::DwarfSections;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum AttributeValue {
+    Address(u64),
+    AddressIndex(u64), // Index into .debug_addr, resolved to an Address once the unit is read
+    Block(Vec<u8>),
+    Data(u64),
+    SignedData(i64),
+    Flag(bool),
+    String(String),
+    StringIndex(u64), // Index into .debug_str_offsets, resolved to a String once the unit is read
+    Reference(u64),   // Offset of the referenced DIE in .debug_info
+    TypeSignature(u64),
+    SectionOffset(u64),
+    Expression(Vec<u8>),
+    RangeListIndex(u64),
+    LocationListIndex(u64),
+    Unsupported(u64), // References into supplementary object files, holds the form
+}
+
+impl AttributeValue {
+    pub fn get_u64(&self) -> Option<u64> {
+        match self {
+            AttributeValue::Address(v)
+            | AttributeValue::Data(v)
+            | AttributeValue::Reference(v)
+            | AttributeValue::SectionOffset(v) => Some(*v),
+            AttributeValue::SignedData(v) => Some(*v as u64),
+            _ => None,
+        }
+    }
+}
+
+// A debugging information entry, the attributes are kept in the order they appear
+pub struct DebuggingInformationEntry {
+    pub offset: u64, // Offset in .debug_info
+    pub depth: usize,
+    pub tag: DwarfTag,
+    pub attributes: Vec<(DwarfAttribute, AttributeValue)>,
+}
+
+impl DebuggingInformationEntry {
+    pub fn get_attribute(&self, name: DwarfAttribute) -> Option<&AttributeValue> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_string(&self, name: DwarfAttribute) -> Option<&str> {
+        match self.get_attribute(name)? {
+            AttributeValue::String(v) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn get_u64(&self, name: DwarfAttribute) -> Option<u64> {
+        self.get_attribute(name)?.get_u64()
+    }
+
+    pub fn get_low_pc(&self) -> Option<u64> {
+        match self.get_attribute(DwarfAttribute::LowPc)? {
+            AttributeValue::Address(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    // DW_AT_high_pc is an address in DWARF 2 and 3, later versions may instead
+    // encode it as a constant offset from DW_AT_low_pc
+    pub fn get_high_pc(&self) -> Option<u64> {
+        match self.get_attribute(DwarfAttribute::HighPc)? {
+            AttributeValue::Address(v) => Some(*v),
+            AttributeValue::Data(v) => Some(self.get_low_pc()?.wrapping_add(*v)),
+            AttributeValue::SignedData(v) => Some(self.get_low_pc()?.wrapping_add(*v as u64)),
+            _ => None,
+        }
+    }
+}
+
+pub struct CompilationUnit {
+    pub offset: u64, // Offset of the unit header in .debug_info
+    pub version: u16,
+    pub unit_type: u8, // DW_UT_*, DWARF 5 only, earlier units are DW_UT_compile
+    pub address_size: u8,
+    pub is_64_bit: bool, // Uses the 64-bit DWARF format
+    pub abbreviation_offset: u64,
+    pub entries: Vec<DebuggingInformationEntry>,
+}
+
+impl CompilationUnit {
+    pub fn get_root(&self) -> Option<&DebuggingInformationEntry> {
+        self.entries.first()
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.get_root()?.get_string(DwarfAttribute::Name)
+    }
+
+    pub fn get_comp_dir(&self) -> Option<&str> {
+        self.get_root()?.get_string(DwarfAttribute::CompDir)
+    }
+
+    pub fn get_producer(&self) -> Option<&str> {
+        self.get_root()?.get_string(DwarfAttribute::Producer)
+    }
+
+    pub fn get_stmt_list(&self) -> Option<u64> {
+        self.get_root()?.get_u64(DwarfAttribute::StmtList)
+    }
+
+    // Entries are stored in .debug_info order so we can binary search by offset
+    pub fn get_entry_at(&self, offset: u64) -> Option<&DebuggingInformationEntry> {
+        let index = self
+            .entries
+            .binary_search_by_key(&offset, |e| e.offset)
+            .ok()?;
+        self.entries.get(index)
+    }
+
+    pub fn contains_offset(&self, offset: u64) -> bool {
+        match (self.entries.first(), self.entries.last()) {
+            (Some(first), Some(last)) => offset >= first.offset && offset <= last.offset,
+            _ => false,
+        }
+    }
+}
+
+// Parses every unit in .debug_info. A unit whose entries can't be read is kept
+// with the entries read so far, as the unit length still lets us find the next one.
+pub fn parse_units(sections: &DwarfSections) -> Result<Vec<CompilationUnit>> {
+    let mut units: Vec<CompilationUnit> = vec![];
+    let mut abbreviation_cache: HashMap<u64, HashMap<u64, Abbreviation>> = HashMap::new();
+    let mut reader = Reader::new(sections.debug_info, 0, sections.ei_data);
+    while !reader.is_empty() {
+        let offset = reader.offset as u64;
+        let (length, is_64_bit) = reader.get_initial_length()?;
+        let mut unit_reader = reader.get_sub_reader(length)?;
+        // Entry offsets are kept relative to the start of .debug_info
+        let base_offset = offset + if is_64_bit { 12 } else { 4 };
+        let mut unit = parse_unit_header(&mut unit_reader, offset, is_64_bit)?;
+        if unit.unit_type == DW_UT_TYPE || unit.unit_type == DW_UT_SPLIT_TYPE {
+            // Type units are only of interest for type information, skip them
+            continue;
+        }
+
+        if let Entry::Vacant(v) = abbreviation_cache.entry(unit.abbreviation_offset) {
+            let mut abbreviation_reader = Reader::new(
+                sections.debug_abbrev,
+                unit.abbreviation_offset as usize,
+                sections.ei_data,
+            );
+            v.insert(parse_abbreviations(&mut abbreviation_reader)?);
+        }
+        let abbreviations = &abbreviation_cache[&unit.abbreviation_offset];
+        let _ = parse_entries(
+            &mut unit_reader,
+            base_offset,
+            &mut unit,
+            abbreviations,
+            sections,
+        );
+        resolve_indexed_values(&mut unit, sections);
+        units.push(unit);
+    }
+    Ok(units)
+}
+
+fn parse_unit_header(reader: &mut Reader, offset: u64, is_64_bit: bool) -> Result<CompilationUnit> {
+    let version = reader.get_u16()?;
+    if !(2..=5).contains(&version) {
+        return Err(Error::invalid_value(
+            offset,
+            u64::from(version),
+            "DWARF version",
+        ));
+    }
+    let mut unit = CompilationUnit {
+        offset,
+        version,
+        unit_type: DW_UT_COMPILE,
+        address_size: 0,
+        is_64_bit,
+        abbreviation_offset: 0,
+        entries: vec![],
+    };
+    if version >= 5 {
+        unit.unit_type = reader.get_u8()?;
+        unit.address_size = reader.get_u8()?;
+        unit.abbreviation_offset = reader.get_offset(is_64_bit)?;
+        match unit.unit_type {
+            DW_UT_SKELETON | DW_UT_SPLIT_COMPILE => reader.skip(8)?, // dwo_id
+            DW_UT_TYPE | DW_UT_SPLIT_TYPE => {
+                reader.skip(8)?; // type_signature
+                reader.get_offset(is_64_bit)?; // type_offset
+            }
+            _ => {}
+        }
+    } else {
+        unit.abbreviation_offset = reader.get_offset(is_64_bit)?;
+        unit.address_size = reader.get_u8()?;
+    }
+    Ok(unit)
+}
+
+// Reads the tree of entries as a flat list, tracking the depth of each entry
+fn parse_entries(
+    reader: &mut Reader,
+    base_offset: u64,
+    unit: &mut CompilationUnit,
+    abbreviations: &HashMap<u64, Abbreviation>,
+    sections: &DwarfSections,
+) -> Result<()> {
+    let mut depth: usize = 0;
+    while !reader.is_empty() {
+        let offset = base_offset + reader.offset as u64;
+        let code = reader.get_uleb128()?;
+        if code == 0 {
+            // End of a list of siblings
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+            continue;
+        }
+        let abbreviation = match abbreviations.get(&code) {
+            Some(v) => v,
+            None => {
+                return Err(Error::invalid_value(
+                    reader.offset as u64,
+                    code,
+                    "DWARF abbreviation code",
+                ))
+            }
+        };
+        let mut attributes: Vec<(DwarfAttribute, AttributeValue)> = vec![];
+        for specification in &abbreviation.attributes {
+            let value = parse_attribute_value(
+                reader,
+                specification.form,
+                specification.implicit_const,
+                unit,
+                sections,
+            )?;
+            attributes.push((specification.name, value));
+        }
+        unit.entries.push(DebuggingInformationEntry {
+            offset,
+            depth,
+            tag: abbreviation.tag,
+            attributes,
+        });
+        if abbreviation.has_children {
+            depth += 1;
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_attribute_value(
+    reader: &mut Reader,
+    form: u64,
+    implicit_const: i64,
+    unit: &CompilationUnit,
+    sections: &DwarfSections,
+) -> Result<AttributeValue> {
+    let value = match form {
+        DW_FORM_ADDR => AttributeValue::Address(reader.get_sized(unit.address_size)?),
+        DW_FORM_DATA1 => AttributeValue::Data(u64::from(reader.get_u8()?)),
+        DW_FORM_DATA2 => AttributeValue::Data(u64::from(reader.get_u16()?)),
+        DW_FORM_DATA4 => AttributeValue::Data(u64::from(reader.get_u32()?)),
+        DW_FORM_DATA8 => AttributeValue::Data(reader.get_u64()?),
+        DW_FORM_DATA16 => AttributeValue::Block(reader.get_bytes(16)?.to_vec()),
+        DW_FORM_UDATA => AttributeValue::Data(reader.get_uleb128()?),
+        DW_FORM_SDATA => AttributeValue::SignedData(reader.get_sleb128()?),
+        DW_FORM_IMPLICIT_CONST => AttributeValue::SignedData(implicit_const),
+        DW_FORM_FLAG => AttributeValue::Flag(reader.get_u8()? != 0),
+        DW_FORM_FLAG_PRESENT => AttributeValue::Flag(true),
+        DW_FORM_BLOCK1 => {
+            let length = reader.get_u8()? as usize;
+            AttributeValue::Block(reader.get_bytes(length)?.to_vec())
+        }
+        DW_FORM_BLOCK2 => {
+            let length = reader.get_u16()? as usize;
+            AttributeValue::Block(reader.get_bytes(length)?.to_vec())
+        }
+        DW_FORM_BLOCK4 => {
+            let length = reader.get_u32()? as usize;
+            AttributeValue::Block(reader.get_bytes(length)?.to_vec())
+        }
+        DW_FORM_BLOCK => {
+            let length = reader.get_uleb128()? as usize;
+            AttributeValue::Block(reader.get_bytes(length)?.to_vec())
+        }
+        DW_FORM_EXPRLOC => {
+            let length = reader.get_uleb128()? as usize;
+            AttributeValue::Expression(reader.get_bytes(length)?.to_vec())
+        }
+        DW_FORM_STRING => AttributeValue::String(reader.get_string()?),
+        DW_FORM_STRP => {
+            let offset = reader.get_offset(unit.is_64_bit)?;
+            AttributeValue::String(get_string_at(sections.debug_str, offset)?)
+        }
+        DW_FORM_LINE_STRP => {
+            let offset = reader.get_offset(unit.is_64_bit)?;
+            AttributeValue::String(get_string_at(sections.debug_line_str, offset)?)
+        }
+        DW_FORM_STRX | DW_FORM_GNU_STR_INDEX => AttributeValue::StringIndex(reader.get_uleb128()?),
+        DW_FORM_STRX1 => AttributeValue::StringIndex(u64::from(reader.get_u8()?)),
+        DW_FORM_STRX2 => AttributeValue::StringIndex(u64::from(reader.get_u16()?)),
+        DW_FORM_STRX3 => AttributeValue::StringIndex(u64::from(reader.get_u24()?)),
+        DW_FORM_STRX4 => AttributeValue::StringIndex(u64::from(reader.get_u32()?)),
+        DW_FORM_ADDRX | DW_FORM_GNU_ADDR_INDEX => {
+            AttributeValue::AddressIndex(reader.get_uleb128()?)
+        }
+        DW_FORM_ADDRX1 => AttributeValue::AddressIndex(u64::from(reader.get_u8()?)),
+        DW_FORM_ADDRX2 => AttributeValue::AddressIndex(u64::from(reader.get_u16()?)),
+        DW_FORM_ADDRX3 => AttributeValue::AddressIndex(u64::from(reader.get_u24()?)),
+        DW_FORM_ADDRX4 => AttributeValue::AddressIndex(u64::from(reader.get_u32()?)),
+        // Unit relative references are made absolute
+        DW_FORM_REF1 => AttributeValue::Reference(unit.offset + u64::from(reader.get_u8()?)),
+        DW_FORM_REF2 => AttributeValue::Reference(unit.offset + u64::from(reader.get_u16()?)),
+        DW_FORM_REF4 => AttributeValue::Reference(unit.offset + u64::from(reader.get_u32()?)),
+        DW_FORM_REF8 => AttributeValue::Reference(unit.offset + reader.get_u64()?),
+        DW_FORM_REF_UDATA => AttributeValue::Reference(unit.offset + reader.get_uleb128()?),
+        // DWARF 2 used the address size for DW_FORM_ref_addr
+        DW_FORM_REF_ADDR if unit.version == 2 => {
+            AttributeValue::Reference(reader.get_sized(unit.address_size)?)
+        }
+        DW_FORM_REF_ADDR => AttributeValue::Reference(reader.get_offset(unit.is_64_bit)?),
+        DW_FORM_REF_SIG8 => AttributeValue::TypeSignature(reader.get_u64()?),
+        DW_FORM_SEC_OFFSET => AttributeValue::SectionOffset(reader.get_offset(unit.is_64_bit)?),
+        DW_FORM_LOCLISTX => AttributeValue::LocationListIndex(reader.get_uleb128()?),
+        DW_FORM_RNGLISTX => AttributeValue::RangeListIndex(reader.get_uleb128()?),
+        DW_FORM_REF_SUP4 => {
+            reader.skip(4)?;
+            AttributeValue::Unsupported(form)
+        }
+        DW_FORM_REF_SUP8 => {
+            reader.skip(8)?;
+            AttributeValue::Unsupported(form)
+        }
+        DW_FORM_STRP_SUP | DW_FORM_GNU_REF_ALT | DW_FORM_GNU_STRP_ALT => {
+            reader.get_offset(unit.is_64_bit)?;
+            AttributeValue::Unsupported(form)
+        }
+        DW_FORM_INDIRECT => {
+            let form = reader.get_uleb128()?;
+            parse_attribute_value(reader, form, implicit_const, unit, sections)?
+        }
+        _ => {
+            return Err(Error::invalid_value(
+                reader.offset as u64,
+                form,
+                "DWARF attribute form",
+            ))
+        }
+    };
+    Ok(value)
+}
+
+// DW_FORM_strx and DW_FORM_addrx values are indexes into tables whose base is
+// given by attributes on the unit entry, which may come after the attribute using
+// them, so they are resolved once the whole unit is read
+fn resolve_indexed_values(unit: &mut CompilationUnit, sections: &DwarfSections) {
+    let offset_size: u64 = if unit.is_64_bit { 8 } else { 4 };
+    let (string_base, address_base) = match unit.get_root() {
+        Some(root) => (
+            // Without a base the table header of a single unit is assumed
+            root.get_u64(DwarfAttribute::StrOffsetsBase)
+                .unwrap_or(offset_size * 2),
+            root.get_u64(DwarfAttribute::AddrBase)
+                .or_else(|| root.get_u64(DwarfAttribute::GNUAddrBase))
+                .unwrap_or(8),
+        ),
+        None => return,
+    };
+    let address_size = unit.address_size;
+    let is_64_bit = unit.is_64_bit;
+
+    for entry in &mut unit.entries {
+        for (_, value) in &mut entry.attributes {
+            match value {
+                AttributeValue::StringIndex(index) => {
+                    let mut reader = Reader::new(
+                        sections.debug_str_offsets,
+                        (string_base + *index * offset_size) as usize,
+                        sections.ei_data,
+                    );
+                    if let Ok(offset) = reader.get_offset(is_64_bit) {
+                        if let Ok(string) = get_string_at(sections.debug_str, offset) {
+                            *value = AttributeValue::String(string);
+                        }
+                    }
+                }
+                AttributeValue::AddressIndex(index) => {
+                    let mut reader = Reader::new(
+                        sections.debug_addr,
+                        (address_base + *index * u64::from(address_size)) as usize,
+                        sections.ei_data,
+                    );
+                    if let Ok(address) = reader.get_sized(address_size) {
+                        *value = AttributeValue::Address(address);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::super::DwarfSections;
+    use super::*;
+    use crate::elf::elf_header::EI_Data;
+
+    #[test]
+    fn can_parse_dwarf_4_unit() {
+        let abbrev = [
+            0x01, 0x11, 0x01, // 1: DW_TAG_compile_unit, has children
+            0x03, 0x08, // DW_AT_name, DW_FORM_string
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x07, // DW_AT_high_pc, DW_FORM_data8
+            0x00, 0x00, // end of attributes
+            0x02, 0x2e, 0x00, // 2: DW_TAG_subprogram, no children
+            0x03, 0x0e, // DW_AT_name, DW_FORM_strp
+            0x11, 0x01, // DW_AT_low_pc, DW_FORM_addr
+            0x12, 0x06, // DW_AT_high_pc, DW_FORM_data4
+            0x00, 0x00, // end of attributes
+            0x00, // end of abbreviations
+        ];
+        let info = [
+            0x2e, 0x00, 0x00, 0x00, // unit_length
+            0x04, 0x00, // version
+            0x00, 0x00, 0x00, 0x00, // debug_abbrev_offset
+            0x08, // address_size
+            0x01, 0x74, 0x2e, 0x63, 0x00, // compile_unit "t.c"
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // low_pc 0x1000
+            0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // high_pc +0x20
+            0x02, 0x00, 0x00, 0x00, 0x00, // subprogram, strp 0 "main"
+            0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // low_pc 0x1010
+            0x10, 0x00, 0x00, 0x00, // high_pc +0x10
+            0x00, // end of children
+        ];
+        let strings = [0x6d, 0x61, 0x69, 0x6e, 0x00]; // "main"
+        let sections = DwarfSections {
+            debug_info: &info,
+            debug_abbrev: &abbrev,
+            debug_str: &strings,
+            ..DwarfSections::new(&EI_Data::LittleEndian)
+        };
+        let units = parse_units(&sections).unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].get_name(), Some("t.c"));
+        assert_eq!(units[0].entries.len(), 2);
+        let subprogram = &units[0].entries[1];
+        assert_eq!(subprogram.tag, DwarfTag::Subprogram);
+        assert_eq!(subprogram.offset, 0x20);
+        assert_eq!(subprogram.depth, 1);
+        assert_eq!(subprogram.get_string(DwarfAttribute::Name), Some("main"));
+        assert_eq!(subprogram.get_low_pc(), Some(0x1010));
+        assert_eq!(subprogram.get_high_pc(), Some(0x1020));
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::dynamic::{
+    get_dynamic_entry, get_flags, get_flags_1, DynamicFlags, DynamicFlags1, DynamicTag,
+};
+use super::elf_header::E_Type;
+use super::note::{AArch64Features, GnuProperty, NoteDescriptor, X86Features};
+use super::program_header::ProgramHeaderType;
+use super::section::SectionFlags;
+use super::ELF;
+
+// p_flags bits
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+
+// Symbols that are only referenced when the stack protector is compiled in
+const STACK_CHK_SYMBOLS: [&str; 3] = [
+    "__stack_chk_fail",
+    "__stack_chk_guard",
+    "__intel_security_cookie",
+];
+
+// The libc functions that have a __<name>_chk version used by _FORTIFY_SOURCE
+const FORTIFIABLE_FUNCTIONS: [&str; 51] = [
+    "confstr",
+    "dprintf",
+    "explicit_bzero",
+    "fgets",
+    "fgets_unlocked",
+    "fgetws",
+    "fgetws_unlocked",
+    "fprintf",
+    "fread",
+    "fread_unlocked",
+    "fwprintf",
+    "getcwd",
+    "getdomainname",
+    "getgroups",
+    "gethostname",
+    "getlogin_r",
+    "gets",
+    "getwd",
+    "mbsnrtowcs",
+    "mbsrtowcs",
+    "mbstowcs",
+    "memcpy",
+    "memmove",
+    "mempcpy",
+    "memset",
+    "poll",
+    "ppoll",
+    "pread",
+    "pread64",
+    "printf",
+    "read",
+    "readlink",
+    "readlinkat",
+    "realpath",
+    "recv",
+    "recvfrom",
+    "snprintf",
+    "sprintf",
+    "stpcpy",
+    "stpncpy",
+    "strcat",
+    "strcpy",
+    "strncat",
+    "strncpy",
+    "swprintf",
+    "syslog",
+    "ttyname_r",
+    "vfprintf",
+    "vprintf",
+    "vsnprintf",
+    "vsprintf",
+];
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Pie {
+    Disabled,     // A fixed address executable
+    Enabled,      // A position independent executable
+    SharedObject, // A shared library, always position independent
+    Relocatable,  // An object file that hasn't been linked
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Relro {
+    None,
+    Partial, // PT_GNU_RELRO, the GOT used for lazy binding stays writable
+    Full,    // PT_GNU_RELRO with BIND_NOW, the whole GOT is read only
+}
+
+// The hardening a binary was built with, the same checks as the checksec script
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Checksec {
+    pub pie: Pie,
+    pub nx: bool, // Neither the stack nor any segment is writable and executable
+    pub executable_stack: bool, // No PT_GNU_STACK, or one that asks for execute permission
+    pub relro: Relro,
+    pub bind_now: bool,
+    pub stack_canary: bool,
+    pub fortified: Vec<String>, // Functions called through their __*_chk version
+    pub unfortified: Vec<String>, // Functions that have a __*_chk version but aren't using it
+    pub x86_features: X86Features, // CET, from the GNU property note
+    pub aarch64_features: AArch64Features,
+    pub rpath: Vec<String>,
+    pub runpath: Vec<String>,
+}
+
+impl Checksec {
+    pub fn from_elf(elf: &ELF) -> Checksec {
+        let flags = get_flags(&elf.dynamic);
+        let flags_1 = get_flags_1(&elf.dynamic);
+        let has_segment = |header_type: ProgramHeaderType| {
+            elf.program_headers
+                .iter()
+                .any(|p| p.header_type == header_type)
+        };
+
+        let pie = match elf.elf_header.e_type {
+            E_Type::REL => Pie::Relocatable,
+            E_Type::DYN
+                if flags_1.contains(DynamicFlags1::PIE)
+                    || has_segment(ProgramHeaderType::Interp) =>
+            {
+                Pie::Enabled
+            }
+            E_Type::DYN => Pie::SharedObject,
+            _ => Pie::Disabled,
+        };
+
+        let stack = elf
+            .program_headers
+            .iter()
+            .find(|p| p.header_type == ProgramHeaderType::GNU_Stack);
+        // Object files ask for the stack they need with a .note.GNU-stack section,
+        // the linker turns that into PT_GNU_STACK
+        let stack_section = elf
+            .section_headers
+            .iter()
+            .find(|s| s.name_string == ".note.GNU-stack");
+        let executable_stack = match (stack, stack_section) {
+            (Some(v), _) => v.flags & PF_X != 0,
+            (None, Some(v)) if elf.program_headers.is_empty() => {
+                v.flags.contains(&SectionFlags::ExecInstr)
+            }
+            _ => true,
+        };
+        let writable_code = elf.program_headers.iter().any(|p| {
+            p.header_type == ProgramHeaderType::Load && p.flags & (PF_W | PF_X) == PF_W | PF_X
+        });
+
+        let bind_now = get_dynamic_entry(DynamicTag::BindNow, &elf.dynamic).is_some()
+            || flags.contains(DynamicFlags::BIND_NOW)
+            || flags_1.contains(DynamicFlags1::NOW);
+        let relro = if !has_segment(ProgramHeaderType::GNU_RO_AfterRelocation) {
+            Relro::None
+        } else if bind_now {
+            Relro::Full
+        } else {
+            Relro::Partial
+        };
+
+        // Static binaries have the functions in .symtab instead of importing them
+        let names: BTreeSet<&str> = elf
+            .dynamic_symbol_table
+            .iter()
+            .chain(elf.symbol_table.iter())
+            .map(|s| s.name_string.as_str())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let (fortified, unfortified) = get_fortify_status(&names);
+
+        let mut x86_features = X86Features::empty();
+        let mut aarch64_features = AArch64Features::empty();
+        for note in elf.notes() {
+            if let NoteDescriptor::GnuProperties(properties) = note.descriptor {
+                for property in properties {
+                    match property {
+                        GnuProperty::X86Feature1(v) => x86_features |= v,
+                        GnuProperty::AArch64Feature1(v) => aarch64_features |= v,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let get_strings = |tag: DynamicTag| -> Vec<String> {
+            elf.dynamic
+                .iter()
+                .filter(|e| e.tag == tag)
+                .filter_map(|e| e.string_value.clone())
+                .collect()
+        };
+
+        Checksec {
+            pie,
+            nx: !executable_stack && !writable_code,
+            executable_stack,
+            relro,
+            bind_now,
+            stack_canary: STACK_CHK_SYMBOLS.iter().any(|s| names.contains(s)),
+            fortified,
+            unfortified,
+            x86_features,
+            aarch64_features,
+            rpath: get_strings(DynamicTag::RPath),
+            runpath: get_strings(DynamicTag::RunPath),
+        }
+    }
+
+    pub fn has_ibt(&self) -> bool {
+        self.x86_features.contains(X86Features::IBT)
+    }
+
+    pub fn has_shadow_stack(&self) -> bool {
+        self.x86_features.contains(X86Features::SHSTK)
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:20}{:?}", "PIE:", self.pie),
+            format!("{:20}{}", "NX:", self.nx),
+            format!("{:20}{}", "Executable stack:", self.executable_stack),
+            format!("{:20}{:?}", "RELRO:", self.relro),
+            format!("{:20}{}", "BIND_NOW:", self.bind_now),
+            format!("{:20}{}", "Stack canary:", self.stack_canary),
+            format!("{:20}{:?}", "Fortified:", self.fortified),
+            format!("{:20}{:?}", "Unfortified:", self.unfortified),
+            format!("{:20}{:?}", "x86 features:", self.x86_features),
+            format!("{:20}{:?}", "AArch64 features:", self.aarch64_features),
+            format!("{:20}{:?}", "RPATH:", self.rpath),
+            format!("{:20}{:?}", "RUNPATH:", self.runpath),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for Checksec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// Splits the fortifiable functions a binary uses into those called through their
+// checked __<name>_chk version and those called directly
+fn get_fortify_status(names: &BTreeSet<&str>) -> (Vec<String>, Vec<String>) {
+    let mut fortified: Vec<String> = vec![];
+    let mut unfortified: Vec<String> = vec![];
+    for function in FORTIFIABLE_FUNCTIONS.iter() {
+        if names.contains(format!("__{}_chk", function).as_str()) {
+            fortified.push(function.to_string());
+        } else if names.contains(function) {
+            unfortified.push(function.to_string());
+        }
+    }
+    (fortified, unfortified)
+}
+
+#[cfg(test)]
+mod checksec_tests {
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    // A position independent x86_64 executable built with -z relro -z now, IBT and
+    // shadow stacks, a RUNPATH and imports of __stack_chk_fail, __printf_chk and
+    // memcpy
+    fn get_test_binary() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, x86_64, version 1
+            0xc0, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x98, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x07, 0x00, 0x40, 0x00, 0x08, 0x00, 0x07, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment =
+            |header_type: u32, flags: u32, offset: u64, address: u64, size: u64| {
+                push_u32(&mut raw, header_type);
+                push_u32(&mut raw, flags);
+                push_u64(&mut raw, offset);
+                push_u64(&mut raw, address);
+                push_u64(&mut raw, address);
+                push_u64(&mut raw, size);
+                push_u64(&mut raw, size);
+                push_u64(&mut raw, if header_type == 1 { 0x1000 } else { 8 });
+            };
+        // PT_LOAD, PT_LOAD, PT_DYNAMIC, PT_INTERP, PT_NOTE, PT_GNU_STACK and
+        // PT_GNU_RELRO
+        push_segment(1, 5, 0, 0, 0x2d0);
+        push_segment(1, 6, 0x2d0, 0x12d0, 0x80);
+        push_segment(2, 6, 0x2d0, 0x12d0, 0x80);
+        push_segment(3, 4, 0x1c8, 0x1c8, 28);
+        push_segment(4, 4, 0x1e8, 0x1e8, 32);
+        push_segment(0x6474_e551, 6, 0, 0, 0);
+        push_segment(0x6474_e552, 4, 0x2d0, 0x12d0, 0x80);
+
+        // .interp
+        raw.extend_from_slice(b"/lib64/ld-linux-x86-64.so.2\0");
+        // .note.gnu.property with GNU_PROPERTY_X86_FEATURE_1_AND of IBT and SHSTK
+        raw.resize(0x1e8, 0);
+        push_u32(&mut raw, 4);
+        push_u32(&mut raw, 16);
+        push_u32(&mut raw, 5);
+        raw.extend_from_slice(b"GNU\0");
+        push_u32(&mut raw, 0xc000_0002);
+        push_u32(&mut raw, 4);
+        push_u64(&mut raw, 3);
+        // .dynsym, all undefined functions, and .dynstr
+        raw.extend_from_slice(&[0; 24]);
+        for name in [1u32, 6, 13, 26] {
+            push_u32(&mut raw, name);
+            raw.extend_from_slice(&[0x12, 0, 0, 0]);
+            push_u64(&mut raw, 0);
+            push_u64(&mut raw, 0);
+        }
+        raw.extend_from_slice(b"\0puts\0memcpy\0__printf_chk\0__stack_chk_fail\0$ORIGIN/lib\0");
+        // .text
+        raw.resize(0x2c0, 0);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0x2d0, 0xcc);
+        // .dynamic: DT_STRTAB, DT_STRSZ, DT_SYMTAB, DT_SYMENT, DT_RUNPATH, DT_FLAGS
+        // with DF_BIND_NOW, DT_FLAGS_1 with DF_1_NOW and DF_1_PIE, DT_NULL
+        for (tag, value) in [
+            (5, 0x280),
+            (10, 55),
+            (6, 0x208),
+            (11, 24),
+            (29, 43),
+            (30, 8),
+            (0x6fff_fffb, 0x0800_0001),
+            (0, 0),
+        ] {
+            push_u64(&mut raw, tag);
+            push_u64(&mut raw, value);
+        }
+        raw.extend_from_slice(
+            b"\0.interp\0.note.gnu.property\0.dynsym\0.dynstr\0.text\0.dynamic\0.shstrtab\0",
+        );
+
+        raw.resize(0x398, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 2, 0x1c8, 0x1c8, 28, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [9, 7, 2, 0x1e8, 0x1e8, 32, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [28, 11, 2, 0x208, 0x208, 0x78, 4, 1, 8, 24]);
+        push_section_header(&mut raw, [36, 3, 2, 0x280, 0x280, 55, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [44, 1, 6, 0x2c0, 0x2c0, 0x10, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [50, 6, 3, 0x12d0, 0x2d0, 0x80, 4, 0, 8, 16]);
+        push_section_header(&mut raw, [59, 3, 0, 0, 0x350, 69, 0, 0, 1, 0]);
+        raw
+    }
+
+    #[test]
+    fn can_get_fortify_status() {
+        let names: BTreeSet<&str> = [
+            "__printf_chk",
+            "printf",
+            "memcpy",
+            "__stack_chk_fail",
+            "puts",
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        let (fortified, unfortified) = get_fortify_status(&names);
+        assert_eq!(fortified, vec!["printf"]);
+        assert_eq!(unfortified, vec!["memcpy"]);
+    }
+
+    #[test]
+    fn can_check_test_binary() {
+        let elf = load_elf_from_vec(get_test_binary()).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert_eq!(
+            elf.checksec(),
+            Checksec {
+                pie: Pie::Enabled,
+                nx: true,
+                executable_stack: false,
+                relro: Relro::Full,
+                bind_now: true,
+                stack_canary: true,
+                fortified: vec![String::from("printf")],
+                unfortified: vec![String::from("memcpy")],
+                x86_features: X86Features::IBT | X86Features::SHSTK,
+                aarch64_features: AArch64Features::empty(),
+                rpath: vec![],
+                runpath: vec![String::from("$ORIGIN/lib")],
+            }
+        );
+    }
+
+    #[test]
+    fn can_find_executable_stack() {
+        // p_flags of PT_GNU_STACK, the 6th program header, and p_type of PT_GNU_RELRO
+        let mut raw = get_test_binary();
+        raw[0x40 + 5 * 0x38 + 4] = 0x7;
+        raw[0x40 + 6 * 0x38..0x40 + 6 * 0x38 + 4].copy_from_slice(&[0, 0, 0, 0]);
+        let checksec = load_elf_from_vec(raw).unwrap().checksec();
+        assert!(checksec.executable_stack);
+        assert!(!checksec.nx);
+        assert_eq!(checksec.relro, Relro::None);
+        assert!(checksec.bind_now);
+    }
+
+    #[test]
+    fn can_check_object() {
+        // An x86_64 object file with only .note.GNU-stack, which doesn't ask for an
+        // executable stack
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x60, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x40, 0x00, 0x03, 0x00, 0x02, 0x00,
+        ];
+        raw.extend_from_slice(b"\0.note.GNU-stack\0.shstrtab\0");
+        raw.resize(0x60, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 0, 0, 0x40, 0, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [17, 3, 0, 0, 0x40, 27, 0, 0, 1, 0]);
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert_eq!(
+            elf.checksec(),
+            Checksec {
+                pie: Pie::Relocatable,
+                nx: true,
+                executable_stack: false,
+                relro: Relro::None,
+                bind_now: false,
+                stack_canary: false,
+                fortified: vec![],
+                unfortified: vec![],
+                x86_features: X86Features::empty(),
+                aarch64_features: AArch64Features::empty(),
+                rpath: vec![],
+                runpath: vec![],
+            }
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use super::elf_header::{EI_Class, EI_Data, ELFHeader, E_Machine};
+use super::note::{AuxvEntry, AuxvType, FileMapping, NoteDescriptor, PrStatus, SigInfo};
+use super::ELF;
+use crate::binary::AddressSpace;
+
+// Core dumps are ELF files with a PT_LOAD segment for each mapping of the process
+// and notes describing the process and its threads. The kernel only dumps what
+// can't be read back from disk, so segments for code mapped from files usually
+// have no data in the core. NT_FILE says where to find it instead.
+
+// user_regs_struct for i386
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct X86Registers {
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+    pub esi: u32,
+    pub edi: u32,
+    pub ebp: u32,
+    pub eax: u32,
+    pub ds: u32,
+    pub es: u32,
+    pub fs: u32,
+    pub gs: u32,
+    pub orig_eax: u32, // The system call number when stopped in a system call
+    pub eip: u32,
+    pub cs: u32,
+    pub eflags: u32,
+    pub esp: u32,
+    pub ss: u32,
+}
+
+// user_regs_struct for x86-64
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct X86_64Registers {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub orig_rax: u64, // The system call number when stopped in a system call
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+// user_pt_regs for AArch64
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AArch64Registers {
+    pub x: [u64; 31], // x30 is the link register
+    pub sp: u64,
+    pub pc: u64,
+    pub pstate: u64,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Registers {
+    X86(X86Registers),
+    X86_64(X86_64Registers),
+    AArch64(AArch64Registers),
+    Unknown(Vec<u8>), // The raw pr_reg for other machines
+}
+
+impl Registers {
+    // Decodes pr_reg from NT_PRSTATUS for the machine in the header
+    pub fn parse_from_buffer(raw: &[u8], header: &ELFHeader) -> Registers {
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+        let words_32: Vec<u32> = raw
+            .chunks_exact(4)
+            .map(|v| u32_from_bytes(v.try_into().unwrap()))
+            .collect();
+        let words_64: Vec<u64> = raw
+            .chunks_exact(8)
+            .map(|v| u64_from_bytes(v.try_into().unwrap()))
+            .collect();
+
+        match (&header.e_machine, header.ident.ei_class) {
+            (E_Machine::X86, EI_Class::ELF32) if words_32.len() >= 17 => {
+                let r = &words_32;
+                Registers::X86(X86Registers {
+                    ebx: r[0],
+                    ecx: r[1],
+                    edx: r[2],
+                    esi: r[3],
+                    edi: r[4],
+                    ebp: r[5],
+                    eax: r[6],
+                    ds: r[7],
+                    es: r[8],
+                    fs: r[9],
+                    gs: r[10],
+                    orig_eax: r[11],
+                    eip: r[12],
+                    cs: r[13],
+                    eflags: r[14],
+                    esp: r[15],
+                    ss: r[16],
+                })
+            }
+            (E_Machine::X86_64, EI_Class::ELF64) if words_64.len() >= 27 => {
+                let r = &words_64;
+                Registers::X86_64(X86_64Registers {
+                    r15: r[0],
+                    r14: r[1],
+                    r13: r[2],
+                    r12: r[3],
+                    rbp: r[4],
+                    rbx: r[5],
+                    r11: r[6],
+                    r10: r[7],
+                    r9: r[8],
+                    r8: r[9],
+                    rax: r[10],
+                    rcx: r[11],
+                    rdx: r[12],
+                    rsi: r[13],
+                    rdi: r[14],
+                    orig_rax: r[15],
+                    rip: r[16],
+                    cs: r[17],
+                    eflags: r[18],
+                    rsp: r[19],
+                    ss: r[20],
+                    fs_base: r[21],
+                    gs_base: r[22],
+                    ds: r[23],
+                    es: r[24],
+                    fs: r[25],
+                    gs: r[26],
+                })
+            }
+            (E_Machine::ARM_AARCH64, EI_Class::ELF64) if words_64.len() >= 34 => {
+                Registers::AArch64(AArch64Registers {
+                    x: words_64[0..31].try_into().unwrap(),
+                    sp: words_64[31],
+                    pc: words_64[32],
+                    pstate: words_64[33],
+                })
+            }
+            _ => Registers::Unknown(raw.to_vec()),
+        }
+    }
+
+    pub fn get_program_counter(&self) -> Option<u64> {
+        match self {
+            Registers::X86(r) => Some(u64::from(r.eip)),
+            Registers::X86_64(r) => Some(r.rip),
+            Registers::AArch64(r) => Some(r.pc),
+            Registers::Unknown(_) => None,
+        }
+    }
+
+    pub fn get_stack_pointer(&self) -> Option<u64> {
+        match self {
+            Registers::X86(r) => Some(u64::from(r.esp)),
+            Registers::X86_64(r) => Some(r.rsp),
+            Registers::AArch64(r) => Some(r.sp),
+            Registers::Unknown(_) => None,
+        }
+    }
+
+    // Each register with its name, in the order they are stored
+    pub fn get_values(&self) -> Vec<(String, u64)> {
+        let named = |names: &[&str], values: &[u64]| -> Vec<(String, u64)> {
+            names
+                .iter()
+                .map(|v| v.to_string())
+                .zip(values.iter().cloned())
+                .collect()
+        };
+        match self {
+            Registers::X86(r) => named(
+                &[
+                    "ebx", "ecx", "edx", "esi", "edi", "ebp", "eax", "ds", "es", "fs", "gs",
+                    "orig_eax", "eip", "cs", "eflags", "esp", "ss",
+                ],
+                &[
+                    r.ebx, r.ecx, r.edx, r.esi, r.edi, r.ebp, r.eax, r.ds, r.es, r.fs, r.gs,
+                    r.orig_eax, r.eip, r.cs, r.eflags, r.esp, r.ss,
+                ]
+                .iter()
+                .map(|v| u64::from(*v))
+                .collect::<Vec<u64>>(),
+            ),
+            Registers::X86_64(r) => named(
+                &[
+                    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax",
+                    "rcx", "rdx", "rsi", "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss",
+                    "fs_base", "gs_base", "ds", "es", "fs", "gs",
+                ],
+                &[
+                    r.r15, r.r14, r.r13, r.r12, r.rbp, r.rbx, r.r11, r.r10, r.r9, r.r8, r.rax,
+                    r.rcx, r.rdx, r.rsi, r.rdi, r.orig_rax, r.rip, r.cs, r.eflags, r.rsp, r.ss,
+                    r.fs_base, r.gs_base, r.ds, r.es, r.fs, r.gs,
+                ],
+            ),
+            Registers::AArch64(r) => {
+                let mut values: Vec<(String, u64)> =
+                    r.x.iter()
+                        .enumerate()
+                        .map(|(i, v)| (format!("x{}", i), *v))
+                        .collect();
+                values.extend(named(&["sp", "pc", "pstate"], &[r.sp, r.pc, r.pstate]));
+                values
+            }
+            Registers::Unknown(_) => vec![],
+        }
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Registers::Unknown(raw) = self {
+            return writeln!(f, "{:15}{} bytes", "Unknown:", raw.len());
+        }
+        let strings: Vec<String> = self
+            .get_values()
+            .iter()
+            .map(|(name, value)| format!("{:15}{:#018x}", format!("{}:", name), value))
+            .collect();
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+pub struct Thread {
+    pub status: PrStatus, // status.pid is the thread id
+    pub registers: Registers,
+}
+
+pub struct Core {
+    pub threads: Vec<Thread>, // The thread that caused the dump comes first
+    pub mappings: Vec<FileMapping>,
+    pub auxv: Vec<AuxvEntry>,
+    pub siginfo: Option<SigInfo>,
+}
+
+impl Core {
+    pub fn from_elf(elf: &ELF) -> Core {
+        let mut core = Core {
+            threads: vec![],
+            mappings: vec![],
+            auxv: vec![],
+            siginfo: None,
+        };
+        for note in elf.notes() {
+            match note.descriptor {
+                NoteDescriptor::PrStatus(status) => {
+                    let registers =
+                        Registers::parse_from_buffer(&status.registers, &elf.elf_header);
+                    core.threads.push(Thread { status, registers });
+                }
+                NoteDescriptor::File(v) => core.mappings.extend(v),
+                NoteDescriptor::Auxv(v) => core.auxv.extend(v),
+                // There is one for the process, taken from the thread that caused the dump
+                NoteDescriptor::SigInfo(v) if core.siginfo.is_none() => core.siginfo = Some(v),
+                _ => {}
+            }
+        }
+        core
+    }
+
+    pub fn get_crashing_thread(&self) -> Option<&Thread> {
+        self.threads.first()
+    }
+
+    // The program counter of the thread that caused the dump
+    pub fn get_crash_address(&self) -> Option<u64> {
+        self.get_crashing_thread()?.registers.get_program_counter()
+    }
+
+    pub fn get_auxv_value(&self, key: AuxvType) -> Option<u64> {
+        self.auxv.iter().find(|v| v.key == key).map(|v| v.value)
+    }
+
+    pub fn get_mapping_for_address(&self, address: u64) -> Option<&FileMapping> {
+        self.mappings
+            .iter()
+            .find(|m| address >= m.start && address < m.end)
+    }
+
+    // The mapped file the program was started from, found through its entry point
+    pub fn get_executable_path(&self) -> Option<&str> {
+        let entry = self.get_auxv_value(AuxvType::AT_ENTRY)?;
+        self.get_mapping_for_address(entry).map(|m| m.path.as_str())
+    }
+
+    // Reads memory of the process. Data that wasn't dumped is read from the file
+    // it was mapped from, which has to be the same version that was mapped when
+    // the core was written.
+    pub fn read(&self, space: &AddressSpace, address: u64, size: u64) -> Option<Vec<u8>> {
+        if let Some(v) = space.get_file_data(address) {
+            if v.len() as u64 >= size {
+                return Some(v[..size as usize].to_vec());
+            }
+        }
+        let mapping = self.get_mapping_for_address(address)?;
+        if address.checked_add(size)? > mapping.end {
+            return None;
+        }
+        let mut file = File::open(&mapping.path).ok()?;
+        file.seek(SeekFrom::Start(
+            mapping.file_offset + (address - mapping.start),
+        ))
+        .ok()?;
+        let mut data = vec![0u8; size as usize];
+        file.read_exact(&mut data).ok()?;
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod core_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_header(class: u8, machine: u8) -> ELFHeader {
+        let mut raw = vec![
+            0x7F, 0x45, 0x4C, 0x46, class, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x04, 0x00, machine, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ];
+        raw.resize(64, 0);
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(&raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_parse_x86_64_registers() {
+        let raw: Vec<u8> = (0..27u64).flat_map(|v| (v * 0x10).to_le_bytes()).collect();
+        let registers = Registers::parse_from_buffer(&raw, &get_header(2, 0x3e));
+        match &registers {
+            Registers::X86_64(r) => {
+                assert_eq!(r.r15, 0);
+                assert_eq!(r.rax, 0xa0);
+                assert_eq!(r.gs, 0x1a0);
+            }
+            _ => panic!("expected x86-64 registers"),
+        }
+        assert_eq!(registers.get_program_counter(), Some(0x100));
+        assert_eq!(registers.get_stack_pointer(), Some(0x130));
+        assert_eq!(registers.get_values()[16], ("rip".to_string(), 0x100));
+    }
+
+    #[test]
+    fn can_parse_x86_and_aarch64_registers() {
+        let raw: Vec<u8> = (0..17u32).flat_map(|v| v.to_le_bytes()).collect();
+        let registers = Registers::parse_from_buffer(&raw, &get_header(1, 0x03));
+        assert_eq!(registers.get_program_counter(), Some(12));
+        assert_eq!(registers.get_stack_pointer(), Some(15));
+        assert_eq!(registers.get_values().len(), 17);
+
+        let raw: Vec<u8> = (0..34u64).flat_map(|v| v.to_le_bytes()).collect();
+        let registers = Registers::parse_from_buffer(&raw, &get_header(2, 0xb7));
+        match &registers {
+            Registers::AArch64(r) => assert_eq!(r.x[30], 30),
+            _ => panic!("expected AArch64 registers"),
+        }
+        assert_eq!(registers.get_program_counter(), Some(32));
+        assert_eq!(registers.get_stack_pointer(), Some(31));
+        assert_eq!(registers.get_values()[0], ("x0".to_string(), 0));
+
+        // Too short for the machine
+        assert_eq!(
+            Registers::parse_from_buffer(&raw[..16], &get_header(2, 0xb7)),
+            Registers::Unknown(raw[..16].to_vec())
+        );
+    }
+}
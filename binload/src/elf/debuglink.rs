@@ -0,0 +1,350 @@
+use std::collections::HashSet;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+use super::elf_header::{EI_Data, ELFHeader};
+use super::note::get_build_id_string;
+use super::section::get_section_by_name;
+use super::symbol::Symbol;
+use super::utils::get_null_terminated_string_from_vec;
+use super::{load_elf_from_vec, ELF};
+use crate::error::{get_bytes, Error, Result};
+
+// Where distributions install the debug files for the binaries they strip
+pub const DEFAULT_DEBUG_DIRECTORY: &str = "/usr/lib/debug";
+
+// The contents of .gnu_debuglink, the name of the separate debug file and the CRC32
+// of its contents. The name is padded to a multiple of 4 bytes before the CRC.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DebugLink {
+    pub file_name: String,
+    pub crc: u32,
+}
+
+impl DebugLink {
+    pub fn parse_from_buffer(raw: &[u8], header: &ELFHeader) -> Result<DebugLink> {
+        let name_end = match raw.iter().position(|b| *b == 0) {
+            Some(0) => return Err(Error::malformed(0, ".gnu_debuglink has an empty file name")),
+            Some(v) => v,
+            None => return Err(Error::truncated(0, raw.len() as u64 + 1, "debug file name")),
+        };
+        // The CRC is after the name and its terminator, aligned to 4 bytes
+        let crc_offset = (name_end as u64 + 1 + 3) & !3;
+        let crc_raw = get_bytes(raw, crc_offset, 4, || String::from("debug file CRC"))?;
+
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        Ok(DebugLink {
+            file_name: get_null_terminated_string_from_vec(raw, 0),
+            crc: u32_from_bytes(crc_raw.try_into().unwrap()),
+        })
+    }
+}
+
+// The CRC .gnu_debuglink uses is the same CRC32 as zlib
+pub fn get_debug_link_crc(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+pub fn get_debug_link(elf: &ELF) -> Option<DebugLink> {
+    let section = get_section_by_name(".gnu_debuglink", &elf.section_headers)?;
+    DebugLink::parse_from_buffer(section.get_data(&elf.data), &elf.elf_header).ok()
+}
+
+// <directory>/.build-id/ab/cdef....debug, the first byte of the build ID names the
+// subdirectory
+pub fn get_build_id_path(directory: &Path, build_id: &[u8]) -> Option<PathBuf> {
+    if build_id.len() < 2 {
+        return None;
+    }
+    Some(
+        directory
+            .join(".build-id")
+            .join(get_build_id_string(&build_id[..1]))
+            .join(format!("{}.debug", get_build_id_string(&build_id[1..]))),
+    )
+}
+
+// The places gdb looks for a .gnu_debuglink file, next to the binary, in a .debug
+// directory next to it, then under each debug directory at the binary's own path
+// (e.g. /usr/lib/debug/usr/bin/ls.debug)
+pub fn get_debug_link_paths(
+    path: &Path,
+    file_name: &str,
+    debug_directories: &[PathBuf],
+) -> Vec<PathBuf> {
+    let directory = match path.canonicalize() {
+        Ok(v) => v.parent().map(Path::to_path_buf),
+        Err(_) => path.parent().map(Path::to_path_buf),
+    }
+    .unwrap_or_default();
+    let mut paths = vec![
+        directory.join(file_name),
+        directory.join(".debug").join(file_name),
+    ];
+    let relative = directory.strip_prefix("/").unwrap_or(&directory);
+    for debug_directory in debug_directories {
+        paths.push(debug_directory.join(relative).join(file_name));
+    }
+    paths
+}
+
+// Looks for the debug file by build ID first, since that can't pick up a file from a
+// different build, then through .gnu_debuglink. The debuglink search needs the path
+// the binary was loaded from. Candidates that can't be read or have the wrong build
+// ID or CRC are skipped.
+pub fn find_debug_file(
+    elf: &ELF,
+    path: Option<&Path>,
+    debug_directories: &[PathBuf],
+) -> Result<(PathBuf, ELF)> {
+    let own_path = path.and_then(|p| p.canonicalize().ok());
+    let is_own_file =
+        |candidate: &Path| own_path.is_some() && candidate.canonicalize().ok() == own_path;
+
+    if let Some(build_id) = elf.build_id() {
+        for directory in debug_directories {
+            let candidate = match get_build_id_path(directory, &build_id) {
+                Some(v) => v,
+                None => break,
+            };
+            if !candidate.is_file() || is_own_file(&candidate) {
+                continue;
+            }
+            match std::fs::read(&candidate).map(load_elf_from_vec) {
+                Ok(Ok(v)) if v.build_id().as_ref() == Some(&build_id) => return Ok((candidate, v)),
+                _ => continue,
+            }
+        }
+    }
+
+    if let (Some(link), Some(path)) = (get_debug_link(elf), path) {
+        for candidate in get_debug_link_paths(path, &link.file_name, debug_directories) {
+            if !candidate.is_file() || is_own_file(&candidate) {
+                continue;
+            }
+            let data = match std::fs::read(&candidate) {
+                Ok(v) if get_debug_link_crc(&v) == link.crc => v,
+                _ => continue,
+            };
+            if let Ok(v) = load_elf_from_vec(data) {
+                return Ok((candidate, v));
+            }
+        }
+    }
+
+    Err(Error::not_found("separate debug file"))
+}
+
+// The debug file's .symtab, without the symbols the binary already has. Debug files
+// are the binary with the contents of the loaded sections removed, so the symbols
+// have the same addresses.
+pub fn get_missing_symbols(symbols: &[Symbol], debug_file: &ELF) -> Vec<Symbol> {
+    let known: HashSet<(&str, u64)> = symbols
+        .iter()
+        .map(|s| (s.name_string.as_str(), s.address))
+        .collect();
+    debug_file
+        .symbol_table
+        .iter()
+        .filter(|s| !known.contains(&(s.name_string.as_str(), s.address)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod debuglink_tests {
+    use super::super::section::SectionType;
+    use super::super::ELFWriter;
+    use super::*;
+
+    const BUILD_ID: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    // A position independent x86_64 executable with a build ID note and a .symtab
+    // with main
+    fn get_test_binary() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, x86_64, version 1
+            0xd0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x70, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x02, 0x00, 0x40, 0x00, 0x06, 0x00, 0x05, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment = |header_type: u32, offset: u64, size: u64, align: u64| {
+            push_u32(&mut raw, header_type);
+            push_u32(&mut raw, if header_type == 1 { 5 } else { 4 });
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, align);
+        };
+        // PT_LOAD for everything up to .symtab and PT_NOTE
+        push_segment(1, 0, 0xe0, 0x1000);
+        push_segment(4, 0xb0, 24, 4);
+
+        // .note.gnu.build-id
+        push_u32(&mut raw, 4);
+        push_u32(&mut raw, 8);
+        push_u32(&mut raw, 3);
+        raw.extend_from_slice(b"GNU\0");
+        raw.extend_from_slice(&BUILD_ID);
+        // .text
+        raw.resize(0xd0, 0);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0xe0, 0xcc);
+        // .symtab and .strtab
+        raw.extend_from_slice(&[0; 24]);
+        raw.extend_from_slice(&[1, 0, 0, 0, 0x04, 0, 0xf1, 0xff]);
+        raw.extend_from_slice(&[0; 16]);
+        raw.extend_from_slice(&[8, 0, 0, 0, 0x12, 0, 2, 0]);
+        push_u64(&mut raw, 0xd0);
+        push_u64(&mut raw, 5);
+        raw.extend_from_slice(b"\0test.c\0main\0");
+        raw.extend_from_slice(b"\0.note.gnu.build-id\0.text\0.symtab\0.strtab\0.shstrtab\0");
+
+        raw.resize(0x170, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 7, 2, 0xb0, 0xb0, 24, 0, 0, 4, 0]);
+        push_section_header(&mut raw, [20, 1, 6, 0xd0, 0xd0, 0x10, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [26, 2, 0, 0, 0xe0, 0x48, 4, 2, 8, 24]);
+        push_section_header(&mut raw, [34, 3, 0, 0, 0x128, 13, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [42, 3, 0, 0, 0x135, 52, 0, 0, 1, 0]);
+        raw
+    }
+
+    fn get_temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("binload-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    // The test binary, written out so it can be found by path
+    fn write_test_binary(directory: &Path) -> (PathBuf, ELF) {
+        let path = directory.join("test");
+        std::fs::write(&path, get_test_binary()).unwrap();
+        (path, load_elf_from_vec(get_test_binary()).unwrap())
+    }
+
+    #[test]
+    fn can_parse_debug_link() {
+        let elf = load_elf_from_vec(get_test_binary()).unwrap();
+        let mut raw = b"ls.debug\0\0\0\0".to_vec();
+        raw.extend_from_slice(&[0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(
+            DebugLink::parse_from_buffer(&raw, &elf.elf_header).unwrap(),
+            DebugLink {
+                file_name: String::from("ls.debug"),
+                crc: 0x1234_5678,
+            }
+        );
+        assert!(DebugLink::parse_from_buffer(&raw[..10], &elf.elf_header).is_err());
+        assert!(DebugLink::parse_from_buffer(b"ls.debug", &elf.elf_header).is_err());
+
+        assert_eq!(get_debug_link_crc(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            get_build_id_path(Path::new("/usr/lib/debug"), &[0xab, 0xcd, 0xef]),
+            Some(PathBuf::from("/usr/lib/debug/.build-id/ab/cdef.debug"))
+        );
+        assert_eq!(get_build_id_path(Path::new("/"), &[0xab]), None);
+        assert_eq!(
+            get_debug_link_paths(
+                Path::new("/no/such/bin/ls"),
+                "ls.debug",
+                &[PathBuf::from("/usr/lib/debug")]
+            ),
+            vec![
+                PathBuf::from("/no/such/bin/ls.debug"),
+                PathBuf::from("/no/such/bin/.debug/ls.debug"),
+                PathBuf::from("/usr/lib/debug/no/such/bin/ls.debug"),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_find_debug_file_by_build_id() {
+        let directory = get_temp_directory("build-id");
+        let (path, elf) = write_test_binary(&directory);
+        assert_eq!(elf.build_id(), Some(BUILD_ID.to_vec()));
+        let debug_directories = vec![directory.clone()];
+        let debug_path = directory.join(".build-id/01/23456789abcdef.debug");
+        assert_eq!(
+            get_build_id_path(&directory, &BUILD_ID),
+            Some(debug_path.clone())
+        );
+        std::fs::create_dir_all(debug_path.parent().unwrap()).unwrap();
+        std::fs::copy(&path, &debug_path).unwrap();
+
+        // Stands in for the stripped binary
+        let mut stripped = load_elf_from_vec(elf.data.clone()).unwrap();
+        stripped.symbol_table.clear();
+        let found = stripped.load_debug_file(None, &debug_directories).unwrap();
+        assert_eq!(found, debug_path);
+        assert_eq!(stripped.symbol_table.len(), 3);
+        assert!(stripped.debug_file.is_some());
+
+        // Anything that isn't the debug file is skipped
+        std::fs::write(&debug_path, b"not an ELF file").unwrap();
+        let mut stripped = load_elf_from_vec(elf.data.clone()).unwrap();
+        assert!(stripped.load_debug_file(None, &debug_directories).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn can_find_debug_file_by_debug_link() {
+        let directory = get_temp_directory("debuglink");
+        let (path, elf) = write_test_binary(&directory);
+        let debug_data = std::fs::read(&path).unwrap();
+        std::fs::write(directory.join("test.debug"), &debug_data).unwrap();
+
+        let mut link = b"test.debug\0\0".to_vec();
+        link.extend_from_slice(&get_debug_link_crc(&debug_data).to_le_bytes());
+        let mut writer = ELFWriter::new(&elf);
+        writer
+            .add_section(".gnu_debuglink", SectionType::ProgBits, 0, 0, 4, link)
+            .unwrap();
+        let binary_path = directory.join("linked");
+        writer.write_to_file(&binary_path).unwrap();
+
+        let mut stripped = load_elf_from_vec(std::fs::read(&binary_path).unwrap()).unwrap();
+        stripped.symbol_table.clear();
+        assert_eq!(
+            get_debug_link(&stripped).unwrap().file_name,
+            String::from("test.debug")
+        );
+        // The debug file has to be looked for next to the binary
+        assert!(stripped.load_debug_file(None, &[]).is_err());
+        let found = stripped.load_debug_file(Some(&binary_path), &[]).unwrap();
+        assert_eq!(
+            found.canonicalize().unwrap(),
+            directory.join("test.debug").canonicalize().unwrap()
+        );
+        assert_eq!(stripped.symbol_table.len(), 3);
+
+        // Or with the wrong CRC
+        std::fs::write(directory.join("test.debug"), b"changed").unwrap();
+        let mut stripped = load_elf_from_vec(std::fs::read(&binary_path).unwrap()).unwrap();
+        assert!(stripped.load_debug_file(Some(&binary_path), &[]).is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
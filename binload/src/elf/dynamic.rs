@@ -0,0 +1,620 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use bitflags::bitflags;
+
+use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::program_header::{get_data_for_address, ProgramHeader, ProgramHeaderType};
+use super::section::{SectionHeader, SectionType};
+use super::utils::get_null_terminated_string_from_vec;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DynamicTag {
+    Null,             // 0 Marks end of dynamic section
+    Needed,           // 1 Name of needed library
+    PltRelSize,       // 2 Size in bytes of PLT relocs
+    PltGot,           // 3 Processor defined value
+    Hash,             // 4 Address of symbol hash table
+    StrTab,           // 5 Address of string table
+    SymTab,           // 6 Address of symbol table
+    Rela,             // 7 Address of Rela relocs
+    RelaSize,         // 8 Total size of Rela relocs
+    RelaEnt,          // 9 Size of one Rela reloc
+    StrSize,          // 10 Size of string table
+    SymEnt,           // 11 Size of one symbol table entry
+    Init,             // 12 Address of init function
+    Fini,             // 13 Address of termination function
+    SOName,           // 14 Name of shared object
+    RPath,            // 15 Library search path (deprecated)
+    Symbolic,         // 16 Start symbol search here
+    Rel,              // 17 Address of Rel relocs
+    RelSize,          // 18 Total size of Rel relocs
+    RelEnt,           // 19 Size of one Rel reloc
+    PltRel,           // 20 Type of reloc in PLT
+    Debug,            // 21 For debugging; unspecified
+    TextRel,          // 22 Reloc might modify .text
+    JmpRel,           // 23 Address of PLT relocs
+    BindNow,          // 24 Process relocations of object
+    InitArray,        // 25 Array with addresses of init fct
+    FiniArray,        // 26 Array with addresses of fini fct
+    InitArraySize,    // 27 Size in bytes of DT_INIT_ARRAY
+    FiniArraySize,    // 28 Size in bytes of DT_FINI_ARRAY
+    RunPath,          // 29 Library search path
+    Flags,            // 30 Flags for the object being loaded
+    PreinitArray,     // 32 Array with addresses of preinit fct
+    PreinitArraySize, // 33 Size in bytes of DT_PREINIT_ARRAY
+    SymTabShNdx,      // 34 Address of SYMTAB_SHNDX section
+    RelrSize,         // 35 Total size of RELR relative relocations
+    Relr,             // 36 Address of RELR relative relocations
+    RelrEnt,          // 37 Size of one RELR relative relocaction
+    GNUPrelinked,     // 0x6ffffdf5 Prelinking timestamp
+    GNUConflictSize,  // 0x6ffffdf6 Size of conflict section
+    GNULibListSize,   // 0x6ffffdf7 Size of library list
+    Checksum,         // 0x6ffffdf8
+    PltPadSize,       // 0x6ffffdf9
+    MoveEnt,          // 0x6ffffdfa
+    MoveSize,         // 0x6ffffdfb
+    Feature1,         // 0x6ffffdfc Feature selection (DTF_*)
+    PosFlag1,         // 0x6ffffdfd Flags for DT_* entries, effecting the following DT_* entry
+    SymInfoSize,      // 0x6ffffdfe Size of syminfo table (in bytes)
+    SymInfoEnt,       // 0x6ffffdff Entry size of syminfo
+    GNUHash,          // 0x6ffffef5 GNU-style hash table
+    TLSDescPlt,       // 0x6ffffef6
+    TLSDescGot,       // 0x6ffffef7
+    GNUConflict,      // 0x6ffffef8 Start of conflict section
+    GNULibList,       // 0x6ffffef9 Library list
+    Config,           // 0x6ffffefa Configuration information
+    DepAudit,         // 0x6ffffefb Dependency auditing
+    Audit,            // 0x6ffffefc Object auditing
+    PltPad,           // 0x6ffffefd PLT padding
+    MoveTab,          // 0x6ffffefe Move table
+    SymInfo,          // 0x6ffffeff Syminfo table
+    VerSym,           // 0x6ffffff0 Version symbol table (.gnu.version)
+    RelaCount,        // 0x6ffffff9 Number of relative Rela relocs
+    RelCount,         // 0x6ffffffa Number of relative Rel relocs
+    Flags1,           // 0x6ffffffb State flags, see DF_1_* below
+    VerDef,           // 0x6ffffffc Address of version definition table
+    VerDefNum,        // 0x6ffffffd Number of version definitions
+    VerNeed,          // 0x6ffffffe Address of table with needed versions
+    VerNeedNum,       // 0x6fffffff Number of needed versions
+    Auxiliary,        // 0x7ffffffd Shared object to load before self
+    Filter,           // 0x7fffffff Shared object to get values from
+    OS,               // 0x6000000d-0x6ffff000 OS specific
+    Proc,             // 0x70000000-0x7fffffff Processor specific
+    Unknown,
+}
+impl DynamicTag {
+    pub fn from_u64(value: u64) -> DynamicTag {
+        match value {
+            0 => DynamicTag::Null,
+            1 => DynamicTag::Needed,
+            2 => DynamicTag::PltRelSize,
+            3 => DynamicTag::PltGot,
+            4 => DynamicTag::Hash,
+            5 => DynamicTag::StrTab,
+            6 => DynamicTag::SymTab,
+            7 => DynamicTag::Rela,
+            8 => DynamicTag::RelaSize,
+            9 => DynamicTag::RelaEnt,
+            10 => DynamicTag::StrSize,
+            11 => DynamicTag::SymEnt,
+            12 => DynamicTag::Init,
+            13 => DynamicTag::Fini,
+            14 => DynamicTag::SOName,
+            15 => DynamicTag::RPath,
+            16 => DynamicTag::Symbolic,
+            17 => DynamicTag::Rel,
+            18 => DynamicTag::RelSize,
+            19 => DynamicTag::RelEnt,
+            20 => DynamicTag::PltRel,
+            21 => DynamicTag::Debug,
+            22 => DynamicTag::TextRel,
+            23 => DynamicTag::JmpRel,
+            24 => DynamicTag::BindNow,
+            25 => DynamicTag::InitArray,
+            26 => DynamicTag::FiniArray,
+            27 => DynamicTag::InitArraySize,
+            28 => DynamicTag::FiniArraySize,
+            29 => DynamicTag::RunPath,
+            30 => DynamicTag::Flags,
+            32 => DynamicTag::PreinitArray,
+            33 => DynamicTag::PreinitArraySize,
+            34 => DynamicTag::SymTabShNdx,
+            35 => DynamicTag::RelrSize,
+            36 => DynamicTag::Relr,
+            37 => DynamicTag::RelrEnt,
+            0x6fff_fdf5 => DynamicTag::GNUPrelinked,
+            0x6fff_fdf6 => DynamicTag::GNUConflictSize,
+            0x6fff_fdf7 => DynamicTag::GNULibListSize,
+            0x6fff_fdf8 => DynamicTag::Checksum,
+            0x6fff_fdf9 => DynamicTag::PltPadSize,
+            0x6fff_fdfa => DynamicTag::MoveEnt,
+            0x6fff_fdfb => DynamicTag::MoveSize,
+            0x6fff_fdfc => DynamicTag::Feature1,
+            0x6fff_fdfd => DynamicTag::PosFlag1,
+            0x6fff_fdfe => DynamicTag::SymInfoSize,
+            0x6fff_fdff => DynamicTag::SymInfoEnt,
+            0x6fff_fef5 => DynamicTag::GNUHash,
+            0x6fff_fef6 => DynamicTag::TLSDescPlt,
+            0x6fff_fef7 => DynamicTag::TLSDescGot,
+            0x6fff_fef8 => DynamicTag::GNUConflict,
+            0x6fff_fef9 => DynamicTag::GNULibList,
+            0x6fff_fefa => DynamicTag::Config,
+            0x6fff_fefb => DynamicTag::DepAudit,
+            0x6fff_fefc => DynamicTag::Audit,
+            0x6fff_fefd => DynamicTag::PltPad,
+            0x6fff_fefe => DynamicTag::MoveTab,
+            0x6fff_feff => DynamicTag::SymInfo,
+            0x6fff_fff0 => DynamicTag::VerSym,
+            0x6fff_fff9 => DynamicTag::RelaCount,
+            0x6fff_fffa => DynamicTag::RelCount,
+            0x6fff_fffb => DynamicTag::Flags1,
+            0x6fff_fffc => DynamicTag::VerDef,
+            0x6fff_fffd => DynamicTag::VerDefNum,
+            0x6fff_fffe => DynamicTag::VerNeed,
+            0x6fff_ffff => DynamicTag::VerNeedNum,
+            0x7fff_fffd => DynamicTag::Auxiliary,
+            0x7fff_ffff => DynamicTag::Filter,
+            0x6000_000d..=0x6fff_f000 => DynamicTag::OS,
+            0x7000_0000..=0x7fff_ffff => DynamicTag::Proc,
+            _ => DynamicTag::Unknown,
+        }
+    }
+
+    // The d_tag value, None for the variants that cover a range of values
+    pub fn to_u64(&self) -> Option<u64> {
+        match self {
+            DynamicTag::Null => Some(0),
+            DynamicTag::Needed => Some(1),
+            DynamicTag::PltRelSize => Some(2),
+            DynamicTag::PltGot => Some(3),
+            DynamicTag::Hash => Some(4),
+            DynamicTag::StrTab => Some(5),
+            DynamicTag::SymTab => Some(6),
+            DynamicTag::Rela => Some(7),
+            DynamicTag::RelaSize => Some(8),
+            DynamicTag::RelaEnt => Some(9),
+            DynamicTag::StrSize => Some(10),
+            DynamicTag::SymEnt => Some(11),
+            DynamicTag::Init => Some(12),
+            DynamicTag::Fini => Some(13),
+            DynamicTag::SOName => Some(14),
+            DynamicTag::RPath => Some(15),
+            DynamicTag::Symbolic => Some(16),
+            DynamicTag::Rel => Some(17),
+            DynamicTag::RelSize => Some(18),
+            DynamicTag::RelEnt => Some(19),
+            DynamicTag::PltRel => Some(20),
+            DynamicTag::Debug => Some(21),
+            DynamicTag::TextRel => Some(22),
+            DynamicTag::JmpRel => Some(23),
+            DynamicTag::BindNow => Some(24),
+            DynamicTag::InitArray => Some(25),
+            DynamicTag::FiniArray => Some(26),
+            DynamicTag::InitArraySize => Some(27),
+            DynamicTag::FiniArraySize => Some(28),
+            DynamicTag::RunPath => Some(29),
+            DynamicTag::Flags => Some(30),
+            DynamicTag::PreinitArray => Some(32),
+            DynamicTag::PreinitArraySize => Some(33),
+            DynamicTag::SymTabShNdx => Some(34),
+            DynamicTag::RelrSize => Some(35),
+            DynamicTag::Relr => Some(36),
+            DynamicTag::RelrEnt => Some(37),
+            DynamicTag::GNUPrelinked => Some(0x6ffffdf5),
+            DynamicTag::GNUConflictSize => Some(0x6ffffdf6),
+            DynamicTag::GNULibListSize => Some(0x6ffffdf7),
+            DynamicTag::Checksum => Some(0x6ffffdf8),
+            DynamicTag::PltPadSize => Some(0x6ffffdf9),
+            DynamicTag::MoveEnt => Some(0x6ffffdfa),
+            DynamicTag::MoveSize => Some(0x6ffffdfb),
+            DynamicTag::Feature1 => Some(0x6ffffdfc),
+            DynamicTag::PosFlag1 => Some(0x6ffffdfd),
+            DynamicTag::SymInfoSize => Some(0x6ffffdfe),
+            DynamicTag::SymInfoEnt => Some(0x6ffffdff),
+            DynamicTag::GNUHash => Some(0x6ffffef5),
+            DynamicTag::TLSDescPlt => Some(0x6ffffef6),
+            DynamicTag::TLSDescGot => Some(0x6ffffef7),
+            DynamicTag::GNUConflict => Some(0x6ffffef8),
+            DynamicTag::GNULibList => Some(0x6ffffef9),
+            DynamicTag::Config => Some(0x6ffffefa),
+            DynamicTag::DepAudit => Some(0x6ffffefb),
+            DynamicTag::Audit => Some(0x6ffffefc),
+            DynamicTag::PltPad => Some(0x6ffffefd),
+            DynamicTag::MoveTab => Some(0x6ffffefe),
+            DynamicTag::SymInfo => Some(0x6ffffeff),
+            DynamicTag::VerSym => Some(0x6ffffff0),
+            DynamicTag::RelaCount => Some(0x6ffffff9),
+            DynamicTag::RelCount => Some(0x6ffffffa),
+            DynamicTag::Flags1 => Some(0x6ffffffb),
+            DynamicTag::VerDef => Some(0x6ffffffc),
+            DynamicTag::VerDefNum => Some(0x6ffffffd),
+            DynamicTag::VerNeed => Some(0x6ffffffe),
+            DynamicTag::VerNeedNum => Some(0x6fffffff),
+            DynamicTag::Auxiliary => Some(0x7ffffffd),
+            DynamicTag::Filter => Some(0x7fffffff),
+            DynamicTag::OS | DynamicTag::Proc | DynamicTag::Unknown => None,
+        }
+    }
+
+    // Tags whose value is an offset into the string table pointed to by DT_STRTAB
+    pub fn has_string_value(&self) -> bool {
+        matches!(
+            self,
+            DynamicTag::Needed
+                | DynamicTag::SOName
+                | DynamicTag::RPath
+                | DynamicTag::RunPath
+                | DynamicTag::Auxiliary
+                | DynamicTag::Filter
+                | DynamicTag::Config
+                | DynamicTag::DepAudit
+                | DynamicTag::Audit
+        )
+    }
+}
+
+impl fmt::Display for DynamicTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value: &str = match self {
+            DynamicTag::Null => "NULL",
+            DynamicTag::Needed => "NEEDED",
+            DynamicTag::PltRelSize => "PLTRELSZ",
+            DynamicTag::PltGot => "PLTGOT",
+            DynamicTag::Hash => "HASH",
+            DynamicTag::StrTab => "STRTAB",
+            DynamicTag::SymTab => "SYMTAB",
+            DynamicTag::Rela => "RELA",
+            DynamicTag::RelaSize => "RELASZ",
+            DynamicTag::RelaEnt => "RELAENT",
+            DynamicTag::StrSize => "STRSZ",
+            DynamicTag::SymEnt => "SYMENT",
+            DynamicTag::Init => "INIT",
+            DynamicTag::Fini => "FINI",
+            DynamicTag::SOName => "SONAME",
+            DynamicTag::RPath => "RPATH",
+            DynamicTag::Symbolic => "SYMBOLIC",
+            DynamicTag::Rel => "REL",
+            DynamicTag::RelSize => "RELSZ",
+            DynamicTag::RelEnt => "RELENT",
+            DynamicTag::PltRel => "PLTREL",
+            DynamicTag::Debug => "DEBUG",
+            DynamicTag::TextRel => "TEXTREL",
+            DynamicTag::JmpRel => "JMPREL",
+            DynamicTag::BindNow => "BIND_NOW",
+            DynamicTag::InitArray => "INIT_ARRAY",
+            DynamicTag::FiniArray => "FINI_ARRAY",
+            DynamicTag::InitArraySize => "INIT_ARRAYSZ",
+            DynamicTag::FiniArraySize => "FINI_ARRAYSZ",
+            DynamicTag::RunPath => "RUNPATH",
+            DynamicTag::Flags => "FLAGS",
+            DynamicTag::PreinitArray => "PREINIT_ARRAY",
+            DynamicTag::PreinitArraySize => "PREINIT_ARRAYSZ",
+            DynamicTag::SymTabShNdx => "SYMTAB_SHNDX",
+            DynamicTag::RelrSize => "RELRSZ",
+            DynamicTag::Relr => "RELR",
+            DynamicTag::RelrEnt => "RELRENT",
+            DynamicTag::GNUPrelinked => "GNU_PRELINKED",
+            DynamicTag::GNUConflictSize => "GNU_CONFLICTSZ",
+            DynamicTag::GNULibListSize => "GNU_LIBLISTSZ",
+            DynamicTag::Checksum => "CHECKSUM",
+            DynamicTag::PltPadSize => "PLTPADSZ",
+            DynamicTag::MoveEnt => "MOVEENT",
+            DynamicTag::MoveSize => "MOVESZ",
+            DynamicTag::Feature1 => "FEATURE_1",
+            DynamicTag::PosFlag1 => "POSFLAG_1",
+            DynamicTag::SymInfoSize => "SYMINSZ",
+            DynamicTag::SymInfoEnt => "SYMINENT",
+            DynamicTag::GNUHash => "GNU_HASH",
+            DynamicTag::TLSDescPlt => "TLSDESC_PLT",
+            DynamicTag::TLSDescGot => "TLSDESC_GOT",
+            DynamicTag::GNUConflict => "GNU_CONFLICT",
+            DynamicTag::GNULibList => "GNU_LIBLIST",
+            DynamicTag::Config => "CONFIG",
+            DynamicTag::DepAudit => "DEPAUDIT",
+            DynamicTag::Audit => "AUDIT",
+            DynamicTag::PltPad => "PLTPAD",
+            DynamicTag::MoveTab => "MOVETAB",
+            DynamicTag::SymInfo => "SYMINFO",
+            DynamicTag::VerSym => "VERSYM",
+            DynamicTag::RelaCount => "RELACOUNT",
+            DynamicTag::RelCount => "RELCOUNT",
+            DynamicTag::Flags1 => "FLAGS_1",
+            DynamicTag::VerDef => "VERDEF",
+            DynamicTag::VerDefNum => "VERDEFNUM",
+            DynamicTag::VerNeed => "VERNEED",
+            DynamicTag::VerNeedNum => "VERNEEDNUM",
+            DynamicTag::Auxiliary => "AUXILIARY",
+            DynamicTag::Filter => "FILTER",
+            DynamicTag::OS => "OS specific",
+            DynamicTag::Proc => "Processor specific",
+            DynamicTag::Unknown => "Unknown",
+        };
+        write!(f, "{}", value)
+    }
+}
+
+bitflags! {
+    // Values of the DT_FLAGS entry
+    pub struct DynamicFlags: u64 {
+        const ORIGIN = 0x0000_0001; // Object may use DF_ORIGIN
+        const SYMBOLIC = 0x0000_0002; // Symbol resolutions starts here
+        const TEXTREL = 0x0000_0004; // Object contains text relocations
+        const BIND_NOW = 0x0000_0008; // No lazy binding for this object
+        const STATIC_TLS = 0x0000_0010; // Module uses the static TLS model
+    }
+}
+
+bitflags! {
+    // Values of the DT_FLAGS_1 entry
+    pub struct DynamicFlags1: u64 {
+        const NOW = 0x0000_0001; // Set RTLD_NOW for this object
+        const GLOBAL = 0x0000_0002; // Set RTLD_GLOBAL for this object
+        const GROUP = 0x0000_0004; // Set RTLD_GROUP for this object
+        const NODELETE = 0x0000_0008; // Set RTLD_NODELETE for this object
+        const LOADFLTR = 0x0000_0010; // Trigger filtee loading at runtime
+        const INITFIRST = 0x0000_0020; // Set RTLD_INITFIRST for this object
+        const NOOPEN = 0x0000_0040; // Set RTLD_NOOPEN for this object
+        const ORIGIN = 0x0000_0080; // $ORIGIN must be handled
+        const DIRECT = 0x0000_0100; // Direct binding enabled
+        const TRANS = 0x0000_0200;
+        const INTERPOSE = 0x0000_0400; // Object is used to interpose
+        const NODEFLIB = 0x0000_0800; // Ignore default lib search path
+        const NODUMP = 0x0000_1000; // Object can't be dldump'ed
+        const CONFALT = 0x0000_2000; // Configuration alternative created
+        const ENDFILTEE = 0x0000_4000; // Filtee terminates filters search
+        const DISPRELDNE = 0x0000_8000; // Disp reloc applied at build time
+        const DISPRELPND = 0x0001_0000; // Disp reloc applied at run-time
+        const NODIRECT = 0x0002_0000; // Object has no-direct binding
+        const IGNMULDEF = 0x0004_0000;
+        const NOKSYMS = 0x0008_0000;
+        const NOHDR = 0x0010_0000;
+        const EDITED = 0x0020_0000; // Object is modified after built
+        const NORELOC = 0x0040_0000;
+        const SYMINTPOSE = 0x0080_0000; // Object has individual interposers
+        const GLOBAUDIT = 0x0100_0000; // Global auditing required
+        const SINGLETON = 0x0200_0000; // Singleton symbols are used
+        const STUB = 0x0400_0000;
+        const PIE = 0x0800_0000;
+        const KMOD = 0x1000_0000;
+        const WEAKFILTER = 0x2000_0000;
+        const NOCOMMON = 0x4000_0000;
+    }
+}
+
+pub struct DynamicEntry {
+    pub tag: DynamicTag,              // d_tag, the type of this entry
+    pub raw_tag: u64,                 // d_tag as read from the file
+    pub value: u64,                   // d_val or d_ptr depending on the tag
+    pub string_value: Option<String>, // Resolved through DT_STRTAB for string valued tags
+}
+
+impl DynamicEntry {
+    // Parses entries until DT_NULL or the end of the data is reached. The string values
+    // are not resolved here as DT_STRTAB may come after the entries that reference it.
+    pub fn parse_from_dynamic_table(data: &[u8], header: &ELFHeader) -> Vec<DynamicEntry> {
+        let size: usize = match header.ident.ei_class {
+            EI_Class::ELF32 => 8,
+            EI_Class::ELF64 => 16,
+        };
+        let num_entries = data.len() / size;
+
+        // Now get our conversion functions to read numbers based on endianness
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+
+        let mut entries: Vec<DynamicEntry> = vec![];
+        for index in 0..num_entries {
+            let start_index = index * size;
+            let end_index = start_index + size;
+            let raw: &[u8] = &data[start_index..end_index];
+
+            let (raw_tag, value) = match header.ident.ei_class {
+                EI_Class::ELF32 => (
+                    u64::from(u32_from_bytes(raw[0..4].try_into().unwrap())),
+                    u64::from(u32_from_bytes(raw[4..8].try_into().unwrap())),
+                ),
+                EI_Class::ELF64 => (
+                    u64_from_bytes(raw[0..8].try_into().unwrap()),
+                    u64_from_bytes(raw[8..16].try_into().unwrap()),
+                ),
+            };
+            let tag = DynamicTag::from_u64(raw_tag);
+            entries.push(DynamicEntry {
+                tag,
+                raw_tag,
+                value,
+                string_value: None,
+            });
+            if tag == DynamicTag::Null {
+                break;
+            }
+        }
+        entries
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}{}", "Tag:", self.tag),
+            format!("{:15}0x{:x?}", "Value:", self.value),
+            format!("{:15}{:?}", "String:", self.string_value),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for DynamicEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for DynamicEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// Returns the raw bytes of the dynamic table. The PT_DYNAMIC segment is what the loader uses
+// so we prefer that and only fall back to the .dynamic section if there is no segment.
+fn get_dynamic_table_data<'a>(
+    data: &'a [u8],
+    program_headers: &[ProgramHeader],
+    section_headers: &[SectionHeader],
+) -> Option<&'a [u8]> {
+    for ph in program_headers {
+        if ph.header_type == ProgramHeaderType::Dynamic {
+            return Some(ph.get_data(data));
+        }
+    }
+    for sh in section_headers {
+        if sh.section_type == SectionType::Dynamic {
+            return Some(sh.get_data(data));
+        }
+    }
+    None
+}
+
+// Parses the dynamic table (if there is one) and resolves the string valued entries
+// through the DT_STRTAB string table.
+pub fn get_dynamic_entries(
+    data: &[u8],
+    program_headers: &[ProgramHeader],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+) -> Vec<DynamicEntry> {
+    let table_data = match get_dynamic_table_data(data, program_headers, section_headers) {
+        Some(v) => v,
+        None => return vec![],
+    };
+    let mut entries = DynamicEntry::parse_from_dynamic_table(table_data, elf_header);
+
+    let strings = get_dynamic_string_table(data, &entries, program_headers);
+    if let Some(strings) = strings {
+        for entry in &mut entries {
+            if entry.tag.has_string_value() && (entry.value as usize) < strings.len() {
+                entry.string_value = Some(get_null_terminated_string_from_vec(
+                    strings,
+                    entry.value as usize,
+                ));
+            }
+        }
+    }
+    entries
+}
+
+// DT_STRTAB holds a virtual address so it needs to be mapped back to a file offset
+// through the loadable segments. DT_STRSZ gives us the size.
+pub fn get_dynamic_string_table<'a>(
+    data: &'a [u8],
+    entries: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+) -> Option<&'a [u8]> {
+    let address = get_dynamic_entry(DynamicTag::StrTab, entries)?.value;
+    let size = match get_dynamic_entry(DynamicTag::StrSize, entries) {
+        Some(v) => v.value,
+        None => data.len() as u64,
+    };
+    get_data_for_address(data, address, size, program_headers)
+}
+
+// Returns the first entry with the given tag
+pub fn get_dynamic_entry(tag: DynamicTag, entries: &[DynamicEntry]) -> Option<&DynamicEntry> {
+    entries.iter().find(|entry| entry.tag == tag)
+}
+
+// Returns the names of all DT_NEEDED libraries in the order the loader will see them
+pub fn get_needed_libraries(entries: &[DynamicEntry]) -> Vec<&str> {
+    entries
+        .iter()
+        .filter(|entry| entry.tag == DynamicTag::Needed)
+        .filter_map(|entry| entry.string_value.as_deref())
+        .collect()
+}
+
+pub fn get_flags(entries: &[DynamicEntry]) -> DynamicFlags {
+    match get_dynamic_entry(DynamicTag::Flags, entries) {
+        Some(v) => DynamicFlags::from_bits_truncate(v.value),
+        None => DynamicFlags::empty(),
+    }
+}
+
+pub fn get_flags_1(entries: &[DynamicEntry]) -> DynamicFlags1 {
+    match get_dynamic_entry(DynamicTag::Flags1, entries) {
+        Some(v) => DynamicFlags1::from_bits_truncate(v.value),
+        None => DynamicFlags1::empty(),
+    }
+}
+
+#[cfg(test)]
+mod dynamic_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x23,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x0D, 0x00, 0x40, 0x00, 0x1E, 0x00, 0x1D, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_parse_64_bit_dynamic_table() {
+        let raw = [
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DT_NEEDED
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // offset 1
+            0x1e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DT_FLAGS
+            0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DF_BIND_NOW
+            0xfb, 0xff, 0xff, 0x6f, 0x00, 0x00, 0x00, 0x00, // DT_FLAGS_1
+            0x01, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, // DF_1_NOW | DF_1_PIE
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DT_NULL
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0
+            0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // DT_INIT past DT_NULL, ignored
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0x1000
+        ];
+        let entries = DynamicEntry::parse_from_dynamic_table(&raw, &get_64_bit_header());
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].tag, DynamicTag::Needed);
+        assert_eq!(entries[0].value, 1);
+        assert_eq!(entries[3].tag, DynamicTag::Null);
+        assert_eq!(get_flags(&entries), DynamicFlags::BIND_NOW);
+        assert_eq!(
+            get_flags_1(&entries),
+            DynamicFlags1::NOW | DynamicFlags1::PIE
+        );
+        assert!(get_dynamic_entry(DynamicTag::Init, &entries).is_none());
+    }
+
+    #[test]
+    fn can_get_needed_libraries() {
+        let entries = vec![
+            DynamicEntry {
+                tag: DynamicTag::Needed,
+                raw_tag: 1,
+                value: 1,
+                string_value: Some(String::from("libc.so.6")),
+            },
+            DynamicEntry {
+                tag: DynamicTag::SOName,
+                raw_tag: 14,
+                value: 11,
+                string_value: Some(String::from("libfoo.so.1")),
+            },
+            DynamicEntry {
+                tag: DynamicTag::Needed,
+                raw_tag: 1,
+                value: 23,
+                string_value: Some(String::from("libm.so.6")),
+            },
+        ];
+        assert_eq!(
+            get_needed_libraries(&entries),
+            vec!["libc.so.6", "libm.so.6"]
+        );
+    }
+}
@@ -4,8 +4,8 @@
 
 use std::convert::TryInto;
 use std::fmt;
-use std::mem;
-use std::result::Result::Err;
+
+use crate::error::{get_bytes, Error, Result};
 
 // This makes it easy to get the function to convert from bytes to a number type
 // with a specified endianness
@@ -21,33 +21,33 @@ macro_rules! get_num_from_bytes {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum EI_Data {
     LittleEndian,
     BigEndian,
 }
 impl EI_Data {
-    fn from_u8(value: u8) -> EI_Data {
+    fn from_u8(value: u8) -> Option<EI_Data> {
         match value {
-            1 => EI_Data::LittleEndian,
-            2 => EI_Data::LittleEndian,
-            _ => panic!("Invalid ei_data value"),
+            1 => Some(EI_Data::LittleEndian),
+            2 => Some(EI_Data::BigEndian),
+            _ => None,
         }
     }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum EI_Class {
     ELF32,
     ELF64,
 }
 impl EI_Class {
-    fn from_u8(value: u8) -> EI_Class {
+    fn from_u8(value: u8) -> Option<EI_Class> {
         match value {
-            1 => EI_Class::ELF32,
-            2 => EI_Class::ELF64,
-            _ => panic!("Invalid ei_class value"),
+            1 => Some(EI_Class::ELF32),
+            2 => Some(EI_Class::ELF64),
+            _ => None,
         }
     }
 }
@@ -105,6 +105,10 @@ impl EI_OSABI {
     }
 }
 
+const ELF_IDENT_SIZE: usize = 16;
+const ELF32_HEADER_SIZE: usize = 52;
+const ELF64_HEADER_SIZE: usize = 64;
+
 #[derive(Eq, PartialEq)]
 pub struct ELFIdent {
     pub ei_magic: [u8; 4],
@@ -117,23 +121,31 @@ pub struct ELFIdent {
 }
 
 impl ELFIdent {
-    pub fn parse_from_buffer(binary: &[u8]) -> Result<ELFIdent, &'static str> {
-        const SIZE: usize = mem::size_of::<ELFIdent>();
-        let raw_ident: &[u8] = &binary[0..SIZE];
+    pub fn parse_from_buffer(binary: &[u8]) -> Result<ELFIdent> {
+        if binary.len() < 4 || binary[0..4] != [0x7F, 0x45, 0x4C, 0x46] {
+            return Err(Error::BadMagic {
+                format: "ELF",
+                magic: binary.iter().take(4).cloned().collect(),
+            });
+        }
+        let raw_ident = get_bytes(binary, 0, ELF_IDENT_SIZE as u64, || {
+            String::from("ELF ident")
+        })?;
 
         let mut magic = [0; 4];
         magic.copy_from_slice(&raw_ident[0..4]);
-        if magic != [0x7F, 0x45, 0x4C, 0x46] {
-            return Err("invalid magic bytes!");
-        }
 
         let mut pad = [0; 7];
         pad.copy_from_slice(&raw_ident[9..16]);
 
         let result: ELFIdent = ELFIdent {
             ei_magic: magic,
-            ei_class: EI_Class::from_u8(raw_ident[4]),
-            ei_data: EI_Data::from_u8(raw_ident[5]),
+            ei_class: EI_Class::from_u8(raw_ident[4]).ok_or_else(|| {
+                Error::invalid_value(4, u64::from(raw_ident[4]), "ELF ident class")
+            })?,
+            ei_data: EI_Data::from_u8(raw_ident[5]).ok_or_else(|| {
+                Error::invalid_value(5, u64::from(raw_ident[5]), "ELF ident data encoding")
+            })?,
             ei_version: raw_ident[6],
             ei_os_abi: EI_OSABI::from_u8(raw_ident[7]),
             ei_abi_version: raw_ident[8],
@@ -652,10 +664,15 @@ pub struct ELFHeader {
 }
 
 impl ELFHeader {
-    pub fn parse_from_buffer(binary: &[u8], ident: ELFIdent) -> ELFHeader {
-        // First get the bytes for our header
-        const SIZE: usize = mem::size_of::<ELFHeader>();
-        let raw: &[u8] = &binary[mem::size_of::<ELFIdent>()..SIZE];
+    pub fn parse_from_buffer(binary: &[u8], ident: ELFIdent) -> Result<ELFHeader> {
+        // First get the bytes for our header, the part after the ident
+        let size = match ident.ei_class {
+            EI_Class::ELF32 => ELF32_HEADER_SIZE,
+            EI_Class::ELF64 => ELF64_HEADER_SIZE,
+        } - ELF_IDENT_SIZE;
+        let raw: &[u8] = get_bytes(binary, ELF_IDENT_SIZE as u64, size as u64, || {
+            String::from("ELF header")
+        })?;
 
         // Now get our conversion functions to read numbers based on endianness
         let u16_from_bytes = get_num_from_bytes!(u16, ident.ei_data);
@@ -700,7 +717,7 @@ impl ELFHeader {
             },
         };
 
-        result
+        Ok(result)
     }
 
     fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -790,7 +807,7 @@ mod elf_header_tests {
             Ok(v) => v,
             Err(_) => panic!("Unable to parse valid ident"),
         };
-        let header = ELFHeader::parse_from_buffer(raw, ident);
+        let header = ELFHeader::parse_from_buffer(raw, ident).unwrap();
         assert_eq!(expected, header);
     }
 }
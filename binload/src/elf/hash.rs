@@ -0,0 +1,400 @@
+use std::convert::TryInto;
+
+use super::dynamic::{get_dynamic_entry, get_dynamic_string_table, DynamicEntry, DynamicTag};
+use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::program_header::{get_data_for_address, ProgramHeader};
+use super::symbol::Symbol;
+use super::utils::get_null_terminated_string_from_vec;
+
+// Hash function used by DT_HASH, from the System V ABI
+pub fn sysv_hash(name: &str) -> u32 {
+    let mut hash: u32 = 0;
+    for byte in name.bytes() {
+        hash = (hash << 4).wrapping_add(u32::from(byte));
+        let high = hash & 0xf000_0000;
+        if high != 0 {
+            hash ^= high >> 24;
+        }
+        hash &= !high;
+    }
+    hash
+}
+
+// Hash function used by DT_GNU_HASH (djb2)
+pub fn gnu_hash(name: &str) -> u32 {
+    let mut hash: u32 = 5381;
+    for byte in name.bytes() {
+        hash = hash.wrapping_mul(33).wrapping_add(u32::from(byte));
+    }
+    hash
+}
+
+// DT_HASH: nbucket, nchain, then the bucket and chain arrays. nchain is always
+// the number of entries in the dynamic symbol table.
+pub struct SysvHashTable {
+    pub buckets: Vec<u32>,
+    pub chains: Vec<u32>,
+}
+
+impl SysvHashTable {
+    pub fn parse_from_buffer(data: &[u8], header: &ELFHeader) -> Option<SysvHashTable> {
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let read_words = |start: usize, count: usize| -> Option<Vec<u32>> {
+            let end = start.checked_add(count.checked_mul(4)?)?;
+            Some(
+                data.get(start..end)?
+                    .chunks_exact(4)
+                    .map(|v| u32_from_bytes(v.try_into().unwrap()))
+                    .collect(),
+            )
+        };
+
+        let counts = read_words(0, 2)?;
+        let (bucket_count, chain_count) = (counts[0] as usize, counts[1] as usize);
+        Some(SysvHashTable {
+            buckets: read_words(8, bucket_count)?,
+            chains: read_words(8 + bucket_count * 4, chain_count)?,
+        })
+    }
+
+    pub fn get_symbol_count(&self) -> usize {
+        self.chains.len()
+    }
+
+    pub fn lookup<'a>(&self, name: &str, symbols: &'a [Symbol]) -> Option<&'a Symbol> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let hash = sysv_hash(name);
+        let mut index = self.buckets[hash as usize % self.buckets.len()] as usize;
+        // The chain count bounds the walk in case the table has a loop
+        for _ in 0..self.chains.len() {
+            if index == 0 {
+                break;
+            }
+            let symbol = symbols.get(index)?;
+            if symbol.name_string == name {
+                return Some(symbol);
+            }
+            index = *self.chains.get(index)? as usize;
+        }
+        None
+    }
+}
+
+// DT_GNU_HASH: nbuckets, symoffset, bloom_size and bloom_shift followed by the
+// bloom filter (in words of the ELF class), buckets, and a chain of hash values
+// for every symbol from symoffset onwards. The last hash of each chain has its
+// low bit set.
+pub struct GnuHashTable {
+    pub symbol_offset: u32,
+    pub bloom_shift: u32,
+    pub bloom: Vec<u64>,
+    pub buckets: Vec<u32>,
+    pub chains: Vec<u32>,
+    word_bits: u32,
+}
+
+impl GnuHashTable {
+    pub fn parse_from_buffer(data: &[u8], header: &ELFHeader) -> Option<GnuHashTable> {
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+        let read_word = |offset: usize| -> Option<u32> {
+            Some(u32_from_bytes(
+                data.get(offset..offset + 4)?.try_into().unwrap(),
+            ))
+        };
+
+        let bucket_count = read_word(0)? as usize;
+        let symbol_offset = read_word(4)?;
+        let bloom_size = read_word(8)? as usize;
+        let bloom_shift = read_word(12)?;
+
+        let word_size: usize = match header.ident.ei_class {
+            EI_Class::ELF32 => 4,
+            EI_Class::ELF64 => 8,
+        };
+        // The loader shifts the 32 bit hash by bloom_shift, anything wider can't be
+        // a real table
+        if bloom_shift >= 32 {
+            return None;
+        }
+        let mut offset = 16;
+        // The counts come from the file so don't trust them for allocations
+        let mut bloom: Vec<u64> = Vec::with_capacity(std::cmp::min(bloom_size, data.len()));
+        for _ in 0..bloom_size {
+            let raw = data.get(offset..offset + word_size)?;
+            bloom.push(match header.ident.ei_class {
+                EI_Class::ELF32 => u64::from(u32_from_bytes(raw.try_into().unwrap())),
+                EI_Class::ELF64 => u64_from_bytes(raw.try_into().unwrap()),
+            });
+            offset += word_size;
+        }
+
+        let mut buckets: Vec<u32> = Vec::with_capacity(std::cmp::min(bucket_count, data.len()));
+        for _ in 0..bucket_count {
+            buckets.push(read_word(offset)?);
+            offset += 4;
+        }
+
+        // The chain array has no stored length, it runs until the end of the
+        // chain started by the highest bucket.
+        let mut chains: Vec<u32> = vec![];
+        if let Some(last) = buckets.iter().max() {
+            if *last >= symbol_offset {
+                let mut index = symbol_offset;
+                loop {
+                    let value = read_word(offset)?;
+                    chains.push(value);
+                    offset += 4;
+                    if index >= *last && value & 1 == 1 {
+                        break;
+                    }
+                    index = index.checked_add(1)?;
+                }
+            }
+        }
+
+        Some(GnuHashTable {
+            symbol_offset,
+            bloom_shift,
+            bloom,
+            buckets,
+            chains,
+            word_bits: (word_size * 8) as u32,
+        })
+    }
+
+    pub fn get_symbol_count(&self) -> usize {
+        if self.chains.is_empty() {
+            // Only the symbols below symoffset, which are never hashed
+            return self.symbol_offset as usize;
+        }
+        self.symbol_offset as usize + self.chains.len()
+    }
+
+    // Checks the bloom filter, false means the name is definitely not in the table
+    fn may_contain(&self, hash: u32) -> bool {
+        if self.bloom.is_empty() {
+            return true;
+        }
+        let word = self.bloom[((hash / self.word_bits) as usize) % self.bloom.len()];
+        let mask = (1u64 << (hash % self.word_bits))
+            | (1u64 << ((hash >> self.bloom_shift) % self.word_bits));
+        word & mask == mask
+    }
+
+    pub fn lookup<'a>(&self, name: &str, symbols: &'a [Symbol]) -> Option<&'a Symbol> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let hash = gnu_hash(name);
+        if !self.may_contain(hash) {
+            return None;
+        }
+        let mut index = self.buckets[hash as usize % self.buckets.len()];
+        if index < self.symbol_offset {
+            return None;
+        }
+        loop {
+            let chain_hash = *self.chains.get((index - self.symbol_offset) as usize)?;
+            if hash | 1 == chain_hash | 1 {
+                let symbol = symbols.get(index as usize)?;
+                if symbol.name_string == name {
+                    return Some(symbol);
+                }
+            }
+            if chain_hash & 1 == 1 {
+                return None;
+            }
+            index = index.checked_add(1)?;
+        }
+    }
+}
+
+// Uses whichever hash table the binary has, or a linear scan when it has neither
+pub fn lookup_dynamic_symbol<'a>(
+    name: &str,
+    gnu_hash_table: Option<&GnuHashTable>,
+    sysv_hash_table: Option<&SysvHashTable>,
+    symbols: &'a [Symbol],
+) -> Option<&'a Symbol> {
+    if let Some(table) = gnu_hash_table {
+        return table.lookup(name, symbols);
+    }
+    if let Some(table) = sysv_hash_table {
+        return table.lookup(name, symbols);
+    }
+    symbols.iter().find(|s| s.name_string == name)
+}
+
+pub fn get_sysv_hash_table(
+    data: &[u8],
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+) -> Option<SysvHashTable> {
+    let address = get_dynamic_entry(DynamicTag::Hash, dynamic)?.value;
+    let table = get_data_for_address(data, address, data.len() as u64, program_headers)?;
+    SysvHashTable::parse_from_buffer(table, elf_header)
+}
+
+pub fn get_gnu_hash_table(
+    data: &[u8],
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+) -> Option<GnuHashTable> {
+    let address = get_dynamic_entry(DynamicTag::GNUHash, dynamic)?.value;
+    let table = get_data_for_address(data, address, data.len() as u64, program_headers)?;
+    GnuHashTable::parse_from_buffer(table, elf_header)
+}
+
+// Reads the dynamic symbol table using only the dynamic section. The table has
+// no size of its own so the number of symbols comes from the hash tables. This
+// works for binaries whose section headers are stripped or corrupted.
+pub fn get_dynamic_symbols_from_hash(
+    data: &[u8],
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+) -> Vec<Symbol> {
+    let count = match get_gnu_hash_table(data, dynamic, program_headers, elf_header) {
+        Some(v) => v.get_symbol_count(),
+        None => match get_sysv_hash_table(data, dynamic, program_headers, elf_header) {
+            Some(v) => v.get_symbol_count(),
+            None => return vec![],
+        },
+    };
+    let address = match get_dynamic_entry(DynamicTag::SymTab, dynamic) {
+        Some(v) => v.value,
+        None => return vec![],
+    };
+    let entry_size = match get_dynamic_entry(DynamicTag::SymEnt, dynamic) {
+        Some(v) => v.value,
+        None => match elf_header.ident.ei_class {
+            EI_Class::ELF32 => 16,
+            EI_Class::ELF64 => 24,
+        },
+    };
+    let table = match get_data_for_address(
+        data,
+        address,
+        (count as u64).saturating_mul(entry_size),
+        program_headers,
+    ) {
+        Some(v) => v,
+        None => return vec![],
+    };
+
+    let mut symbols = Symbol::parse_from_symbol_table(table, elf_header);
+    if let Some(strings) = get_dynamic_string_table(data, dynamic, program_headers) {
+        for symbol in &mut symbols {
+            symbol.name_string = get_null_terminated_string_from_vec(strings, symbol.name as usize);
+        }
+    }
+    symbols
+}
+
+#[cfg(test)]
+mod hash_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x23,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x0D, 0x00, 0x40, 0x00, 0x1E, 0x00, 0x1D, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    fn get_symbols(names: &[&str]) -> Vec<Symbol> {
+        // st_name, st_info, st_other, st_shndx, st_value, st_size
+        let raw = [0u8; 24 * 3];
+        let mut symbols =
+            Symbol::parse_from_symbol_table(&raw[0..24 * names.len()], &get_64_bit_header());
+        for (symbol, name) in symbols.iter_mut().zip(names) {
+            symbol.name_string = name.to_string();
+        }
+        symbols
+    }
+
+    #[test]
+    fn can_hash_names() {
+        assert_eq!(gnu_hash(""), 0x1505);
+        assert_eq!(gnu_hash("printf"), 0x156b_2bb8);
+        assert_eq!(sysv_hash("printf"), 0x0779_05a6);
+        assert_eq!(sysv_hash("exit"), 0x0006_cf04);
+    }
+
+    #[test]
+    fn can_lookup_in_sysv_hash_table() {
+        let raw = [
+            0x01, 0x00, 0x00, 0x00, // nbucket
+            0x03, 0x00, 0x00, 0x00, // nchain
+            0x02, 0x00, 0x00, 0x00, // bucket 0 -> symbol 2
+            0x00, 0x00, 0x00, 0x00, // chain 0
+            0x00, 0x00, 0x00, 0x00, // chain 1 -> end
+            0x01, 0x00, 0x00, 0x00, // chain 2 -> symbol 1
+        ];
+        let table = SysvHashTable::parse_from_buffer(&raw, &get_64_bit_header()).unwrap();
+        let symbols = get_symbols(&["", "exit", "puts"]);
+        assert_eq!(table.get_symbol_count(), 3);
+        assert_eq!(table.lookup("exit", &symbols).unwrap().name_string, "exit");
+        assert_eq!(table.lookup("puts", &symbols).unwrap().name_string, "puts");
+        assert!(table.lookup("printf", &symbols).is_none());
+    }
+
+    #[test]
+    fn can_lookup_in_gnu_hash_table() {
+        // exit = 0x7c967e3f and puts = 0x7c9c7b11, both land in the same 64 bit
+        // bloom word with a shift of 6
+        let raw = [
+            0x01, 0x00, 0x00, 0x00, // nbuckets
+            0x01, 0x00, 0x00, 0x00, // symoffset
+            0x01, 0x00, 0x00, 0x00, // bloom_size
+            0x06, 0x00, 0x00, 0x00, // bloom_shift
+            0x00, 0x00, 0x02, 0x00, 0x00, 0x10, 0x00, 0x81, // bloom
+            0x01, 0x00, 0x00, 0x00, // bucket 0 -> symbol 1
+            0x3e, 0x7e, 0x96, 0x7c, // exit hash, chain continues
+            0x11, 0x7b, 0x9c, 0x7c, // puts hash, end of chain
+        ];
+        let table = GnuHashTable::parse_from_buffer(&raw, &get_64_bit_header()).unwrap();
+        let symbols = get_symbols(&["", "exit", "puts"]);
+        assert_eq!(table.get_symbol_count(), 3);
+        assert_eq!(table.lookup("exit", &symbols).unwrap().name_string, "exit");
+        assert_eq!(table.lookup("puts", &symbols).unwrap().name_string, "puts");
+        assert!(table.lookup("printf", &symbols).is_none());
+    }
+
+    #[test]
+    fn can_reject_bad_gnu_hash_tables() {
+        let mut raw = vec![
+            0x01, 0x00, 0x00, 0x00, // nbuckets
+            0xff, 0xff, 0xff, 0xff, // symoffset
+            0x00, 0x00, 0x00, 0x00, // bloom_size
+            0x20, 0x00, 0x00, 0x00, // bloom_shift
+            0xff, 0xff, 0xff, 0xff, // bucket 0 -> symbol 0xffffffff
+            0x00, 0x00, 0x00, 0x00, // chain continues past the last symbol
+        ];
+        assert!(GnuHashTable::parse_from_buffer(&raw, &get_64_bit_header()).is_none());
+        raw[12] = 0x06;
+        assert!(GnuHashTable::parse_from_buffer(&raw, &get_64_bit_header()).is_none());
+
+        // A chain without an end that runs into the last symbol index
+        let table = GnuHashTable {
+            symbol_offset: u32::MAX,
+            bloom_shift: 6,
+            bloom: vec![],
+            buckets: vec![u32::MAX],
+            chains: vec![0],
+            word_bits: 64,
+        };
+        assert!(table.lookup("exit", &get_symbols(&[""])).is_none());
+    }
+}
@@ -0,0 +1,602 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use super::dynamic::{get_dynamic_entry, DynamicTag};
+use super::elf_header::{EI_Class, EI_Data};
+use super::program_header::get_data_for_address;
+use super::relocatable;
+use super::relocation::get_relocation_for_address;
+use super::section::SectionType;
+use super::ELF;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum InitArrayType {
+    PreInit, // DT_PREINIT_ARRAY, run before any shared library is initialised
+    Init,    // DT_INIT_ARRAY, constructors, run in order before main
+    Fini,    // DT_FINI_ARRAY, destructors, run in reverse order at exit
+}
+
+impl InitArrayType {
+    fn get_tags(self) -> (DynamicTag, DynamicTag, SectionType) {
+        match self {
+            InitArrayType::PreInit => (
+                DynamicTag::PreinitArray,
+                DynamicTag::PreinitArraySize,
+                SectionType::PreinitArray,
+            ),
+            InitArrayType::Init => (
+                DynamicTag::InitArray,
+                DynamicTag::InitArraySize,
+                SectionType::InitArray,
+            ),
+            InitArrayType::Fini => (
+                DynamicTag::FiniArray,
+                DynamicTag::FiniArraySize,
+                SectionType::FiniArray,
+            ),
+        }
+    }
+}
+
+// An array of function pointers the loader or the C runtime calls. In position
+// independent binaries the pointers are filled in by relocations, so the entries
+// are the values after those are applied at the linked address.
+pub struct InitArray {
+    pub array_type: InitArrayType,
+    pub address: u64,
+    pub entries: Vec<u64>,
+}
+
+impl InitArray {
+    // The functions that get called, 0 and -1 are placeholders left by some linkers
+    pub fn get_functions(&self, word_size: usize) -> Vec<u64> {
+        let all_ones = if word_size == 8 {
+            u64::MAX
+        } else {
+            0xffff_ffff
+        };
+        self.entries
+            .iter()
+            .cloned()
+            .filter(|v| *v != 0 && *v != all_ones)
+            .collect()
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let entries: Vec<String> = self.entries.iter().map(|v| format!("{:#x}", v)).collect();
+        writeln!(
+            f,
+            "{:10?} {:#018x} [{}]",
+            self.array_type,
+            self.address,
+            entries.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for InitArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for InitArray {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// The arrays from the dynamic table, or from their sections when there is no dynamic
+// table (static executables and relocatable objects)
+pub fn get_init_arrays(elf: &ELF) -> Vec<InitArray> {
+    let mut arrays: Vec<InitArray> = vec![];
+    for array_type in &[
+        InitArrayType::PreInit,
+        InitArrayType::Init,
+        InitArrayType::Fini,
+    ] {
+        let array = if elf.is_relocatable() {
+            get_relocatable_init_array(elf, *array_type)
+        } else {
+            get_init_array(elf, *array_type)
+        };
+        if let Some(v) = array {
+            arrays.push(v);
+        }
+    }
+    arrays
+}
+
+// Functions that run without being called from the code, the entries of the init
+// arrays and then DT_INIT and DT_FINI. The dynamic loader adds the load address to
+// DT_INIT and DT_FINI itself rather than through relocations, so unlike the array
+// entries they are already addresses at the linked base.
+pub fn get_init_functions(elf: &ELF) -> Vec<u64> {
+    let word_size = get_word_size(elf);
+    let mut functions: Vec<u64> = get_init_arrays(elf)
+        .iter()
+        .flat_map(|a| a.get_functions(word_size))
+        .collect();
+    for tag in &[DynamicTag::Init, DynamicTag::Fini] {
+        if let Some(v) = get_dynamic_entry(*tag, &elf.dynamic) {
+            functions.push(v.value);
+        }
+    }
+    functions
+}
+
+pub(super) fn get_word_size(elf: &ELF) -> usize {
+    match elf.elf_header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    }
+}
+
+fn read_words(elf: &ELF, raw: &[u8]) -> Vec<u64> {
+    let u32_from_bytes = get_num_from_bytes!(u32, elf.elf_header.ident.ei_data);
+    let u64_from_bytes = get_num_from_bytes!(u64, elf.elf_header.ident.ei_data);
+    raw.chunks_exact(get_word_size(elf))
+        .map(|v| match elf.elf_header.ident.ei_class {
+            EI_Class::ELF32 => u64::from(u32_from_bytes(v.try_into().unwrap())),
+            EI_Class::ELF64 => u64_from_bytes(v.try_into().unwrap()),
+        })
+        .collect()
+}
+
+fn get_init_array(elf: &ELF, array_type: InitArrayType) -> Option<InitArray> {
+    let (address_tag, size_tag, section_type) = array_type.get_tags();
+    let (address, size) = match (
+        get_dynamic_entry(address_tag, &elf.dynamic),
+        get_dynamic_entry(size_tag, &elf.dynamic),
+    ) {
+        (Some(address), Some(size)) => (address.value, size.value),
+        _ => {
+            let section = elf
+                .section_headers
+                .iter()
+                .find(|s| s.section_type == section_type)?;
+            (section.address, section.size)
+        }
+    };
+    let raw = get_data_for_address(&elf.data, address, size, &elf.program_headers)?;
+
+    let word_size = get_word_size(elf);
+    let mask = if word_size == 8 {
+        u64::MAX
+    } else {
+        0xffff_ffff
+    };
+    let entries = read_words(elf, raw)
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let slot = address.wrapping_add((i * word_size) as u64);
+            get_relocation_for_address(slot, &elf.dynamic_relocations)
+                .and_then(|r| r.get_relocated_value(value))
+                .map_or(value, |v| v & mask)
+        })
+        .collect();
+    Some(InitArray {
+        array_type,
+        address,
+        entries,
+    })
+}
+
+// Objects have the array in a section with a relocation against a section or function
+// symbol for each entry, the entries are addresses in the relocatable layout
+fn get_relocatable_init_array(elf: &ELF, array_type: InitArrayType) -> Option<InitArray> {
+    let (_, _, section_type) = array_type.get_tags();
+    let index = elf
+        .section_headers
+        .iter()
+        .position(|s| s.section_type == section_type)?;
+    let section = &elf.section_headers[index];
+    let section_addresses = elf.get_section_addresses();
+
+    let word_size = get_word_size(elf);
+    let mask = if word_size == 8 {
+        u64::MAX
+    } else {
+        0xffff_ffff
+    };
+    let mut entries = read_words(elf, section.get_data(&elf.data));
+    for table in elf
+        .relocation_tables
+        .iter()
+        .filter(|t| t.target_section_index as usize == index)
+    {
+        for relocation in &table.relocations {
+            // A relocation that doesn't start on an entry isn't patching a pointer
+            if relocation.offset % word_size as u64 != 0 {
+                continue;
+            }
+            let slot = (relocation.offset / word_size as u64) as usize;
+            let symbol_address = relocation
+                .symbol
+                .as_ref()
+                .and_then(|s| relocatable::get_symbol_address(s, &section_addresses));
+            if let (Some(entry), Some(address)) = (entries.get_mut(slot), symbol_address) {
+                let addend = relocation.addend.map_or(*entry, |v| v as u64);
+                *entry = address.wrapping_add(addend) & mask;
+            }
+        }
+    }
+    Some(InitArray {
+        array_type,
+        address: section_addresses[index],
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod init_array_tests {
+    use super::super::relocation::{RelocationType, RelocationX86, RelocationX86_64};
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    // The same fields as push_section_header, all 32 bits wide in ELF32
+    fn push_section_header_32(raw: &mut Vec<u8>, fields: [u32; 10]) {
+        for field in &fields {
+            raw.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+
+    fn get_entries(arrays: &[InitArray]) -> Vec<(InitArrayType, u64, Vec<u64>)> {
+        arrays
+            .iter()
+            .map(|a| (a.array_type, a.address, a.entries.clone()))
+            .collect()
+    }
+
+    // An x86_64 shared library with one function in each array and DT_INIT and
+    // DT_FINI. The init array entries are filled in by an R_X86_64_RELATIVE and an
+    // R_X86_64_64 against the exported function from .rela.dyn, the others by
+    // .relr.dyn.
+    fn get_test_library() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, x86_64, version 1
+            0x30, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x20, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x02, 0x00, 0x40, 0x00, 0x0b, 0x00, 0x0a, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment = |header_type: u32, flags: u32, offset: u64, size: u64| {
+            push_u32(&mut raw, header_type);
+            push_u32(&mut raw, flags);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, size);
+            push_u64(&mut raw, if header_type == 1 { 0x1000 } else { 8 });
+        };
+        // PT_LOAD and PT_DYNAMIC
+        push_segment(1, 7, 0, 0x2b8);
+        push_segment(2, 6, 0x188, 0x130);
+
+        // .dynsym and .dynstr
+        raw.extend_from_slice(&[0; 24]);
+        push_u32(&mut raw, 1);
+        raw.extend_from_slice(&[0x12, 0, 5, 0]);
+        push_u64(&mut raw, 0x150);
+        push_u64(&mut raw, 1);
+        raw.extend_from_slice(b"\0exported\0");
+        // .rela.dyn and .relr.dyn
+        raw.resize(0xf0, 0);
+        for (offset, info, addend) in [(0x168, 8, 0x148), (0x170, (1 << 32) | 1, 0)] {
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, info);
+            push_u64(&mut raw, addend);
+        }
+        push_u64(&mut raw, 0x160);
+        push_u64(&mut raw, 0x180);
+        // .text, DT_INIT, DT_FINI and then the functions in the arrays
+        raw.resize(0x160, 0xc3);
+        // .preinit_array, .init_array with a placeholder and .fini_array
+        for value in [0x140, 0, 0, u64::MAX, 0x158] {
+            push_u64(&mut raw, value);
+        }
+        // .dynamic
+        for (tag, value) in [
+            (5, 0xe0),
+            (10, 10),
+            (6, 0xb0),
+            (11, 24),
+            (7, 0xf0),
+            (8, 48),
+            (9, 24),
+            (36, 0x120),
+            (35, 16),
+            (37, 8),
+            (12, 0x130),
+            (13, 0x138),
+            (32, 0x160),
+            (33, 8),
+            (25, 0x168),
+            (27, 24),
+            (26, 0x180),
+            (28, 8),
+            (0, 0),
+        ] {
+            push_u64(&mut raw, tag);
+            push_u64(&mut raw, value);
+        }
+        raw.extend_from_slice(
+            b"\0.dynsym\0.dynstr\0.rela.dyn\0.relr.dyn\0.text\0.preinit_array\0.init_array\0\
+              .fini_array\0.dynamic\0.shstrtab\0",
+        );
+
+        raw.resize(0x320, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 11, 2, 0xb0, 0xb0, 48, 2, 1, 8, 24]);
+        push_section_header(&mut raw, [9, 3, 2, 0xe0, 0xe0, 10, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [17, 4, 2, 0xf0, 0xf0, 48, 1, 0, 8, 24]);
+        push_section_header(&mut raw, [27, 19, 2, 0x120, 0x120, 16, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [37, 1, 6, 0x130, 0x130, 0x30, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [43, 16, 3, 0x160, 0x160, 8, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [58, 14, 3, 0x168, 0x168, 24, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [70, 15, 3, 0x180, 0x180, 8, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [82, 6, 3, 0x188, 0x188, 0x130, 2, 0, 8, 16]);
+        push_section_header(&mut raw, [91, 3, 0, 0, 0x2b8, 101, 0, 0, 1, 0]);
+        raw
+    }
+
+    // An i386 shared library with .rel.dyn, the second init array entry is an
+    // R_386_32 against the exported function with 4 in the file
+    fn get_test_library_32() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x01, 0x01, 0x01, 0x00, // 32 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, i386, version 1
+            0xc0, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00, // e_entry and e_phoff
+            0x88, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff and e_flags
+            0x34, 0x00, 0x20, 0x00, 0x02, 0x00, 0x28, 0x00, // e_ehsize to e_shentsize
+            0x09, 0x00, 0x08, 0x00, // e_shnum and e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment = |header_type: u32, flags: u32, offset: u32, size: u32| {
+            for field in [header_type, offset, offset, offset, size, size, flags] {
+                push_u32(&mut raw, field);
+            }
+            push_u32(&mut raw, if header_type == 1 { 0x1000 } else { 4 });
+        };
+        // PT_LOAD and PT_DYNAMIC
+        push_segment(1, 7, 0, 0x13c);
+        push_segment(2, 6, 0xdc, 0x60);
+
+        // .dynsym and .dynstr
+        raw.extend_from_slice(&[0; 16]);
+        for value in [1, 0xc8, 1] {
+            push_u32(&mut raw, value);
+        }
+        raw.extend_from_slice(&[0x12, 0, 4, 0]);
+        raw.extend_from_slice(b"\0exported\0");
+        // .rel.dyn, R_386_RELATIVE, R_386_32 and R_386_RELATIVE
+        raw.resize(0xa0, 0);
+        for (offset, info) in [(0xd0, 8), (0xd4, (1 << 8) | 1), (0xd8, 8)] {
+            push_u32(&mut raw, offset);
+            push_u32(&mut raw, info);
+        }
+        // .text, .init_array and .fini_array
+        raw.resize(0xd0, 0xc3);
+        for value in [0xc0, 4, 0xc4] {
+            push_u32(&mut raw, value);
+        }
+        // .dynamic
+        for (tag, value) in [
+            (5, 0x94),
+            (10, 10),
+            (6, 0x74),
+            (11, 16),
+            (17, 0xa0),
+            (18, 24),
+            (19, 8),
+            (25, 0xd0),
+            (27, 8),
+            (26, 0xd8),
+            (28, 4),
+            (0, 0),
+        ] {
+            push_u32(&mut raw, tag);
+            push_u32(&mut raw, value);
+        }
+        raw.extend_from_slice(
+            b"\0.dynsym\0.dynstr\0.rel.dyn\0.text\0.init_array\0.fini_array\0.dynamic\0.shstrtab\0",
+        );
+
+        raw.resize(0x188, 0);
+        push_section_header_32(&mut raw, [0; 10]);
+        push_section_header_32(&mut raw, [1, 11, 2, 0x74, 0x74, 32, 2, 1, 4, 16]);
+        push_section_header_32(&mut raw, [9, 3, 2, 0x94, 0x94, 10, 0, 0, 1, 0]);
+        push_section_header_32(&mut raw, [17, 9, 2, 0xa0, 0xa0, 24, 1, 0, 4, 8]);
+        push_section_header_32(&mut raw, [26, 1, 6, 0xc0, 0xc0, 0x10, 0, 0, 16, 0]);
+        push_section_header_32(&mut raw, [32, 14, 3, 0xd0, 0xd0, 8, 0, 0, 4, 4]);
+        push_section_header_32(&mut raw, [44, 15, 3, 0xd8, 0xd8, 4, 0, 0, 4, 4]);
+        push_section_header_32(&mut raw, [56, 6, 3, 0xdc, 0xdc, 0x60, 2, 0, 4, 8]);
+        push_section_header_32(&mut raw, [65, 3, 0, 0, 0x13c, 75, 0, 0, 1, 0]);
+        raw
+    }
+
+    #[test]
+    fn can_get_rela_and_relr_init_arrays() {
+        let elf = load_elf_from_vec(get_test_library()).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        let arrays = get_init_arrays(&elf);
+        // The placeholder is kept as an entry but isn't a function
+        assert_eq!(
+            get_entries(&arrays),
+            vec![
+                (InitArrayType::PreInit, 0x160, vec![0x140]),
+                (InitArrayType::Init, 0x168, vec![0x148, 0x150, u64::MAX]),
+                (InitArrayType::Fini, 0x180, vec![0x158]),
+            ]
+        );
+        assert_eq!(arrays[1].get_functions(8), vec![0x148, 0x150]);
+
+        // Each entry was filled in by a different kind of relocation
+        let get_type = |address: u64| {
+            get_relocation_for_address(address, &elf.dynamic_relocations).map(|r| r.relocation_type)
+        };
+        let relative = Some(RelocationType::X86_64(RelocationX86_64::R_X86_64_RELATIVE));
+        assert_eq!(get_type(0x168), relative);
+        assert_eq!(
+            get_type(0x170),
+            Some(RelocationType::X86_64(RelocationX86_64::R_X86_64_64))
+        );
+        assert_eq!(get_type(0x178), None);
+        // From .relr.dyn, its addend is the value in the file
+        assert_eq!(get_type(0x180), relative);
+
+        // DT_INIT and DT_FINI come after the arrays
+        assert_eq!(
+            get_init_functions(&elf),
+            vec![0x140, 0x148, 0x150, 0x158, 0x130, 0x138]
+        );
+    }
+
+    #[test]
+    fn can_get_rel_init_arrays() {
+        let elf = load_elf_from_vec(get_test_library_32()).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert_eq!(
+            elf.dynamic_relocations[1].relocation_type,
+            RelocationType::X86(RelocationX86::R_386_32)
+        );
+        // The R_386_32 entry is the symbol plus the 4 already in the file
+        assert_eq!(
+            get_entries(&get_init_arrays(&elf)),
+            vec![
+                (InitArrayType::Init, 0xd0, vec![0xc0, 0xcc]),
+                (InitArrayType::Fini, 0xd8, vec![0xc4]),
+            ]
+        );
+        assert_eq!(get_init_functions(&elf), vec![0xc0, 0xcc, 0xc4]);
+    }
+
+    #[test]
+    fn can_get_relocatable_init_arrays() {
+        // An x86_64 object with R_X86_64_64 relocations against the .text section
+        // symbol and a destructor's symbol
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_REL, x86_64, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x50, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, // e_flags to e_phentsize
+            0x00, 0x00, 0x40, 0x00, 0x09, 0x00, 0x08, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        // .text, .init_array and .fini_array
+        raw.resize(0x70, 0xc3);
+        raw[0x60..0x70].copy_from_slice(&[0; 16]);
+        // .rela.init_array and .rela.fini_array
+        for symbol in [1, 2] {
+            push_u64(&mut raw, 0);
+            push_u64(&mut raw, (symbol << 32) | 1);
+            push_u64(&mut raw, 0);
+        }
+        // .symtab with the section symbol and the destructor, then .strtab
+        raw.extend_from_slice(&[0; 24]);
+        raw.extend_from_slice(&[0, 0, 0, 0, 0x03, 0, 1, 0]);
+        push_u64(&mut raw, 0);
+        push_u64(&mut raw, 0);
+        raw.extend_from_slice(&[1, 0, 0, 0, 0x02, 0, 1, 0]);
+        push_u64(&mut raw, 0x10);
+        push_u64(&mut raw, 1);
+        raw.extend_from_slice(b"\0destructor\0");
+        raw.extend_from_slice(
+            b"\0.text\0.init_array\0.rela.init_array\0.fini_array\0.rela.fini_array\0\
+              .symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x150, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 6, 0, 0x40, 0x20, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [7, 14, 3, 0, 0x60, 8, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [19, 4, 0x40, 0, 0x70, 24, 6, 2, 8, 24]);
+        push_section_header(&mut raw, [36, 15, 3, 0, 0x68, 8, 0, 0, 8, 8]);
+        push_section_header(&mut raw, [48, 4, 0x40, 0, 0x88, 24, 6, 4, 8, 24]);
+        push_section_header(&mut raw, [65, 2, 0, 0, 0xa0, 72, 7, 3, 8, 24]);
+        push_section_header(&mut raw, [73, 3, 0, 0, 0xe8, 12, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [81, 3, 0, 0, 0xf4, 91, 0, 0, 1, 0]);
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert_eq!(
+            get_entries(&get_init_arrays(&elf)),
+            vec![
+                (InitArrayType::Init, 0x1020, vec![0x1000]),
+                (InitArrayType::Fini, 0x1028, vec![0x1010]),
+            ]
+        );
+        assert_eq!(get_init_functions(&elf), vec![0x1000, 0x1010]);
+    }
+
+    #[test]
+    fn can_get_relocatable_rel_init_arrays() {
+        // An i386 object whose init array entry is .text plus the 8 in the file. The
+        // second R_386_32 is misaligned and ignored.
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x01, 0x01, 0x01, 0x00, // 32 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x01, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_REL, i386, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry and e_phoff
+            0xc8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff and e_flags
+            0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x28, 0x00, // e_ehsize to e_shentsize
+            0x07, 0x00, 0x06, 0x00, // e_shnum and e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        // .text and .init_array
+        raw.resize(0x50, 0xc3);
+        push_u32(&mut raw, 8);
+        push_u32(&mut raw, 0);
+        // .rel.init_array
+        for offset in [0, 6] {
+            push_u32(&mut raw, offset);
+            push_u32(&mut raw, (1 << 8) | 1);
+        }
+        // .symtab with the section symbol and an empty .strtab
+        raw.extend_from_slice(&[0; 16]);
+        raw.extend_from_slice(&[0; 12]);
+        raw.extend_from_slice(&[0x03, 0, 1, 0]);
+        raw.push(0);
+        raw.extend_from_slice(
+            b"\0.text\0.init_array\0.rel.init_array\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0xc8, 0);
+        push_section_header_32(&mut raw, [0; 10]);
+        push_section_header_32(&mut raw, [1, 1, 6, 0, 0x40, 0x10, 0, 0, 16, 0]);
+        push_section_header_32(&mut raw, [7, 14, 3, 0, 0x50, 8, 0, 0, 4, 4]);
+        push_section_header_32(&mut raw, [19, 9, 0x40, 0, 0x58, 16, 4, 2, 4, 8]);
+        push_section_header_32(&mut raw, [35, 2, 0, 0, 0x68, 32, 5, 2, 4, 16]);
+        push_section_header_32(&mut raw, [43, 3, 0, 0, 0x88, 1, 0, 0, 1, 0]);
+        push_section_header_32(&mut raw, [51, 3, 0, 0, 0x89, 61, 0, 0, 1, 0]);
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert_eq!(
+            get_entries(&get_init_arrays(&elf)),
+            vec![(InitArrayType::Init, 0x1010, vec![0x1008, 0])]
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use std::cell::{OnceCell, RefCell};
+use std::fs::File;
+use std::path::Path;
+
+use log::{debug, trace, warn};
+use memmap2::Mmap;
+
+use super::dynamic::{self, DynamicEntry};
+use super::elf_header::{ELFHeader, ELFIdent};
+use super::hash::{self, GnuHashTable, SysvHashTable};
+use super::note::{self, Note, NoteIterator};
+use super::plt::{self, PltEntry};
+use super::program_header::ProgramHeader;
+use super::relocation::{self, Relocation, RelocationTable};
+use super::section::{self, SectionHeader};
+use super::symbol::Symbol;
+use super::utils::get_null_terminated_string_from_vec;
+use super::validate;
+use super::version::{self, VersionDefinition, VersionNeed};
+use super::ELF;
+use crate::error::{Diagnostic, Result};
+
+// Maps a file into memory so it can be given to LazyELF::parse without reading it
+// all in. The file must not be modified while it is mapped.
+pub fn map_file<P: AsRef<Path>>(path: P) -> Result<Mmap> {
+    let file = File::open(path)?;
+    // Safety: the mapping is read only, changes to the file by another process
+    // are the caller's responsibility as documented above
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(map)
+}
+
+// An ELF file that borrows its data rather than owning a copy of it. Only the
+// ELF header is read up front, every other table is parsed the first time it is
+// asked for, so large binaries can be inspected cheaply. load_elf_from_buffer
+// builds the owned ELF on top of this.
+pub struct LazyELF<'a> {
+    pub elf_header: ELFHeader,
+    data: &'a [u8],
+    file_section_headers: OnceCell<Vec<SectionHeader>>,
+    synthetic_section_headers: OnceCell<Vec<SectionHeader>>,
+    program_headers: OnceCell<Vec<ProgramHeader>>,
+    dynamic: OnceCell<Vec<DynamicEntry>>,
+    dynamic_symbol_table: OnceCell<Vec<Symbol>>,
+    gnu_hash_table: OnceCell<Option<GnuHashTable>>,
+    sysv_hash_table: OnceCell<Option<SysvHashTable>>,
+    symbol_table: OnceCell<Vec<Symbol>>,
+    version_needs: OnceCell<Vec<VersionNeed>>,
+    version_definitions: OnceCell<Vec<VersionDefinition>>,
+    relocation_tables: OnceCell<Vec<RelocationTable>>,
+    dynamic_relocations: OnceCell<Vec<Relocation>>,
+    plt_relocations: OnceCell<Vec<Relocation>>,
+    plt_entries: OnceCell<Vec<PltEntry>>,
+    diagnostics: RefCell<Vec<Diagnostic>>,
+}
+
+impl<'a> LazyELF<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<LazyELF<'a>> {
+        let elf_ident = ELFIdent::parse_from_buffer(data)?;
+        let elf_header = ELFHeader::parse_from_buffer(data, elf_ident)?;
+        trace!("ELF header\n{:#?}", elf_header);
+        Ok(LazyELF {
+            elf_header,
+            data,
+            file_section_headers: OnceCell::new(),
+            synthetic_section_headers: OnceCell::new(),
+            program_headers: OnceCell::new(),
+            dynamic: OnceCell::new(),
+            dynamic_symbol_table: OnceCell::new(),
+            gnu_hash_table: OnceCell::new(),
+            sysv_hash_table: OnceCell::new(),
+            symbol_table: OnceCell::new(),
+            version_needs: OnceCell::new(),
+            version_definitions: OnceCell::new(),
+            relocation_tables: OnceCell::new(),
+            dynamic_relocations: OnceCell::new(),
+            plt_relocations: OnceCell::new(),
+            plt_entries: OnceCell::new(),
+            diagnostics: RefCell::new(vec![]),
+        })
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    fn add_diagnostics(&self, diagnostics: Vec<Diagnostic>) {
+        for diagnostic in &diagnostics {
+            warn!("{}", diagnostic);
+        }
+        self.diagnostics.borrow_mut().extend(diagnostics);
+    }
+
+    // Problems found in the tables parsed so far
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
+
+    // The section headers as they are in the file, which may be none at all
+    fn file_section_headers(&self) -> &[SectionHeader] {
+        self.file_section_headers.get_or_init(|| {
+            let mut diagnostics: Vec<Diagnostic> = vec![];
+            let section_headers =
+                parse_section_headers(self.data, &self.elf_header, &mut diagnostics);
+            debug!("read {} section headers", section_headers.len());
+            self.add_diagnostics(diagnostics);
+            section_headers
+        })
+    }
+
+    // Without section headers everything else is driven from the program headers,
+    // so stand in sections are made for the tables they point at
+    pub fn section_headers(&self) -> &[SectionHeader] {
+        let section_headers = self.file_section_headers();
+        if !section_headers.is_empty() {
+            return section_headers;
+        }
+        self.synthetic_section_headers.get_or_init(|| {
+            let section_headers = section::get_synthetic_section_headers(
+                self.data,
+                self.program_headers(),
+                self.dynamic(),
+                &self.elf_header,
+            );
+            debug!("made {} synthetic section headers", section_headers.len());
+            section_headers
+        })
+    }
+
+    pub fn program_headers(&self) -> &[ProgramHeader] {
+        self.program_headers.get_or_init(|| {
+            let mut diagnostics: Vec<Diagnostic> = vec![];
+            let program_headers =
+                parse_program_headers(self.data, &self.elf_header, &mut diagnostics);
+            debug!("read {} program headers", program_headers.len());
+            self.add_diagnostics(diagnostics);
+            program_headers
+        })
+    }
+
+    pub fn dynamic(&self) -> &[DynamicEntry] {
+        self.dynamic.get_or_init(|| {
+            dynamic::get_dynamic_entries(
+                self.data,
+                self.program_headers(),
+                self.file_section_headers(),
+                &self.elf_header,
+            )
+        })
+    }
+
+    pub fn gnu_hash_table(&self) -> Option<&GnuHashTable> {
+        self.gnu_hash_table
+            .get_or_init(|| {
+                hash::get_gnu_hash_table(
+                    self.data,
+                    self.dynamic(),
+                    self.program_headers(),
+                    &self.elf_header,
+                )
+            })
+            .as_ref()
+    }
+
+    pub fn sysv_hash_table(&self) -> Option<&SysvHashTable> {
+        self.sysv_hash_table
+            .get_or_init(|| {
+                hash::get_sysv_hash_table(
+                    self.data,
+                    self.dynamic(),
+                    self.program_headers(),
+                    &self.elf_header,
+                )
+            })
+            .as_ref()
+    }
+
+    pub fn dynamic_symbol_table(&self) -> &[Symbol] {
+        self.dynamic_symbol_table.get_or_init(|| {
+            let symbols = match section::get_dynamic_symbols(
+                self.data,
+                self.section_headers(),
+                &self.elf_header,
+            ) {
+                Ok(v) => v,
+                Err(e) => {
+                    self.add_diagnostics(vec![e.into()]);
+                    vec![]
+                }
+            };
+            if !symbols.is_empty() {
+                return symbols;
+            }
+            // No usable .dynsym section so walk the hash tables instead
+            hash::get_dynamic_symbols_from_hash(
+                self.data,
+                self.dynamic(),
+                self.program_headers(),
+                &self.elf_header,
+            )
+        })
+    }
+
+    pub fn symbol_table(&self) -> &[Symbol] {
+        self.symbol_table.get_or_init(|| {
+            match section::get_symbols(self.data, self.section_headers(), &self.elf_header) {
+                Ok(v) => v,
+                Err(e) => {
+                    self.add_diagnostics(vec![e.into()]);
+                    vec![]
+                }
+            }
+        })
+    }
+
+    pub fn version_needs(&self) -> &[VersionNeed] {
+        self.version_needs.get_or_init(|| {
+            version::get_version_needs(self.data, self.section_headers(), &self.elf_header)
+        })
+    }
+
+    pub fn version_definitions(&self) -> &[VersionDefinition] {
+        self.version_definitions.get_or_init(|| {
+            version::get_version_definitions(self.data, self.section_headers(), &self.elf_header)
+        })
+    }
+
+    pub fn relocation_tables(&self) -> &[RelocationTable] {
+        self.relocation_tables.get_or_init(|| {
+            relocation::get_relocation_tables(
+                self.data,
+                self.section_headers(),
+                &self.elf_header,
+                self.dynamic_symbol_table(),
+                self.symbol_table(),
+            )
+        })
+    }
+
+    pub fn dynamic_relocations(&self) -> &[Relocation] {
+        self.dynamic_relocations.get_or_init(|| {
+            relocation::get_dynamic_relocations(
+                self.data,
+                self.dynamic(),
+                self.program_headers(),
+                &self.elf_header,
+                self.dynamic_symbol_table(),
+            )
+        })
+    }
+
+    pub fn plt_relocations(&self) -> &[Relocation] {
+        self.plt_relocations.get_or_init(|| {
+            relocation::get_plt_relocations(
+                self.data,
+                self.dynamic(),
+                self.program_headers(),
+                &self.elf_header,
+                self.dynamic_symbol_table(),
+            )
+        })
+    }
+
+    pub fn plt_entries(&self) -> &[PltEntry] {
+        self.plt_entries.get_or_init(|| {
+            plt::get_plt_entries(
+                self.data,
+                self.section_headers(),
+                &self.elf_header,
+                self.dynamic(),
+                self.plt_relocations(),
+                self.dynamic_relocations(),
+            )
+        })
+    }
+
+    // Iterates over the notes in the PT_NOTE segments, or the SHT_NOTE sections
+    // when there are no note segments
+    pub fn notes(&self) -> impl Iterator<Item = Note> + '_ {
+        note::get_note_areas(self.data, self.program_headers(), self.section_headers())
+            .into_iter()
+            .flat_map(move |(data, align)| NoteIterator::new(data, align, &self.elf_header))
+    }
+
+    // Finds a dynamic symbol through DT_GNU_HASH or DT_HASH, falling back to a
+    // linear scan when the binary has neither
+    pub fn lookup_dynamic_symbol(&self, name: &str) -> Option<&Symbol> {
+        hash::lookup_dynamic_symbol(
+            name,
+            self.gnu_hash_table(),
+            self.sysv_hash_table(),
+            self.dynamic_symbol_table(),
+        )
+    }
+
+    pub fn build_id(&self) -> Option<Vec<u8>> {
+        note::get_build_id(self.notes())
+    }
+
+    // Parses everything and moves the tables into an ELF. The tables don't borrow
+    // the file data, so the caller is free to move its data into the ELF after.
+    pub(super) fn into_elf(mut self) -> ELF {
+        // Parse in the same order the tables depend on each other
+        self.section_headers();
+        self.plt_entries();
+        self.relocation_tables();
+        self.version_needs();
+        self.version_definitions();
+        self.gnu_hash_table();
+        self.sysv_hash_table();
+
+        let section_headers = match self.synthetic_section_headers.take() {
+            Some(v) => v,
+            None => self.file_section_headers.take().unwrap_or_default(),
+        };
+        ELF {
+            elf_header: self.elf_header,
+            program_headers: self.program_headers.take().unwrap_or_default(),
+            section_headers,
+            dynamic: self.dynamic.take().unwrap_or_default(),
+            dynamic_symbol_table: self.dynamic_symbol_table.take().unwrap_or_default(),
+            gnu_hash_table: self.gnu_hash_table.take().flatten(),
+            sysv_hash_table: self.sysv_hash_table.take().flatten(),
+            symbol_table: self.symbol_table.take().unwrap_or_default(),
+            version_needs: self.version_needs.take().unwrap_or_default(),
+            version_definitions: self.version_definitions.take().unwrap_or_default(),
+            relocation_tables: self.relocation_tables.take().unwrap_or_default(),
+            dynamic_relocations: self.dynamic_relocations.take().unwrap_or_default(),
+            plt_relocations: self.plt_relocations.take().unwrap_or_default(),
+            plt_entries: self.plt_entries.take().unwrap_or_default(),
+            diagnostics: self.diagnostics.into_inner(),
+            debug_file: None,
+            data: vec![],
+        }
+    }
+}
+
+// Reads the section headers and their names. Anything past the end of the file
+// is dropped with a diagnostic.
+fn parse_section_headers(
+    data: &[u8],
+    elf_header: &ELFHeader,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<SectionHeader> {
+    let mut section_headers: Vec<SectionHeader> = Vec::new();
+    for i in 0..validate::get_section_header_count(data, elf_header, diagnostics) {
+        match SectionHeader::parse_from_buffer(i, data, elf_header) {
+            Ok(v) => section_headers.push(v),
+            Err(e) => {
+                diagnostics.push(e.into());
+                break;
+            }
+        }
+    }
+
+    // get names for section headers
+    let name_index = validate::get_section_name_index(data, elf_header) as usize;
+    if name_index < section_headers.len() {
+        let name_data = section_headers[name_index].get_data(data);
+        for (i, sh) in section_headers.iter_mut().enumerate() {
+            if sh.name as usize >= name_data.len() {
+                diagnostics.push(Diagnostic::new(
+                    elf_header.e_shoff,
+                    format!("section {} name offset {:#x} is out of range", i, sh.name),
+                ));
+                continue;
+            }
+            sh.name_string = get_null_terminated_string_from_vec(name_data, sh.name as usize);
+        }
+    } else if name_index != 0 && !section_headers.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            elf_header.e_shoff,
+            format!(
+                "section name table index {} is not a valid section",
+                name_index
+            ),
+        ));
+    }
+    section::group::resolve_section_groups(data, &mut section_headers, elf_header, diagnostics);
+    diagnostics.append(&mut validate::check_section_headers(data, &section_headers));
+    section_headers
+}
+
+fn parse_program_headers(
+    data: &[u8],
+    elf_header: &ELFHeader,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<ProgramHeader> {
+    let mut program_headers: Vec<ProgramHeader> = Vec::new();
+    for i in 0..validate::get_program_header_count(data, elf_header, diagnostics) {
+        match ProgramHeader::parse_from_buffer(i, data, elf_header) {
+            Ok(v) => program_headers.push(v),
+            Err(e) => {
+                diagnostics.push(e.into());
+                break;
+            }
+        }
+    }
+    diagnostics.append(&mut validate::check_program_headers(data, &program_headers));
+    program_headers
+}
+
+#[cfg(test)]
+mod lazy_tests {
+    use super::*;
+    use crate::elf::load_elf_from_buffer;
+    use std::io::Cursor;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    fn push_symbol(raw: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64, size: u64) {
+        raw.extend_from_slice(&name.to_le_bytes());
+        raw.extend_from_slice(&[info, 0]);
+        raw.extend_from_slice(&shndx.to_le_bytes());
+        raw.extend_from_slice(&value.to_le_bytes());
+        raw.extend_from_slice(&size.to_le_bytes());
+    }
+
+    // A 64 bit x86_64 executable with one PT_LOAD for .text, .dynsym and .dynstr,
+    // exporting a function, and a .symtab with main
+    fn get_test_binary() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x02, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_EXEC, x86_64, version 1
+            0x80, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x58, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x01, 0x00, 0x40, 0x00, 0x07, 0x00, 0x06, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+
+        // PT_LOAD, read and execute, for everything up to .symtab
+        push_u32(&mut raw, 1);
+        push_u32(&mut raw, 5);
+        push_u64(&mut raw, 0);
+        push_u64(&mut raw, 0x40_0000);
+        push_u64(&mut raw, 0x40_0000);
+        push_u64(&mut raw, 0xd0);
+        push_u64(&mut raw, 0xd0);
+        push_u64(&mut raw, 0x1000);
+
+        // .text
+        raw.resize(0x80, 0);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0x90, 0xcc);
+        // .dynsym and .dynstr
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 1, 0x12, 1, 0x40_0084, 1);
+        raw.extend_from_slice(b"\0exported\0");
+        // .symtab and .strtab
+        raw.resize(0xd0, 0);
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 1, 0x04, 0xfff1, 0, 0);
+        push_symbol(&mut raw, 8, 0x12, 1, 0x40_0080, 5);
+        raw.extend_from_slice(b"\0test.c\0main\0");
+        // .shstrtab
+        raw.extend_from_slice(b"\0.text\0.dynsym\0.dynstr\0.symtab\0.strtab\0.shstrtab\0");
+
+        raw.resize(0x158, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 6, 0x40_0080, 0x80, 0x10, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [7, 11, 2, 0x40_0090, 0x90, 0x30, 3, 1, 8, 24]);
+        push_section_header(&mut raw, [15, 3, 2, 0x40_00c0, 0xc0, 0x0a, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [23, 2, 0, 0, 0xd0, 0x48, 5, 2, 8, 24]);
+        push_section_header(&mut raw, [31, 3, 0, 0, 0x118, 0x0d, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [39, 3, 0, 0, 0x125, 0x31, 0, 0, 1, 0]);
+        raw
+    }
+
+    #[test]
+    fn can_load_lazily() {
+        let path = std::env::temp_dir().join(format!("binload-lazy-{}", std::process::id()));
+        std::fs::write(&path, get_test_binary()).unwrap();
+        let map = map_file(&path).unwrap();
+        let lazy = LazyELF::parse(&map).unwrap();
+        assert!(lazy.program_headers.get().is_none());
+        let names: Vec<&str> = lazy
+            .section_headers()
+            .iter()
+            .map(|s| s.name_string.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "",
+                ".text",
+                ".dynsym",
+                ".dynstr",
+                ".symtab",
+                ".strtab",
+                ".shstrtab"
+            ]
+        );
+        assert!(lazy.program_headers.get().is_none());
+        assert!(lazy.symbol_table.get().is_none());
+        assert_eq!(lazy.program_headers()[0].file_size, 0xd0);
+        assert_eq!(lazy.symbol_table()[2].name_string, "main");
+        assert_eq!(lazy.symbol_table()[2].address, 0x40_0080);
+        assert!(lazy.dynamic_symbol_table.get().is_none());
+        assert_eq!(
+            lazy.lookup_dynamic_symbol("exported").map(|s| s.address),
+            Some(0x40_0084)
+        );
+        assert!(lazy.lookup_dynamic_symbol("main").is_none());
+
+        let mut cursor = Cursor::new(map.to_vec());
+        let elf = load_elf_from_buffer(&mut cursor).unwrap();
+        assert_eq!(elf.data.len(), lazy.data().len());
+        assert_eq!(elf.section_headers.len(), lazy.section_headers().len());
+        assert_eq!(elf.program_headers.len(), lazy.program_headers().len());
+        assert_eq!(elf.symbol_table.len(), lazy.symbol_table().len());
+        assert_eq!(elf.dynamic_symbol_table.len(), 2);
+        assert!(lazy.diagnostics().is_empty());
+        assert_eq!(elf.diagnostics, lazy.diagnostics());
+        drop(map);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
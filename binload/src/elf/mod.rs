@@ -1,81 +1,284 @@
+// elf_header must come first as it defines the get_num_from_bytes macro
 pub mod elf_header;
+
+pub mod checksec;
+pub mod core;
+pub mod debuglink;
+pub mod dynamic;
+pub mod hash;
+pub mod init_array;
+pub mod lazy;
+pub mod note;
+pub mod plt;
 pub mod program_header;
+pub mod relocatable;
+pub mod relocation;
 pub mod section;
 pub mod symbol;
+pub mod tls;
 pub mod utils;
+pub mod validate;
+pub mod version;
+pub mod writer;
 
+use crate::elf::elf_header::E_Type;
 use crate::elf::symbol::Symbol;
+use crate::error::{Diagnostic, Result};
+use dynamic::DynamicEntry;
+use hash::{GnuHashTable, SysvHashTable};
+pub use lazy::{map_file, LazyELF};
+use note::{Note, NoteDescriptor, NoteIterator};
+use plt::PltEntry;
 use program_header::ProgramHeader;
-use section::SectionHeader;
-use utils::get_null_terminated_string_from_vec;
+use relocation::{Relocation, RelocationTable};
+use section::{SectionHeader, SectionType};
+use std::path::{Path, PathBuf};
+use version::{VersionDefinition, VersionNeed};
+pub use writer::ELFWriter;
 
 pub struct ELF {
     pub elf_header: elf_header::ELFHeader,
     pub program_headers: Vec<ProgramHeader>,
     pub section_headers: Vec<SectionHeader>,
+    pub dynamic: Vec<DynamicEntry>,
     pub dynamic_symbol_table: Vec<Symbol>,
+    pub gnu_hash_table: Option<GnuHashTable>,
+    pub sysv_hash_table: Option<SysvHashTable>,
     pub symbol_table: Vec<Symbol>,
+    pub version_needs: Vec<VersionNeed>,
+    pub version_definitions: Vec<VersionDefinition>,
+    pub relocation_tables: Vec<RelocationTable>,
+    pub dynamic_relocations: Vec<Relocation>,
+    pub plt_relocations: Vec<Relocation>,
+    pub plt_entries: Vec<PltEntry>,
+    pub diagnostics: Vec<Diagnostic>, // Problems worked around while loading
+    pub debug_file: Option<Box<ELF>>, // The separate debug file, see load_debug_file
     pub data: Vec<u8>,
 }
 
-pub fn load_elf_from_buffer<T: std::io::Read + std::io::Seek>(buffer: &mut T) -> Result<ELF, &str> {
-    let mut data: Vec<u8> = vec![];
-    buffer.read_to_end(&mut data).unwrap();
+impl ELF {
+    // Iterates over the notes in the PT_NOTE segments, or the SHT_NOTE sections
+    // when there are no note segments
+    pub fn notes(&self) -> impl Iterator<Item = Note> + '_ {
+        note::get_note_areas(&self.data, &self.program_headers, &self.section_headers)
+            .into_iter()
+            .flat_map(move |(data, align)| NoteIterator::new(data, align, &self.elf_header))
+    }
 
-    // First we get ELF headers
-    let elf_ident = elf_header::ELFIdent::parse_from_buffer(&data)?;
+    // Finds a dynamic symbol through DT_GNU_HASH or DT_HASH, falling back to a
+    // linear scan when the binary has neither
+    pub fn lookup_dynamic_symbol(&self, name: &str) -> Option<&Symbol> {
+        hash::lookup_dynamic_symbol(
+            name,
+            self.gnu_hash_table.as_ref(),
+            self.sysv_hash_table.as_ref(),
+            &self.dynamic_symbol_table,
+        )
+    }
 
-    println!("Read struct: \n{:#?}", elf_ident);
-    let elf_header = elf_header::ELFHeader::parse_from_buffer(&data, elf_ident);
-    println!("header32\n{:#?}", elf_header);
+    pub fn build_id(&self) -> Option<Vec<u8>> {
+        note::get_build_id(self.notes())
+    }
+
+    // Finds the separate debug file of a stripped binary through its build ID or
+    // .gnu_debuglink, adds the symbols from its .symtab and keeps it for the DWARF
+    // sections. path is where this file was loaded from, see
+    // debuglink::find_debug_file. Returns where the debug file was found.
+    pub fn load_debug_file(
+        &mut self,
+        path: Option<&Path>,
+        debug_directories: &[PathBuf],
+    ) -> Result<PathBuf> {
+        let (debug_path, debug_file) = debuglink::find_debug_file(self, path, debug_directories)?;
+        let mut symbols = debuglink::get_missing_symbols(&self.symbol_table, &debug_file);
+        self.symbol_table.append(&mut symbols);
+        self.debug_file = Some(Box::new(debug_file));
+        Ok(debug_path)
+    }
+
+    // The thread local storage template from PT_TLS
+    pub fn get_tls_template(&self) -> Option<tls::TlsTemplate> {
+        tls::TlsTemplate::from_elf(self)
+    }
 
-    // Now we get section headers
-    let mut section_headers: Vec<SectionHeader> = Vec::new();
-    for i in 0..elf_header.e_shnum {
-        section_headers.push(SectionHeader::parse_from_buffer(i, &data, &elf_header));
+    // The preinit, init and fini arrays, with relocations applied
+    pub fn get_init_arrays(&self) -> Vec<init_array::InitArray> {
+        init_array::get_init_arrays(self)
     }
 
-    // get names for section headers
-    {
-        let name_data = section_headers[elf_header.e_shstrndx as usize].get_data(&data);
-        for sh in &mut section_headers {
-            sh.name_string = get_null_terminated_string_from_vec(&name_data, sh.name as usize);
+    // Functions that run without being called from the code, see
+    // init_array::get_init_functions
+    pub fn get_init_functions(&self) -> Vec<u64> {
+        init_array::get_init_functions(self)
+    }
+
+    // The hardening the binary was built with (PIE, NX, RELRO, canaries, ...)
+    pub fn checksec(&self) -> checksec::Checksec {
+        checksec::Checksec::from_elf(self)
+    }
+
+    pub fn is_core(&self) -> bool {
+        self.elf_header.e_type == E_Type::CORE
+    }
+
+    // The threads, mapped files and other process state from the notes of a core
+    // dump. None for other kinds of file.
+    pub fn get_core(&self) -> Option<core::Core> {
+        if self.is_core() {
+            Some(core::Core::from_elf(self))
+        } else {
+            None
         }
     }
 
-    // get program headers
-    let mut program_headers: Vec<ProgramHeader> = Vec::new();
-    for i in 0..elf_header.e_phnum {
-        program_headers.push(ProgramHeader::parse_from_buffer(i, &data, &elf_header));
-    }
-    println!("Section Headers");
-    println!("{}", get_section_headers_print_string(&section_headers));
-    println!();
-    println!("Program Headers");
-    println!("{}", get_program_headers_print_string(&program_headers));
-    println!();
-
-    // TODO: you are here - time to get ELF symbols!!
-    let dynamic_symbol_table = section::get_dynamic_symbols(&data, &section_headers, &elf_header)?;
-    println!("Dynamic Symbols");
-    println!("{}", get_symbol_print_string(&dynamic_symbol_table));
-    println!();
-    let symbol_table = section::get_symbols(&data, &section_headers, &elf_header)?;
-    println!("Dynamic Symbols");
-    println!("{}", get_symbol_print_string(&symbol_table));
-    println!();
-
-    Ok(ELF {
-        elf_header,
-        program_headers,
-        section_headers,
-        dynamic_symbol_table,
-        symbol_table,
-        data,
-    })
-}
-
-fn get_section_headers_print_string(section_headers: &[SectionHeader]) -> String {
+    // Relocatable objects (.o files) haven't been linked, symbol values and
+    // relocation offsets are relative to a section rather than addresses
+    pub fn is_relocatable(&self) -> bool {
+        self.elf_header.e_type == E_Type::REL
+    }
+
+    // None for undefined, absolute and common symbols
+    pub fn get_symbol_section(&self, symbol: &Symbol) -> Option<&SectionHeader> {
+        self.section_headers.get(symbol.section_index.get_index()?)
+    }
+
+    // The section the relocations are applied to, from sh_info. None for the
+    // dynamic relocation tables, which apply to the whole image.
+    pub fn get_relocation_target(&self, table: &RelocationTable) -> Option<&SectionHeader> {
+        if table.target_section_index == 0 {
+            return None;
+        }
+        self.section_headers.get(table.target_section_index as usize)
+    }
+
+    // Where the symbol's data starts in the file, None when it isn't backed by the file
+    pub fn get_symbol_file_offset(&self, symbol: &Symbol) -> Option<u64> {
+        let section = self.get_symbol_section(symbol)?;
+        if section.section_type == SectionType::NoBits {
+            return None;
+        }
+        let delta = if self.is_relocatable() {
+            symbol.address
+        } else {
+            symbol.address.checked_sub(section.address)?
+        };
+        if delta >= section.size {
+            return None;
+        }
+        section.offset.checked_add(delta)
+    }
+
+    // The address of each section header. Relocatable objects are given a layout,
+    // see relocatable::get_section_layout.
+    pub fn get_section_addresses(&self) -> Vec<u64> {
+        if self.is_relocatable() {
+            relocatable::get_section_layout(&self.section_headers)
+        } else {
+            self.section_headers.iter().map(|s| s.address).collect()
+        }
+    }
+}
+
+// Reads the whole file and parses everything up front. LazyELF parses on demand
+// and can borrow a memory mapped file instead.
+pub fn load_elf_from_buffer<T: std::io::Read + std::io::Seek>(buffer: &mut T) -> Result<ELF> {
+    let mut data: Vec<u8> = vec![];
+    buffer.read_to_end(&mut data)?;
+
+    load_elf_from_vec(data)
+}
+
+// Takes ownership of data that has already been read so it isn't copied again
+pub fn load_elf_from_vec(data: Vec<u8>) -> Result<ELF> {
+    let mut elf = LazyELF::parse(&data)?.into_elf();
+    elf.data = data;
+    Ok(elf)
+}
+
+pub fn is_elf(binary: &[u8]) -> bool {
+    binary.get(0..4) == Some(&[0x7F, 0x45, 0x4C, 0x46])
+}
+
+// Everything that was loaded, formatted as a report
+pub fn get_elf_print_string(elf: &ELF) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!("ELF Header\n{:#?}", elf.elf_header));
+    // Stand ins made from the program headers are labelled so they aren't
+    // mistaken for the real thing
+    let section_title = if elf.section_headers.iter().any(|sh| sh.synthetic) {
+        "Synthetic Section Headers"
+    } else {
+        "Section Headers"
+    };
+    strings.push(format!(
+        "\n{}\n{}",
+        section_title,
+        get_section_headers_print_string(&elf.section_headers)
+    ));
+    strings.push(format!(
+        "\nProgram Headers\n{}",
+        get_program_headers_print_string(&elf.program_headers)
+    ));
+    strings.push(format!(
+        "\nDynamic Section\n{}",
+        get_dynamic_print_string(&elf.dynamic)
+    ));
+    strings.push(format!(
+        "\nDynamic Symbols\n{}",
+        get_symbol_print_string(&elf.dynamic_symbol_table)
+    ));
+    strings.push(format!(
+        "\nSymbols\n{}",
+        get_symbol_print_string(&elf.symbol_table)
+    ));
+    strings.push(format!(
+        "\nVersion Needs\n{}",
+        get_version_needs_print_string(&elf.version_needs)
+    ));
+    strings.push(format!(
+        "\nVersion Definitions\n{}",
+        get_version_definitions_print_string(&elf.version_definitions)
+    ));
+    for table in &elf.relocation_tables {
+        strings.push(format!(
+            "\nRelocation section {}\n{}",
+            table.name,
+            get_relocation_print_string(&table.relocations)
+        ));
+    }
+    strings.push(format!(
+        "\nPLT Entries\n{}",
+        get_plt_print_string(&elf.plt_entries)
+    ));
+    strings.push(format!(
+        "\nInit Arrays\n{}",
+        get_init_array_print_string(&elf.get_init_arrays())
+    ));
+    if let Some(template) = elf.get_tls_template() {
+        strings.push(format!("\nTLS Template\n{}", template));
+    }
+    strings.push(format!("\nNotes\n{}", get_note_print_string(elf.notes())));
+    strings.push(format!(
+        "\nDiagnostics\n{}",
+        get_diagnostics_print_string(&elf.diagnostics)
+    ));
+    strings.join("\n")
+}
+
+pub fn get_diagnostics_print_string(diagnostics: &[Diagnostic]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!("{:18} {}", "Offset", "Message"));
+    for i in diagnostics {
+        strings.push(format!(
+            "{:18} {}",
+            i.offset.map(|v| format!("{:#018x}", v)).unwrap_or_default(),
+            i.message,
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_section_headers_print_string(section_headers: &[SectionHeader]) -> String {
     let mut strings: Vec<String> = vec![];
     strings.push(format!(
         "{:15} {:15} {:10} {:18} {:18} {:10} {:10} {:10} {:10} {:10}",
@@ -99,7 +302,7 @@ fn get_section_headers_print_string(section_headers: &[SectionHeader]) -> String
     strings.join("\n")
 }
 
-fn get_symbol_print_string(symbols: &[Symbol]) -> String {
+pub fn get_symbol_print_string(symbols: &[Symbol]) -> String {
     let mut strings: Vec<String> = vec![];
     strings.push(format!(
         "{:25} {:18} {:18} {:10} {:10} {:10} {:6}",
@@ -107,14 +310,130 @@ fn get_symbol_print_string(symbols: &[Symbol]) -> String {
     ));
     for i in symbols {
         strings.push(format!(
-            "{:<25} {:#018x} {:#018x} {:10?} {:10?} {:10?} {:#06x}",
-            i.name_string, i.address, i.size, i.bind, i.symbol_type, i.visibility, i.section_index,
+            "{:<25} {:#018x} {:#018x} {:10?} {:10?} {:10?} {:>6}",
+            i.get_versioned_name(),
+            i.address,
+            i.size,
+            i.bind,
+            i.symbol_type,
+            i.visibility,
+            i.section_index,
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_version_needs_print_string(needs: &[VersionNeed]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!(
+        "{:25} {:20} {:6} {}",
+        "File", "Name", "Index", "Flags"
+    ));
+    for i in needs {
+        for j in &i.versions {
+            strings.push(format!(
+                "{:25} {:20} {:<6} {:?}",
+                i.file, j.name, j.index, j.flags
+            ));
+        }
+    }
+    strings.join("\n")
+}
+
+pub fn get_version_definitions_print_string(definitions: &[VersionDefinition]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!(
+        "{:20} {:6} {:10} {}",
+        "Name", "Index", "Flags", "Parents"
+    ));
+    for i in definitions {
+        strings.push(format!(
+            "{:20} {:<6} {:10} {}",
+            i.name,
+            i.index,
+            format!("{:?}", i.flags),
+            i.parents.join(", ")
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_dynamic_print_string(entries: &[DynamicEntry]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!("{:18} {:18} {}", "Tag", "Value", "String"));
+    for i in entries {
+        strings.push(format!(
+            "{:18} {:#018x} {}",
+            i.tag.to_string(),
+            i.value,
+            i.string_value.as_deref().unwrap_or(""),
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_relocation_print_string(relocations: &[Relocation]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!(
+        "{:18} {:25} {:18} {}",
+        "Offset", "Type", "Addend", "Symbol",
+    ));
+    for i in relocations {
+        strings.push(format!(
+            "{:#018x} {:25} {:#018x} {}",
+            i.offset,
+            i.relocation_type.to_string(),
+            i.addend.unwrap_or(0),
+            i.get_symbol_name().unwrap_or(""),
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_plt_print_string(entries: &[PltEntry]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!(
+        "{:18} {:18} {:10} {}",
+        "Address", "GOT Address", "Section", "Symbol",
+    ));
+    for i in entries {
+        strings.push(format!(
+            "{:#018x} {:#018x} {:10} {}",
+            i.address, i.got_address, i.section_name, i.symbol_name,
+        ));
+    }
+    strings.join("\n")
+}
+
+pub fn get_init_array_print_string(arrays: &[init_array::InitArray]) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!("{:10} {:18} {}", "Type", "Address", "Entries"));
+    for array in arrays {
+        strings.push(array.to_string().trim_end().to_string());
+    }
+    strings.join("\n")
+}
+
+pub fn get_note_print_string<I: Iterator<Item = Note>>(notes: I) -> String {
+    let mut strings: Vec<String> = vec![];
+    strings.push(format!("{:10} {:25} {}", "Owner", "Type", "Description"));
+    for i in notes {
+        let description = match &i.descriptor {
+            NoteDescriptor::BuildId(v) => note::get_build_id_string(v),
+            NoteDescriptor::Raw(v) => format!("{} bytes", v.len()),
+            v => format!("{:?}", v),
+        };
+        strings.push(format!(
+            "{:10} {:25} {}",
+            i.name,
+            i.get_type_string(),
+            description
         ));
     }
     strings.join("\n")
 }
 
-fn get_program_headers_print_string(program_headers: &[ProgramHeader]) -> String {
+pub fn get_program_headers_print_string(program_headers: &[ProgramHeader]) -> String {
     let mut strings: Vec<String> = vec![];
     strings.push(format!(
         "{:15} {:10} {:18} {:18} {:10} {:10} {:6} {:10}",
@@ -0,0 +1,717 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use bitflags::bitflags;
+
+use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::program_header::{ProgramHeader, ProgramHeaderType};
+use super::section::{SectionHeader, SectionType};
+use super::utils::get_null_terminated_string_from_vec;
+
+// Note types for notes owned by "GNU"
+const NT_GNU_ABI_TAG: u32 = 1;
+const NT_GNU_BUILD_ID: u32 = 3;
+const NT_GNU_GOLD_VERSION: u32 = 4;
+const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+// Note types for notes owned by "CORE"
+const NT_PRSTATUS: u32 = 1;
+const NT_AUXV: u32 = 6;
+const NT_SIGINFO: u32 = 0x5349_4749;
+const NT_FILE: u32 = 0x4649_4c45;
+
+// Signals that set si_addr to the address of the fault
+const SIGILL: u32 = 4;
+const SIGTRAP: u32 = 5;
+const SIGBUS: u32 = 7;
+const SIGFPE: u32 = 8;
+const SIGSEGV: u32 = 11;
+
+// Property types found in NT_GNU_PROPERTY_TYPE_0 notes
+const GNU_PROPERTY_STACK_SIZE: u32 = 1;
+const GNU_PROPERTY_NO_COPY_ON_PROTECTED: u32 = 2;
+const GNU_PROPERTY_AARCH64_FEATURE_1_AND: u32 = 0xc000_0000;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+const GNU_PROPERTY_X86_ISA_1_NEEDED: u32 = 0xc000_8002;
+
+bitflags! {
+    // Values of the GNU_PROPERTY_X86_FEATURE_1_AND property
+    pub struct X86Features: u32 {
+        const IBT = 0x0000_0001; // Indirect branch tracking (endbr)
+        const SHSTK = 0x0000_0002; // Shadow stack
+    }
+}
+
+bitflags! {
+    // Values of the GNU_PROPERTY_AARCH64_FEATURE_1_AND property
+    pub struct AArch64Features: u32 {
+        const BTI = 0x0000_0001; // Branch target identification
+        const PAC = 0x0000_0002; // Pointer authentication
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum AbiOS {
+    Linux,   // 0
+    Hurd,    // 1
+    Solaris, // 2
+    FreeBSD, // 3
+    Unknown(u32),
+}
+impl AbiOS {
+    fn from_u32(value: u32) -> AbiOS {
+        match value {
+            0 => AbiOS::Linux,
+            1 => AbiOS::Hurd,
+            2 => AbiOS::Solaris,
+            3 => AbiOS::FreeBSD,
+            _ => AbiOS::Unknown(value),
+        }
+    }
+}
+
+impl fmt::Display for AbiOS {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbiOS::Linux => write!(f, "Linux"),
+            AbiOS::Hurd => write!(f, "Hurd"),
+            AbiOS::Solaris => write!(f, "Solaris"),
+            AbiOS::FreeBSD => write!(f, "FreeBSD"),
+            AbiOS::Unknown(v) => write!(f, "Unknown ({})", v),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum GnuProperty {
+    StackSize(u64),
+    NoCopyOnProtected,
+    X86Feature1(X86Features),
+    X86IsaNeeded(u32),
+    AArch64Feature1(AArch64Features),
+    Unknown { property_type: u32, data: Vec<u8> },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct FileMapping {
+    pub start: u64,       // Start address of the mapping
+    pub end: u64,         // End address of the mapping
+    pub file_offset: u64, // Offset in bytes into the mapped file
+    pub path: String,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PrStatus {
+    pub signal: u32,         // si_signo of the signal that stopped the thread
+    pub code: u32,           // si_code
+    pub errno: u32,          // si_errno
+    pub current_signal: u16, // pr_cursig
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgrp: u32,
+    pub sid: u32,
+    pub registers: Vec<u8>, // Raw pr_reg, layout depends on the machine
+}
+
+// The a_type of an auxiliary vector entry, the values the kernel passed to the
+// process at startup
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum AuxvType {
+    AT_NULL,          // 0
+    AT_IGNORE,        // 1
+    AT_EXECFD,        // 2
+    AT_PHDR,          // 3
+    AT_PHENT,         // 4
+    AT_PHNUM,         // 5
+    AT_PAGESZ,        // 6
+    AT_BASE,          // 7
+    AT_FLAGS,         // 8
+    AT_ENTRY,         // 9
+    AT_NOTELF,        // 10
+    AT_UID,           // 11
+    AT_EUID,          // 12
+    AT_GID,           // 13
+    AT_EGID,          // 14
+    AT_PLATFORM,      // 15
+    AT_HWCAP,         // 16
+    AT_CLKTCK,        // 17
+    AT_SECURE,        // 23
+    AT_BASE_PLATFORM, // 24
+    AT_RANDOM,        // 25
+    AT_HWCAP2,        // 26
+    AT_EXECFN,        // 31
+    AT_SYSINFO,       // 32
+    AT_SYSINFO_EHDR,  // 33
+    AT_MINSIGSTKSZ,   // 51
+    Unknown(u64),
+}
+impl AuxvType {
+    pub fn from_u64(value: u64) -> AuxvType {
+        match value {
+            0 => AuxvType::AT_NULL,
+            1 => AuxvType::AT_IGNORE,
+            2 => AuxvType::AT_EXECFD,
+            3 => AuxvType::AT_PHDR,
+            4 => AuxvType::AT_PHENT,
+            5 => AuxvType::AT_PHNUM,
+            6 => AuxvType::AT_PAGESZ,
+            7 => AuxvType::AT_BASE,
+            8 => AuxvType::AT_FLAGS,
+            9 => AuxvType::AT_ENTRY,
+            10 => AuxvType::AT_NOTELF,
+            11 => AuxvType::AT_UID,
+            12 => AuxvType::AT_EUID,
+            13 => AuxvType::AT_GID,
+            14 => AuxvType::AT_EGID,
+            15 => AuxvType::AT_PLATFORM,
+            16 => AuxvType::AT_HWCAP,
+            17 => AuxvType::AT_CLKTCK,
+            23 => AuxvType::AT_SECURE,
+            24 => AuxvType::AT_BASE_PLATFORM,
+            25 => AuxvType::AT_RANDOM,
+            26 => AuxvType::AT_HWCAP2,
+            31 => AuxvType::AT_EXECFN,
+            32 => AuxvType::AT_SYSINFO,
+            33 => AuxvType::AT_SYSINFO_EHDR,
+            51 => AuxvType::AT_MINSIGSTKSZ,
+            _ => AuxvType::Unknown(value),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct AuxvEntry {
+    pub key: AuxvType,
+    pub value: u64, // A number or an address in the process, depending on the key
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SigInfo {
+    pub signal: u32,                // si_signo
+    pub errno: u32,                 // si_errno
+    pub code: i32,                  // si_code, negative when the signal was sent by a process
+    pub fault_address: Option<u64>, // si_addr, for signals raised by a faulting instruction
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum NoteDescriptor {
+    BuildId(Vec<u8>),
+    AbiTag {
+        os: AbiOS,
+        major: u32,
+        minor: u32,
+        patch: u32,
+    },
+    GoldVersion(String),
+    GnuProperties(Vec<GnuProperty>),
+    PrStatus(PrStatus),
+    File(Vec<FileMapping>),
+    Auxv(Vec<AuxvEntry>),
+    SigInfo(SigInfo),
+    Raw(Vec<u8>),
+}
+
+pub struct Note {
+    pub name: String,   // Owner of the note, e.g. "GNU" or "CORE"
+    pub note_type: u32, // Meaning depends on the owner
+    pub descriptor: NoteDescriptor,
+}
+
+impl Note {
+    pub fn parse_from_buffer(
+        name: String,
+        note_type: u32,
+        desc: &[u8],
+        header: &ELFHeader,
+    ) -> Note {
+        let descriptor = match (name.as_str(), note_type) {
+            ("GNU", NT_GNU_BUILD_ID) => Some(NoteDescriptor::BuildId(desc.to_vec())),
+            ("GNU", NT_GNU_ABI_TAG) => parse_abi_tag(desc, header),
+            ("GNU", NT_GNU_GOLD_VERSION) => Some(NoteDescriptor::GoldVersion(
+                get_null_terminated_string_from_vec(desc, 0),
+            )),
+            ("GNU", NT_GNU_PROPERTY_TYPE_0) => Some(NoteDescriptor::GnuProperties(
+                parse_gnu_properties(desc, header),
+            )),
+            ("CORE", NT_PRSTATUS) => parse_prstatus(desc, header),
+            ("CORE", NT_FILE) => parse_file_mappings(desc, header),
+            ("CORE", NT_AUXV) => Some(NoteDescriptor::Auxv(parse_auxv(desc, header))),
+            ("CORE", NT_SIGINFO) => parse_siginfo(desc, header),
+            _ => None,
+        }
+        .unwrap_or_else(|| NoteDescriptor::Raw(desc.to_vec()));
+        Note {
+            name,
+            note_type,
+            descriptor,
+        }
+    }
+
+    pub fn get_type_string(&self) -> String {
+        match (self.name.as_str(), self.note_type) {
+            ("GNU", NT_GNU_ABI_TAG) => "NT_GNU_ABI_TAG".to_string(),
+            ("GNU", NT_GNU_BUILD_ID) => "NT_GNU_BUILD_ID".to_string(),
+            ("GNU", NT_GNU_GOLD_VERSION) => "NT_GNU_GOLD_VERSION".to_string(),
+            ("GNU", NT_GNU_PROPERTY_TYPE_0) => "NT_GNU_PROPERTY_TYPE_0".to_string(),
+            ("CORE", NT_PRSTATUS) => "NT_PRSTATUS".to_string(),
+            ("CORE", NT_FILE) => "NT_FILE".to_string(),
+            ("CORE", NT_AUXV) => "NT_AUXV".to_string(),
+            ("CORE", NT_SIGINFO) => "NT_SIGINFO".to_string(),
+            _ => format!("{:#x}", self.note_type),
+        }
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}{}", "Name:", self.name),
+            format!("{:15}{}", "Type:", self.get_type_string()),
+            format!("{:15}{:?}", "Descriptor:", self.descriptor),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// Walks the notes in a single PT_NOTE segment or SHT_NOTE section. Each note is
+// a namesz/descsz/type header followed by the name and descriptor, both padded
+// to the alignment of the containing segment or section.
+pub struct NoteIterator<'a> {
+    data: &'a [u8],
+    offset: usize,
+    align: usize,
+    header: &'a ELFHeader,
+}
+
+impl<'a> NoteIterator<'a> {
+    pub fn new(data: &'a [u8], align: u64, header: &'a ELFHeader) -> NoteIterator<'a> {
+        NoteIterator {
+            data,
+            offset: 0,
+            // Only 4 and 8 byte alignments are used in practice
+            align: if align == 8 { 8 } else { 4 },
+            header,
+        }
+    }
+}
+
+impl<'a> Iterator for NoteIterator<'a> {
+    type Item = Note;
+
+    fn next(&mut self) -> Option<Note> {
+        let u32_from_bytes = get_num_from_bytes!(u32, self.header.ident.ei_data);
+
+        let raw = self.data.get(self.offset..self.offset + 12)?;
+        let name_size = u32_from_bytes(raw[0..4].try_into().unwrap()) as usize;
+        let desc_size = u32_from_bytes(raw[4..8].try_into().unwrap()) as usize;
+        let note_type = u32_from_bytes(raw[8..12].try_into().unwrap());
+
+        let name_start = self.offset + 12;
+        let desc_start = align_up(name_start + name_size, self.align);
+        let desc_end = desc_start.checked_add(desc_size)?;
+        let name = get_null_terminated_string_from_vec(
+            self.data.get(name_start..name_start + name_size)?,
+            0,
+        );
+        let desc = self.data.get(desc_start..desc_end)?;
+
+        self.offset = align_up(desc_end, self.align);
+        Some(Note::parse_from_buffer(name, note_type, desc, self.header))
+    }
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+// Returns the raw data and alignment for every area of the file holding notes.
+// PT_NOTE segments are preferred, falling back to SHT_NOTE sections for objects
+// without program headers.
+pub fn get_note_areas<'a>(
+    data: &'a [u8],
+    program_headers: &[ProgramHeader],
+    section_headers: &[SectionHeader],
+) -> Vec<(&'a [u8], u64)> {
+    let mut areas: Vec<(&[u8], u64)> = vec![];
+    for ph in program_headers {
+        if ph.header_type == ProgramHeaderType::Note {
+            let v = ph.get_data(data);
+            if !v.is_empty() {
+                areas.push((v, ph.align));
+            }
+        }
+    }
+    if !areas.is_empty() {
+        return areas;
+    }
+    for sh in section_headers {
+        if sh.section_type == SectionType::Note {
+            let v = sh.get_data(data);
+            if !v.is_empty() {
+                areas.push((v, sh.addralign));
+            }
+        }
+    }
+    areas
+}
+
+// The GNU build ID is the first NT_GNU_BUILD_ID note
+pub fn get_build_id<I: Iterator<Item = Note>>(mut notes: I) -> Option<Vec<u8>> {
+    notes.find_map(|n| match n.descriptor {
+        NoteDescriptor::BuildId(v) => Some(v),
+        _ => None,
+    })
+}
+
+pub fn get_build_id_string(build_id: &[u8]) -> String {
+    build_id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_abi_tag(desc: &[u8], header: &ELFHeader) -> Option<NoteDescriptor> {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+    if desc.len() < 16 {
+        return None;
+    }
+    Some(NoteDescriptor::AbiTag {
+        os: AbiOS::from_u32(u32_from_bytes(desc[0..4].try_into().unwrap())),
+        major: u32_from_bytes(desc[4..8].try_into().unwrap()),
+        minor: u32_from_bytes(desc[8..12].try_into().unwrap()),
+        patch: u32_from_bytes(desc[12..16].try_into().unwrap()),
+    })
+}
+
+// Each property is a type/size pair followed by data padded to the word size
+fn parse_gnu_properties(desc: &[u8], header: &ELFHeader) -> Vec<GnuProperty> {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+    let word_size = match header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    };
+
+    let mut properties: Vec<GnuProperty> = vec![];
+    let mut offset = 0;
+    while offset + 8 <= desc.len() {
+        let property_type = u32_from_bytes(desc[offset..offset + 4].try_into().unwrap());
+        let size = u32_from_bytes(desc[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let data = match desc.get(offset + 8..offset + 8 + size) {
+            Some(v) => v,
+            None => break,
+        };
+        let value = if size >= 4 {
+            u32_from_bytes(data[0..4].try_into().unwrap())
+        } else {
+            0
+        };
+        properties.push(match property_type {
+            GNU_PROPERTY_STACK_SIZE if size == word_size => {
+                GnuProperty::StackSize(read_word(data, header))
+            }
+            GNU_PROPERTY_NO_COPY_ON_PROTECTED => GnuProperty::NoCopyOnProtected,
+            GNU_PROPERTY_X86_FEATURE_1_AND if size == 4 => {
+                GnuProperty::X86Feature1(X86Features::from_bits_truncate(value))
+            }
+            GNU_PROPERTY_X86_ISA_1_NEEDED if size == 4 => GnuProperty::X86IsaNeeded(value),
+            GNU_PROPERTY_AARCH64_FEATURE_1_AND if size == 4 => {
+                GnuProperty::AArch64Feature1(AArch64Features::from_bits_truncate(value))
+            }
+            _ => GnuProperty::Unknown {
+                property_type,
+                data: data.to_vec(),
+            },
+        });
+        offset = align_up(offset + 8 + size, word_size);
+    }
+    properties
+}
+
+// Reads an unsigned long sized value
+fn read_word(raw: &[u8], header: &ELFHeader) -> u64 {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+    let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+    match header.ident.ei_class {
+        EI_Class::ELF32 => u64::from(u32_from_bytes(raw[0..4].try_into().unwrap())),
+        EI_Class::ELF64 => u64_from_bytes(raw[0..8].try_into().unwrap()),
+    }
+}
+
+// struct elf_prstatus from the kernel. The layout only differs by the size of
+// unsigned long and struct timeval, after which comes pr_reg and pr_fpvalid.
+fn parse_prstatus(desc: &[u8], header: &ELFHeader) -> Option<NoteDescriptor> {
+    let u16_from_bytes = get_num_from_bytes!(u16, header.ident.ei_data);
+    let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+    let (pid_offset, registers_offset, trailer_size) = match header.ident.ei_class {
+        EI_Class::ELF32 => (24, 72, 4),
+        EI_Class::ELF64 => (32, 112, 8),
+    };
+    if desc.len() < registers_offset + trailer_size {
+        return None;
+    }
+    Some(NoteDescriptor::PrStatus(PrStatus {
+        signal: u32_from_bytes(desc[0..4].try_into().unwrap()),
+        code: u32_from_bytes(desc[4..8].try_into().unwrap()),
+        errno: u32_from_bytes(desc[8..12].try_into().unwrap()),
+        current_signal: u16_from_bytes(desc[12..14].try_into().unwrap()),
+        pid: u32_from_bytes(desc[pid_offset..pid_offset + 4].try_into().unwrap()),
+        ppid: u32_from_bytes(desc[pid_offset + 4..pid_offset + 8].try_into().unwrap()),
+        pgrp: u32_from_bytes(desc[pid_offset + 8..pid_offset + 12].try_into().unwrap()),
+        sid: u32_from_bytes(desc[pid_offset + 12..pid_offset + 16].try_into().unwrap()),
+        registers: desc[registers_offset..desc.len() - trailer_size].to_vec(),
+    }))
+}
+
+// NT_FILE is a count and page size, then a start/end/page offset triple for
+// each mapping, followed by the null terminated path of each mapping.
+fn parse_file_mappings(desc: &[u8], header: &ELFHeader) -> Option<NoteDescriptor> {
+    let word_size = match header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    };
+    if desc.len() < word_size * 2 {
+        return None;
+    }
+    let count = read_word(desc, header) as usize;
+    let page_size = read_word(&desc[word_size..], header);
+    let mut offset = word_size * 2;
+    let paths_offset = offset.checked_add(count.checked_mul(word_size * 3)?)?;
+    if paths_offset > desc.len() {
+        return None;
+    }
+
+    let mut mappings: Vec<FileMapping> = vec![];
+    let mut path_offset = paths_offset;
+    for _ in 0..count {
+        let path = get_null_terminated_string_from_vec(desc, path_offset);
+        path_offset += path.len() + 1;
+        mappings.push(FileMapping {
+            start: read_word(&desc[offset..], header),
+            end: read_word(&desc[offset + word_size..], header),
+            file_offset: read_word(&desc[offset + word_size * 2..], header) * page_size,
+            path,
+        });
+        offset += word_size * 3;
+    }
+    Some(NoteDescriptor::File(mappings))
+}
+
+// Pairs of unsigned longs, ending with AT_NULL
+fn parse_auxv(desc: &[u8], header: &ELFHeader) -> Vec<AuxvEntry> {
+    let word_size = match header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    };
+    let mut entries: Vec<AuxvEntry> = vec![];
+    for raw in desc.chunks_exact(word_size * 2) {
+        let key = AuxvType::from_u64(read_word(raw, header));
+        if key == AuxvType::AT_NULL {
+            break;
+        }
+        entries.push(AuxvEntry {
+            key,
+            value: read_word(&raw[word_size..], header),
+        });
+    }
+    entries
+}
+
+// The start of siginfo_t. The union after si_code is aligned to the word size,
+// for the fault signals it starts with si_addr.
+fn parse_siginfo(desc: &[u8], header: &ELFHeader) -> Option<NoteDescriptor> {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+    let address_offset = match header.ident.ei_class {
+        EI_Class::ELF32 => 12,
+        EI_Class::ELF64 => 16,
+    };
+    if desc.len() < address_offset + 8 {
+        return None;
+    }
+    let signal = u32_from_bytes(desc[0..4].try_into().unwrap());
+    let code = u32_from_bytes(desc[8..12].try_into().unwrap()) as i32;
+    // Only the kernel (si_code > 0) fills in si_addr
+    let fault_address = match signal {
+        SIGILL | SIGTRAP | SIGBUS | SIGFPE | SIGSEGV if code > 0 => {
+            Some(read_word(&desc[address_offset..], header))
+        }
+        _ => None,
+    };
+    Some(NoteDescriptor::SigInfo(SigInfo {
+        signal,
+        errno: u32_from_bytes(desc[4..8].try_into().unwrap()),
+        code,
+        fault_address,
+    }))
+}
+
+#[cfg(test)]
+mod note_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x23,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x0D, 0x00, 0x40, 0x00, 0x1E, 0x00, 0x1D, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_parse_build_id_and_abi_tag_notes() {
+        let raw = [
+            0x04, 0x00, 0x00, 0x00, // namesz
+            0x10, 0x00, 0x00, 0x00, // descsz
+            0x01, 0x00, 0x00, 0x00, // NT_GNU_ABI_TAG
+            0x47, 0x4e, 0x55, 0x00, // "GNU"
+            0x00, 0x00, 0x00, 0x00, // Linux
+            0x03, 0x00, 0x00, 0x00, // 3
+            0x02, 0x00, 0x00, 0x00, // 2
+            0x00, 0x00, 0x00, 0x00, // 0
+            0x04, 0x00, 0x00, 0x00, // namesz
+            0x06, 0x00, 0x00, 0x00, // descsz
+            0x03, 0x00, 0x00, 0x00, // NT_GNU_BUILD_ID
+            0x47, 0x4e, 0x55, 0x00, // "GNU"
+            0xde, 0xad, 0xbe, 0xef, // build id
+            0x01, 0x02, 0x00, 0x00, // build id and padding
+        ];
+        let header = get_64_bit_header();
+        let notes: Vec<Note> = NoteIterator::new(&raw, 4, &header).collect();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(
+            notes[0].descriptor,
+            NoteDescriptor::AbiTag {
+                os: AbiOS::Linux,
+                major: 3,
+                minor: 2,
+                patch: 0
+            }
+        );
+        match &notes[1].descriptor {
+            NoteDescriptor::BuildId(v) => assert_eq!(get_build_id_string(v), "deadbeef0102"),
+            _ => panic!("expected a build id"),
+        }
+    }
+
+    #[test]
+    fn can_parse_gnu_property_note() {
+        let raw = [
+            0x04, 0x00, 0x00, 0x00, // namesz
+            0x20, 0x00, 0x00, 0x00, // descsz
+            0x05, 0x00, 0x00, 0x00, // NT_GNU_PROPERTY_TYPE_0
+            0x47, 0x4e, 0x55, 0x00, // "GNU"
+            0x02, 0x00, 0x00, 0xc0, // GNU_PROPERTY_X86_FEATURE_1_AND
+            0x04, 0x00, 0x00, 0x00, // size
+            0x03, 0x00, 0x00, 0x00, // IBT | SHSTK
+            0x00, 0x00, 0x00, 0x00, // padding
+            0x02, 0x80, 0x00, 0xc0, // GNU_PROPERTY_X86_ISA_1_NEEDED
+            0x04, 0x00, 0x00, 0x00, // size
+            0x01, 0x00, 0x00, 0x00, // baseline
+            0x00, 0x00, 0x00, 0x00, // padding
+        ];
+        let header = get_64_bit_header();
+        let notes: Vec<Note> = NoteIterator::new(&raw, 8, &header).collect();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(
+            notes[0].descriptor,
+            NoteDescriptor::GnuProperties(vec![
+                GnuProperty::X86Feature1(X86Features::IBT | X86Features::SHSTK),
+                GnuProperty::X86IsaNeeded(1),
+            ])
+        );
+    }
+
+    #[test]
+    fn can_parse_nt_file_note() {
+        let raw = [
+            0x05, 0x00, 0x00, 0x00, // namesz
+            0x2e, 0x00, 0x00, 0x00, // descsz
+            0x45, 0x4c, 0x49, 0x46, // NT_FILE
+            0x43, 0x4f, 0x52, 0x45, // "CORE"
+            0x00, 0x00, 0x00, 0x00, // "\0" and padding
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // count
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // page size
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // start
+            0x00, 0x20, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // end
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // page offset
+            0x2f, 0x62, 0x69, 0x6e, 0x2f, 0x00, // "/bin/"
+        ];
+        let header = get_64_bit_header();
+        let notes: Vec<Note> = NoteIterator::new(&raw, 4, &header).collect();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(
+            notes[0].descriptor,
+            NoteDescriptor::File(vec![FileMapping {
+                start: 0x40_0000,
+                end: 0x40_2000,
+                file_offset: 0x2000,
+                path: "/bin/".to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn can_parse_auxv_and_siginfo_notes() {
+        let raw = [
+            0x05, 0x00, 0x00, 0x00, // namesz
+            0x30, 0x00, 0x00, 0x00, // descsz
+            0x06, 0x00, 0x00, 0x00, // NT_AUXV
+            0x43, 0x4f, 0x52, 0x45, // "CORE"
+            0x00, 0x00, 0x00, 0x00, // "\0" and padding
+            0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // AT_PAGESZ
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0x1000
+            0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // AT_ENTRY
+            0x60, 0x10, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // 0x401060
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // AT_NULL
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+            0x05, 0x00, 0x00, 0x00, // namesz
+            0x18, 0x00, 0x00, 0x00, // descsz
+            0x49, 0x47, 0x49, 0x53, // NT_SIGINFO
+            0x43, 0x4f, 0x52, 0x45, // "CORE"
+            0x00, 0x00, 0x00, 0x00, // "\0" and padding
+            0x0b, 0x00, 0x00, 0x00, // SIGSEGV
+            0x00, 0x00, 0x00, 0x00, // errno
+            0x01, 0x00, 0x00, 0x00, // SEGV_MAPERR
+            0x00, 0x00, 0x00, 0x00, // padding
+            0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // si_addr
+        ];
+        let header = get_64_bit_header();
+        let notes: Vec<Note> = NoteIterator::new(&raw, 4, &header).collect();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(
+            notes[0].descriptor,
+            NoteDescriptor::Auxv(vec![
+                AuxvEntry {
+                    key: AuxvType::AT_PAGESZ,
+                    value: 0x1000,
+                },
+                AuxvEntry {
+                    key: AuxvType::AT_ENTRY,
+                    value: 0x40_1060,
+                },
+            ])
+        );
+        assert_eq!(notes[1].get_type_string(), "NT_SIGINFO");
+        assert_eq!(
+            notes[1].descriptor,
+            NoteDescriptor::SigInfo(SigInfo {
+                signal: 11,
+                errno: 0,
+                code: 1,
+                fault_address: Some(0x10),
+            })
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use super::dynamic::{get_dynamic_entry, DynamicEntry, DynamicTag};
+use super::elf_header::{EI_Data, ELFHeader, E_Machine};
+use super::relocation::{get_relocation_for_address, Relocation};
+use super::section::{get_section_by_name, SectionHeader};
+
+// The sections that hold PLT stubs. .plt.sec is used when IBT is enabled and .plt.got holds
+// stubs for functions that are also referenced through the GOT (non-lazy binding).
+const PLT_SECTION_NAMES: [&str; 4] = [".plt", ".plt.sec", ".plt.got", ".iplt"];
+
+pub struct PltEntry {
+    pub address: u64,         // Address of the stub
+    pub got_address: u64,     // Address of the GOT slot the stub jumps through
+    pub symbol_name: String,  // Name of the dynamic symbol the GOT slot is bound to
+    pub section_name: String, // Section the stub lives in
+}
+
+impl PltEntry {
+    // Returns the name in the form objdump uses, e.g. puts@plt
+    pub fn get_display_name(&self) -> String {
+        format!("{}@plt", self.symbol_name)
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}0x{:x?}", "Address:", self.address),
+            format!("{:15}0x{:x?}", "GOT address:", self.got_address),
+            format!("{:15}{}", "Symbol:", self.symbol_name),
+            format!("{:15}{}", "Section:", self.section_name),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for PltEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for PltEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// What we could work out from the instructions of a single stub
+enum StubTarget {
+    GotSlot(u64),         // The stub jumps through this GOT slot
+    RelocationIndex(u64), // The stub pushes this index into DT_JMPREL (lazy binding stubs)
+}
+
+// Decodes the GOT slot used by an x86 or x86-64 stub. We look for an indirect jmp
+// (optionally with a bnd prefix) and fall back to the lazy binding push.
+fn decode_x86_stub(
+    bytes: &[u8],
+    address: u64,
+    is_64_bit: bool,
+    got_base: Option<u64>,
+) -> Option<StubTarget> {
+    let mut push_index: Option<u64> = None;
+    for i in 0..bytes.len() {
+        let rest = &bytes[i..];
+        if rest.len() >= 6 && rest[0] == 0xff && (rest[1] == 0x25 || rest[1] == 0xa3) {
+            let disp = u32::from_le_bytes(rest[2..6].try_into().unwrap());
+            if is_64_bit && rest[1] == 0x25 {
+                // jmp *disp32(%rip), relative to the next instruction
+                let next = address.wrapping_add(i as u64 + 6);
+                return Some(StubTarget::GotSlot(
+                    next.wrapping_add(i64::from(disp as i32) as u64),
+                ));
+            } else if !is_64_bit && rest[1] == 0x25 {
+                // jmp *abs32, used by non-PIC executables
+                return Some(StubTarget::GotSlot(u64::from(disp)));
+            } else if !is_64_bit {
+                // jmp *disp32(%ebx), %ebx holds the address of the GOT
+                if let Some(base) = got_base {
+                    return Some(StubTarget::GotSlot(
+                        base.wrapping_add(i64::from(disp as i32) as u64),
+                    ));
+                }
+            }
+        }
+        if push_index.is_none() && rest.len() >= 5 && rest[0] == 0x68 {
+            let value = u64::from(u32::from_le_bytes(rest[1..5].try_into().unwrap()));
+            // x86-64 pushes the relocation index, i386 pushes the byte offset of an Elf32_Rel
+            push_index = Some(if is_64_bit { value } else { value / 8 });
+        }
+    }
+    push_index.map(StubTarget::RelocationIndex)
+}
+
+// Decodes the `adrp x16, page; ldr x17, [x16, #offset]` pair used by AArch64 stubs
+fn decode_aarch64_stub(first: u32, second: u32, address: u64) -> Option<StubTarget> {
+    // adrp x16, imm
+    if first & 0x9f00_001f != 0x9000_0010 {
+        return None;
+    }
+    // ldr x17, [x16, #imm]
+    if second & 0xffc0_03ff != 0xf940_0211 {
+        return None;
+    }
+    let immlo = u64::from((first >> 29) & 0x3);
+    let immhi = u64::from((first >> 5) & 0x7ffff);
+    let mut imm = ((immhi << 2) | immlo) << 12;
+    // sign extend from 33 bits
+    if imm & (1 << 32) != 0 {
+        imm |= !0x1_ffff_ffff;
+    }
+    let page = (address & !0xfff).wrapping_add(imm);
+    let offset = u64::from((second >> 10) & 0xfff) * 8;
+    Some(StubTarget::GotSlot(page.wrapping_add(offset)))
+}
+
+// ARM immediates are an 8 bit value rotated right by twice the 4 bit rotate field
+fn decode_arm_immediate(instruction: u32) -> u64 {
+    let value = instruction & 0xff;
+    let rotate = ((instruction >> 8) & 0xf) * 2;
+    u64::from(value.rotate_right(rotate))
+}
+
+// Decodes the `add ip, pc, #imm; add ip, ip, #imm; ldr pc, [ip, #imm]!` sequence used by
+// ARM stubs
+fn decode_arm_stub(instructions: &[u32], address: u64) -> Option<StubTarget> {
+    if instructions.len() < 3
+        || instructions[0] & 0xffff_f000 != 0xe28f_c000
+        || instructions[1] & 0xffff_f000 != 0xe28c_c000
+        || instructions[2] & 0xffff_f000 != 0xe5bc_f000
+    {
+        return None;
+    }
+    // pc reads as the address of the current instruction plus 8
+    let mut ip = address
+        .wrapping_add(8)
+        .wrapping_add(decode_arm_immediate(instructions[0]));
+    ip = ip.wrapping_add(decode_arm_immediate(instructions[1]));
+    ip = ip.wrapping_add(u64::from(instructions[2] & 0xfff));
+    Some(StubTarget::GotSlot(ip))
+}
+
+fn get_symbol_name_for_slot(
+    got_address: u64,
+    plt_relocations: &[Relocation],
+    dynamic_relocations: &[Relocation],
+) -> Option<String> {
+    get_relocation_for_address(got_address, plt_relocations)
+        .or_else(|| get_relocation_for_address(got_address, dynamic_relocations))
+        .and_then(|relocation| relocation.get_symbol_name())
+        .map(String::from)
+}
+
+fn resolve_stub(
+    address: u64,
+    target: StubTarget,
+    section_name: &str,
+    plt_relocations: &[Relocation],
+    dynamic_relocations: &[Relocation],
+) -> Option<PltEntry> {
+    let (got_address, symbol_name) = match target {
+        StubTarget::GotSlot(got_address) => (
+            got_address,
+            get_symbol_name_for_slot(got_address, plt_relocations, dynamic_relocations)?,
+        ),
+        StubTarget::RelocationIndex(index) => {
+            let relocation = plt_relocations.get(index as usize)?;
+            (
+                relocation.offset,
+                String::from(relocation.get_symbol_name()?),
+            )
+        }
+    };
+    Some(PltEntry {
+        address,
+        got_address,
+        symbol_name,
+        section_name: String::from(section_name),
+    })
+}
+
+fn get_x86_plt_entries(
+    section: &SectionHeader,
+    bytes: &[u8],
+    is_64_bit: bool,
+    got_base: Option<u64>,
+    plt_relocations: &[Relocation],
+    dynamic_relocations: &[Relocation],
+) -> Vec<PltEntry> {
+    let mut entries: Vec<PltEntry> = vec![];
+    let entry_size = match section.entsize {
+        0 => 16,
+        v if v > section.size => 16,
+        v => v as usize,
+    };
+    for (index, stub) in bytes.chunks(entry_size).enumerate() {
+        let address = section.address.wrapping_add((index * entry_size) as u64);
+        if let Some(target) = decode_x86_stub(stub, address, is_64_bit, got_base) {
+            if let Some(entry) = resolve_stub(
+                address,
+                target,
+                &section.name_string,
+                plt_relocations,
+                dynamic_relocations,
+            ) {
+                entries.push(entry);
+            }
+        }
+    }
+    entries
+}
+
+fn get_arm_plt_entries(
+    section: &SectionHeader,
+    bytes: &[u8],
+    elf_header: &ELFHeader,
+    plt_relocations: &[Relocation],
+    dynamic_relocations: &[Relocation],
+) -> Vec<PltEntry> {
+    let u32_from_bytes = get_num_from_bytes!(u32, elf_header.ident.ei_data);
+    let instructions: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|raw| u32_from_bytes(raw.try_into().unwrap()))
+        .collect();
+
+    let mut entries: Vec<PltEntry> = vec![];
+    for index in 0..instructions.len() {
+        let address = section.address.wrapping_add((index * 4) as u64);
+        let target = match elf_header.e_machine {
+            E_Machine::ARM_AARCH64 if index + 1 < instructions.len() => {
+                decode_aarch64_stub(instructions[index], instructions[index + 1], address)
+            }
+            E_Machine::ARM => decode_arm_stub(&instructions[index..], address),
+            _ => None,
+        };
+        let target = match target {
+            Some(v) => v,
+            None => continue,
+        };
+        // With BTI the stub starts with a `bti c` landing pad before the adrp
+        let stub_address = if index > 0 && instructions[index - 1] == 0xd503_245f {
+            address.wrapping_sub(4)
+        } else {
+            address
+        };
+        if let Some(entry) = resolve_stub(
+            stub_address,
+            target,
+            &section.name_string,
+            plt_relocations,
+            dynamic_relocations,
+        ) {
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+// Maps each stub in the PLT sections to the dynamic symbol it ends up calling. The stubs are
+// decoded to find the GOT slot they jump through, which is then matched with the relocation
+// the dynamic loader applies to that slot (DT_JMPREL, or GLOB_DAT for .plt.got).
+pub fn get_plt_entries(
+    data: &[u8],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+    dynamic: &[DynamicEntry],
+    plt_relocations: &[Relocation],
+    dynamic_relocations: &[Relocation],
+) -> Vec<PltEntry> {
+    let got_base = get_dynamic_entry(DynamicTag::PltGot, dynamic).map(|v| v.value);
+
+    let mut entries: Vec<PltEntry> = vec![];
+    for name in PLT_SECTION_NAMES.iter() {
+        let section = match get_section_by_name(name, section_headers) {
+            Some(v) => v,
+            None => continue,
+        };
+        let bytes = section.get_data(data);
+        entries.append(&mut match elf_header.e_machine {
+            E_Machine::X86 | E_Machine::X86_64 => get_x86_plt_entries(
+                section,
+                bytes,
+                elf_header.e_machine == E_Machine::X86_64,
+                got_base,
+                plt_relocations,
+                dynamic_relocations,
+            ),
+            E_Machine::ARM | E_Machine::ARM_AARCH64 => get_arm_plt_entries(
+                section,
+                bytes,
+                elf_header,
+                plt_relocations,
+                dynamic_relocations,
+            ),
+            _ => vec![],
+        });
+    }
+
+    // A lazy .plt stub and its .plt.sec counterpart both resolve to the same symbol; callers
+    // may jump to either so we keep both, but never the same address twice.
+    entries.sort_by_key(|entry| entry.address);
+    entries.dedup_by_key(|entry| entry.address);
+    entries
+}
+
+pub fn get_plt_entry_for_address(address: u64, entries: &[PltEntry]) -> Option<&PltEntry> {
+    entries.iter().find(|entry| entry.address == address)
+}
+
+#[cfg(test)]
+mod plt_tests {
+    use super::*;
+
+    #[test]
+    fn can_decode_x86_64_plt_stub() {
+        // jmp *0x2fe2(%rip); push $0x1; jmp .plt
+        let raw = [
+            0xff, 0x25, 0xe2, 0x2f, 0x00, 0x00, 0x68, 0x01, 0x00, 0x00, 0x00, 0xe9, 0xd0, 0xff,
+            0xff, 0xff,
+        ];
+        match decode_x86_stub(&raw, 0x1030, true, None) {
+            Some(StubTarget::GotSlot(v)) => assert_eq!(v, 0x4018),
+            _ => panic!("failed to decode stub"),
+        }
+    }
+
+    #[test]
+    fn can_decode_x86_64_ibt_plt_stub() {
+        // endbr64; push $0x2; bnd jmp .plt; nop
+        let raw = [
+            0xf3, 0x0f, 0x1e, 0xfa, 0x68, 0x02, 0x00, 0x00, 0x00, 0xf2, 0xe9, 0xe1, 0xff, 0xff,
+            0xff, 0x90,
+        ];
+        match decode_x86_stub(&raw, 0x1040, true, None) {
+            Some(StubTarget::RelocationIndex(v)) => assert_eq!(v, 2),
+            _ => panic!("failed to decode stub"),
+        }
+    }
+
+    #[test]
+    fn can_decode_i386_pic_plt_stub() {
+        // jmp *0xc(%ebx); push $0x0; jmp .plt
+        let raw = [
+            0xff, 0xa3, 0x0c, 0x00, 0x00, 0x00, 0x68, 0x00, 0x00, 0x00, 0x00, 0xe9, 0xe0, 0xff,
+            0xff, 0xff,
+        ];
+        match decode_x86_stub(&raw, 0x1030, false, Some(0x3ff4)) {
+            Some(StubTarget::GotSlot(v)) => assert_eq!(v, 0x4000),
+            _ => panic!("failed to decode stub"),
+        }
+    }
+
+    #[test]
+    fn can_decode_aarch64_plt_stub() {
+        // 0x610: adrp x16, 0x10000; ldr x17, [x16, #0xfa8]
+        match decode_aarch64_stub(0x9000_0090, 0xf947_d611, 0x610) {
+            Some(StubTarget::GotSlot(v)) => assert_eq!(v, 0x10fa8),
+            _ => panic!("failed to decode stub"),
+        }
+    }
+
+    #[test]
+    fn can_decode_arm_plt_stub() {
+        // 0x3e4: add ip, pc, #0, 12; add ip, ip, #16, 20; ldr pc, [ip, #3108]!
+        let instructions = [0xe28f_c600, 0xe28c_ca10, 0xe5bc_fc24];
+        match decode_arm_stub(&instructions, 0x3e4) {
+            Some(StubTarget::GotSlot(v)) => assert_eq!(v, 0x3e4 + 8 + 0x10000 + 0xc24),
+            _ => panic!("failed to decode stub"),
+        }
+    }
+}
@@ -7,9 +7,11 @@ use std::convert::TryInto;
 use std::fmt;
 
 use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::utils::get_clamped_slice;
+use crate::error::{get_bytes, Result};
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 pub enum ProgramHeaderType {
     Null,                   // 0x00 Program header table entry unused
     Load,                   // 0x01 Loadable segment
@@ -95,24 +97,34 @@ pub struct ProgramHeader {
 }
 
 impl ProgramHeader {
+    // Clamped to the end of the file like SectionHeader::get_data
     pub fn get_data<'a>(&self, binary: &'a [u8]) -> &'a [u8] {
-        let start = self.offset as usize;
-        let end = start + self.file_size as usize;
-        &binary[start..end]
+        get_clamped_slice(binary, self.offset, self.file_size)
     }
 
-    pub fn parse_from_buffer(index: u16, binary: &[u8], header: &ELFHeader) -> ProgramHeader {
-        // First get the bytes for our header
-        let start_index = header.e_phoff as usize + (index * header.e_phentsize) as usize;
-        let end_index = start_index + header.e_phentsize as usize;
-        let raw: &[u8] = &binary[start_index..end_index];
+    pub fn parse_from_buffer(
+        index: u32,
+        binary: &[u8],
+        header: &ELFHeader,
+    ) -> Result<ProgramHeader> {
+        // First get the bytes for our header, entries may be padded past the fields we read
+        let start_index = header
+            .e_phoff
+            .saturating_add(u64::from(index) * u64::from(header.e_phentsize));
+        let size = match header.ident.ei_class {
+            EI_Class::ELF32 => 32,
+            EI_Class::ELF64 => 56,
+        };
+        let raw: &[u8] = get_bytes(binary, start_index, size, || {
+            format!("program header {}", index)
+        })?;
 
         // Now get our conversion functions to read numbers based on endianness
         let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
         let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
 
         // Finally we can create our header
-        match header.ident.ei_class {
+        Ok(match header.ident.ei_class {
             EI_Class::ELF32 => ProgramHeader {
                 header_type: ProgramHeaderType::from_u32(u32_from_bytes(
                     raw[0x00..0x04].try_into().unwrap(),
@@ -137,7 +149,7 @@ impl ProgramHeader {
                 memory_size: u64_from_bytes(raw[0x28..0x30].try_into().unwrap()),
                 align: u64_from_bytes(raw[0x30..0x38].try_into().unwrap()),
             },
-        }
+        })
     }
 
     fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -166,3 +178,31 @@ impl fmt::Debug for ProgramHeader {
         self.formatter(f)
     }
 }
+
+// Translates a virtual address to an offset in the file using the loadable segments.
+// Returns None if the address is not backed by file data (e.g. .bss)
+pub fn get_offset_for_address(address: u64, program_headers: &[ProgramHeader]) -> Option<u64> {
+    for ph in program_headers {
+        if ph.header_type == ProgramHeaderType::Load
+            && address >= ph.virtual_address
+            && address - ph.virtual_address < ph.file_size
+        {
+            return (address - ph.virtual_address).checked_add(ph.offset);
+        }
+    }
+    None
+}
+
+// Returns the file data for a range of virtual addresses, clamped to what is in the file
+pub fn get_data_for_address<'a>(
+    data: &'a [u8],
+    address: u64,
+    size: u64,
+    program_headers: &[ProgramHeader],
+) -> Option<&'a [u8]> {
+    let start = get_offset_for_address(address, program_headers)?;
+    if start >= data.len() as u64 {
+        return None;
+    }
+    Some(get_clamped_slice(data, start, size))
+}
@@ -0,0 +1,195 @@
+use super::relocation::{Relocation, RelocationTable};
+use super::section::{SectionFlags, SectionHeader};
+use super::symbol::{SectionIndex, Symbol};
+
+// Relocatable objects (.o files) haven't been linked, so their sections all sit at
+// address 0 and symbol values and relocation offsets are relative to a section.
+// To give them usable addresses the loaded sections are laid out one after another
+// from here, the way a linker would place them.
+pub const RELOCATABLE_BASE: u64 = 0x1000;
+
+fn align_up(value: u64, align: u64) -> Option<u64> {
+    if align <= 1 {
+        return Some(value);
+    }
+    Some(value.checked_add(align - 1)? / align * align)
+}
+
+// An address for each section header, 0 for the sections that aren't loaded
+pub fn get_section_layout(section_headers: &[SectionHeader]) -> Vec<u64> {
+    let mut addresses: Vec<u64> = Vec::with_capacity(section_headers.len());
+    let mut next = RELOCATABLE_BASE;
+    for section in section_headers {
+        if !section.flags.contains(&SectionFlags::Alloc) {
+            addresses.push(0);
+            continue;
+        }
+        match align_up(next, section.addralign)
+            .and_then(|a| Some((a, a.checked_add(section.size)?)))
+        {
+            Some((address, end)) => {
+                addresses.push(address);
+                next = end;
+            }
+            // Sizes from a corrupt file that run off the end of the address space
+            None => addresses.push(0),
+        }
+    }
+    addresses
+}
+
+// The address of a symbol in the layout. None for undefined and common symbols, which
+// don't have one until they are linked.
+pub fn get_symbol_address(symbol: &Symbol, section_addresses: &[u64]) -> Option<u64> {
+    if symbol.section_index == SectionIndex::Absolute {
+        return Some(symbol.address);
+    }
+    let index = symbol.section_index.get_index()?;
+    match section_addresses.get(index) {
+        Some(0) | None => None,
+        Some(base) => base.checked_add(symbol.address),
+    }
+}
+
+// The address a relocation patches in the layout, None when it applies to a section
+// that isn't loaded (e.g. debug information)
+pub fn get_relocation_address(
+    table: &RelocationTable,
+    relocation: &Relocation,
+    section_addresses: &[u64],
+) -> Option<u64> {
+    match section_addresses.get(table.target_section_index as usize) {
+        Some(0) | None => None,
+        Some(base) => base.checked_add(relocation.offset),
+    }
+}
+
+#[cfg(test)]
+mod relocatable_tests {
+    use super::*;
+    use crate::elf::{load_elf_from_vec, ELF};
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    fn push_symbol(raw: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64, size: u64) {
+        raw.extend_from_slice(&name.to_le_bytes());
+        raw.extend_from_slice(&[info, 0]);
+        raw.extend_from_slice(&shndx.to_le_bytes());
+        raw.extend_from_slice(&value.to_le_bytes());
+        raw.extend_from_slice(&size.to_le_bytes());
+    }
+
+    // An x86_64 object file with main and helper in .text, greeting in .data,
+    // counter in .bss and a call from main to the undefined puts
+    fn get_test_object() -> ELF {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_REL, x86_64, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0xb8, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, // e_flags to e_phentsize
+            0x00, 0x00, 0x40, 0x00, 0x09, 0x00, 0x08, 0x00, // e_phnum to e_shstrndx
+        ];
+        // .text, the call to puts is at 4 and helper at 0x10
+        raw.extend_from_slice(&[0x55, 0x48, 0x89, 0xe5, 0xe8, 0, 0, 0, 0, 0x5d, 0xc3]);
+        raw.resize(0x50, 0xcc);
+        raw.extend_from_slice(&[0x31, 0xc0, 0xc3]);
+        raw.resize(0x60, 0xcc);
+        // .data
+        raw.extend_from_slice(b"hi\0\0");
+        // .rela.text, R_X86_64_PLT32 against puts
+        raw.resize(0x68, 0);
+        raw.extend_from_slice(&5u64.to_le_bytes());
+        raw.extend_from_slice(&((7u64 << 32) | 4).to_le_bytes());
+        raw.extend_from_slice(&(-4i64).to_le_bytes());
+        // .symtab, the locals are the file, the .text section and helper
+        push_symbol(&mut raw, 0, 0, 0, 0, 0);
+        push_symbol(&mut raw, 1, 0x04, 0xfff1, 0, 0);
+        push_symbol(&mut raw, 0, 0x03, 1, 0, 0);
+        push_symbol(&mut raw, 8, 0x02, 1, 0x10, 0x10);
+        push_symbol(&mut raw, 15, 0x12, 1, 0, 0x10);
+        push_symbol(&mut raw, 20, 0x11, 3, 0, 4);
+        push_symbol(&mut raw, 29, 0x11, 4, 4, 4);
+        push_symbol(&mut raw, 37, 0x10, 0, 0, 0);
+        raw.extend_from_slice(b"\0test.c\0helper\0main\0greeting\0counter\0puts\0");
+        raw.extend_from_slice(
+            b"\0.text\0.rela.text\0.data\0.bss\0.note.GNU-stack\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x1b8, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 6, 0, 0x40, 0x20, 0, 0, 16, 0]);
+        push_section_header(&mut raw, [7, 4, 0x40, 0, 0x68, 24, 6, 1, 8, 24]);
+        push_section_header(&mut raw, [18, 1, 3, 0, 0x60, 4, 0, 0, 4, 0]);
+        push_section_header(&mut raw, [24, 8, 3, 0, 0x64, 8, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [29, 1, 0, 0, 0x64, 0, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [45, 2, 0, 0, 0x80, 0xc0, 7, 4, 8, 24]);
+        push_section_header(&mut raw, [53, 3, 0, 0, 0x140, 0x2a, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [61, 3, 0, 0, 0x16a, 0x47, 0, 0, 1, 0]);
+        load_elf_from_vec(raw).unwrap()
+    }
+
+    #[test]
+    fn can_lay_out_sections() {
+        let elf = get_test_object();
+        assert!(elf.is_relocatable());
+        assert!(elf.diagnostics.is_empty());
+        // .text, .data and .bss are loaded, each at its alignment
+        assert_eq!(
+            elf.get_section_addresses(),
+            vec![0, 0x1000, 0, 0x1020, 0x1028, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn can_resolve_symbols_and_relocations() {
+        let elf = get_test_object();
+        let addresses = elf.get_section_addresses();
+
+        let get_address = |name: &str| {
+            let symbol = elf
+                .symbol_table
+                .iter()
+                .find(|s| s.name_string == name)
+                .unwrap();
+            get_symbol_address(symbol, &addresses)
+        };
+        assert_eq!(get_address("main"), Some(0x1000));
+        assert_eq!(get_address("helper"), Some(0x1010));
+        assert_eq!(get_address("greeting"), Some(0x1020));
+        assert_eq!(get_address("counter"), Some(0x102c));
+        assert_eq!(get_address("test.c"), Some(0));
+        assert_eq!(get_address("puts"), None);
+
+        // .text is at 0x40 in the file, .bss isn't in it
+        let helper = &elf.symbol_table[3];
+        assert_eq!(elf.get_symbol_section(helper).unwrap().name_string, ".text");
+        assert_eq!(elf.get_symbol_file_offset(helper), Some(0x50));
+        assert_eq!(elf.get_symbol_file_offset(&elf.symbol_table[6]), None);
+
+        assert_eq!(elf.relocation_tables.len(), 1);
+        let table = &elf.relocation_tables[0];
+        assert_eq!(
+            elf.get_relocation_target(table).unwrap().name_string,
+            ".text"
+        );
+        let call = &table.relocations[0];
+        assert_eq!(
+            get_relocation_address(table, call, &addresses),
+            Some(0x1005)
+        );
+        assert_eq!(call.symbol.as_ref().unwrap().name_string, "puts");
+        assert_eq!(call.addend, Some(-4));
+    }
+}
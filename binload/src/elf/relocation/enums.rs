@@ -0,0 +1,487 @@
+use std::fmt;
+
+use enum_primitive::enum_from_primitive;
+use enum_primitive::enum_from_primitive_impl;
+use enum_primitive::enum_from_primitive_impl_ty;
+use enum_primitive::FromPrimitive;
+
+use crate::elf::elf_header::E_Machine;
+
+enum_from_primitive! {
+// Intel 80386 relocation types
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationX86 {
+    R_386_NONE = 0,             // No reloc
+    R_386_32 = 1,               // Direct 32 bit
+    R_386_PC32 = 2,             // PC relative 32 bit
+    R_386_GOT32 = 3,            // 32 bit GOT entry
+    R_386_PLT32 = 4,            // 32 bit PLT address
+    R_386_COPY = 5,             // Copy symbol at runtime
+    R_386_GLOB_DAT = 6,         // Create GOT entry
+    R_386_JMP_SLOT = 7,         // Create PLT entry
+    R_386_RELATIVE = 8,         // Adjust by program base
+    R_386_GOTOFF = 9,           // 32 bit offset to GOT
+    R_386_GOTPC = 10,           // 32 bit PC relative offset to GOT
+    R_386_32PLT = 11,
+    R_386_TLS_TPOFF = 14,       // Offset in static TLS block
+    R_386_TLS_IE = 15,
+    R_386_TLS_GOTIE = 16,
+    R_386_TLS_LE = 17,
+    R_386_TLS_GD = 18,
+    R_386_TLS_LDM = 19,
+    R_386_16 = 20,
+    R_386_PC16 = 21,
+    R_386_8 = 22,
+    R_386_PC8 = 23,
+    R_386_TLS_GD_32 = 24,
+    R_386_TLS_GD_PUSH = 25,     // Tag for pushl in GD TLS code
+    R_386_TLS_GD_CALL = 26,
+    R_386_TLS_GD_POP = 27,      // Tag for popl in GD TLS code
+    R_386_TLS_LDM_32 = 28,
+    R_386_TLS_LDM_PUSH = 29,    // Tag for pushl in LDM TLS code
+    R_386_TLS_LDM_CALL = 30,
+    R_386_TLS_LDM_POP = 31,     // Tag for popl in LDM TLS code
+    R_386_TLS_LDO_32 = 32,      // Offset relative to TLS block
+    R_386_TLS_IE_32 = 33,
+    R_386_TLS_LE_32 = 34,
+    R_386_TLS_DTPMOD32 = 35,    // ID of module containing symbol
+    R_386_TLS_DTPOFF32 = 36,    // Offset in TLS block
+    R_386_TLS_TPOFF32 = 37,     // Negated offset in static TLS block
+    R_386_SIZE32 = 38,          // 32-bit symbol size
+    R_386_TLS_GOTDESC = 39,     // GOT offset for TLS descriptor.
+    R_386_TLS_DESC_CALL = 40,
+    R_386_TLS_DESC = 41,
+    R_386_IRELATIVE = 42,       // Adjust indirectly by program base
+    R_386_GOT32X = 43,
+}
+}
+
+enum_from_primitive! {
+// AMD x86-64 relocation types
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationX86_64 {
+    R_X86_64_NONE = 0,               // No reloc
+    R_X86_64_64 = 1,                 // Direct 64 bit
+    R_X86_64_PC32 = 2,               // PC relative 32 bit signed
+    R_X86_64_GOT32 = 3,              // 32 bit GOT entry
+    R_X86_64_PLT32 = 4,              // 32 bit PLT address
+    R_X86_64_COPY = 5,               // Copy symbol at runtime
+    R_X86_64_GLOB_DAT = 6,           // Create GOT entry
+    R_X86_64_JUMP_SLOT = 7,          // Create PLT entry
+    R_X86_64_RELATIVE = 8,           // Adjust by program base
+    R_X86_64_GOTPCREL = 9,
+    R_X86_64_32 = 10,                // Direct 32 bit zero extended
+    R_X86_64_32S = 11,               // Direct 32 bit sign extended
+    R_X86_64_16 = 12,                // Direct 16 bit zero extended
+    R_X86_64_PC16 = 13,              // 16 bit sign extended pc relative
+    R_X86_64_8 = 14,                 // Direct 8 bit sign extended
+    R_X86_64_PC8 = 15,               // 8 bit sign extended pc relative
+    R_X86_64_DTPMOD64 = 16,          // ID of module containing symbol
+    R_X86_64_DTPOFF64 = 17,          // Offset in module's TLS block
+    R_X86_64_TPOFF64 = 18,           // Offset in initial TLS block
+    R_X86_64_TLSGD = 19,
+    R_X86_64_TLSLD = 20,
+    R_X86_64_DTPOFF32 = 21,          // Offset in TLS block
+    R_X86_64_GOTTPOFF = 22,
+    R_X86_64_TPOFF32 = 23,           // Offset in initial TLS block
+    R_X86_64_PC64 = 24,              // PC relative 64 bit
+    R_X86_64_GOTOFF64 = 25,          // 64 bit offset to GOT
+    R_X86_64_GOTPC32 = 26,
+    R_X86_64_GOT64 = 27,             // 64-bit GOT entry offset
+    R_X86_64_GOTPCREL64 = 28,
+    R_X86_64_GOTPC64 = 29,           // 64-bit PC relative offset to GOT
+    R_X86_64_GOTPLT64 = 30,          // like GOT64, says PLT entry needed
+    R_X86_64_PLTOFF64 = 31,
+    R_X86_64_SIZE32 = 32,            // Size of symbol plus 32-bit addend
+    R_X86_64_SIZE64 = 33,            // Size of symbol plus 64-bit addend
+    R_X86_64_GOTPC32_TLSDESC = 34,   // GOT offset for TLS descriptor.
+    R_X86_64_TLSDESC_CALL = 35,
+    R_X86_64_TLSDESC = 36,           // TLS descriptor.
+    R_X86_64_IRELATIVE = 37,         // Adjust indirectly by program base
+    R_X86_64_RELATIVE64 = 38,        // 64-bit adjust by program base
+    R_X86_64_GOTPCRELX = 41,
+    R_X86_64_REX_GOTPCRELX = 42,
+}
+}
+
+enum_from_primitive! {
+// ARM relocation types
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationARM {
+    R_ARM_NONE = 0,                  // No reloc
+    R_ARM_PC24 = 1,
+    R_ARM_ABS32 = 2,                 // Direct 32 bit
+    R_ARM_REL32 = 3,                 // PC relative 32 bit
+    R_ARM_PC13 = 4,
+    R_ARM_ABS16 = 5,                 // Direct 16 bit
+    R_ARM_ABS12 = 6,                 // Direct 12 bit
+    R_ARM_THM_ABS5 = 7,              // Direct & 0x7C (LDR, STR).
+    R_ARM_ABS8 = 8,                  // Direct 8 bit
+    R_ARM_SBREL32 = 9,
+    R_ARM_THM_PC22 = 10,             // PC relative 24 bit (Thumb32 BL).
+    R_ARM_THM_PC8 = 11,
+    R_ARM_AMP_VCALL9 = 12,
+    R_ARM_SWI24 = 13,                // Obsolete static relocation.
+    R_ARM_THM_SWI8 = 14,             // Reserved.
+    R_ARM_XPC25 = 15,                // Reserved.
+    R_ARM_THM_XPC22 = 16,            // Reserved.
+    R_ARM_TLS_DTPMOD32 = 17,         // ID of module containing symbol
+    R_ARM_TLS_DTPOFF32 = 18,         // Offset in TLS block
+    R_ARM_TLS_TPOFF32 = 19,          // Offset in static TLS block
+    R_ARM_COPY = 20,                 // Copy symbol at runtime
+    R_ARM_GLOB_DAT = 21,             // Create GOT entry
+    R_ARM_JUMP_SLOT = 22,            // Create PLT entry
+    R_ARM_RELATIVE = 23,             // Adjust by program base
+    R_ARM_GOTOFF = 24,               // 32 bit offset to GOT
+    R_ARM_GOTPC = 25,                // 32 bit PC relative offset to GOT
+    R_ARM_GOT32 = 26,                // 32 bit GOT entry
+    R_ARM_PLT32 = 27,                // Deprecated, 32 bit PLT address.
+    R_ARM_CALL = 28,                 // PC relative 24 bit (BL, BLX).
+    R_ARM_JUMP24 = 29,
+    R_ARM_THM_JUMP24 = 30,           // PC relative 24 bit (Thumb32 B.W).
+    R_ARM_BASE_ABS = 31,             // Adjust by program base.
+    R_ARM_ALU_PCREL_7_0 = 32,        // Obsolete.
+    R_ARM_ALU_PCREL_15_8 = 33,       // Obsolete.
+    R_ARM_ALU_PCREL_23_15 = 34,      // Obsolete.
+    R_ARM_LDR_SBREL_11_0 = 35,       // Deprecated, prog. base relative.
+    R_ARM_ALU_SBREL_19_12 = 36,      // Deprecated, prog. base relative.
+    R_ARM_ALU_SBREL_27_20 = 37,      // Deprecated, prog. base relative.
+    R_ARM_TARGET1 = 38,
+    R_ARM_SBREL31 = 39,              // Program base relative.
+    R_ARM_V4BX = 40,
+    R_ARM_TARGET2 = 41,
+    R_ARM_PREL31 = 42,               // 32 bit PC relative.
+    R_ARM_MOVW_ABS_NC = 43,          // Direct 16-bit (MOVW).
+    R_ARM_MOVT_ABS = 44,             // Direct high 16-bit (MOVT).
+    R_ARM_MOVW_PREL_NC = 45,         // PC relative 16-bit (MOVW).
+    R_ARM_MOVT_PREL = 46,            // PC relative (MOVT).
+    R_ARM_THM_MOVW_ABS_NC = 47,      // Direct 16 bit (Thumb32 MOVW).
+    R_ARM_THM_MOVT_ABS = 48,
+    R_ARM_THM_MOVW_PREL_NC = 49,
+    R_ARM_THM_MOVT_PREL = 50,
+    R_ARM_THM_JUMP19 = 51,
+    R_ARM_THM_JUMP6 = 52,
+    R_ARM_THM_ALU_PREL_11_0 = 53,
+    R_ARM_THM_PC12 = 54,
+    R_ARM_ABS32_NOI = 55,            // Direct 32-bit.
+    R_ARM_REL32_NOI = 56,            // PC relative 32-bit.
+    R_ARM_ALU_PC_G0_NC = 57,         // PC relative (ADD, SUB).
+    R_ARM_ALU_PC_G0 = 58,            // PC relative (ADD, SUB).
+    R_ARM_ALU_PC_G1_NC = 59,         // PC relative (ADD, SUB).
+    R_ARM_ALU_PC_G1 = 60,            // PC relative (ADD, SUB).
+    R_ARM_ALU_PC_G2 = 61,            // PC relative (ADD, SUB).
+    R_ARM_LDR_PC_G1 = 62,            // PC relative (LDR,STR,LDRB,STRB).
+    R_ARM_LDR_PC_G2 = 63,            // PC relative (LDR,STR,LDRB,STRB).
+    R_ARM_LDRS_PC_G0 = 64,
+    R_ARM_LDRS_PC_G1 = 65,
+    R_ARM_LDRS_PC_G2 = 66,
+    R_ARM_LDC_PC_G0 = 67,            // PC relative (LDC, STC).
+    R_ARM_LDC_PC_G1 = 68,            // PC relative (LDC, STC).
+    R_ARM_LDC_PC_G2 = 69,            // PC relative (LDC, STC).
+    R_ARM_ALU_SB_G0_NC = 70,         // Program base relative (ADD,SUB).
+    R_ARM_ALU_SB_G0 = 71,            // Program base relative (ADD,SUB).
+    R_ARM_ALU_SB_G1_NC = 72,         // Program base relative (ADD,SUB).
+    R_ARM_ALU_SB_G1 = 73,            // Program base relative (ADD,SUB).
+    R_ARM_ALU_SB_G2 = 74,            // Program base relative (ADD,SUB).
+    R_ARM_LDR_SB_G0 = 75,
+    R_ARM_LDR_SB_G1 = 76,
+    R_ARM_LDR_SB_G2 = 77,
+    R_ARM_LDRS_SB_G0 = 78,
+    R_ARM_LDRS_SB_G1 = 79,
+    R_ARM_LDRS_SB_G2 = 80,
+    R_ARM_LDC_SB_G0 = 81,            // Program base relative (LDC,STC).
+    R_ARM_LDC_SB_G1 = 82,            // Program base relative (LDC,STC).
+    R_ARM_LDC_SB_G2 = 83,            // Program base relative (LDC,STC).
+    R_ARM_MOVW_BREL_NC = 84,
+    R_ARM_MOVT_BREL = 85,
+    R_ARM_MOVW_BREL = 86,
+    R_ARM_THM_MOVW_BREL_NC = 87,
+    R_ARM_THM_MOVT_BREL = 88,
+    R_ARM_THM_MOVW_BREL = 89,
+    R_ARM_TLS_GOTDESC = 90,
+    R_ARM_TLS_CALL = 91,
+    R_ARM_TLS_DESCSEQ = 92,          // TLS relaxation.
+    R_ARM_THM_TLS_CALL = 93,
+    R_ARM_PLT32_ABS = 94,
+    R_ARM_GOT_ABS = 95,              // GOT entry.
+    R_ARM_GOT_PREL = 96,             // PC relative GOT entry.
+    R_ARM_GOT_BREL12 = 97,
+    R_ARM_GOTOFF12 = 98,
+    R_ARM_GOTRELAX = 99,
+    R_ARM_GNU_VTENTRY = 100,
+    R_ARM_GNU_VTINHERIT = 101,
+    R_ARM_THM_PC11 = 102,            // PC relative & 0xFFE (Thumb16 B).
+    R_ARM_THM_PC9 = 103,
+    R_ARM_TLS_GD32 = 104,
+    R_ARM_TLS_LDM32 = 105,
+    R_ARM_TLS_LDO32 = 106,
+    R_ARM_TLS_IE32 = 107,
+    R_ARM_TLS_LE32 = 108,
+    R_ARM_TLS_LDO12 = 109,
+    R_ARM_TLS_LE12 = 110,
+    R_ARM_TLS_IE12GP = 111,
+    R_ARM_ME_TOO = 128,              // Obsolete.
+    R_ARM_THM_TLS_DESCSEQ = 129,
+    R_ARM_THM_TLS_DESCSEQ32 = 130,
+    R_ARM_THM_GOT_BREL12 = 131,
+    R_ARM_IRELATIVE = 160,
+    R_ARM_RXPC25 = 249,
+    R_ARM_RSBREL32 = 250,
+    R_ARM_THM_RPC22 = 251,
+    R_ARM_RREL32 = 252,
+    R_ARM_RABS22 = 253,
+    R_ARM_RPC24 = 254,
+    R_ARM_RBASE = 255,               // Keep this the last entry.
+}
+}
+
+enum_from_primitive! {
+// AArch64 relocation types
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationAArch64 {
+    R_AARCH64_NONE = 0,                             // No relocation.
+    R_AARCH64_P32_ABS32 = 1,                        // Direct 32 bit.
+    R_AARCH64_P32_COPY = 180,                       // Copy symbol at runtime.
+    R_AARCH64_P32_GLOB_DAT = 181,                   // Create GOT entry.
+    R_AARCH64_P32_JUMP_SLOT = 182,                  // Create PLT entry.
+    R_AARCH64_P32_RELATIVE = 183,                   // Adjust by program base.
+    R_AARCH64_P32_TLS_DTPMOD = 184,                 // Module number, 32 bit.
+    R_AARCH64_P32_TLS_DTPREL = 185,                 // Module-relative offset, 32 bit.
+    R_AARCH64_P32_TLS_TPREL = 186,                  // TP-relative offset, 32 bit.
+    R_AARCH64_P32_TLSDESC = 187,                    // TLS Descriptor.
+    R_AARCH64_P32_IRELATIVE = 188,                  // STT_GNU_IFUNC relocation.
+    R_AARCH64_ABS64 = 257,                          // Direct 64 bit.
+    R_AARCH64_ABS32 = 258,                          // Direct 32 bit.
+    R_AARCH64_ABS16 = 259,                          // Direct 16-bit.
+    R_AARCH64_PREL64 = 260,                         // PC-relative 64-bit.
+    R_AARCH64_PREL32 = 261,                         // PC-relative 32-bit.
+    R_AARCH64_PREL16 = 262,                         // PC-relative 16-bit.
+    R_AARCH64_MOVW_UABS_G0 = 263,                   // Dir. MOVZ imm. from bits 15:0.
+    R_AARCH64_MOVW_UABS_G0_NC = 264,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_UABS_G1 = 265,                   // Dir. MOVZ imm. from bits 31:16.
+    R_AARCH64_MOVW_UABS_G1_NC = 266,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_UABS_G2 = 267,                   // Dir. MOVZ imm. from bits 47:32.
+    R_AARCH64_MOVW_UABS_G2_NC = 268,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_UABS_G3 = 269,                   // Dir. MOV{K,Z} imm. from 63:48.
+    R_AARCH64_MOVW_SABS_G0 = 270,                   // Dir. MOV{N,Z} imm. from 15:0.
+    R_AARCH64_MOVW_SABS_G1 = 271,                   // Dir. MOV{N,Z} imm. from 31:16.
+    R_AARCH64_MOVW_SABS_G2 = 272,                   // Dir. MOV{N,Z} imm. from 47:32.
+    R_AARCH64_LD_PREL_LO19 = 273,                   // PC-rel. LD imm. from bits 20:2.
+    R_AARCH64_ADR_PREL_LO21 = 274,                  // PC-rel. ADR imm. from bits 20:0.
+    R_AARCH64_ADR_PREL_PG_HI21 = 275,               // Page-rel. ADRP imm. from 32:12.
+    R_AARCH64_ADR_PREL_PG_HI21_NC = 276,            // Likewise; no overflow check.
+    R_AARCH64_ADD_ABS_LO12_NC = 277,                // Dir. ADD imm. from bits 11:0.
+    R_AARCH64_LDST8_ABS_LO12_NC = 278,              // Likewise for LD/ST; no check.
+    R_AARCH64_TSTBR14 = 279,                        // PC-rel. TBZ/TBNZ imm. from 15:2.
+    R_AARCH64_CONDBR19 = 280,                       // PC-rel. cond. br. imm. from 20:2.
+    R_AARCH64_JUMP26 = 282,                         // PC-rel. B imm. from bits 27:2.
+    R_AARCH64_CALL26 = 283,                         // Likewise for CALL.
+    R_AARCH64_LDST16_ABS_LO12_NC = 284,             // Dir. ADD imm. from bits 11:1.
+    R_AARCH64_LDST32_ABS_LO12_NC = 285,             // Likewise for bits 11:2.
+    R_AARCH64_LDST64_ABS_LO12_NC = 286,             // Likewise for bits 11:3.
+    R_AARCH64_MOVW_PREL_G0 = 287,                   // PC-rel. MOV{N,Z} imm. from 15:0.
+    R_AARCH64_MOVW_PREL_G0_NC = 288,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_PREL_G1 = 289,                   // PC-rel. MOV{N,Z} imm. from 31:16.
+    R_AARCH64_MOVW_PREL_G1_NC = 290,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_PREL_G2 = 291,                   // PC-rel. MOV{N,Z} imm. from 47:32.
+    R_AARCH64_MOVW_PREL_G2_NC = 292,                // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_PREL_G3 = 293,                   // PC-rel. MOV{N,Z} imm. from 63:48.
+    R_AARCH64_LDST128_ABS_LO12_NC = 299,            // Dir. ADD imm. from bits 11:4.
+    R_AARCH64_MOVW_GOTOFF_G0 = 300,                 // GOT-rel. off. MOV{N,Z} imm. 15:0.
+    R_AARCH64_MOVW_GOTOFF_G0_NC = 301,              // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_GOTOFF_G1 = 302,                 // GOT-rel. o. MOV{N,Z} imm. 31:16.
+    R_AARCH64_MOVW_GOTOFF_G1_NC = 303,              // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_GOTOFF_G2 = 304,                 // GOT-rel. o. MOV{N,Z} imm. 47:32.
+    R_AARCH64_MOVW_GOTOFF_G2_NC = 305,              // Likewise for MOVK; no check.
+    R_AARCH64_MOVW_GOTOFF_G3 = 306,                 // GOT-rel. o. MOV{N,Z} imm. 63:48.
+    R_AARCH64_GOTREL64 = 307,                       // GOT-relative 64-bit.
+    R_AARCH64_GOTREL32 = 308,                       // GOT-relative 32-bit.
+    R_AARCH64_GOT_LD_PREL19 = 309,                  // PC-rel. GOT off. load imm. 20:2.
+    R_AARCH64_LD64_GOTOFF_LO15 = 310,               // GOT-rel. off. LD/ST imm. 14:3.
+    R_AARCH64_ADR_GOT_PAGE = 311,                   // P-page-rel. GOT off. ADRP 32:12.
+    R_AARCH64_LD64_GOT_LO12_NC = 312,               // Dir. GOT off. LD/ST imm. 11:3.
+    R_AARCH64_LD64_GOTPAGE_LO15 = 313,              // GOT-page-rel. GOT off. LD/ST 14:3
+    R_AARCH64_TLSGD_ADR_PREL21 = 512,               // PC-relative ADR imm. 20:0.
+    R_AARCH64_TLSGD_ADR_PAGE21 = 513,               // page-rel. ADRP imm. 32:12.
+    R_AARCH64_TLSGD_ADD_LO12_NC = 514,              // direct ADD imm. from 11:0.
+    R_AARCH64_TLSGD_MOVW_G1 = 515,                  // GOT-rel. MOV{N,Z} 31:16.
+    R_AARCH64_TLSGD_MOVW_G0_NC = 516,               // GOT-rel. MOVK imm. 15:0.
+    R_AARCH64_TLSLD_ADR_PREL21 = 517,               // Like 512; local dynamic model.
+    R_AARCH64_TLSLD_ADR_PAGE21 = 518,               // Like 513; local dynamic model.
+    R_AARCH64_TLSLD_ADD_LO12_NC = 519,              // Like 514; local dynamic model.
+    R_AARCH64_TLSLD_MOVW_G1 = 520,                  // Like 515; local dynamic model.
+    R_AARCH64_TLSLD_MOVW_G0_NC = 521,               // Like 516; local dynamic model.
+    R_AARCH64_TLSLD_LD_PREL19 = 522,                // TLS PC-rel. load imm. 20:2.
+    R_AARCH64_TLSLD_MOVW_DTPREL_G2 = 523,           // TLS DTP-rel. MOV{N,Z} 47:32.
+    R_AARCH64_TLSLD_MOVW_DTPREL_G1 = 524,           // TLS DTP-rel. MOV{N,Z} 31:16.
+    R_AARCH64_TLSLD_MOVW_DTPREL_G1_NC = 525,        // Likewise; MOVK; no check.
+    R_AARCH64_TLSLD_MOVW_DTPREL_G0 = 526,           // TLS DTP-rel. MOV{N,Z} 15:0.
+    R_AARCH64_TLSLD_MOVW_DTPREL_G0_NC = 527,        // Likewise; MOVK; no check.
+    R_AARCH64_TLSLD_ADD_DTPREL_HI12 = 528,          // DTP-rel. ADD imm. from 23:12.
+    R_AARCH64_TLSLD_ADD_DTPREL_LO12 = 529,          // DTP-rel. ADD imm. from 11:0.
+    R_AARCH64_TLSLD_ADD_DTPREL_LO12_NC = 530,       // Likewise; no ovfl. check.
+    R_AARCH64_TLSLD_LDST8_DTPREL_LO12 = 531,        // DTP-rel. LD/ST imm. 11:0.
+    R_AARCH64_TLSLD_LDST8_DTPREL_LO12_NC = 532,     // Likewise; no check.
+    R_AARCH64_TLSLD_LDST16_DTPREL_LO12 = 533,       // DTP-rel. LD/ST imm. 11:1.
+    R_AARCH64_TLSLD_LDST16_DTPREL_LO12_NC = 534,    // Likewise; no check.
+    R_AARCH64_TLSLD_LDST32_DTPREL_LO12 = 535,       // DTP-rel. LD/ST imm. 11:2.
+    R_AARCH64_TLSLD_LDST32_DTPREL_LO12_NC = 536,    // Likewise; no check.
+    R_AARCH64_TLSLD_LDST64_DTPREL_LO12 = 537,       // DTP-rel. LD/ST imm. 11:3.
+    R_AARCH64_TLSLD_LDST64_DTPREL_LO12_NC = 538,    // Likewise; no check.
+    R_AARCH64_TLSIE_MOVW_GOTTPREL_G1 = 539,         // GOT-rel. MOV{N,Z} 31:16.
+    R_AARCH64_TLSIE_MOVW_GOTTPREL_G0_NC = 540,      // GOT-rel. MOVK 15:0.
+    R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21 = 541,      // Page-rel. ADRP 32:12.
+    R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC = 542,    // Direct LD off. 11:3.
+    R_AARCH64_TLSIE_LD_GOTTPREL_PREL19 = 543,       // PC-rel. load imm. 20:2.
+    R_AARCH64_TLSLE_MOVW_TPREL_G2 = 544,            // TLS TP-rel. MOV{N,Z} 47:32.
+    R_AARCH64_TLSLE_MOVW_TPREL_G1 = 545,            // TLS TP-rel. MOV{N,Z} 31:16.
+    R_AARCH64_TLSLE_MOVW_TPREL_G1_NC = 546,         // Likewise; MOVK; no check.
+    R_AARCH64_TLSLE_MOVW_TPREL_G0 = 547,            // TLS TP-rel. MOV{N,Z} 15:0.
+    R_AARCH64_TLSLE_MOVW_TPREL_G0_NC = 548,         // Likewise; MOVK; no check.
+    R_AARCH64_TLSLE_ADD_TPREL_HI12 = 549,           // TP-rel. ADD imm. 23:12.
+    R_AARCH64_TLSLE_ADD_TPREL_LO12 = 550,           // TP-rel. ADD imm. 11:0.
+    R_AARCH64_TLSLE_ADD_TPREL_LO12_NC = 551,        // Likewise; no ovfl. check.
+    R_AARCH64_TLSLE_LDST8_TPREL_LO12 = 552,         // TP-rel. LD/ST off. 11:0.
+    R_AARCH64_TLSLE_LDST8_TPREL_LO12_NC = 553,      // Likewise; no ovfl. check.
+    R_AARCH64_TLSLE_LDST16_TPREL_LO12 = 554,        // TP-rel. LD/ST off. 11:1.
+    R_AARCH64_TLSLE_LDST16_TPREL_LO12_NC = 555,     // Likewise; no check.
+    R_AARCH64_TLSLE_LDST32_TPREL_LO12 = 556,        // TP-rel. LD/ST off. 11:2.
+    R_AARCH64_TLSLE_LDST32_TPREL_LO12_NC = 557,     // Likewise; no check.
+    R_AARCH64_TLSLE_LDST64_TPREL_LO12 = 558,        // TP-rel. LD/ST off. 11:3.
+    R_AARCH64_TLSLE_LDST64_TPREL_LO12_NC = 559,     // Likewise; no check.
+    R_AARCH64_TLSDESC_LD_PREL19 = 560,              // PC-rel. load immediate 20:2.
+    R_AARCH64_TLSDESC_ADR_PREL21 = 561,             // PC-rel. ADR immediate 20:0.
+    R_AARCH64_TLSDESC_ADR_PAGE21 = 562,             // Page-rel. ADRP imm. 32:12.
+    R_AARCH64_TLSDESC_LD64_LO12 = 563,              // Direct LD off. from 11:3.
+    R_AARCH64_TLSDESC_ADD_LO12 = 564,               // Direct ADD imm. from 11:0.
+    R_AARCH64_TLSDESC_OFF_G1 = 565,                 // GOT-rel. MOV{N,Z} imm. 31:16.
+    R_AARCH64_TLSDESC_OFF_G0_NC = 566,              // GOT-rel. MOVK imm. 15:0; no ck.
+    R_AARCH64_TLSDESC_LDR = 567,                    // Relax LDR.
+    R_AARCH64_TLSDESC_ADD = 568,                    // Relax ADD.
+    R_AARCH64_TLSDESC_CALL = 569,                   // Relax BLR.
+    R_AARCH64_TLSLE_LDST128_TPREL_LO12 = 570,       // TP-rel. LD/ST off. 11:4.
+    R_AARCH64_TLSLE_LDST128_TPREL_LO12_NC = 571,    // Likewise; no check.
+    R_AARCH64_TLSLD_LDST128_DTPREL_LO12 = 572,      // DTP-rel. LD/ST imm. 11:4.
+    R_AARCH64_TLSLD_LDST128_DTPREL_LO12_NC = 573,   // Likewise; no check.
+    R_AARCH64_COPY = 1024,                          // Copy symbol at runtime.
+    R_AARCH64_GLOB_DAT = 1025,                      // Create GOT entry.
+    R_AARCH64_JUMP_SLOT = 1026,                     // Create PLT entry.
+    R_AARCH64_RELATIVE = 1027,                      // Adjust by program base.
+    R_AARCH64_TLS_DTPMOD = 1028,                    // Module number, 64 bit.
+    R_AARCH64_TLS_DTPREL = 1029,                    // Module-relative offset, 64 bit.
+    R_AARCH64_TLS_TPREL = 1030,                     // TP-relative offset, 64 bit.
+    R_AARCH64_TLSDESC = 1031,                       // TLS Descriptor.
+    R_AARCH64_IRELATIVE = 1032,                     // STT_GNU_IFUNC relocation.
+}
+}
+
+// The meaning of the type field in r_info depends on the machine so we carry the
+// architecture along with the value
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationType {
+    X86(RelocationX86),
+    X86_64(RelocationX86_64),
+    ARM(RelocationARM),
+    AArch64(RelocationAArch64),
+    Unknown(u32),
+}
+
+impl RelocationType {
+    pub fn from_u32(machine: &E_Machine, value: u32) -> RelocationType {
+        let result = match machine {
+            E_Machine::X86 => RelocationX86::from_u32(value).map(RelocationType::X86),
+            E_Machine::X86_64 => RelocationX86_64::from_u32(value).map(RelocationType::X86_64),
+            E_Machine::ARM => RelocationARM::from_u32(value).map(RelocationType::ARM),
+            E_Machine::ARM_AARCH64 => {
+                RelocationAArch64::from_u32(value).map(RelocationType::AArch64)
+            }
+            _ => None,
+        };
+        result.unwrap_or(RelocationType::Unknown(value))
+    }
+
+    // RELR tables only encode offsets, the type is always the machine's relative relocation
+    pub fn relative(machine: &E_Machine) -> RelocationType {
+        match machine {
+            E_Machine::X86 => RelocationType::X86(RelocationX86::R_386_RELATIVE),
+            E_Machine::X86_64 => RelocationType::X86_64(RelocationX86_64::R_X86_64_RELATIVE),
+            E_Machine::ARM => RelocationType::ARM(RelocationARM::R_ARM_RELATIVE),
+            E_Machine::ARM_AARCH64 => {
+                RelocationType::AArch64(RelocationAArch64::R_AARCH64_RELATIVE)
+            }
+            _ => RelocationType::Unknown(0),
+        }
+    }
+
+    pub fn is_jump_slot(&self) -> bool {
+        matches!(
+            self,
+            RelocationType::X86(RelocationX86::R_386_JMP_SLOT)
+                | RelocationType::X86_64(RelocationX86_64::R_X86_64_JUMP_SLOT)
+                | RelocationType::ARM(RelocationARM::R_ARM_JUMP_SLOT)
+                | RelocationType::AArch64(RelocationAArch64::R_AARCH64_JUMP_SLOT)
+        )
+    }
+
+    pub fn is_glob_dat(&self) -> bool {
+        matches!(
+            self,
+            RelocationType::X86(RelocationX86::R_386_GLOB_DAT)
+                | RelocationType::X86_64(RelocationX86_64::R_X86_64_GLOB_DAT)
+                | RelocationType::ARM(RelocationARM::R_ARM_GLOB_DAT)
+                | RelocationType::AArch64(RelocationAArch64::R_AARCH64_GLOB_DAT)
+        )
+    }
+
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            RelocationType::X86(RelocationX86::R_386_RELATIVE)
+                | RelocationType::X86_64(RelocationX86_64::R_X86_64_RELATIVE)
+                | RelocationType::ARM(RelocationARM::R_ARM_RELATIVE)
+                | RelocationType::AArch64(RelocationAArch64::R_AARCH64_RELATIVE)
+        )
+    }
+
+    // A pointer sized symbol value plus addend
+    pub fn is_absolute(&self) -> bool {
+        matches!(
+            self,
+            RelocationType::X86(RelocationX86::R_386_32)
+                | RelocationType::X86_64(RelocationX86_64::R_X86_64_64)
+                | RelocationType::ARM(RelocationARM::R_ARM_ABS32)
+                | RelocationType::AArch64(RelocationAArch64::R_AARCH64_ABS64)
+        )
+    }
+
+    // The number of bytes a direct symbol value plus addend relocation writes, None
+    // for the other types
+    pub fn get_data_size(&self) -> Option<usize> {
+        match self {
+            RelocationType::X86(RelocationX86::R_386_32)
+            | RelocationType::X86_64(RelocationX86_64::R_X86_64_32)
+            | RelocationType::X86_64(RelocationX86_64::R_X86_64_32S)
+            | RelocationType::ARM(RelocationARM::R_ARM_ABS32)
+            | RelocationType::AArch64(RelocationAArch64::R_AARCH64_ABS32) => Some(4),
+            RelocationType::X86_64(RelocationX86_64::R_X86_64_64)
+            | RelocationType::AArch64(RelocationAArch64::R_AARCH64_ABS64) => Some(8),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RelocationType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelocationType::X86(v) => write!(f, "{:?}", v),
+            RelocationType::X86_64(v) => write!(f, "{:?}", v),
+            RelocationType::ARM(v) => write!(f, "{:?}", v),
+            RelocationType::AArch64(v) => write!(f, "{:?}", v),
+            RelocationType::Unknown(v) => write!(f, "Unknown ({:#x})", v),
+        }
+    }
+}
@@ -0,0 +1,425 @@
+mod enums;
+
+use std::convert::TryInto;
+use std::fmt;
+
+use super::dynamic::{get_dynamic_entry, DynamicEntry, DynamicTag};
+use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::program_header::{get_data_for_address, ProgramHeader};
+use super::section::{SectionHeader, SectionType};
+use super::symbol::{SectionIndex, Symbol};
+pub use enums::{
+    RelocationAArch64, RelocationARM, RelocationType, RelocationX86, RelocationX86_64,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RelocationKind {
+    Rel,  // Elf_Rel, implicit addend stored at the location
+    Rela, // Elf_Rela, explicit addend
+    Relr, // Compact relative relocations, offsets only
+}
+
+pub struct Relocation {
+    pub offset: u64,                     // r_offset, location to patch
+    pub relocation_type: RelocationType, // low bits of r_info
+    pub symbol_index: u32,               // high bits of r_info
+    pub addend: Option<i64>,             // r_addend, only present for RELA entries
+    pub symbol: Option<Symbol>,          // The symbol at symbol_index, None for STN_UNDEF
+}
+
+impl Relocation {
+    pub fn parse_from_relocation_table(
+        data: &[u8],
+        kind: RelocationKind,
+        header: &ELFHeader,
+    ) -> Vec<Relocation> {
+        if kind == RelocationKind::Relr {
+            return Relocation::parse_from_relr_table(data, header);
+        }
+
+        let size: usize = match (&header.ident.ei_class, kind) {
+            (EI_Class::ELF32, RelocationKind::Rel) => 8,
+            (EI_Class::ELF32, _) => 12,
+            (EI_Class::ELF64, RelocationKind::Rel) => 16,
+            (EI_Class::ELF64, _) => 24,
+        };
+        let num_entries = data.len() / size;
+
+        // Now get our conversion functions to read numbers based on endianness
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+
+        let mut relocations: Vec<Relocation> = vec![];
+        for index in 0..num_entries {
+            let start_index = index * size;
+            let end_index = start_index + size;
+            let raw: &[u8] = &data[start_index..end_index];
+
+            // r_info packs the symbol index and type differently for each class
+            let (offset, symbol_index, relocation_type, addend) = match header.ident.ei_class {
+                EI_Class::ELF32 => {
+                    let info = u32_from_bytes(raw[4..8].try_into().unwrap());
+                    let addend = match kind {
+                        RelocationKind::Rela => Some(i64::from(u32_from_bytes(
+                            raw[8..12].try_into().unwrap(),
+                        ) as i32)),
+                        _ => None,
+                    };
+                    (
+                        u64::from(u32_from_bytes(raw[0..4].try_into().unwrap())),
+                        info >> 8,
+                        info & 0xff,
+                        addend,
+                    )
+                }
+                EI_Class::ELF64 => {
+                    let info = u64_from_bytes(raw[8..16].try_into().unwrap());
+                    let addend = match kind {
+                        RelocationKind::Rela => {
+                            Some(u64_from_bytes(raw[16..24].try_into().unwrap()) as i64)
+                        }
+                        _ => None,
+                    };
+                    (
+                        u64_from_bytes(raw[0..8].try_into().unwrap()),
+                        (info >> 32) as u32,
+                        (info & 0xffff_ffff) as u32,
+                        addend,
+                    )
+                }
+            };
+            relocations.push(Relocation {
+                offset,
+                relocation_type: RelocationType::from_u32(&header.e_machine, relocation_type),
+                symbol_index,
+                addend,
+                symbol: None,
+            });
+        }
+        relocations
+    }
+
+    // RELR is a list of words. An even word is an address to relocate, an odd word is a bitmap
+    // of the following (word size * 8 - 1) words after the last address, one bit per word.
+    fn parse_from_relr_table(data: &[u8], header: &ELFHeader) -> Vec<Relocation> {
+        let word_size: usize = match header.ident.ei_class {
+            EI_Class::ELF32 => 4,
+            EI_Class::ELF64 => 8,
+        };
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+        let relocation_type = RelocationType::relative(&header.e_machine);
+
+        let mut offsets: Vec<u64> = vec![];
+        let mut next: u64 = 0;
+        for raw in data.chunks_exact(word_size) {
+            let entry = match header.ident.ei_class {
+                EI_Class::ELF32 => u64::from(u32_from_bytes(raw.try_into().unwrap())),
+                EI_Class::ELF64 => u64_from_bytes(raw.try_into().unwrap()),
+            };
+            if entry & 1 == 0 {
+                offsets.push(entry);
+                next = entry.wrapping_add(word_size as u64);
+            } else {
+                let bits = (word_size * 8 - 1) as u64;
+                for i in 0..bits {
+                    if (entry >> (i + 1)) & 1 == 1 {
+                        offsets.push(next.wrapping_add(i * word_size as u64));
+                    }
+                }
+                next = next.wrapping_add(bits * word_size as u64);
+            }
+        }
+
+        offsets
+            .into_iter()
+            .map(|offset| Relocation {
+                offset,
+                relocation_type,
+                symbol_index: 0,
+                addend: None,
+                symbol: None,
+            })
+            .collect()
+    }
+
+    // Fills in the symbol for each relocation from the table it is linked to
+    pub fn link_symbols(relocations: &mut [Relocation], symbols: &[Symbol]) {
+        for relocation in relocations {
+            if relocation.symbol_index != 0 {
+                relocation.symbol = symbols.get(relocation.symbol_index as usize).cloned();
+            }
+        }
+    }
+
+    // The value the loader stores at the offset when the image is loaded at the
+    // address it was linked at. value is what the file has at the offset, which is the
+    // addend of REL and RELR entries. None for relocations that need another module,
+    // like imports, and types other than relative and absolute ones.
+    pub fn get_relocated_value(&self, value: u64) -> Option<u64> {
+        let addend = match self.addend {
+            Some(v) => v as u64,
+            None => value,
+        };
+        if self.relocation_type.is_relative() {
+            return Some(addend);
+        }
+        match &self.symbol {
+            Some(v)
+                if self.relocation_type.is_absolute()
+                    && v.section_index != SectionIndex::Undefined =>
+            {
+                Some(v.address.wrapping_add(addend))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_symbol_name(&self) -> Option<&str> {
+        match &self.symbol {
+            Some(v) if !v.name_string.is_empty() => Some(&v.name_string),
+            _ => None,
+        }
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}0x{:x?}", "Offset:", self.offset),
+            format!("{:15}{}", "Type:", self.relocation_type),
+            format!("{:15}{:?}", "Symbol Index:", self.symbol_index),
+            format!("{:15}{:?}", "Symbol:", self.get_symbol_name()),
+            format!("{:15}{:?}", "Addend:", self.addend),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for Relocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for Relocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// The relocations found in a single SHT_REL, SHT_RELA or SHT_RELR section
+pub struct RelocationTable {
+    pub name: String,
+    pub kind: RelocationKind,
+    pub symbol_table_index: u32,   // sh_link, the associated symbol table
+    pub target_section_index: u32, // sh_info, the section the relocations apply to
+    pub relocations: Vec<Relocation>,
+}
+
+// Parses every relocation section, linking each relocation to the symbol table named by
+// the section's sh_link field.
+pub fn get_relocation_tables(
+    data: &[u8],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+    dynamic_symbol_table: &[Symbol],
+    symbol_table: &[Symbol],
+) -> Vec<RelocationTable> {
+    let mut tables: Vec<RelocationTable> = vec![];
+    for section in section_headers {
+        let kind = match section.section_type {
+            SectionType::RelocationEnt => RelocationKind::Rel,
+            SectionType::Rela => RelocationKind::Rela,
+            SectionType::Relr => RelocationKind::Relr,
+            _ => continue,
+        };
+        let mut relocations =
+            Relocation::parse_from_relocation_table(section.get_data(data), kind, elf_header);
+        match section_headers.get(section.link as usize) {
+            Some(v) if v.section_type == SectionType::DynSym => {
+                Relocation::link_symbols(&mut relocations, dynamic_symbol_table)
+            }
+            Some(v) if v.section_type == SectionType::SymTab => {
+                Relocation::link_symbols(&mut relocations, symbol_table)
+            }
+            _ => {}
+        }
+        tables.push(RelocationTable {
+            name: section.name_string.clone(),
+            kind,
+            symbol_table_index: section.link,
+            target_section_index: section.info,
+            relocations,
+        });
+    }
+    tables
+}
+
+fn get_dynamic_relocations_for(
+    data: &[u8],
+    address_tag: DynamicTag,
+    size_tag: DynamicTag,
+    kind: RelocationKind,
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+) -> Vec<Relocation> {
+    let (address, size) = match (
+        get_dynamic_entry(address_tag, dynamic),
+        get_dynamic_entry(size_tag, dynamic),
+    ) {
+        (Some(address), Some(size)) => (address.value, size.value),
+        _ => return vec![],
+    };
+    match get_data_for_address(data, address, size, program_headers) {
+        Some(v) => Relocation::parse_from_relocation_table(v, kind, elf_header),
+        None => vec![],
+    }
+}
+
+// Returns the relocations the dynamic loader applies at load time, as described by
+// DT_RELA, DT_REL and DT_RELR. The PLT relocations are handled separately below.
+pub fn get_dynamic_relocations(
+    data: &[u8],
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+    dynamic_symbol_table: &[Symbol],
+) -> Vec<Relocation> {
+    let mut relocations: Vec<Relocation> = vec![];
+    for (address_tag, size_tag, kind) in &[
+        (DynamicTag::Rela, DynamicTag::RelaSize, RelocationKind::Rela),
+        (DynamicTag::Rel, DynamicTag::RelSize, RelocationKind::Rel),
+        (DynamicTag::Relr, DynamicTag::RelrSize, RelocationKind::Relr),
+    ] {
+        relocations.append(&mut get_dynamic_relocations_for(
+            data,
+            *address_tag,
+            *size_tag,
+            *kind,
+            dynamic,
+            program_headers,
+            elf_header,
+        ));
+    }
+    Relocation::link_symbols(&mut relocations, dynamic_symbol_table);
+    relocations
+}
+
+// Returns the relocations for the PLT (DT_JMPREL), DT_PLTREL tells us if they are REL or RELA
+pub fn get_plt_relocations(
+    data: &[u8],
+    dynamic: &[DynamicEntry],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+    dynamic_symbol_table: &[Symbol],
+) -> Vec<Relocation> {
+    let kind = match get_dynamic_entry(DynamicTag::PltRel, dynamic) {
+        Some(v) if v.value == 17 => RelocationKind::Rel,
+        Some(_) => RelocationKind::Rela,
+        None => return vec![],
+    };
+    let mut relocations = get_dynamic_relocations_for(
+        data,
+        DynamicTag::JmpRel,
+        DynamicTag::PltRelSize,
+        kind,
+        dynamic,
+        program_headers,
+        elf_header,
+    );
+    Relocation::link_symbols(&mut relocations, dynamic_symbol_table);
+    relocations
+}
+
+// Finds the relocation that patches the given address, e.g. to see what a GOT slot points at
+pub fn get_relocation_for_address(address: u64, relocations: &[Relocation]) -> Option<&Relocation> {
+    relocations
+        .iter()
+        .find(|relocation| relocation.offset == address)
+}
+
+#[cfg(test)]
+mod relocation_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x23,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x0D, 0x00, 0x40, 0x00, 0x1E, 0x00, 0x1D, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_parse_64_bit_rela_table() {
+        let raw = [
+            0x18, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r_offset 0x4018
+            0x07, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // symbol 2, R_X86_64_JUMP_SLOT
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r_addend 0
+            0x20, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r_offset 0x4020
+            0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // R_X86_64_RELATIVE
+            0x30, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // r_addend 0x1130
+        ];
+        let relocations = Relocation::parse_from_relocation_table(
+            &raw,
+            RelocationKind::Rela,
+            &get_64_bit_header(),
+        );
+        assert_eq!(relocations.len(), 2);
+        assert_eq!(relocations[0].offset, 0x4018);
+        assert_eq!(relocations[0].symbol_index, 2);
+        assert!(relocations[0].relocation_type.is_jump_slot());
+        assert_eq!(relocations[0].addend, Some(0));
+        assert_eq!(
+            relocations[1].relocation_type,
+            RelocationType::X86_64(RelocationX86_64::R_X86_64_RELATIVE)
+        );
+        assert_eq!(relocations[1].addend, Some(0x1130));
+        assert_eq!(
+            get_relocation_for_address(0x4020, &relocations).map(|r| r.offset),
+            Some(0x4020)
+        );
+    }
+
+    #[test]
+    fn can_parse_64_bit_relr_table() {
+        let raw = [
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // address 0x1000
+            0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // bitmap 0b101, 0x1008 and 0x1018
+        ];
+        let relocations = Relocation::parse_from_relocation_table(
+            &raw,
+            RelocationKind::Relr,
+            &get_64_bit_header(),
+        );
+        let offsets: Vec<u64> = relocations.iter().map(|r| r.offset).collect();
+        assert_eq!(offsets, vec![0x1000, 0x1008, 0x1018]);
+        assert!(relocations.iter().all(|r| r.relocation_type.is_relative()));
+    }
+
+    #[test]
+    fn maps_relocation_types_per_machine() {
+        use super::super::elf_header::E_Machine;
+        assert_eq!(
+            RelocationType::from_u32(&E_Machine::ARM_AARCH64, 1026),
+            RelocationType::AArch64(RelocationAArch64::R_AARCH64_JUMP_SLOT)
+        );
+        assert_eq!(
+            RelocationType::from_u32(&E_Machine::ARM, 22),
+            RelocationType::ARM(RelocationARM::R_ARM_JUMP_SLOT)
+        );
+        assert_eq!(
+            RelocationType::from_u32(&E_Machine::X86, 7),
+            RelocationType::X86(RelocationX86::R_386_JMP_SLOT)
+        );
+        assert_eq!(
+            RelocationType::from_u32(&E_Machine::Sparc, 7),
+            RelocationType::Unknown(7)
+        );
+    }
+}
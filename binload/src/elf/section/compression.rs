@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io::Read;
+
+use super::super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use crate::error::{Error, Result};
+
+// Refuse to inflate sections claiming to be larger than this, a corrupt header
+// could otherwise ask for an enormous allocation
+const MAX_DECOMPRESSED_SIZE: u64 = 1 << 32;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CompressionType {
+    Zlib, // 0x01 ELFCOMPRESS_ZLIB
+    Zstd, // 0x02 ELFCOMPRESS_ZSTD
+    OS,   // 0x60000000-0x6fffffff OS specific
+    PROC, // 0x70000000-0x7fffffff Processor specific
+    Unknown,
+}
+impl CompressionType {
+    fn from_u32(value: u32) -> CompressionType {
+        match value {
+            0x01 => CompressionType::Zlib,
+            0x02 => CompressionType::Zstd,
+            0x6000_0000..=0x6fff_ffff => CompressionType::OS,
+            0x7000_0000..=0x7fff_ffff => CompressionType::PROC,
+            _ => CompressionType::Unknown,
+        }
+    }
+}
+
+// Elf32_Chdr / Elf64_Chdr at the start of SHF_COMPRESSED sections
+#[derive(Debug, Eq, PartialEq)]
+pub struct CompressionHeader {
+    pub compression_type: CompressionType,
+    pub size: u64,      // Size of the uncompressed data
+    pub addralign: u64, // Alignment of the uncompressed data
+}
+
+impl CompressionHeader {
+    pub fn get_header_size(header: &ELFHeader) -> usize {
+        match header.ident.ei_class {
+            EI_Class::ELF32 => 12,
+            EI_Class::ELF64 => 24,
+        }
+    }
+
+    pub fn parse_from_buffer(raw: &[u8], header: &ELFHeader) -> Result<CompressionHeader> {
+        let size = CompressionHeader::get_header_size(header);
+        if raw.len() < size {
+            return Err(Error::truncated(0, size as u64, "compression header"));
+        }
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
+
+        let compression_type =
+            CompressionType::from_u32(u32_from_bytes(raw[0..4].try_into().unwrap()));
+        Ok(match header.ident.ei_class {
+            EI_Class::ELF32 => CompressionHeader {
+                compression_type,
+                size: u64::from(u32_from_bytes(raw[4..8].try_into().unwrap())),
+                addralign: u64::from(u32_from_bytes(raw[8..12].try_into().unwrap())),
+            },
+            // There are 4 reserved bytes after the type in the 64 bit header
+            EI_Class::ELF64 => CompressionHeader {
+                compression_type,
+                size: u64_from_bytes(raw[8..16].try_into().unwrap()),
+                addralign: u64_from_bytes(raw[16..24].try_into().unwrap()),
+            },
+        })
+    }
+}
+
+// Decompresses the data of a SHF_COMPRESSED section, header included
+pub fn get_decompressed_section_data(raw: &[u8], header: &ELFHeader) -> Result<Vec<u8>> {
+    let compression_header = CompressionHeader::parse_from_buffer(raw, header)?;
+    let compressed = &raw[CompressionHeader::get_header_size(header)..];
+    match compression_header.compression_type {
+        CompressionType::Zlib => get_zlib_decompressed_data(compressed, compression_header.size),
+        CompressionType::Zstd => get_zstd_decompressed_data(compressed, compression_header.size),
+        _ => Err(Error::unsupported(0, "section compression type")),
+    }
+}
+
+// Legacy GNU .zdebug_* sections start with "ZLIB" and the uncompressed size as a
+// 64 bit big endian number, followed by the zlib stream
+pub fn get_decompressed_zdebug_data(raw: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if raw.len() < 12 || &raw[0..4] != b"ZLIB" {
+        // Small sections are left uncompressed by the tools
+        return Ok(Cow::Borrowed(raw));
+    }
+    let u64_from_bytes = get_num_from_bytes!(u64, EI_Data::BigEndian);
+    let size = u64_from_bytes(raw[4..12].try_into().unwrap());
+    get_zlib_decompressed_data(&raw[12..], size).map(Cow::Owned)
+}
+
+fn get_zlib_decompressed_data(compressed: &[u8], size: u64) -> Result<Vec<u8>> {
+    let decoder = flate2::read::ZlibDecoder::new(compressed);
+    read_decompressed_data(decoder, size)
+}
+
+fn get_zstd_decompressed_data(compressed: &[u8], size: u64) -> Result<Vec<u8>> {
+    let mut source = compressed;
+    let decoder = match ruzstd::StreamingDecoder::new(&mut source) {
+        Ok(v) => v,
+        Err(_) => return Err(Error::malformed(0, "invalid zstd frame header")),
+    };
+    read_decompressed_data(decoder, size)
+}
+
+fn read_decompressed_data<R: Read>(decoder: R, size: u64) -> Result<Vec<u8>> {
+    if size > MAX_DECOMPRESSED_SIZE {
+        return Err(Error::invalid_value(0, size, "decompressed section size"));
+    }
+    let mut data: Vec<u8> = Vec::with_capacity(size as usize);
+    // Read one byte past the expected size so we can tell if the stream is longer
+    if decoder.take(size + 1).read_to_end(&mut data).is_err() {
+        return Err(Error::malformed(0, "compressed section data is corrupt"));
+    }
+    if data.len() as u64 != size {
+        return Err(Error::malformed(
+            0,
+            format!(
+                "section decompressed to {} bytes but its header says {}",
+                data.len(),
+                size
+            ),
+        ));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod compression_tests {
+    use super::*;
+    use crate::elf::elf_header::ELFIdent;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x23,
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x0D, 0x00, 0x40, 0x00, 0x1E, 0x00, 0x1D, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_decompress_zlib_section() {
+        let raw = [
+            0x01, 0x00, 0x00, 0x00, // ELFCOMPRESS_ZLIB
+            0x00, 0x00, 0x00, 0x00, // reserved
+            0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // size 5
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // alignment 1
+            // zlib stream of "hello"
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15,
+        ];
+        let header = get_64_bit_header();
+        let compression_header = CompressionHeader::parse_from_buffer(&raw, &header).unwrap();
+        assert_eq!(compression_header.compression_type, CompressionType::Zlib);
+        assert_eq!(compression_header.size, 5);
+        assert_eq!(
+            get_decompressed_section_data(&raw, &header).unwrap(),
+            b"hello".to_vec()
+        );
+    }
+}
@@ -2,7 +2,7 @@
 
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum SectionType {
     Null,                // 0x00 Section header table entry unused
     ProgBits,            // 0x01 Program data
@@ -21,7 +21,8 @@ pub enum SectionType {
     PreinitArray,        // 0x10 Array of pre-constructors
     Group,               // 0x11 Section group
     SymTabShNdx,         // 0x12 Extended section indices  SYMTAB_SHNDX
-    Num,                 // 0x13 Number of defined types
+    Relr,                // 0x13 RELR relative relocations
+    Num,                 // 0x14 Number of defined types
     OSSpecific,          // 0x60000000-0x6fffffff Start OS-specific
     GNUAttributes,       // 0x6ffffff5 Object attributes.
     GNUHash,             // 0x6ffffff6 GNU-style hash table.
@@ -58,7 +59,8 @@ impl SectionType {
             0x10 => SectionType::PreinitArray,
             0x11 => SectionType::Group,
             0x12 => SectionType::SymTabShNdx,
-            0x13 => SectionType::Num,
+            0x13 => SectionType::Relr,
+            0x14 => SectionType::Num,
             0x6fff_fff5 => SectionType::GNUAttributes,
             0x6fff_fff6 => SectionType::GNUHash,
             0x6fff_fff7 => SectionType::GNULibList,
@@ -75,6 +77,45 @@ impl SectionType {
             _ => SectionType::Unknown,
         }
     }
+
+    // The sh_type value, None for the variants that cover a range of values
+    pub fn to_u32(&self) -> Option<u32> {
+        match self {
+            SectionType::Null => Some(0x00),
+            SectionType::ProgBits => Some(0x01),
+            SectionType::SymTab => Some(0x02),
+            SectionType::StrTab => Some(0x03),
+            SectionType::Rela => Some(0x04),
+            SectionType::Hash => Some(0x05),
+            SectionType::Dynamic => Some(0x06),
+            SectionType::Note => Some(0x07),
+            SectionType::NoBits => Some(0x08),
+            SectionType::RelocationEnt => Some(0x09),
+            SectionType::ShLib => Some(0x0a),
+            SectionType::DynSym => Some(0x0b),
+            SectionType::InitArray => Some(0x0e),
+            SectionType::FiniArray => Some(0x0f),
+            SectionType::PreinitArray => Some(0x10),
+            SectionType::Group => Some(0x11),
+            SectionType::SymTabShNdx => Some(0x12),
+            SectionType::Relr => Some(0x13),
+            SectionType::Num => Some(0x14),
+            SectionType::GNUAttributes => Some(0x6fff_fff5),
+            SectionType::GNUHash => Some(0x6fff_fff6),
+            SectionType::GNULibList => Some(0x6fff_fff7),
+            SectionType::Checksum => Some(0x6fff_fff8),
+            SectionType::SunWMove => Some(0x6fff_fffa),
+            SectionType::SunWCOMDAT => Some(0x6fff_fffb),
+            SectionType::SunWSyminfo => Some(0x6fff_fffc),
+            SectionType::GNUVersionDef => Some(0x6fff_fffd),
+            SectionType::GNUVersionNeeds => Some(0x6fff_fffe),
+            SectionType::GNUVersionSymTbl => Some(0x6fff_ffff),
+            SectionType::OSSpecific
+            | SectionType::ProcessorSpecific
+            | SectionType::ApplicationSpecific
+            | SectionType::Unknown => None,
+        }
+    }
 }
 impl fmt::Display for SectionType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -96,6 +137,7 @@ impl fmt::Display for SectionType {
             SectionType::PreinitArray => "PreinitArray",
             SectionType::Group => "Group",
             SectionType::SymTabShNdx => "SymTabShNdx",
+            SectionType::Relr => "Relr",
             SectionType::Num => "Num",
             SectionType::GNUAttributes => "GNU Object attributes",
             SectionType::GNUHash => "GNU Hash table",
@@ -116,6 +158,7 @@ impl fmt::Display for SectionType {
     }
 }
 
+#[derive(Eq, PartialEq, Clone, Copy)]
 pub enum SectionFlags {
     Write,           // (1 << 0)  Writable
     Alloc,           // (1 << 1)  Occupies memory during execution
@@ -0,0 +1,247 @@
+use std::convert::TryInto;
+
+use super::super::elf_header::{EI_Data, ELFHeader};
+use super::super::symbol::{Symbol, SymbolType};
+use super::super::utils::get_null_terminated_string_from_vec;
+use super::enums::SectionType;
+use super::section_header::SectionHeader;
+use crate::error::Diagnostic;
+
+// Flag in the first word of an SHT_GROUP section
+pub const GRP_COMDAT: u32 = 0x1;
+
+// The SHT_GROUP section a section belongs to. Sections in a group are kept or
+// discarded together by the linker, and only the first COMDAT group with a given
+// signature is kept, which is how inline functions and template instances that
+// are emitted in every object end up in the output once.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SectionGroup {
+    pub section_index: u32, // Index of the SHT_GROUP section
+    pub signature: String,  // Name of the symbol named by the group's sh_info
+    pub comdat: bool,       // GRP_COMDAT
+}
+
+// The signature is the name of the symbol at sh_info in the symbol table at
+// sh_link. Section symbols stand for the name of their section.
+fn get_signature(
+    data: &[u8],
+    group: &SectionHeader,
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+) -> Option<String> {
+    let symbol_table = section_headers.get(group.link as usize)?;
+    let entry_size = match symbol_table.entsize {
+        0 => return None,
+        v => v as usize,
+    };
+    let start = (group.info as usize).checked_mul(entry_size)?;
+    let raw = symbol_table
+        .get_data(data)
+        .get(start..start.checked_add(entry_size)?)?;
+    let mut symbols = Symbol::parse_from_symbol_table(raw, elf_header);
+    let indexes = super::get_extended_section_indexes(data, group.link as usize, section_headers);
+    Symbol::apply_extended_section_indexes(
+        &mut symbols,
+        indexes.get(group.info as usize * 4..).unwrap_or(&[]),
+        elf_header,
+    );
+    let symbol = symbols.pop()?;
+    if let SymbolType::Section = symbol.symbol_type {
+        return Some(
+            section_headers
+                .get(symbol.section_index.get_index()?)?
+                .name_string
+                .clone(),
+        );
+    }
+    let strings = section_headers
+        .get(symbol_table.link as usize)?
+        .get_data(data);
+    if symbol.name as usize >= strings.len() {
+        return None;
+    }
+    Some(get_null_terminated_string_from_vec(
+        strings,
+        symbol.name as usize,
+    ))
+}
+
+// Reads each SHT_GROUP section (a flags word followed by the indexes of its
+// members) and records the group on every member section
+pub fn resolve_section_groups(
+    data: &[u8],
+    section_headers: &mut [SectionHeader],
+    elf_header: &ELFHeader,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let u32_from_bytes = get_num_from_bytes!(u32, elf_header.ident.ei_data);
+    let mut memberships: Vec<(u32, SectionGroup)> = vec![];
+    for (index, group) in section_headers.iter().enumerate() {
+        if group.section_type != SectionType::Group {
+            continue;
+        }
+        let words: Vec<u32> = group
+            .get_data(data)
+            .chunks_exact(4)
+            .map(|v| u32_from_bytes(v.try_into().unwrap()))
+            .collect();
+        let flags = match words.first() {
+            Some(v) => *v,
+            None => {
+                diagnostics.push(Diagnostic::new(
+                    group.offset,
+                    format!("group section {} is empty", index),
+                ));
+                continue;
+            }
+        };
+        let signature = match get_signature(data, group, section_headers, elf_header) {
+            Some(v) => v,
+            None => {
+                diagnostics.push(Diagnostic::new(
+                    group.offset,
+                    format!(
+                        "group section {} has no signature symbol {} in section {}",
+                        index, group.info, group.link
+                    ),
+                ));
+                String::new()
+            }
+        };
+        let section_group = SectionGroup {
+            section_index: index as u32,
+            signature,
+            comdat: flags & GRP_COMDAT != 0,
+        };
+        for member in &words[1..] {
+            if *member as usize >= section_headers.len() || *member as usize == index {
+                diagnostics.push(Diagnostic::new(
+                    group.offset,
+                    format!("group section {} has an invalid member {}", index, member),
+                ));
+                continue;
+            }
+            memberships.push((*member, section_group.clone()));
+        }
+    }
+    for (member, group) in memberships {
+        section_headers[member as usize].group = Some(group);
+    }
+}
+
+// The sections in the group at group_index, in section header order
+pub fn get_group_members(
+    group_index: u32,
+    section_headers: &[SectionHeader],
+) -> Vec<&SectionHeader> {
+    section_headers
+        .iter()
+        .filter(|s| s.group.as_ref().map(|g| g.section_index) == Some(group_index))
+        .collect()
+}
+
+#[cfg(test)]
+mod group_tests {
+    use super::super::super::elf_header::ELFIdent;
+    use super::super::enums::SectionFlags;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let mut raw = vec![
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ];
+        raw.resize(64, 0);
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(&raw, ident).unwrap()
+    }
+
+    fn get_section(
+        name: &str,
+        section_type: SectionType,
+        offset: u64,
+        size: u64,
+        link: u32,
+        info: u32,
+        entsize: u64,
+    ) -> SectionHeader {
+        SectionHeader {
+            name: 0,
+            section_type,
+            flags: SectionFlags::from_u64(0),
+            address: 0,
+            offset,
+            size,
+            link,
+            info,
+            addralign: 0,
+            entsize,
+            name_string: name.to_string(),
+            synthetic: false,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn can_resolve_comdat_group() {
+        let mut data = vec![0u8; 0x40];
+        // .group at 0x00: GRP_COMDAT, then sections 2 and 3
+        data[0x00] = 0x01;
+        data[0x04] = 0x02;
+        data[0x08] = 0x03;
+        // .strtab at 0x10
+        data[0x10..0x1a].copy_from_slice(b"\0_Z3foov\0\0");
+        // .symtab at 0x40 is a null symbol followed by _Z3foov, weak function in section 2
+        let mut symtab = vec![0u8; 48];
+        symtab[24] = 0x01;
+        symtab[28] = 0x22;
+        symtab[30] = 0x02;
+        data.extend(symtab);
+
+        let mut section_headers = vec![
+            get_section("", SectionType::Null, 0, 0, 0, 0, 0),
+            get_section(".group", SectionType::Group, 0x00, 0x0c, 4, 1, 4),
+            get_section(".text._Z3foov", SectionType::ProgBits, 0, 0, 0, 0, 0),
+            get_section(".rela.text._Z3foov", SectionType::Rela, 0, 0, 4, 2, 24),
+            get_section(".symtab", SectionType::SymTab, 0x40, 48, 5, 1, 24),
+            get_section(".strtab", SectionType::StrTab, 0x10, 0x0a, 0, 0, 0),
+        ];
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+        resolve_section_groups(
+            &data,
+            &mut section_headers,
+            &get_64_bit_header(),
+            &mut diagnostics,
+        );
+
+        assert!(diagnostics.is_empty());
+        let group = SectionGroup {
+            section_index: 1,
+            signature: "_Z3foov".to_string(),
+            comdat: true,
+        };
+        assert_eq!(section_headers[2].group, Some(group.clone()));
+        assert_eq!(section_headers[3].group, Some(group));
+        assert_eq!(section_headers[4].group, None);
+        let members: Vec<&str> = get_group_members(1, &section_headers)
+            .iter()
+            .map(|s| s.name_string.as_str())
+            .collect();
+        assert_eq!(members, vec![".text._Z3foov", ".rela.text._Z3foov"]);
+
+        // A member index past the end of the section headers
+        data[0x08] = 0x09;
+        for section in section_headers.iter_mut() {
+            section.group = None;
+        }
+        resolve_section_groups(
+            &data,
+            &mut section_headers,
+            &get_64_bit_header(),
+            &mut diagnostics,
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(section_headers[2].group.is_some());
+        assert!(section_headers[3].group.is_none());
+    }
+}
@@ -1,11 +1,19 @@
+pub mod compression;
 mod enums;
+pub mod group;
 mod section_header;
+mod synthetic;
 mod utils;
 
 use super::symbol::Symbol;
 use super::utils::get_null_terminated_string_from_vec;
+use super::version;
 use crate::elf::elf_header::ELFHeader;
+use crate::error::{Error, Result};
+pub use enums::{SectionFlags, SectionType};
+pub use group::SectionGroup;
 pub use section_header::SectionHeader;
+pub use synthetic::get_synthetic_section_headers;
 pub use utils::get_section_by_name;
 
 fn _get_symbols(
@@ -14,7 +22,7 @@ fn _get_symbols(
     string_table_name: &str,
     section_headers: &[SectionHeader],
     elf_header: &ELFHeader,
-) -> Result<Vec<Symbol>, &'static str> {
+) -> Result<Vec<Symbol>> {
     let result: Vec<Symbol> = vec![];
     let symbol_table = match utils::get_section_by_name(table_section_name, section_headers) {
         Some(v) => v,
@@ -22,7 +30,15 @@ fn _get_symbols(
     };
     let string_table = match utils::get_section_by_name(string_table_name, section_headers) {
         Some(v) => v,
-        None => return Err("No string table section for the symbol section."),
+        None => {
+            return Err(Error::malformed(
+                symbol_table.offset,
+                format!(
+                    "no {} section for {}",
+                    string_table_name, table_section_name
+                ),
+            ))
+        }
     };
 
     let symtab_data = symbol_table.get_data(&data);
@@ -32,25 +48,58 @@ fn _get_symbols(
     for symbol in &mut result {
         symbol.name_string = get_null_terminated_string_from_vec(strings, symbol.name as usize);
     }
+    if let Some(index) = section_headers
+        .iter()
+        .position(|s| std::ptr::eq(s, symbol_table))
+    {
+        Symbol::apply_extended_section_indexes(
+            &mut result,
+            get_extended_section_indexes(data, index, section_headers),
+            elf_header,
+        );
+    }
 
     Ok(result)
 }
 
+// The SHT_SYMTAB_SHNDX section for the symbol table at symbol_table_index, empty
+// when it doesn't have one. Only objects with SHN_LORESERVE or more sections need it.
+pub fn get_extended_section_indexes<'a>(
+    data: &'a [u8],
+    symbol_table_index: usize,
+    section_headers: &[SectionHeader],
+) -> &'a [u8] {
+    section_headers
+        .iter()
+        .find(|s| {
+            s.section_type == SectionType::SymTabShNdx && s.link as usize == symbol_table_index
+        })
+        .map_or(&[], |s| s.get_data(data))
+}
+
 // parses the dynamic symbol table if it exists
 // making the assumption (should really verify) that the if ".dynsym" exists then
 // ".dynstr" should also.
+// Symbols are tagged with their version from .gnu.version when present.
 pub fn get_dynamic_symbols(
     data: &[u8],
     section_headers: &[SectionHeader],
     elf_header: &ELFHeader,
-) -> Result<Vec<Symbol>, &'static str> {
-    _get_symbols(data, ".dynsym", ".dynstr", section_headers, elf_header)
+) -> Result<Vec<Symbol>> {
+    let mut symbols = _get_symbols(data, ".dynsym", ".dynstr", section_headers, elf_header)?;
+    version::apply_symbol_versions(
+        &mut symbols,
+        &version::get_version_indexes(data, section_headers, elf_header),
+        &version::get_version_needs(data, section_headers, elf_header),
+        &version::get_version_definitions(data, section_headers, elf_header),
+    );
+    Ok(symbols)
 }
 
 pub fn get_symbols(
     data: &[u8],
     section_headers: &[SectionHeader],
     elf_header: &ELFHeader,
-) -> Result<Vec<Symbol>, &'static str> {
+) -> Result<Vec<Symbol>> {
     _get_symbols(data, ".symtab", ".strtab", section_headers, elf_header)
 }
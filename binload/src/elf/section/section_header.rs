@@ -1,8 +1,13 @@
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::fmt;
 
 use super::super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::compression::{get_decompressed_section_data, get_decompressed_zdebug_data};
 use super::enums::{SectionFlags, SectionType};
+use super::group::SectionGroup;
+use crate::elf::utils::get_clamped_slice;
+use crate::error::{get_bytes, Result};
 
 pub struct SectionHeader {
     pub name: u32,                 // Offset to string in .shstrtab section
@@ -16,31 +21,71 @@ pub struct SectionHeader {
     pub addralign: u64, // Required alignment of the section (power of 2) u32 or 64
     pub entsize: u64, // Size in bytes of each entry for sections that contain fixed size entries, else 0 u32 or 64
     pub name_string: String,
+    pub synthetic: bool, // Reconstructed from the program headers rather than read from the file
+    pub group: Option<SectionGroup>, // The SHT_GROUP section listing this one, if any
 }
 
 impl SectionHeader {
     pub fn contains_address(&self, address: u64) -> bool {
-        address >= self.address && address < (self.address + self.size)
+        address >= self.address && address - self.address < self.size
     }
 
+    // Sections running past the end of the file are truncated, the loader records
+    // a diagnostic for them
     pub fn get_data<'a>(&self, binary: &'a [u8]) -> &'a [u8] {
-        let start = self.offset as usize;
-        let end = start + self.size as usize;
-        &binary[start..end]
+        // .bss and friends take no space in the file
+        if self.section_type == SectionType::NoBits {
+            return &[];
+        }
+        get_clamped_slice(binary, self.offset, self.size)
+    }
+
+    // Same as get_data but inflates SHF_COMPRESSED and legacy .zdebug_* sections,
+    // uncompressed sections are returned as is without copying
+    pub fn get_decompressed_data<'a>(
+        &self,
+        binary: &'a [u8],
+        header: &ELFHeader,
+    ) -> Result<Cow<'a, [u8]>> {
+        let data = self.get_data(binary);
+        if self.is_compressed() {
+            return get_decompressed_section_data(data, header).map(Cow::Owned);
+        }
+        if self.name_string.starts_with(".zdebug") {
+            return get_decompressed_zdebug_data(data);
+        }
+        Ok(Cow::Borrowed(data))
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags
+            .iter()
+            .any(|f| matches!(f, SectionFlags::Compressed))
     }
 
-    pub fn parse_from_buffer(index: u16, binary: &[u8], header: &ELFHeader) -> SectionHeader {
-        // First get the bytes for our header
-        let start_index = header.e_shoff as usize + (index * header.e_shentsize) as usize;
-        let end_index = start_index + header.e_shentsize as usize;
-        let raw: &[u8] = &binary[start_index..end_index];
+    pub fn parse_from_buffer(
+        index: u32,
+        binary: &[u8],
+        header: &ELFHeader,
+    ) -> Result<SectionHeader> {
+        // First get the bytes for our header, entries may be padded past the fields we read
+        let start_index = header
+            .e_shoff
+            .saturating_add(u64::from(index) * u64::from(header.e_shentsize));
+        let size = match header.ident.ei_class {
+            EI_Class::ELF32 => 40,
+            EI_Class::ELF64 => 64,
+        };
+        let raw: &[u8] = get_bytes(binary, start_index, size, || {
+            format!("section header {}", index)
+        })?;
 
         // Now get our conversion functions to read numbers based on endianness
         let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
         let u64_from_bytes = get_num_from_bytes!(u64, header.ident.ei_data);
 
         // Finally we can create our header
-        match header.ident.ei_class {
+        Ok(match header.ident.ei_class {
             EI_Class::ELF32 => SectionHeader {
                 name: u32_from_bytes(raw[0..4].try_into().unwrap()),
                 section_type: SectionType::from_u32(u32_from_bytes(raw[4..8].try_into().unwrap())),
@@ -55,6 +100,8 @@ impl SectionHeader {
                 addralign: u64::from(u32_from_bytes(raw[32..36].try_into().unwrap())),
                 entsize: u64::from(u32_from_bytes(raw[36..40].try_into().unwrap())),
                 name_string: std::string::String::new(),
+                synthetic: false,
+                group: None,
             },
             EI_Class::ELF64 => SectionHeader {
                 name: u32_from_bytes(raw[0..4].try_into().unwrap()),
@@ -68,8 +115,10 @@ impl SectionHeader {
                 addralign: u64_from_bytes(raw[48..56].try_into().unwrap()),
                 entsize: u64_from_bytes(raw[56..64].try_into().unwrap()),
                 name_string: std::string::String::new(),
+                synthetic: false,
+                group: None,
             },
-        }
+        })
     }
 
     fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -88,6 +137,7 @@ impl SectionHeader {
             format!("{:15}{:?}", "Info:", self.info),
             format!("{:15}{:?}", "Address Align:", self.addralign),
             format!("{:15}{:?} {}", "Entity Size", self.entsize, "(bytes)"),
+            format!("{:15}{:?}", "Group:", self.group),
         ];
         writeln!(f, "{}", strings.join("\n"))
     }
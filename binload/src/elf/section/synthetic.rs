@@ -0,0 +1,741 @@
+use std::convert::TryInto;
+
+use super::super::dynamic::{get_dynamic_entry, DynamicEntry, DynamicTag};
+use super::super::elf_header::{EI_Class, EI_Data, ELFHeader, E_Machine};
+use super::super::hash::{get_gnu_hash_table, get_sysv_hash_table};
+use super::super::program_header::{get_offset_for_address, ProgramHeader, ProgramHeaderType};
+use super::super::relocation::RelocationType;
+use super::enums::{SectionFlags, SectionType};
+use super::section_header::SectionHeader;
+
+// Segment permission bits in p_flags
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+
+// A section before it has been placed in the final list, links are by name as
+// the indexes are not known until the sections are sorted
+struct SyntheticSection {
+    header: SectionHeader,
+    link_name: Option<&'static str>,
+}
+
+fn new_section(
+    name: &str,
+    section_type: SectionType,
+    flags: u64,
+    address: u64,
+    offset: u64,
+    size: u64,
+    entsize: u64,
+) -> SectionHeader {
+    SectionHeader {
+        name: 0,
+        section_type,
+        flags: SectionFlags::from_u64(flags),
+        address,
+        offset,
+        size,
+        link: 0,
+        info: 0,
+        addralign: 0,
+        entsize,
+        name_string: name.to_string(),
+        synthetic: true,
+        group: None,
+    }
+}
+
+// Builds a section for a table the dynamic section points at, if the address is
+// backed by the file
+fn new_dynamic_section(
+    name: &str,
+    section_type: SectionType,
+    address: u64,
+    size: u64,
+    entsize: u64,
+    data: &[u8],
+    program_headers: &[ProgramHeader],
+) -> Option<SectionHeader> {
+    let offset = get_offset_for_address(address, program_headers)?;
+    if offset.checked_add(size)? > data.len() as u64 {
+        return None;
+    }
+    Some(new_section(
+        name,
+        section_type,
+        0x2, // SHF_ALLOC
+        address,
+        offset,
+        size,
+        entsize,
+    ))
+}
+
+// Reads a pointer sized value at a virtual address
+fn read_word(
+    data: &[u8],
+    address: u64,
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+) -> Option<u64> {
+    let offset = get_offset_for_address(address, program_headers)? as usize;
+    let ei_data = elf_header.ident.ei_data;
+    match elf_header.ident.ei_class {
+        EI_Class::ELF32 => {
+            let raw = data.get(offset..offset.checked_add(4)?)?;
+            Some(u64::from(get_num_from_bytes!(u32, ei_data)(
+                raw.try_into().unwrap(),
+            )))
+        }
+        EI_Class::ELF64 => {
+            let raw = data.get(offset..offset.checked_add(8)?)?;
+            Some(get_num_from_bytes!(u64, ei_data)(raw.try_into().unwrap()))
+        }
+    }
+}
+
+// Until a symbol is bound its GOT slot points back into the PLT, so the slots that
+// DT_JMPREL relocates give away where the stubs are. On x86 each slot holds the
+// address of the push in its own .plt stub, or of the whole lazy stub when IBT moves
+// the calls to .plt.sec. On ARM every slot holds the address of the first stub and
+// the standard stub sizes give the end. Binaries linked with other stub layouts
+// (AArch64 PAC, ARM long PLT entries) or bound with the GOT already filled in don't
+// get a .plt, nor does .plt.got which has no relocations of its own.
+fn get_plt_sections(
+    data: &[u8],
+    program_headers: &[ProgramHeader],
+    elf_header: &ELFHeader,
+    relocations: (u64, u64, u64), // DT_JMPREL, DT_PLTRELSZ and the entry size
+) -> Vec<SectionHeader> {
+    let (table, table_size, entry_size) = relocations;
+    let is_executable = |address: u64| {
+        program_headers.iter().any(|ph| {
+            ph.header_type == ProgramHeaderType::Load
+                && ph.flags & PF_X != 0
+                && address >= ph.virtual_address
+                && address - ph.virtual_address < ph.memory_size
+        })
+    };
+    let word_size = match elf_header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    };
+    // r_offset and r_info are the first two words of both REL and RELA entries. Only
+    // the JUMP_SLOT ones are lazily bound, IRELATIVE slots hold the resolver address.
+    let slots: Vec<u64> = (0..table_size / entry_size)
+        .map_while(|i| {
+            let address = table.checked_add(i * entry_size)?;
+            let got = read_word(data, address, program_headers, elf_header)?;
+            let info = read_word(
+                data,
+                address.checked_add(word_size)?,
+                program_headers,
+                elf_header,
+            )?;
+            Some((got, info))
+        })
+        .filter(|(_, info)| {
+            let relocation_type = match elf_header.ident.ei_class {
+                EI_Class::ELF32 => info & 0xff,
+                EI_Class::ELF64 => info & 0xffff_ffff,
+            };
+            RelocationType::from_u32(&elf_header.e_machine, relocation_type as u32).is_jump_slot()
+        })
+        .filter_map(|(got, _)| read_word(data, got, program_headers, elf_header))
+        .filter(|address| is_executable(*address))
+        .collect();
+    let (lowest, highest) = match (slots.iter().min(), slots.iter().max()) {
+        (Some(l), Some(h)) => (*l, *h),
+        _ => return vec![],
+    };
+    let count = slots.len() as u64;
+    let starts_with = |address: u64, instruction: &[u8]| {
+        get_offset_for_address(address, program_headers)
+            .and_then(|offset| data.get(offset as usize..)?.get(..instruction.len()))
+            == Some(instruction)
+    };
+
+    // (name, address, size, entsize), the first stub (PLT0) pushes the link map and
+    // jumps to the resolver
+    let ranges: Vec<(&str, u64, u64, u64)> = match elf_header.e_machine {
+        E_Machine::X86 | E_Machine::X86_64 => {
+            // endbr64 or endbr32
+            if starts_with(lowest, &[0xf3, 0x0f, 0x1e, 0xfa])
+                || starts_with(lowest, &[0xf3, 0x0f, 0x1e, 0xfb])
+            {
+                let start = lowest.wrapping_sub(16);
+                let end = highest.wrapping_add(16);
+                vec![
+                    (".plt", start, end.wrapping_sub(start), 16),
+                    (".plt.sec", end, count * 16, 16),
+                ]
+            } else {
+                // The slot points past the 6 byte jmp at the start of the stub
+                let start = lowest.wrapping_sub(6 + 16);
+                let end = highest.wrapping_sub(6).wrapping_add(16);
+                vec![(".plt", start, end.wrapping_sub(start), 16)]
+            }
+        }
+        E_Machine::ARM_AARCH64 => {
+            // With BTI both PLT0 and the stubs start with a `bti c` landing pad
+            let bti = 0xd503_245fu32;
+            let stub_size = match elf_header.ident.ei_data {
+                EI_Data::LittleEndian if starts_with(lowest, &bti.to_le_bytes()) => 24,
+                EI_Data::BigEndian if starts_with(lowest, &bti.to_be_bytes()) => 24,
+                _ => 16,
+            };
+            vec![(".plt", lowest, 32 + count * stub_size, stub_size)]
+        }
+        E_Machine::ARM => vec![(".plt", lowest, 20 + count * 12, 12)],
+        _ => vec![],
+    };
+    ranges
+        .into_iter()
+        .filter(|(_, start, _, _)| is_executable(*start))
+        .filter_map(|(name, start, size, entsize)| {
+            let mut header = new_dynamic_section(
+                name,
+                SectionType::ProgBits,
+                start,
+                size,
+                entsize,
+                data,
+                program_headers,
+            )?;
+            header.flags = SectionFlags::from_u64(0x6); // SHF_ALLOC | SHF_EXECINSTR
+            Some(header)
+        })
+        .collect()
+}
+
+// Builds a list of section headers for a binary with no usable section header
+// table. Everything is recovered from the program headers and the tables that
+// the dynamic section points to, so sizes are only as good as what those record.
+// The executable and writable PT_LOAD segments stand in for .text, .data and .bss.
+pub fn get_synthetic_section_headers(
+    data: &[u8],
+    program_headers: &[ProgramHeader],
+    dynamic: &[DynamicEntry],
+    elf_header: &ELFHeader,
+) -> Vec<SectionHeader> {
+    let word_size: u64 = match elf_header.ident.ei_class {
+        EI_Class::ELF32 => 4,
+        EI_Class::ELF64 => 8,
+    };
+    let mut sections: Vec<SyntheticSection> = vec![];
+    let mut push = |header: Option<SectionHeader>, link_name: Option<&'static str>| {
+        if let Some(header) = header {
+            sections.push(SyntheticSection { header, link_name });
+        }
+    };
+
+    for ph in program_headers {
+        match ph.header_type {
+            ProgramHeaderType::Interp => push(
+                Some(new_section(
+                    ".interp",
+                    SectionType::ProgBits,
+                    0x2,
+                    ph.virtual_address,
+                    ph.offset,
+                    ph.file_size,
+                    0,
+                )),
+                None,
+            ),
+            ProgramHeaderType::Dynamic => push(
+                Some(new_section(
+                    ".dynamic",
+                    SectionType::Dynamic,
+                    0x3,
+                    ph.virtual_address,
+                    ph.offset,
+                    ph.file_size,
+                    word_size * 2,
+                )),
+                Some(".dynstr"),
+            ),
+            ProgramHeaderType::Note => push(
+                Some(new_section(
+                    ".note",
+                    SectionType::Note,
+                    0x2,
+                    ph.virtual_address,
+                    ph.offset,
+                    ph.file_size,
+                    0,
+                )),
+                None,
+            ),
+            ProgramHeaderType::GNU_EH_Frame => push(
+                Some(new_section(
+                    ".eh_frame_hdr",
+                    SectionType::ProgBits,
+                    0x2,
+                    ph.virtual_address,
+                    ph.offset,
+                    ph.file_size,
+                    0,
+                )),
+                None,
+            ),
+            ProgramHeaderType::ThreadLocalStorage => {
+                // SHF_WRITE | SHF_ALLOC | SHF_TLS
+                push(
+                    Some(new_section(
+                        ".tdata",
+                        SectionType::ProgBits,
+                        0x403,
+                        ph.virtual_address,
+                        ph.offset,
+                        ph.file_size,
+                        0,
+                    )),
+                    None,
+                );
+                if ph.memory_size > ph.file_size {
+                    push(
+                        Some(new_section(
+                            ".tbss",
+                            SectionType::NoBits,
+                            0x403,
+                            ph.virtual_address.wrapping_add(ph.file_size),
+                            ph.offset.saturating_add(ph.file_size),
+                            ph.memory_size - ph.file_size,
+                            0,
+                        )),
+                        None,
+                    );
+                }
+            }
+            ProgramHeaderType::Load if ph.flags & PF_X != 0 => push(
+                Some(new_section(
+                    ".text",
+                    SectionType::ProgBits,
+                    0x6, // SHF_ALLOC | SHF_EXECINSTR
+                    ph.virtual_address,
+                    ph.offset,
+                    ph.file_size,
+                    0,
+                )),
+                None,
+            ),
+            ProgramHeaderType::Load if ph.flags & PF_W != 0 => {
+                push(
+                    Some(new_section(
+                        ".data",
+                        SectionType::ProgBits,
+                        0x3, // SHF_WRITE | SHF_ALLOC
+                        ph.virtual_address,
+                        ph.offset,
+                        ph.file_size,
+                        0,
+                    )),
+                    None,
+                );
+                if ph.memory_size > ph.file_size {
+                    push(
+                        Some(new_section(
+                            ".bss",
+                            SectionType::NoBits,
+                            0x3,
+                            ph.virtual_address.wrapping_add(ph.file_size),
+                            ph.offset.saturating_add(ph.file_size),
+                            ph.memory_size - ph.file_size,
+                            0,
+                        )),
+                        None,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let value = |tag: DynamicTag| get_dynamic_entry(tag, dynamic).map(|v| v.value);
+    let new_table = |name: &str,
+                     section_type: SectionType,
+                     address: Option<u64>,
+                     size: Option<u64>,
+                     entsize: u64| {
+        new_dynamic_section(
+            name,
+            section_type,
+            address?,
+            size?,
+            entsize,
+            data,
+            program_headers,
+        )
+    };
+
+    // The dynamic symbol table has no size of its own, the hash tables give the count
+    let symbol_count = match get_gnu_hash_table(data, dynamic, program_headers, elf_header) {
+        Some(v) => Some(v.get_symbol_count() as u64),
+        None => get_sysv_hash_table(data, dynamic, program_headers, elf_header)
+            .map(|v| v.get_symbol_count() as u64),
+    };
+    let symbol_size = value(DynamicTag::SymEnt).unwrap_or(word_size * 2 + 8);
+    push(
+        new_table(
+            ".dynsym",
+            SectionType::DynSym,
+            value(DynamicTag::SymTab),
+            symbol_count.map(|v| v.saturating_mul(symbol_size)),
+            symbol_size,
+        ),
+        Some(".dynstr"),
+    );
+    push(
+        new_table(
+            ".dynstr",
+            SectionType::StrTab,
+            value(DynamicTag::StrTab),
+            value(DynamicTag::StrSize),
+            0,
+        ),
+        None,
+    );
+    push(
+        new_table(
+            ".gnu.version",
+            SectionType::GNUVersionSymTbl,
+            value(DynamicTag::VerSym),
+            symbol_count.map(|v| v * 2),
+            2,
+        ),
+        Some(".dynsym"),
+    );
+
+    if let Some(table) = get_sysv_hash_table(data, dynamic, program_headers, elf_header) {
+        let size = (2 + table.buckets.len() + table.chains.len()) as u64 * 4;
+        push(
+            new_table(
+                ".hash",
+                SectionType::Hash,
+                value(DynamicTag::Hash),
+                Some(size),
+                4,
+            ),
+            Some(".dynsym"),
+        );
+    }
+    if let Some(table) = get_gnu_hash_table(data, dynamic, program_headers, elf_header) {
+        let size = 16
+            + table.bloom.len() as u64 * word_size
+            + (table.buckets.len() + table.chains.len()) as u64 * 4;
+        push(
+            new_table(
+                ".gnu.hash",
+                SectionType::GNUHash,
+                value(DynamicTag::GNUHash),
+                Some(size),
+                0,
+            ),
+            Some(".dynsym"),
+        );
+    }
+
+    let u16_from_bytes = get_num_from_bytes!(u16, elf_header.ident.ei_data);
+    let u32_from_bytes = get_num_from_bytes!(u32, elf_header.ident.ei_data);
+    for (name, section_type, tag, count_tag, entry_size, aux_size) in &[
+        (
+            ".gnu.version_r",
+            SectionType::GNUVersionNeeds,
+            DynamicTag::VerNeed,
+            DynamicTag::VerNeedNum,
+            16,
+            16,
+        ),
+        (
+            ".gnu.version_d",
+            SectionType::GNUVersionDef,
+            DynamicTag::VerDef,
+            DynamicTag::VerDefNum,
+            20,
+            8,
+        ),
+    ] {
+        let (address, count) = match (value(*tag), value(*count_tag)) {
+            (Some(a), Some(c)) => (a, c),
+            _ => continue,
+        };
+        // Walk the chain of entries to find where the table ends
+        let table = match get_offset_for_address(address, program_headers) {
+            Some(v) if (v as usize) < data.len() => &data[v as usize..],
+            _ => continue,
+        };
+        let (count_offset, aux_offset, next_offset) = if *entry_size == 16 {
+            (2, 8, 12)
+        } else {
+            (6, 12, 16)
+        };
+        let mut offset = 0;
+        let mut end = 0;
+        for _ in 0..count {
+            let raw = match table.get(offset..offset + entry_size) {
+                Some(v) => v,
+                None => break,
+            };
+            let aux_count = u16_from_bytes(raw[count_offset..count_offset + 2].try_into().unwrap());
+            let aux = u32_from_bytes(raw[aux_offset..aux_offset + 4].try_into().unwrap());
+            end = std::cmp::max(end, offset + entry_size);
+            end = std::cmp::max(end, offset + aux as usize + aux_count as usize * aux_size);
+            let next = u32_from_bytes(raw[next_offset..next_offset + 4].try_into().unwrap());
+            if next == 0 {
+                break;
+            }
+            offset += next as usize;
+        }
+        if let Some(mut header) = new_table(name, *section_type, Some(address), Some(end as u64), 0)
+        {
+            header.info = count as u32;
+            push(Some(header), Some(".dynstr"));
+        }
+    }
+
+    let relocation_entry_size = match elf_header.ident.ei_class {
+        EI_Class::ELF32 => (8, 12),
+        EI_Class::ELF64 => (16, 24),
+    };
+    push(
+        new_table(
+            ".rela.dyn",
+            SectionType::Rela,
+            value(DynamicTag::Rela),
+            value(DynamicTag::RelaSize),
+            value(DynamicTag::RelaEnt).unwrap_or(relocation_entry_size.1),
+        ),
+        Some(".dynsym"),
+    );
+    push(
+        new_table(
+            ".rel.dyn",
+            SectionType::RelocationEnt,
+            value(DynamicTag::Rel),
+            value(DynamicTag::RelSize),
+            value(DynamicTag::RelEnt).unwrap_or(relocation_entry_size.0),
+        ),
+        Some(".dynsym"),
+    );
+    push(
+        new_table(
+            ".relr.dyn",
+            SectionType::Relr,
+            value(DynamicTag::Relr),
+            value(DynamicTag::RelrSize),
+            word_size,
+        ),
+        None,
+    );
+    // DT_PLTREL holds DT_REL or DT_RELA
+    let (plt_name, plt_type, plt_entry_size) = match value(DynamicTag::PltRel) {
+        Some(17) => (
+            ".rel.plt",
+            SectionType::RelocationEnt,
+            relocation_entry_size.0,
+        ),
+        _ => (".rela.plt", SectionType::Rela, relocation_entry_size.1),
+    };
+    push(
+        new_table(
+            plt_name,
+            plt_type,
+            value(DynamicTag::JmpRel),
+            value(DynamicTag::PltRelSize),
+            plt_entry_size,
+        ),
+        Some(".dynsym"),
+    );
+    if let (Some(table), Some(size)) = (value(DynamicTag::JmpRel), value(DynamicTag::PltRelSize)) {
+        for header in get_plt_sections(
+            data,
+            program_headers,
+            elf_header,
+            (table, size, plt_entry_size),
+        ) {
+            push(Some(header), None);
+        }
+    }
+
+    for (name, section_type, tag, size_tag) in &[
+        (
+            ".init_array",
+            SectionType::InitArray,
+            DynamicTag::InitArray,
+            DynamicTag::InitArraySize,
+        ),
+        (
+            ".fini_array",
+            SectionType::FiniArray,
+            DynamicTag::FiniArray,
+            DynamicTag::FiniArraySize,
+        ),
+        (
+            ".preinit_array",
+            SectionType::PreinitArray,
+            DynamicTag::PreinitArray,
+            DynamicTag::PreinitArraySize,
+        ),
+    ] {
+        if let Some(mut header) = new_table(
+            name,
+            *section_type,
+            value(*tag),
+            value(*size_tag),
+            word_size,
+        ) {
+            header.flags = SectionFlags::from_u64(0x3);
+            push(Some(header), None);
+        }
+    }
+
+    // Sort by address behind a null section and resolve the links
+    sections.sort_by_key(|s| (s.header.address, s.header.name_string.clone()));
+    let null_section = new_section("", SectionType::Null, 0, 0, 0, 0, 0);
+    let names: Vec<String> = sections
+        .iter()
+        .map(|s| s.header.name_string.clone())
+        .collect();
+    let mut section_headers: Vec<SectionHeader> = vec![null_section];
+    for mut section in sections {
+        if let Some(link_name) = section.link_name {
+            if let Some(index) = names.iter().position(|n| n == link_name) {
+                section.header.link = index as u32 + 1;
+            }
+        }
+        section_headers.push(section.header);
+    }
+    section_headers
+}
+
+#[cfg(test)]
+mod synthetic_tests {
+    use super::super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn new_dynamic_entry(tag: DynamicTag, raw_tag: u64, value: u64) -> DynamicEntry {
+        DynamicEntry {
+            tag,
+            raw_tag,
+            value,
+            string_value: None,
+        }
+    }
+
+    fn get_64_bit_header() -> ELFHeader {
+        let raw = &[
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x03, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00, 0xD0, 0x67, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x02, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(raw, ident).unwrap()
+    }
+
+    fn new_load_segment(
+        flags: u32,
+        offset: u64,
+        address: u64,
+        file_size: u64,
+        memory_size: u64,
+    ) -> ProgramHeader {
+        ProgramHeader {
+            header_type: ProgramHeaderType::Load,
+            flags,
+            offset,
+            virtual_address: address,
+            physical_address: address,
+            file_size,
+            memory_size,
+            align: 0x1000,
+        }
+    }
+
+    #[test]
+    fn can_synthesize_sections_from_load_segments() {
+        let program_headers = vec![
+            new_load_segment(0x5, 0x0, 0x40_0000, 0x800, 0x800),
+            new_load_segment(0x6, 0x800, 0x40_1800, 0x100, 0x300),
+        ];
+        let data = [0u8; 0x900];
+        let sections =
+            get_synthetic_section_headers(&data, &program_headers, &[], &get_64_bit_header());
+        let names: Vec<&str> = sections.iter().map(|s| s.name_string.as_str()).collect();
+        assert_eq!(names, vec!["", ".text", ".data", ".bss"]);
+        assert!(sections.iter().all(|s| s.synthetic));
+        assert_eq!(sections[3].address, 0x40_1900);
+        assert_eq!(sections[3].size, 0x200);
+        assert_eq!(sections[3].section_type, SectionType::NoBits);
+    }
+
+    #[test]
+    fn can_synthesize_plt_from_got_slots() {
+        let program_headers = vec![
+            new_load_segment(0x5, 0x0, 0x40_0000, 0x800, 0x800),
+            new_load_segment(0x6, 0x800, 0x40_1800, 0x100, 0x100),
+        ];
+        let dynamic = vec![
+            new_dynamic_entry(DynamicTag::PltRelSize, 2, 72),
+            new_dynamic_entry(DynamicTag::PltRel, 20, 7),
+            new_dynamic_entry(DynamicTag::JmpRel, 23, 0x40_1840),
+        ];
+        // .rela.plt, R_X86_64_JUMP_SLOT for the two stubs after PLT0 at 0x40_0400 and
+        // an R_X86_64_IRELATIVE whose slot holds a resolver in .text
+        let mut data = vec![0u8; 0x900];
+        for (i, (got, info)) in [
+            (0x40_1818u64, 0x1_0000_0007u64),
+            (0x40_1820, 0x2_0000_0007),
+            (0x40_1828, 37),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let entry = 0x840 + i * 24;
+            data[entry..entry + 8].copy_from_slice(&got.to_le_bytes());
+            data[entry + 8..entry + 16].copy_from_slice(&info.to_le_bytes());
+        }
+        let set_slots = |data: &mut Vec<u8>, slots: [u64; 3]| {
+            for (i, slot) in slots.iter().enumerate() {
+                data[0x818 + i * 8..0x820 + i * 8].copy_from_slice(&slot.to_le_bytes());
+            }
+        };
+        let get_section = |sections: &[SectionHeader], name: &str| {
+            sections
+                .iter()
+                .find(|s| s.name_string == name)
+                .map(|s| (s.address, s.offset, s.size))
+        };
+
+        // Lazy binding points each slot at the push after the jmp in its stub
+        set_slots(&mut data, [0x40_0416, 0x40_0426, 0x40_0100]);
+        let sections =
+            get_synthetic_section_headers(&data, &program_headers, &dynamic, &get_64_bit_header());
+        assert_eq!(
+            get_section(&sections, ".plt"),
+            Some((0x40_0400, 0x400, 0x30))
+        );
+        assert_eq!(get_section(&sections, ".plt.sec"), None);
+
+        // With IBT the slots point at the lazy stubs, which start with endbr64
+        set_slots(&mut data, [0x40_0410, 0x40_0420, 0x40_0100]);
+        data[0x410..0x414].copy_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa]);
+        data[0x420..0x424].copy_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa]);
+        let sections =
+            get_synthetic_section_headers(&data, &program_headers, &dynamic, &get_64_bit_header());
+        assert_eq!(
+            get_section(&sections, ".plt"),
+            Some((0x40_0400, 0x400, 0x30))
+        );
+        assert_eq!(
+            get_section(&sections, ".plt.sec"),
+            Some((0x40_0430, 0x430, 0x20))
+        );
+        assert!(sections
+            .iter()
+            .filter(|s| s.name_string.starts_with(".plt"))
+            .all(|s| s.flags == SectionFlags::from_u64(0x6)));
+    }
+}
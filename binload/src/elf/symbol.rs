@@ -6,9 +6,74 @@ use std::convert::TryInto;
 use std::fmt;
 
 use super::elf_header::{EI_Class, EI_Data, ELFHeader};
+use super::version::SymbolVersion;
+
+// Special section indexes for st_shndx
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_LORESERVE: u16 = 0xff00;
+pub const SHN_ABS: u16 = 0xfff1; // value is an absolute address, not in any section
+pub const SHN_COMMON: u16 = 0xfff2; // unallocated data, value is the alignment
+pub const SHN_XINDEX: u16 = 0xffff; // index is in the SHT_SYMTAB_SHNDX section
+
+// Where a symbol is defined, from st_shndx. Objects with more sections than fit
+// below SHN_LORESERVE store SHN_XINDEX and keep the real index in a parallel
+// SHT_SYMTAB_SHNDX table, those are resolved to Index when the table is read.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum SectionIndex {
+    Undefined,     // 0 - SHN_UNDEF
+    Absolute,      // 0xfff1 - SHN_ABS
+    Common,        // 0xfff2 - SHN_COMMON
+    Index(u32),    // A section header index
+    Reserved(u16), // 0xff00-0xffff - Processor, OS specific or an unresolved SHN_XINDEX
+}
+impl SectionIndex {
+    pub fn from_u16(value: u16) -> SectionIndex {
+        match value {
+            SHN_UNDEF => SectionIndex::Undefined,
+            SHN_ABS => SectionIndex::Absolute,
+            SHN_COMMON => SectionIndex::Common,
+            SHN_LORESERVE..=0xffff => SectionIndex::Reserved(value),
+            _ => SectionIndex::Index(u32::from(value)),
+        }
+    }
+
+    // The st_shndx value, indexes that don't fit are SHN_XINDEX
+    pub fn to_u16(self) -> u16 {
+        match self {
+            SectionIndex::Undefined => SHN_UNDEF,
+            SectionIndex::Absolute => SHN_ABS,
+            SectionIndex::Common => SHN_COMMON,
+            SectionIndex::Index(v) if v < u32::from(SHN_LORESERVE) => v as u16,
+            SectionIndex::Index(_) => SHN_XINDEX,
+            SectionIndex::Reserved(v) => v,
+        }
+    }
+
+    // The section header index, None for the special indexes
+    pub fn get_index(self) -> Option<usize> {
+        match self {
+            SectionIndex::Index(v) => Some(v as usize),
+            _ => None,
+        }
+    }
+}
+
+// Shown the way readelf shows it
+impl fmt::Display for SectionIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            SectionIndex::Undefined => String::from("UND"),
+            SectionIndex::Absolute => String::from("ABS"),
+            SectionIndex::Common => String::from("COM"),
+            SectionIndex::Index(v) => v.to_string(),
+            SectionIndex::Reserved(v) => format!("{:#06x}", v),
+        };
+        f.pad(&string)
+    }
+}
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Bind {
     Local,     // 0 - Local symbol
     Global,    // 1 - Global symbol
@@ -32,10 +97,23 @@ impl Bind {
             _ => Bind::Unknown,
         }
     }
+
+    // OS and Proc give the start of their range
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Bind::Local => 0,
+            Bind::Global => 1,
+            Bind::Weak => 2,
+            Bind::Num => 3,
+            Bind::GNUUnique | Bind::OS => 10,
+            Bind::Proc => 13,
+            Bind::Unknown => 0,
+        }
+    }
 }
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum SymbolType {
     NoType,    // 0 - Symbol type is unspecified
     Object,    // 1 - Symbol is a data object
@@ -66,11 +144,27 @@ impl SymbolType {
             _ => SymbolType::NoType,
         }
     }
+
+    // OS and Proc give the start of their range
+    pub fn to_u8(self) -> u8 {
+        match self {
+            SymbolType::NoType => 0,
+            SymbolType::Object => 1,
+            SymbolType::Function => 2,
+            SymbolType::Section => 3,
+            SymbolType::File => 4,
+            SymbolType::Common => 5,
+            SymbolType::TLS => 6,
+            SymbolType::Num => 7,
+            SymbolType::GNUIFunct | SymbolType::OS => 10,
+            SymbolType::Proc => 13,
+        }
+    }
 }
 
 // Visibility is a 3 bit value
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Visibility {
     Default,   // 0 - Default symbol visibility rules
     Internal,  // 1 - Processor specific hidden class
@@ -87,9 +181,19 @@ impl Visibility {
             _ => Visibility::Default,
         }
     }
+
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Visibility::Default => 0,
+            Visibility::Internal => 1,
+            Visibility::Hidden => 2,
+            Visibility::Protected => 3,
+        }
+    }
 }
 
 //  line 519 of elf.h
+#[derive(Clone)]
 pub struct Symbol {
     pub name: u32,               // Symbol name (string table index)
     pub address: u64,            // Symbol value
@@ -97,8 +201,9 @@ pub struct Symbol {
     pub bind: Bind,              // high 4 bits from info field
     pub symbol_type: SymbolType, // low 4 bits from info field
     pub visibility: Visibility,  // Symbol visibility
-    pub section_index: u16,      // Section index
+    pub section_index: SectionIndex,
     pub name_string: String,
+    pub version: Option<SymbolVersion>, // From .gnu.version, dynamic symbols only
 }
 
 impl Symbol {
@@ -129,24 +234,68 @@ impl Symbol {
                     bind: Bind::from_u8(raw[12] >> 4),
                     symbol_type: SymbolType::from_u8(raw[12] & 0xf),
                     visibility: Visibility::from_u8(raw[13] & 0x3),
-                    section_index: u16_from_bytes(raw[14..16].try_into().unwrap()),
+                    section_index: SectionIndex::from_u16(u16_from_bytes(
+                        raw[14..16].try_into().unwrap(),
+                    )),
                     name_string: std::string::String::new(),
+                    version: None,
                 },
                 EI_Class::ELF64 => Symbol {
                     name: u32_from_bytes(raw[0..4].try_into().unwrap()),
                     bind: Bind::from_u8(raw[4] >> 4),
                     symbol_type: SymbolType::from_u8(raw[4] & 0xf),
                     visibility: Visibility::from_u8(raw[5] & 0x3),
-                    section_index: u16_from_bytes(raw[6..8].try_into().unwrap()),
+                    section_index: SectionIndex::from_u16(u16_from_bytes(
+                        raw[6..8].try_into().unwrap(),
+                    )),
                     address: u64_from_bytes(raw[8..16].try_into().unwrap()),
                     size: u64_from_bytes(raw[16..24].try_into().unwrap()),
                     name_string: std::string::String::new(),
+                    version: None,
                 },
             });
         }
         symbols
     }
 
+    // Replaces SHN_XINDEX with the real index from the symbol table's
+    // SHT_SYMTAB_SHNDX section, which has a word for each symbol in the table
+    pub fn apply_extended_section_indexes(
+        symbols: &mut [Symbol],
+        indexes: &[u8],
+        header: &ELFHeader,
+    ) {
+        let u32_from_bytes = get_num_from_bytes!(u32, header.ident.ei_data);
+        for (symbol, raw) in symbols.iter_mut().zip(indexes.chunks_exact(4)) {
+            if symbol.section_index == SectionIndex::Reserved(SHN_XINDEX) {
+                symbol.section_index = SectionIndex::Index(u32_from_bytes(raw.try_into().unwrap()));
+            }
+        }
+    }
+
+    // Defined in a section, rather than undefined, absolute or common
+    pub fn is_in_section(&self) -> bool {
+        matches!(self.section_index, SectionIndex::Index(_))
+    }
+
+    // Common symbols are tentative definitions from relocatable objects, the linker
+    // allocates them in .bss
+    pub fn is_common(&self) -> bool {
+        self.section_index == SectionIndex::Common
+    }
+
+    // Name with the version appended the way readelf shows it, e.g. memcpy@GLIBC_2.14.
+    // Only the default version of a defined symbol uses @@.
+    pub fn get_versioned_name(&self) -> String {
+        match &self.version {
+            Some(v) if self.section_index == SectionIndex::Undefined || v.hidden => {
+                format!("{}@{}", self.name_string, v.name)
+            }
+            Some(v) => format!("{}@@{}", self.name_string, v.name),
+            None => self.name_string.clone(),
+        }
+    }
+
     fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let strings = [
             format!("{:15}{:?}", "Name Index:", self.name),
@@ -159,7 +308,8 @@ impl Symbol {
             format!("{:15}{:?}", "Bind:", self.bind),
             format!("{:15}{:?}", "Type:", self.symbol_type),
             format!("{:15}{:?}", "Visibility:", self.visibility),
-            format!("{:15}{:?}", "Section Index:", self.section_index),
+            format!("{:15}{}", "Section Index:", self.section_index),
+            format!("{:15}{:?}", "Version:", self.version),
         ];
         writeln!(f, "{}", strings.join("\n"))
     }
@@ -176,3 +326,59 @@ impl fmt::Debug for Symbol {
         self.formatter(f)
     }
 }
+
+#[cfg(test)]
+mod symbol_tests {
+    use super::super::elf_header::ELFIdent;
+    use super::*;
+
+    fn get_64_bit_header() -> ELFHeader {
+        let mut raw = vec![
+            0x7F, 0x45, 0x4C, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x01, 0x00, 0x3E, 0x00, 0x01, 0x00, 0x00, 0x00,
+        ];
+        raw.resize(64, 0);
+        let ident = ELFIdent::parse_from_buffer(&raw[0..16]).unwrap();
+        ELFHeader::parse_from_buffer(&raw, ident).unwrap()
+    }
+
+    #[test]
+    fn can_convert_section_indexes() {
+        assert_eq!(SectionIndex::from_u16(0), SectionIndex::Undefined);
+        assert_eq!(SectionIndex::from_u16(0xfff1), SectionIndex::Absolute);
+        assert_eq!(SectionIndex::from_u16(0xfff2), SectionIndex::Common);
+        assert_eq!(SectionIndex::from_u16(0x12), SectionIndex::Index(0x12));
+        assert_eq!(
+            SectionIndex::from_u16(0xff1f),
+            SectionIndex::Reserved(0xff1f)
+        );
+        for value in &[0, 0x12, 0xfeff, 0xff1f, 0xfff1, 0xfff2, 0xffff] {
+            assert_eq!(SectionIndex::from_u16(*value).to_u16(), *value);
+        }
+        assert_eq!(SectionIndex::Index(0x1_0000).to_u16(), SHN_XINDEX);
+        assert_eq!(SectionIndex::Index(0x1_0000).get_index(), Some(0x1_0000));
+        assert_eq!(SectionIndex::Common.get_index(), None);
+        assert_eq!(format!("{:>4}", SectionIndex::Undefined), " UND");
+        assert_eq!(format!("{}", SectionIndex::Index(7)), "7");
+    }
+
+    #[test]
+    fn can_apply_extended_section_indexes() {
+        // A symbol in section 3 and one with SHN_XINDEX
+        let mut raw = vec![0u8; 48];
+        raw[6] = 0x03;
+        raw[24 + 6] = 0xff;
+        raw[24 + 7] = 0xff;
+        let header = get_64_bit_header();
+        let mut symbols = Symbol::parse_from_symbol_table(&raw, &header);
+        assert_eq!(symbols[1].section_index, SectionIndex::Reserved(SHN_XINDEX));
+        assert!(!symbols[1].is_in_section());
+
+        // SHT_SYMTAB_SHNDX has 0 for the symbols that don't use it
+        let indexes = [0x00, 0x00, 0x00, 0x00, 0x34, 0x12, 0x01, 0x00];
+        Symbol::apply_extended_section_indexes(&mut symbols, &indexes, &header);
+        assert_eq!(symbols[0].section_index, SectionIndex::Index(3));
+        assert_eq!(symbols[1].section_index, SectionIndex::Index(0x1_1234));
+        assert!(symbols[1].is_in_section());
+    }
+}
@@ -0,0 +1,397 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use super::elf_header::{EI_Class, EI_Data};
+use super::init_array::get_word_size;
+use super::program_header::ProgramHeaderType;
+use super::symbol::SymbolType;
+use super::ELF;
+
+// Refuse to build templates larger than this, a corrupt p_memsz could otherwise ask
+// for an enormous allocation
+const MAX_TLS_SIZE: u64 = 1 << 32;
+
+// A thread local variable, from an STT_TLS symbol. In executables and shared
+// libraries the symbol value is the offset in the module's TLS block rather than an
+// address.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TlsVariable {
+    pub name: String,
+    pub offset: u64,
+    pub size: u64,
+}
+
+// The initialisation image from PT_TLS that every new thread gets a copy of, the
+// .tdata contents followed by zeroes for .tbss
+pub struct TlsTemplate {
+    pub address: u64,     // p_vaddr of the .tdata contents
+    pub file_size: u64,   // Size of .tdata
+    pub memory_size: u64, // Size of .tdata and .tbss
+    pub alignment: u64,
+    pub image: Vec<u8>, // memory_size bytes, with relocations applied
+    pub variables: Vec<TlsVariable>,
+}
+
+impl TlsTemplate {
+    // None when there is no PT_TLS, relocatable objects only have the .tdata and
+    // .tbss sections
+    pub fn from_elf(elf: &ELF) -> Option<TlsTemplate> {
+        let header = elf
+            .program_headers
+            .iter()
+            .find(|p| p.header_type == ProgramHeaderType::ThreadLocalStorage)?;
+        if header.memory_size > MAX_TLS_SIZE || header.file_size > header.memory_size {
+            return None;
+        }
+        let mut image = header.get_data(&elf.data).to_vec();
+        image.resize(header.memory_size as usize, 0);
+        // .tbss takes no address space, its addresses belong to the sections after it
+        apply_relocations(
+            elf,
+            header.virtual_address,
+            &mut image[..header.file_size as usize],
+        );
+
+        let mut variables: Vec<TlsVariable> = vec![];
+        for symbol in elf
+            .symbol_table
+            .iter()
+            .chain(elf.dynamic_symbol_table.iter())
+        {
+            if !matches!(symbol.symbol_type, SymbolType::TLS)
+                || !symbol.is_in_section()
+                || variables.iter().any(|v| v.name == symbol.name_string)
+            {
+                continue;
+            }
+            variables.push(TlsVariable {
+                name: symbol.name_string.clone(),
+                offset: symbol.address,
+                size: symbol.size,
+            });
+        }
+        variables.sort_by_key(|v| v.offset);
+
+        Some(TlsTemplate {
+            address: header.virtual_address,
+            file_size: header.file_size,
+            memory_size: header.memory_size,
+            alignment: header.align,
+            image,
+            variables,
+        })
+    }
+
+    // The value every thread starts with, None when it is outside the template
+    pub fn get_initial_value(&self, variable: &TlsVariable) -> Option<&[u8]> {
+        let end = variable.offset.checked_add(variable.size)?;
+        self.image.get(variable.offset as usize..end as usize)
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut strings = vec![
+            format!("{:15}{:#x}", "Address:", self.address),
+            format!("{:15}{:#x}", "File Size:", self.file_size),
+            format!("{:15}{:#x}", "Memory Size:", self.memory_size),
+            format!("{:15}{:#x}", "Alignment:", self.alignment),
+        ];
+        for variable in &self.variables {
+            strings.push(format!(
+                "{:15}{:#x} {:#x} {}",
+                "Variable:", variable.offset, variable.size, variable.name
+            ));
+        }
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+
+impl fmt::Display for TlsTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+impl fmt::Debug for TlsTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// Pointers in .tdata of a position independent binary are filled in by relocations
+// like anywhere else, apply the ones that only need the binary itself
+fn apply_relocations(elf: &ELF, address: u64, image: &mut [u8]) {
+    let ei_data = elf.elf_header.ident.ei_data;
+    let u32_from_bytes = get_num_from_bytes!(u32, ei_data);
+    let u64_from_bytes = get_num_from_bytes!(u64, ei_data);
+    let word_size = get_word_size(elf);
+    for relocation in &elf.dynamic_relocations {
+        let start = match relocation.offset.checked_sub(address) {
+            Some(v) if v.saturating_add(word_size as u64) <= image.len() as u64 => v as usize,
+            _ => continue,
+        };
+        let slot = &mut image[start..start + word_size];
+        let value = match elf.elf_header.ident.ei_class {
+            EI_Class::ELF32 => u64::from(u32_from_bytes((&*slot).try_into().unwrap())),
+            EI_Class::ELF64 => u64_from_bytes((&*slot).try_into().unwrap()),
+        };
+        let relocated = match relocation.get_relocated_value(value) {
+            Some(v) => v,
+            None => continue,
+        };
+        match (elf.elf_header.ident.ei_class, ei_data) {
+            (EI_Class::ELF32, EI_Data::LittleEndian) => {
+                slot.copy_from_slice(&(relocated as u32).to_le_bytes())
+            }
+            (EI_Class::ELF32, EI_Data::BigEndian) => {
+                slot.copy_from_slice(&(relocated as u32).to_be_bytes())
+            }
+            (EI_Class::ELF64, EI_Data::LittleEndian) => {
+                slot.copy_from_slice(&relocated.to_le_bytes())
+            }
+            (EI_Class::ELF64, EI_Data::BigEndian) => slot.copy_from_slice(&relocated.to_be_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tls_tests {
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    fn get_variables(template: &TlsTemplate) -> Vec<(&str, u64, u64)> {
+        template
+            .variables
+            .iter()
+            .map(|v| (v.name.as_str(), v.offset, v.size))
+            .collect()
+    }
+
+    #[test]
+    fn can_get_tls_template() {
+        // An x86_64 shared library with an int and a pointer to .data in .tdata and a
+        // buffer in .tbss. .data starts where .tbss does and has a relocation of its
+        // own.
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, x86_64, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x03, 0x00, 0x40, 0x00, 0x09, 0x00, 0x08, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment =
+            |header_type: u32, offset: u64, file_size: u64, memory_size: u64, align: u64| {
+                push_u32(&mut raw, header_type);
+                push_u32(&mut raw, if header_type == 7 { 4 } else { 6 });
+                push_u64(&mut raw, offset);
+                push_u64(&mut raw, offset);
+                push_u64(&mut raw, offset);
+                push_u64(&mut raw, file_size);
+                push_u64(&mut raw, memory_size);
+                push_u64(&mut raw, align);
+            };
+        // PT_LOAD, PT_DYNAMIC and PT_TLS
+        push_segment(1, 0, 0x178, 0x178, 0x1000);
+        push_segment(2, 0x138, 0x40, 0x40, 8);
+        push_segment(7, 0x120, 0x10, 0x30, 8);
+
+        // .rela.dyn with an R_X86_64_RELATIVE for the pointers in .tdata and .data
+        for (offset, addend) in [(0x128, 0x130), (0x130, 0x120)] {
+            push_u64(&mut raw, offset);
+            push_u64(&mut raw, 8);
+            push_u64(&mut raw, addend);
+        }
+        // .tdata, .data and .dynamic
+        raw.resize(0x120, 0);
+        raw.push(0x2a);
+        raw.resize(0x138, 0);
+        for (tag, value) in [(7, 0xe8), (8, 48), (9, 24), (0, 0)] {
+            push_u64(&mut raw, tag);
+            push_u64(&mut raw, value);
+        }
+        // .symtab with the STT_TLS variables and .strtab
+        raw.extend_from_slice(&[0; 24]);
+        for (name, section, value, size) in [(1, 2, 0, 4), (13, 2, 8, 8), (25, 3, 0x10, 0x20)] {
+            push_u32(&mut raw, name);
+            raw.extend_from_slice(&[0x16, 0, section, 0]);
+            push_u64(&mut raw, value);
+            push_u64(&mut raw, size);
+        }
+        raw.extend_from_slice(b"\0tls_counter\0tls_pointer\0tls_buffer\0");
+        raw.extend_from_slice(
+            b"\0.rela.dyn\0.tdata\0.tbss\0.data\0.dynamic\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x240, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 4, 2, 0xe8, 0xe8, 48, 0, 0, 8, 24]);
+        push_section_header(&mut raw, [11, 1, 0x403, 0x120, 0x120, 0x10, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [18, 8, 0x403, 0x130, 0x130, 0x20, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [24, 1, 3, 0x130, 0x130, 8, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [30, 6, 3, 0x138, 0x138, 0x40, 0, 0, 8, 16]);
+        push_section_header(&mut raw, [39, 2, 0, 0, 0x178, 96, 7, 1, 8, 24]);
+        push_section_header(&mut raw, [47, 3, 0, 0, 0x1d8, 36, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [55, 3, 0, 0, 0x1fc, 65, 0, 0, 1, 0]);
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        let template = TlsTemplate::from_elf(&elf).unwrap();
+        assert_eq!(template.address, 0x120);
+        assert_eq!(template.file_size, 0x10);
+        assert_eq!(template.memory_size, 0x30);
+        assert_eq!(template.alignment, 8);
+
+        // The pointer in .tdata is relocated, but the relocation for .data that
+        // overlaps .tbss is not
+        let mut image = vec![0; 0x30];
+        image[0] = 0x2a;
+        image[8] = 0x30;
+        image[9] = 0x01;
+        assert_eq!(template.image, image);
+
+        assert_eq!(
+            get_variables(&template),
+            vec![
+                ("tls_counter", 0, 4),
+                ("tls_pointer", 8, 8),
+                ("tls_buffer", 0x10, 0x20),
+            ]
+        );
+        assert_eq!(
+            template.get_initial_value(&template.variables[0]),
+            Some(&[0x2a, 0, 0, 0][..])
+        );
+        assert_eq!(
+            template.get_initial_value(&template.variables[1]),
+            Some(&0x130u64.to_le_bytes()[..])
+        );
+        assert_eq!(
+            template.get_initial_value(&template.variables[2]),
+            Some(&[0; 0x20][..])
+        );
+    }
+
+    #[test]
+    fn can_get_32_bit_tls_template() {
+        // An i386 shared library with an int and a pointer in .tdata, the pointer is an
+        // R_386_32 against a symbol in .data with 4 in the file. There's no .symtab to
+        // name the variables.
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x01, 0x01, 0x01, 0x00, // 32 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, i386, version 1
+            0x00, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00, // e_entry and e_phoff
+            0x4c, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff and e_flags
+            0x34, 0x00, 0x20, 0x00, 0x03, 0x00, 0x28, 0x00, // e_ehsize to e_shentsize
+            0x08, 0x00, 0x07, 0x00, // e_shnum and e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        // PT_LOAD, PT_DYNAMIC and PT_TLS
+        for (header_type, offset, size, align) in
+            [(1, 0, 0x110, 0x1000), (2, 0xd0, 0x40, 4), (7, 0xc4, 8, 4)]
+        {
+            for field in [header_type, offset, offset, offset, size, size, 6, align] {
+                push_u32(&mut raw, field);
+            }
+        }
+
+        // .dynsym and .dynstr
+        raw.extend_from_slice(&[0; 16]);
+        for value in [1, 0xcc, 4] {
+            push_u32(&mut raw, value);
+        }
+        raw.extend_from_slice(&[0x11, 0, 5, 0]);
+        raw.extend_from_slice(b"\0data\0");
+        // .rel.dyn
+        raw.resize(0xbc, 0);
+        push_u32(&mut raw, 0xc8);
+        push_u32(&mut raw, (1 << 8) | 1);
+        // .tdata and .data
+        for value in [7, 4, 0] {
+            push_u32(&mut raw, value);
+        }
+        // .dynamic
+        for (tag, value) in [
+            (6, 0x94),
+            (11, 16),
+            (5, 0xb4),
+            (10, 6),
+            (17, 0xbc),
+            (18, 8),
+            (19, 8),
+            (0, 0),
+        ] {
+            push_u32(&mut raw, tag);
+            push_u32(&mut raw, value);
+        }
+        raw.extend_from_slice(
+            b"\0.dynsym\0.dynstr\0.rel.dyn\0.tdata\0.data\0.dynamic\0.shstrtab\0",
+        );
+
+        raw.resize(0x14c, 0);
+        for fields in [
+            [0; 10],
+            [1, 11, 2, 0x94, 0x94, 32, 2, 1, 4, 16],
+            [9, 3, 2, 0xb4, 0xb4, 6, 0, 0, 1, 0],
+            [17, 9, 2, 0xbc, 0xbc, 8, 1, 0, 4, 8],
+            [26, 1, 0x403, 0xc4, 0xc4, 8, 0, 0, 4, 0],
+            [33, 1, 3, 0xcc, 0xcc, 4, 0, 0, 4, 0],
+            [39, 6, 3, 0xd0, 0xd0, 0x40, 2, 0, 4, 8],
+            [48, 3, 0, 0, 0x110, 58, 0, 0, 1, 0],
+        ] {
+            for field in fields {
+                push_u32(&mut raw, field);
+            }
+        }
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        let template = TlsTemplate::from_elf(&elf).unwrap();
+        assert_eq!(template.address, 0xc4);
+        assert_eq!(template.file_size, 8);
+        assert_eq!(template.memory_size, 8);
+        assert_eq!(template.alignment, 4);
+        assert_eq!(template.image, vec![7, 0, 0, 0, 0xd0, 0, 0, 0]);
+        assert!(template.variables.is_empty());
+    }
+
+    #[test]
+    fn object_has_no_tls_template() {
+        // An x86_64 object with a .tdata section but no program headers
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x01, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_REL, x86_64, version 1
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, // e_flags to e_phentsize
+            0x00, 0x00, 0x40, 0x00, 0x03, 0x00, 0x02, 0x00, // e_phnum to e_shstrndx
+        ];
+        raw.extend_from_slice(&[0x2a, 0, 0, 0, 0, 0, 0, 0]);
+        raw.extend_from_slice(b"\0.tdata\0.shstrtab\0");
+        raw.resize(0x60, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 1, 0x403, 0, 0x40, 8, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [8, 3, 0, 0, 0x48, 18, 0, 0, 1, 0]);
+
+        let elf = load_elf_from_vec(raw).unwrap();
+        assert!(elf.diagnostics.is_empty());
+        assert!(TlsTemplate::from_elf(&elf).is_none());
+    }
+}
@@ -12,3 +12,11 @@ pub fn get_null_terminated_string_from_vec(vec: &[u8], offset: usize) -> String
     }
     string
 }
+
+// Gets `size` bytes at `offset`, clamped to the end of the data so a corrupt
+// offset or size gives a short (possibly empty) slice rather than a panic
+pub fn get_clamped_slice(data: &[u8], offset: u64, size: u64) -> &[u8] {
+    let end = std::cmp::min(offset.saturating_add(size), data.len() as u64) as usize;
+    let start = std::cmp::min(offset as usize, end);
+    &data[start..end]
+}
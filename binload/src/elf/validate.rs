@@ -0,0 +1,318 @@
+use super::elf_header::{EI_Class, ELFHeader};
+use super::program_header::{ProgramHeader, ProgramHeaderType};
+use super::section::{SectionHeader, SectionType};
+use super::symbol::SHN_XINDEX;
+use crate::error::Diagnostic;
+
+// e_phnum when there are too many program headers to fit, the count is in sh_info of
+// section 0
+pub const PN_XNUM: u16 = 0xffff;
+
+// Extended numbering: objects with SHN_LORESERVE or more sections set e_shnum to 0
+// and e_shstrndx to SHN_XINDEX, and keep the real values in sh_size and sh_link of
+// section 0, which is otherwise unused
+fn get_first_section_header(data: &[u8], header: &ELFHeader) -> Option<SectionHeader> {
+    if header.e_shoff == 0 {
+        return None;
+    }
+    SectionHeader::parse_from_buffer(0, data, header).ok()
+}
+
+// The index of the section name string table, see get_first_section_header
+pub fn get_section_name_index(data: &[u8], header: &ELFHeader) -> u32 {
+    if header.e_shstrndx != SHN_XINDEX {
+        return u32::from(header.e_shstrndx);
+    }
+    get_first_section_header(data, header).map_or(0, |s| s.link)
+}
+
+// Works out how many section headers can be read, see get_table_entry_count and
+// get_first_section_header
+pub fn get_section_header_count(
+    data: &[u8],
+    header: &ELFHeader,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> u32 {
+    let minimum_entry_size = match header.ident.ei_class {
+        EI_Class::ELF32 => 40,
+        EI_Class::ELF64 => 64,
+    };
+    let count = match header.e_shnum {
+        0 => get_first_section_header(data, header)
+            .map_or(0, |s| std::cmp::min(s.size, u64::from(u32::MAX)) as u32),
+        v => u32::from(v),
+    };
+    get_table_entry_count(
+        data,
+        header.e_shoff,
+        count,
+        header.e_shentsize,
+        minimum_entry_size,
+        "section header table",
+        diagnostics,
+    )
+}
+
+// Works out how many program headers can be read, see get_table_entry_count
+pub fn get_program_header_count(
+    data: &[u8],
+    header: &ELFHeader,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> u32 {
+    let minimum_entry_size = match header.ident.ei_class {
+        EI_Class::ELF32 => 32,
+        EI_Class::ELF64 => 56,
+    };
+    let count = match get_first_section_header(data, header) {
+        Some(v) if header.e_phnum == PN_XNUM => v.info,
+        _ => u32::from(header.e_phnum),
+    };
+    get_table_entry_count(
+        data,
+        header.e_phoff,
+        count,
+        header.e_phentsize,
+        minimum_entry_size,
+        "program header table",
+        diagnostics,
+    )
+}
+
+// Entries of a header table that are past the end of the file are dropped rather
+// than failing the whole load. Entries smaller than the structure they hold can't
+// be read at all, so the table is ignored.
+fn get_table_entry_count(
+    data: &[u8],
+    offset: u64,
+    count: u32,
+    entry_size: u16,
+    minimum_entry_size: u16,
+    name: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> u32 {
+    if count == 0 {
+        return 0;
+    }
+    if entry_size < minimum_entry_size {
+        diagnostics.push(Diagnostic::new(
+            offset,
+            format!(
+                "{} entry size {} is smaller than {} bytes, ignored",
+                name, entry_size, minimum_entry_size
+            ),
+        ));
+        return 0;
+    }
+    let available = (data.len() as u64).saturating_sub(offset) / u64::from(entry_size);
+    if available < u64::from(count) {
+        diagnostics.push(Diagnostic::new(
+            offset,
+            format!(
+                "{} extends past EOF, truncated to {} of {} entries",
+                name, available, count
+            ),
+        ));
+        return available as u32;
+    }
+    count
+}
+
+fn get_section_label(index: usize, section: &SectionHeader) -> String {
+    if section.name_string.is_empty() {
+        format!("section {}", index)
+    } else {
+        format!("section {}", section.name_string)
+    }
+}
+
+// Sections types where sh_link is the index of another section
+fn has_section_link(section_type: &SectionType) -> bool {
+    matches!(
+        section_type,
+        SectionType::SymTab
+            | SectionType::DynSym
+            | SectionType::Rela
+            | SectionType::RelocationEnt
+            | SectionType::Hash
+            | SectionType::Dynamic
+            | SectionType::Group
+            | SectionType::SymTabShNdx
+            | SectionType::GNUHash
+            | SectionType::GNUVersionDef
+            | SectionType::GNUVersionNeeds
+            | SectionType::GNUVersionSymTbl
+    )
+}
+
+// Looks for sections that run past the end of the file, overlap each other or
+// point at sections that don't exist
+pub fn check_section_headers(data: &[u8], section_headers: &[SectionHeader]) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let mut in_file: Vec<(u64, u64, usize)> = vec![];
+    for (i, section) in section_headers.iter().enumerate() {
+        if has_section_link(&section.section_type) && section.link as usize >= section_headers.len()
+        {
+            diagnostics.push(Diagnostic::new(
+                section.offset,
+                format!(
+                    "{} links to section {} which doesn't exist",
+                    get_section_label(i, section),
+                    section.link
+                ),
+            ));
+        }
+        if section.section_type == SectionType::NoBits
+            || section.section_type == SectionType::Null
+            || section.size == 0
+        {
+            continue;
+        }
+        let end = section.offset.saturating_add(section.size);
+        if end > data.len() as u64 {
+            diagnostics.push(Diagnostic::new(
+                section.offset,
+                format!(
+                    "{} extends past EOF, truncated to {:#x} of {:#x} bytes",
+                    get_section_label(i, section),
+                    section.get_data(data).len(),
+                    section.size
+                ),
+            ));
+        }
+        in_file.push((section.offset, end, i));
+    }
+
+    // Sorted by offset, a section overlaps if it starts before the furthest end
+    // seen so far
+    in_file.sort();
+    let mut furthest: Option<(u64, usize)> = None;
+    for (start, end, i) in in_file {
+        if let Some((furthest_end, j)) = furthest {
+            if start < furthest_end {
+                diagnostics.push(Diagnostic::new(
+                    start,
+                    format!(
+                        "{} overlaps {}",
+                        get_section_label(i, &section_headers[i]),
+                        get_section_label(j, &section_headers[j])
+                    ),
+                ));
+            }
+            if end <= furthest_end {
+                continue;
+            }
+        }
+        furthest = Some((end, i));
+    }
+    diagnostics
+}
+
+// Looks for segments that run past the end of the file or can't be mapped
+pub fn check_program_headers(data: &[u8], program_headers: &[ProgramHeader]) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    for (i, segment) in program_headers.iter().enumerate() {
+        let label = format!("segment {} ({})", i, segment.header_type);
+        if segment.offset.saturating_add(segment.file_size) > data.len() as u64 {
+            diagnostics.push(Diagnostic::new(
+                segment.offset,
+                format!(
+                    "{} extends past EOF, truncated to {:#x} of {:#x} bytes",
+                    label,
+                    segment.get_data(data).len(),
+                    segment.file_size
+                ),
+            ));
+        }
+        if segment.header_type != ProgramHeaderType::Load {
+            continue;
+        }
+        if segment.file_size > segment.memory_size {
+            diagnostics.push(Diagnostic::new(
+                segment.offset,
+                format!("{} has a file size larger than its memory size", label),
+            ));
+        }
+        if segment
+            .virtual_address
+            .checked_add(segment.memory_size)
+            .is_none()
+        {
+            diagnostics.push(Diagnostic::new(
+                segment.offset,
+                format!("{} wraps around the end of the address space", label),
+            ));
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use super::*;
+    use crate::elf::section::SectionFlags;
+
+    fn get_section(name: &str, section_type: SectionType, offset: u64, size: u64) -> SectionHeader {
+        SectionHeader {
+            name: 0,
+            section_type,
+            flags: SectionFlags::from_u64(0),
+            address: 0,
+            offset,
+            size,
+            link: 0,
+            info: 0,
+            addralign: 0,
+            entsize: 0,
+            name_string: name.to_string(),
+            synthetic: false,
+            group: None,
+        }
+    }
+
+    #[test]
+    fn can_find_bad_sections() {
+        let data = [0u8; 0x100];
+        let mut symtab = get_section(".symtab", SectionType::SymTab, 0x40, 0x18);
+        symtab.link = 9;
+        let sections = [
+            get_section("", SectionType::Null, 0, 0),
+            get_section(".text", SectionType::ProgBits, 0x80, 0x100),
+            get_section(".data", SectionType::ProgBits, 0x90, 0x10),
+            get_section(".bss", SectionType::NoBits, 0x80, 0x1000),
+            symtab,
+        ];
+        let diagnostics: Vec<String> = check_section_headers(&data, &sections)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "section .text extends past EOF, truncated to 0x80 of 0x100 bytes",
+                "section .symtab links to section 9 which doesn't exist",
+                "section .data overlaps section .text",
+            ]
+        );
+        assert_eq!(sections[1].get_data(&data).len(), 0x80);
+    }
+
+    #[test]
+    fn can_truncate_header_tables() {
+        let data = [0u8; 0x100];
+        let mut diagnostics: Vec<Diagnostic> = vec![];
+        assert_eq!(
+            get_table_entry_count(&data, 0x40, 4, 0x40, 0x40, "table", &mut diagnostics),
+            3
+        );
+        assert_eq!(
+            get_table_entry_count(&data, 0x40, 4, 0x20, 0x40, "table", &mut diagnostics),
+            0
+        );
+        assert_eq!(
+            get_table_entry_count(&data, u64::MAX, 4, 0x40, 0x40, "table", &mut diagnostics),
+            0
+        );
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].offset, Some(0x40));
+    }
+}
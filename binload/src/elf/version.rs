@@ -0,0 +1,341 @@
+use std::convert::TryInto;
+use std::fmt;
+
+use bitflags::bitflags;
+
+use super::elf_header::{EI_Data, ELFHeader};
+use super::section::{SectionHeader, SectionType};
+use super::symbol::Symbol;
+use super::utils::get_null_terminated_string_from_vec;
+
+// Special values in .gnu.version
+const VER_NDX_LOCAL: u16 = 0; // Symbol is local
+const VER_NDX_GLOBAL: u16 = 1; // Symbol is global and unversioned
+const VERSYM_HIDDEN: u16 = 0x8000; // Symbol is not the default version
+const VERSYM_VERSION: u16 = 0x7fff; // Mask for the version index
+
+bitflags! {
+    // Values of vd_flags and vna_flags
+    pub struct VersionFlags: u16 {
+        const BASE = 0x0001; // Version definition of the file itself
+        const WEAK = 0x0002; // Weak version identifier
+    }
+}
+
+// A version a symbol is bound to. The library is set for versions that are
+// required from another object and None for versions defined by this one.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SymbolVersion {
+    pub index: u16,   // Version index from .gnu.version
+    pub name: String, // e.g. GLIBC_2.14
+    pub hidden: bool, // Not the default version of the symbol
+    pub library: Option<String>,
+}
+
+// Elfxx_Vernaux
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VersionNeedAux {
+    pub hash: u32,           // Hash value of the name
+    pub flags: VersionFlags, // Dependency specific information
+    pub index: u16,          // Version index as used in .gnu.version
+    pub name: String,
+}
+
+// Elfxx_Verneed, with the auxiliary entries for each version needed from the file
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VersionNeed {
+    pub version: u16, // Version of the structure, should be 1
+    pub file: String, // Name of the library the versions are needed from
+    pub versions: Vec<VersionNeedAux>,
+}
+
+// Elfxx_Verdef, the first Verdaux holds the name and the rest name the parents
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct VersionDefinition {
+    pub version: u16,        // Version of the structure, should be 1
+    pub flags: VersionFlags, // Version information
+    pub index: u16,          // Version index as used in .gnu.version
+    pub hash: u32,           // Hash value of the name
+    pub name: String,
+    pub parents: Vec<String>,
+}
+
+impl fmt::Display for SymbolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.library {
+            Some(library) => write!(f, "{} ({})", self.name, library),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+fn get_linked_string_table<'a>(
+    data: &'a [u8],
+    section_header: &SectionHeader,
+    section_headers: &[SectionHeader],
+) -> &'a [u8] {
+    match section_headers.get(section_header.link as usize) {
+        Some(v) => v.get_data(data),
+        None => &[],
+    }
+}
+
+// Parses .gnu.version_r. Entries are chained through vn_next and vna_next offsets
+// and sh_info holds the number of Verneed entries.
+pub fn get_version_needs(
+    data: &[u8],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+) -> Vec<VersionNeed> {
+    let mut needs: Vec<VersionNeed> = vec![];
+    let section = match section_headers
+        .iter()
+        .find(|s| s.section_type == SectionType::GNUVersionNeeds)
+    {
+        Some(v) => v,
+        None => return needs,
+    };
+    let table = section.get_data(data);
+    let strings = get_linked_string_table(data, section, section_headers);
+
+    let u16_from_bytes = get_num_from_bytes!(u16, elf_header.ident.ei_data);
+    let u32_from_bytes = get_num_from_bytes!(u32, elf_header.ident.ei_data);
+
+    let mut offset = 0;
+    for _ in 0..section.info {
+        let raw = match table.get(offset..offset + 16) {
+            Some(v) => v,
+            None => break,
+        };
+        let count = u16_from_bytes(raw[2..4].try_into().unwrap());
+        let mut need = VersionNeed {
+            version: u16_from_bytes(raw[0..2].try_into().unwrap()),
+            file: get_null_terminated_string_from_vec(
+                strings,
+                u32_from_bytes(raw[4..8].try_into().unwrap()) as usize,
+            ),
+            versions: vec![],
+        };
+        let next = u32_from_bytes(raw[12..16].try_into().unwrap()) as usize;
+
+        let mut aux_offset = offset + u32_from_bytes(raw[8..12].try_into().unwrap()) as usize;
+        for _ in 0..count {
+            let aux = match table.get(aux_offset..aux_offset + 16) {
+                Some(v) => v,
+                None => break,
+            };
+            need.versions.push(VersionNeedAux {
+                hash: u32_from_bytes(aux[0..4].try_into().unwrap()),
+                flags: VersionFlags::from_bits_truncate(u16_from_bytes(
+                    aux[4..6].try_into().unwrap(),
+                )),
+                index: u16_from_bytes(aux[6..8].try_into().unwrap()),
+                name: get_null_terminated_string_from_vec(
+                    strings,
+                    u32_from_bytes(aux[8..12].try_into().unwrap()) as usize,
+                ),
+            });
+            aux_offset += u32_from_bytes(aux[12..16].try_into().unwrap()) as usize;
+        }
+
+        needs.push(need);
+        if next == 0 {
+            break;
+        }
+        offset += next;
+    }
+    needs
+}
+
+// Parses .gnu.version_d. Like .gnu.version_r this is a chain of Verdef entries
+// each pointing at a chain of Verdaux entries.
+pub fn get_version_definitions(
+    data: &[u8],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+) -> Vec<VersionDefinition> {
+    let mut definitions: Vec<VersionDefinition> = vec![];
+    let section = match section_headers
+        .iter()
+        .find(|s| s.section_type == SectionType::GNUVersionDef)
+    {
+        Some(v) => v,
+        None => return definitions,
+    };
+    let table = section.get_data(data);
+    let strings = get_linked_string_table(data, section, section_headers);
+
+    let u16_from_bytes = get_num_from_bytes!(u16, elf_header.ident.ei_data);
+    let u32_from_bytes = get_num_from_bytes!(u32, elf_header.ident.ei_data);
+
+    let mut offset = 0;
+    for _ in 0..section.info {
+        let raw = match table.get(offset..offset + 20) {
+            Some(v) => v,
+            None => break,
+        };
+        let count = u16_from_bytes(raw[6..8].try_into().unwrap());
+        let next = u32_from_bytes(raw[16..20].try_into().unwrap()) as usize;
+
+        let mut names: Vec<String> = vec![];
+        let mut aux_offset = offset + u32_from_bytes(raw[12..16].try_into().unwrap()) as usize;
+        for _ in 0..count {
+            let aux = match table.get(aux_offset..aux_offset + 8) {
+                Some(v) => v,
+                None => break,
+            };
+            names.push(get_null_terminated_string_from_vec(
+                strings,
+                u32_from_bytes(aux[0..4].try_into().unwrap()) as usize,
+            ));
+            aux_offset += u32_from_bytes(aux[4..8].try_into().unwrap()) as usize;
+        }
+
+        definitions.push(VersionDefinition {
+            version: u16_from_bytes(raw[0..2].try_into().unwrap()),
+            flags: VersionFlags::from_bits_truncate(u16_from_bytes(raw[2..4].try_into().unwrap())),
+            index: u16_from_bytes(raw[4..6].try_into().unwrap()),
+            hash: u32_from_bytes(raw[8..12].try_into().unwrap()),
+            name: if names.is_empty() {
+                String::new()
+            } else {
+                names.remove(0)
+            },
+            parents: names,
+        });
+        if next == 0 {
+            break;
+        }
+        offset += next;
+    }
+    definitions
+}
+
+// Parses .gnu.version, which has one entry per .dynsym entry
+pub fn get_version_indexes(
+    data: &[u8],
+    section_headers: &[SectionHeader],
+    elf_header: &ELFHeader,
+) -> Vec<u16> {
+    let section = match section_headers
+        .iter()
+        .find(|s| s.section_type == SectionType::GNUVersionSymTbl)
+    {
+        Some(v) => v,
+        None => return vec![],
+    };
+    let u16_from_bytes = get_num_from_bytes!(u16, elf_header.ident.ei_data);
+    section
+        .get_data(data)
+        .chunks_exact(2)
+        .map(|v| u16_from_bytes(v.try_into().unwrap()))
+        .collect()
+}
+
+pub fn get_symbol_version(
+    version_index: u16,
+    needs: &[VersionNeed],
+    definitions: &[VersionDefinition],
+) -> Option<SymbolVersion> {
+    let index = version_index & VERSYM_VERSION;
+    if index == VER_NDX_LOCAL || index == VER_NDX_GLOBAL {
+        return None;
+    }
+    let hidden = version_index & VERSYM_HIDDEN != 0;
+    if let Some(definition) = definitions.iter().find(|d| d.index == index) {
+        return Some(SymbolVersion {
+            index,
+            name: definition.name.clone(),
+            hidden,
+            library: None,
+        });
+    }
+    for need in needs {
+        if let Some(aux) = need.versions.iter().find(|a| a.index == index) {
+            return Some(SymbolVersion {
+                index,
+                name: aux.name.clone(),
+                hidden,
+                library: Some(need.file.clone()),
+            });
+        }
+    }
+    None
+}
+
+// Attaches version information to the dynamic symbols, indexes into
+// .gnu.version line up with .dynsym
+pub fn apply_symbol_versions(
+    symbols: &mut [Symbol],
+    version_indexes: &[u16],
+    needs: &[VersionNeed],
+    definitions: &[VersionDefinition],
+) {
+    for (symbol, index) in symbols.iter_mut().zip(version_indexes) {
+        symbol.version = get_symbol_version(*index, needs, definitions);
+    }
+}
+
+#[cfg(test)]
+mod version_tests {
+    use super::*;
+
+    fn get_needs() -> Vec<VersionNeed> {
+        vec![VersionNeed {
+            version: 1,
+            file: "libc.so.6".to_string(),
+            versions: vec![
+                VersionNeedAux {
+                    hash: 0x0696_9194,
+                    flags: VersionFlags::empty(),
+                    index: 3,
+                    name: "GLIBC_2.14".to_string(),
+                },
+                VersionNeedAux {
+                    hash: 0x0969_1a75,
+                    flags: VersionFlags::empty(),
+                    index: 2,
+                    name: "GLIBC_2.2.5".to_string(),
+                },
+            ],
+        }]
+    }
+
+    fn get_definitions() -> Vec<VersionDefinition> {
+        vec![
+            VersionDefinition {
+                version: 1,
+                flags: VersionFlags::BASE,
+                index: 1,
+                hash: 0,
+                name: "libfoo.so.1".to_string(),
+                parents: vec![],
+            },
+            VersionDefinition {
+                version: 1,
+                flags: VersionFlags::empty(),
+                index: 4,
+                hash: 0,
+                name: "FOO_1.0".to_string(),
+                parents: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn can_get_required_symbol_version() {
+        let version = get_symbol_version(3, &get_needs(), &get_definitions()).unwrap();
+        assert_eq!(version.name, "GLIBC_2.14");
+        assert_eq!(version.library, Some("libc.so.6".to_string()));
+        assert!(!version.hidden);
+    }
+
+    #[test]
+    fn can_get_hidden_defined_symbol_version() {
+        let version = get_symbol_version(0x8004, &get_needs(), &get_definitions()).unwrap();
+        assert_eq!(version.name, "FOO_1.0");
+        assert_eq!(version.library, None);
+        assert!(version.hidden);
+        assert!(get_symbol_version(VER_NDX_GLOBAL, &get_needs(), &get_definitions()).is_none());
+    }
+}
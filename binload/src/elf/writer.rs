@@ -0,0 +1,1245 @@
+use super::dynamic::DynamicTag;
+use super::elf_header::{EI_Class, EI_Data};
+use super::program_header::ProgramHeaderType;
+use super::section::SectionType;
+use super::symbol::{Bind, SectionIndex, SymbolType, Visibility, SHN_LORESERVE, SHN_XINDEX};
+use super::utils::get_clamped_slice;
+use super::validate;
+use super::ELF;
+use crate::error::{Error, Result};
+use std::convert::TryInto;
+use std::path::Path;
+
+const SHT_NULL: u32 = 0;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHT_REL: u32 = 9;
+const SHT_SYMTAB_SHNDX: u32 = 18;
+const SHF_WRITE: u64 = 1 << 0;
+const SHF_ALLOC: u64 = 1 << 1;
+const SHF_EXECINSTR: u64 = 1 << 2;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const PAGE_SIZE: u64 = 0x1000;
+
+// Reads and writes numbers with the class and byte order of the file
+#[derive(Clone, Copy)]
+struct Encoding {
+    class: EI_Class,
+    data: EI_Data,
+}
+
+impl Encoding {
+    fn is_64(self) -> bool {
+        self.class == EI_Class::ELF64
+    }
+
+    fn put_u16(self, out: &mut [u8], offset: usize, value: u16) {
+        let bytes = match self.data {
+            EI_Data::LittleEndian => value.to_le_bytes(),
+            EI_Data::BigEndian => value.to_be_bytes(),
+        };
+        out[offset..offset + 2].copy_from_slice(&bytes);
+    }
+
+    fn put_u32(self, out: &mut [u8], offset: usize, value: u32) {
+        let bytes = match self.data {
+            EI_Data::LittleEndian => value.to_le_bytes(),
+            EI_Data::BigEndian => value.to_be_bytes(),
+        };
+        out[offset..offset + 4].copy_from_slice(&bytes);
+    }
+
+    fn put_u64(self, out: &mut [u8], offset: usize, value: u64) {
+        let bytes = match self.data {
+            EI_Data::LittleEndian => value.to_le_bytes(),
+            EI_Data::BigEndian => value.to_be_bytes(),
+        };
+        out[offset..offset + 8].copy_from_slice(&bytes);
+    }
+
+    // The class specific fields, 4 bytes for ELF32 and 8 for ELF64
+    fn put_word(self, out: &mut [u8], offset: usize, value: u64) {
+        if self.is_64() {
+            self.put_u64(out, offset, value)
+        } else {
+            self.put_u32(out, offset, value as u32)
+        }
+    }
+
+    fn get_u32(self, raw: &[u8], offset: usize) -> u32 {
+        get_num_from_bytes!(u32, self.data)(raw[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn get_word(self, raw: &[u8], offset: usize) -> u64 {
+        if self.is_64() {
+            get_num_from_bytes!(u64, self.data)(raw[offset..offset + 8].try_into().unwrap())
+        } else {
+            u64::from(self.get_u32(raw, offset))
+        }
+    }
+
+    fn get_section_header_size(self) -> usize {
+        if self.is_64() {
+            64
+        } else {
+            40
+        }
+    }
+
+    fn get_symbol_size(self) -> usize {
+        if self.is_64() {
+            24
+        } else {
+            16
+        }
+    }
+
+    fn get_dynamic_entry_size(self) -> usize {
+        if self.is_64() {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+// A section header with its raw field values, so types and flags we don't have
+// names for are written back unchanged
+#[derive(Clone)]
+struct WriterSection {
+    name: String,
+    section_type: u32,
+    flags: u64,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    addralign: u64,
+    entsize: u64,
+    data: Option<Vec<u8>>, // New contents, None keeps what is in the file
+    is_new: bool,
+}
+
+impl WriterSection {
+    fn parse(raw: &[u8], name: &str, encoding: Encoding) -> WriterSection {
+        // sh_flags, sh_addr, sh_offset and sh_size are words, the fields around
+        // them are always 4 bytes
+        let word = if encoding.is_64() { 8 } else { 4 };
+        let after_size = 8 + 4 * word;
+        WriterSection {
+            name: name.to_string(),
+            section_type: encoding.get_u32(raw, 4),
+            flags: encoding.get_word(raw, 8),
+            address: encoding.get_word(raw, 8 + word),
+            offset: encoding.get_word(raw, 8 + 2 * word),
+            size: encoding.get_word(raw, 8 + 3 * word),
+            link: encoding.get_u32(raw, after_size),
+            info: encoding.get_u32(raw, after_size + 4),
+            addralign: encoding.get_word(raw, after_size + 8),
+            entsize: encoding.get_word(raw, after_size + 8 + word),
+            data: None,
+            is_new: false,
+        }
+    }
+
+    fn encode(&self, name_offset: u32, encoding: Encoding) -> Vec<u8> {
+        let word = if encoding.is_64() { 8 } else { 4 };
+        let after_size = 8 + 4 * word;
+        let mut raw = vec![0u8; encoding.get_section_header_size()];
+        encoding.put_u32(&mut raw, 0, name_offset);
+        encoding.put_u32(&mut raw, 4, self.section_type);
+        encoding.put_word(&mut raw, 8, self.flags);
+        encoding.put_word(&mut raw, 8 + word, self.address);
+        encoding.put_word(&mut raw, 8 + 2 * word, self.offset);
+        encoding.put_word(&mut raw, 8 + 3 * word, self.size);
+        encoding.put_u32(&mut raw, after_size, self.link);
+        encoding.put_u32(&mut raw, after_size + 4, self.info);
+        encoding.put_word(&mut raw, after_size + 8, self.addralign);
+        encoding.put_word(&mut raw, after_size + 8 + word, self.entsize);
+        raw
+    }
+
+    fn is_alloc(&self) -> bool {
+        self.flags & SHF_ALLOC != 0
+    }
+
+    fn has_file_data(&self) -> bool {
+        self.section_type != SHT_NULL && self.section_type != SHT_NOBITS
+    }
+
+    fn get_data<'a>(&'a self, data: &'a [u8]) -> &'a [u8] {
+        match &self.data {
+            Some(v) => v,
+            None if self.has_file_data() => get_clamped_slice(data, self.offset, self.size),
+            None => &[],
+        }
+    }
+
+    fn contains_address(&self, address: u64) -> bool {
+        self.is_alloc()
+            && self.section_type != SHT_NULL
+            && address >= self.address
+            && address - self.address < self.size
+    }
+}
+
+struct NewSymbol {
+    name: String,
+    address: u64,
+    size: u64,
+    symbol_type: SymbolType,
+    bind: Bind,
+}
+
+// Changes to make to a loaded ELF when writing it back out. Loaded sections stay
+// where they are, since the program headers map them, and everything else is laid
+// out again after them with fresh string tables and a new section header table.
+pub struct ELFWriter<'a> {
+    elf: &'a ELF,
+    encoding: Encoding,
+    entry_point: u64,
+    sections: Vec<WriterSection>,
+    section_name_index: usize, // 0 when there is no .shstrtab
+    symbols: Vec<NewSymbol>,
+    dynamic: Option<Vec<(u64, u64)>>, // d_tag and d_val, Some once edited
+}
+
+impl<'a> ELFWriter<'a> {
+    pub fn new(elf: &'a ELF) -> ELFWriter<'a> {
+        let header = &elf.elf_header;
+        let encoding = Encoding {
+            class: header.ident.ei_class,
+            data: header.ident.ei_data,
+        };
+        // Synthetic headers were made up from the dynamic table, they aren't in the
+        // file so the loader dropped whatever table there was
+        let mut sections: Vec<WriterSection> = vec![];
+        for (i, section) in elf.section_headers.iter().enumerate() {
+            if section.synthetic {
+                break;
+            }
+            let offset = header.e_shoff + i as u64 * u64::from(header.e_shentsize);
+            let raw =
+                get_clamped_slice(&elf.data, offset, encoding.get_section_header_size() as u64);
+            sections.push(WriterSection::parse(raw, &section.name_string, encoding));
+        }
+        let section_name_index = match validate::get_section_name_index(&elf.data, header) as usize
+        {
+            i if i < sections.len() && sections[i].section_type == SHT_STRTAB => i,
+            _ => 0,
+        };
+
+        ELFWriter {
+            elf,
+            encoding,
+            entry_point: header.e_entry,
+            sections,
+            section_name_index,
+            symbols: vec![],
+            dynamic: None,
+        }
+    }
+
+    pub fn set_entry_point(&mut self, address: u64) {
+        self.entry_point = address;
+    }
+
+    pub fn get_section_index(&self, name: &str) -> Option<usize> {
+        self.sections.iter().position(|s| s.name == name)
+    }
+
+    // Sections that are loaded can't grow, they are packed in between the others
+    // in their segment. Anything else can be any size.
+    pub fn set_section_data(&mut self, name: &str, data: Vec<u8>) -> Result<()> {
+        let index = match self.get_section_index(name) {
+            Some(v) => v,
+            None => return Err(Error::not_found(format!("{} section", name))),
+        };
+        let section = &mut self.sections[index];
+        if !section.has_file_data() {
+            return Err(Error::unsupported(
+                section.offset,
+                format!("section {} has no data in the file", name),
+            ));
+        }
+        if section.is_alloc() && data.len() as u64 > section.size {
+            return Err(Error::unsupported(
+                section.offset,
+                format!(
+                    "section {} is loaded and can't grow from {:#x} to {:#x} bytes",
+                    name,
+                    section.size,
+                    data.len()
+                ),
+            ));
+        }
+        section.data = Some(data);
+        Ok(())
+    }
+
+    // Adds a section and returns its index. flags is the raw sh_flags value, so OS
+    // and processor specific bits can be set. A section with the Alloc flag gets a
+    // PT_LOAD segment of its own, which needs an unused (PT_NULL) program header.
+    pub fn add_section(
+        &mut self,
+        name: &str,
+        section_type: SectionType,
+        flags: u64,
+        address: u64,
+        addralign: u64,
+        data: Vec<u8>,
+    ) -> Result<usize> {
+        let raw_type = match section_type.to_u32() {
+            Some(v) if v != SHT_NULL => v,
+            _ => {
+                return Err(Error::unsupported(
+                    0,
+                    format!("adding a section of type {}", section_type),
+                ))
+            }
+        };
+        if flags & SHF_ALLOC != 0 && address == 0 {
+            return Err(Error::unsupported(
+                0,
+                format!("section {} is loaded but has no address", name),
+            ));
+        }
+        self.push_section(WriterSection {
+            name: name.to_string(),
+            section_type: raw_type,
+            flags,
+            address,
+            offset: 0,
+            size: data.len() as u64,
+            link: 0,
+            info: 0,
+            addralign,
+            entsize: 0,
+            data: Some(data),
+            is_new: true,
+        });
+        Ok(self.sections.len() - 1)
+    }
+
+    fn push_section(&mut self, section: WriterSection) {
+        // Index 0 is always the null section
+        if self.sections.is_empty() {
+            self.sections.push(WriterSection {
+                name: String::new(),
+                section_type: SHT_NULL,
+                flags: 0,
+                address: 0,
+                offset: 0,
+                size: 0,
+                link: 0,
+                info: 0,
+                addralign: 0,
+                entsize: 0,
+                data: None,
+                is_new: true,
+            });
+        }
+        self.sections.push(section);
+    }
+
+    // Adds a symbol to .symtab, creating it if the file has been stripped. The
+    // section is the loaded section that contains the address.
+    pub fn add_symbol(
+        &mut self,
+        name: &str,
+        address: u64,
+        size: u64,
+        symbol_type: SymbolType,
+        bind: Bind,
+    ) {
+        self.symbols.push(NewSymbol {
+            name: name.to_string(),
+            address,
+            size,
+            symbol_type,
+            bind,
+        });
+    }
+
+    fn get_dynamic_entries(&mut self) -> Result<&mut Vec<(u64, u64)>> {
+        if self.elf.dynamic.is_empty() {
+            return Err(Error::not_found("dynamic table"));
+        }
+        let elf = self.elf;
+        Ok(self.dynamic.get_or_insert_with(|| {
+            elf.dynamic
+                .iter()
+                .take_while(|e| e.tag != DynamicTag::Null)
+                .map(|e| (e.raw_tag, e.value))
+                .collect()
+        }))
+    }
+
+    fn get_raw_tag(tag: DynamicTag) -> Result<u64> {
+        match tag.to_u64() {
+            Some(v) if tag != DynamicTag::Null => Ok(v),
+            _ => Err(Error::unsupported(
+                0,
+                format!("editing dynamic entries with tag {}", tag),
+            )),
+        }
+    }
+
+    // Changes the value of the first entry with the tag, or adds one if there isn't
+    // one. Added entries use the spare DT_NULL entries at the end of the table.
+    pub fn set_dynamic_entry(&mut self, tag: DynamicTag, value: u64) -> Result<()> {
+        let raw_tag = ELFWriter::get_raw_tag(tag)?;
+        let entries = self.get_dynamic_entries()?;
+        match entries.iter_mut().find(|(t, _)| *t == raw_tag) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((raw_tag, value)),
+        }
+        Ok(())
+    }
+
+    // Always adds an entry, for the tags that can appear more than once like DT_NEEDED
+    pub fn add_dynamic_entry(&mut self, tag: DynamicTag, value: u64) -> Result<()> {
+        let raw_tag = ELFWriter::get_raw_tag(tag)?;
+        self.get_dynamic_entries()?.push((raw_tag, value));
+        Ok(())
+    }
+
+    pub fn remove_dynamic_entries(&mut self, tag: DynamicTag) -> Result<()> {
+        let raw_tag = ELFWriter::get_raw_tag(tag)?;
+        self.get_dynamic_entries()?.retain(|(t, _)| *t != raw_tag);
+        Ok(())
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.write()?)?;
+        Ok(())
+    }
+
+    pub fn write(&self) -> Result<Vec<u8>> {
+        let data: &[u8] = &self.elf.data;
+        let header = &self.elf.elf_header;
+        let encoding = self.encoding;
+        let mut sections = self.sections.clone();
+        let mut section_name_index = self.section_name_index;
+
+        if !self.symbols.is_empty() {
+            self.add_symbols_to_table(&mut sections)?;
+        }
+        if !sections.is_empty() && section_name_index == 0 {
+            section_name_index = sections.len();
+            sections.push(WriterSection {
+                name: String::from(".shstrtab"),
+                section_type: SHT_STRTAB,
+                flags: 0,
+                address: 0,
+                offset: 0,
+                size: 0,
+                link: 0,
+                info: 0,
+                addralign: 1,
+                entsize: 0,
+                data: None,
+                is_new: true,
+            });
+        }
+        // Counts that don't fit in the ELF header go in section 0, see
+        // validate::get_first_section_header
+        let section_count = sections.len();
+        let extended_count = section_count >= usize::from(SHN_LORESERVE);
+        let extended_name_index = section_name_index >= usize::from(SHN_LORESERVE);
+        match sections.first_mut() {
+            Some(first) if first.section_type == SHT_NULL => {
+                first.size = if extended_count {
+                    section_count as u64
+                } else {
+                    0
+                };
+                first.link = if extended_name_index {
+                    section_name_index as u32
+                } else {
+                    0
+                };
+            }
+            _ if extended_count => {
+                return Err(Error::unsupported(
+                    0,
+                    format!(
+                        "writing {} sections without a null section 0",
+                        section_count
+                    ),
+                ));
+            }
+            _ => {}
+        }
+        // Section names are rebuilt from scratch
+        let mut name_offsets: Vec<u32> = vec![];
+        if !sections.is_empty() {
+            let mut names: Vec<u8> = vec![0];
+            for section in &sections {
+                if section.name.is_empty() {
+                    name_offsets.push(0);
+                } else {
+                    name_offsets.push(names.len() as u32);
+                    names.extend_from_slice(section.name.as_bytes());
+                    names.push(0);
+                }
+            }
+            sections[section_name_index].data = Some(names);
+        }
+
+        // Everything up to the end of the last thing that is loaded is kept where
+        // it is
+        let header_size: u64 = if encoding.is_64() { 64 } else { 52 };
+        let program_header_table_end = header
+            .e_phoff
+            .saturating_add(u64::from(header.e_phnum) * u64::from(header.e_phentsize));
+        let fixed_end = self
+            .elf
+            .program_headers
+            .iter()
+            .map(|p| p.offset.saturating_add(p.file_size))
+            .chain(
+                self.sections
+                    .iter()
+                    .filter(|s| s.is_alloc() && s.has_file_data() && !s.is_new)
+                    .map(|s| s.offset.saturating_add(s.size)),
+            )
+            .chain(vec![
+                u64::from(header.e_ehsize),
+                header_size,
+                program_header_table_end,
+            ])
+            .max()
+            .unwrap_or(0);
+        let mut out: Vec<u8> =
+            data[..std::cmp::min(fixed_end, data.len() as u64) as usize].to_vec();
+        // The fields patched at the end are in the ELF header
+        if (out.len() as u64) < header_size {
+            return Err(Error::truncated(0, header_size, "ELF header"));
+        }
+
+        for section in sections.iter_mut() {
+            if !section.is_alloc() || section.is_new || !section.has_file_data() {
+                continue;
+            }
+            if let Some(contents) = &section.data {
+                let start = section.offset as usize;
+                let end = start + contents.len();
+                if end > out.len() {
+                    return Err(Error::truncated(
+                        section.offset,
+                        contents.len() as u64,
+                        format!("section {}", section.name),
+                    ));
+                }
+                out[start..end].copy_from_slice(contents);
+                section.size = contents.len() as u64;
+            }
+        }
+
+        self.write_dynamic_table(&mut out)?;
+        self.add_loaded_sections(&mut out, &mut sections)?;
+
+        for section in sections.iter_mut() {
+            if section.is_alloc() || section.section_type == SHT_NULL {
+                continue;
+            }
+            if !section.has_file_data() {
+                section.offset = out.len() as u64;
+                continue;
+            }
+            let contents = section.get_data(data).to_vec();
+            // sh_addralign comes from the file, only pad for alignments a real
+            // section could have
+            let align = match section.addralign {
+                0 | 1 => 1,
+                v if v.is_power_of_two() && v <= PAGE_SIZE => v,
+                v => {
+                    return Err(Error::malformed(
+                        section.offset,
+                        format!("section {} has an alignment of {:#x}", section.name, v),
+                    ))
+                }
+            };
+            out.resize(align_up(out.len() as u64, align) as usize, 0);
+            section.offset = out.len() as u64;
+            section.size = contents.len() as u64;
+            out.extend_from_slice(&contents);
+        }
+
+        let section_header_offset = if sections.is_empty() {
+            0
+        } else {
+            let align = if encoding.is_64() { 8 } else { 4 };
+            out.resize(align_up(out.len() as u64, align) as usize, 0);
+            let offset = out.len() as u64;
+            for (section, name_offset) in sections.iter().zip(name_offsets) {
+                out.extend_from_slice(&section.encode(name_offset, encoding));
+            }
+            offset
+        };
+
+        // e_entry is at 0x18 in both classes, the fields after it move with the
+        // word size
+        let word = if encoding.is_64() { 8 } else { 4 };
+        let shoff_position = 0x18 + 2 * word;
+        let shentsize_position = shoff_position + word + 10;
+        encoding.put_word(&mut out, 0x18, self.entry_point);
+        encoding.put_word(&mut out, shoff_position, section_header_offset);
+        let section_header_size = if section_count == 0 {
+            0
+        } else {
+            encoding.get_section_header_size() as u16
+        };
+        encoding.put_u16(&mut out, shentsize_position, section_header_size);
+        let header_section_count = if extended_count {
+            0
+        } else {
+            section_count as u16
+        };
+        let header_name_index = if extended_name_index {
+            SHN_XINDEX
+        } else {
+            section_name_index as u16
+        };
+        encoding.put_u16(&mut out, shentsize_position + 2, header_section_count);
+        encoding.put_u16(&mut out, shentsize_position + 4, header_name_index);
+        Ok(out)
+    }
+
+    // Symbols are added to the end of the locals or the end of the globals, the
+    // names go on the end of the existing string table
+    fn add_symbols_to_table(&self, sections: &mut Vec<WriterSection>) -> Result<()> {
+        let encoding = self.encoding;
+        let entry_size = encoding.get_symbol_size();
+        let symbol_table_index = match sections.iter().position(|s| s.section_type == SHT_SYMTAB) {
+            Some(v) => v,
+            None => {
+                if sections.is_empty() {
+                    sections.push(WriterSection {
+                        name: String::new(),
+                        section_type: SHT_NULL,
+                        flags: 0,
+                        address: 0,
+                        offset: 0,
+                        size: 0,
+                        link: 0,
+                        info: 0,
+                        addralign: 0,
+                        entsize: 0,
+                        data: None,
+                        is_new: true,
+                    });
+                }
+                sections.push(WriterSection {
+                    name: String::from(".strtab"),
+                    section_type: SHT_STRTAB,
+                    flags: 0,
+                    address: 0,
+                    offset: 0,
+                    size: 0,
+                    link: 0,
+                    info: 0,
+                    addralign: 1,
+                    entsize: 0,
+                    data: Some(vec![0]),
+                    is_new: true,
+                });
+                // The first entry is the null symbol
+                sections.push(WriterSection {
+                    name: String::from(".symtab"),
+                    section_type: SHT_SYMTAB,
+                    flags: 0,
+                    address: 0,
+                    offset: 0,
+                    size: 0,
+                    link: (sections.len() - 1) as u32,
+                    info: 1,
+                    addralign: if encoding.is_64() { 8 } else { 4 },
+                    entsize: entry_size as u64,
+                    data: Some(vec![0u8; entry_size]),
+                    is_new: true,
+                });
+                sections.len() - 1
+            }
+        };
+        let string_table_index = sections[symbol_table_index].link as usize;
+        if string_table_index >= sections.len()
+            || sections[string_table_index].section_type != SHT_STRTAB
+        {
+            return Err(Error::malformed(
+                sections[symbol_table_index].offset,
+                "symbol table isn't linked to a string table",
+            ));
+        }
+
+        let mut strings = sections[string_table_index]
+            .get_data(&self.elf.data)
+            .to_vec();
+        let mut locals: Vec<u8> = vec![];
+        let mut globals: Vec<u8> = vec![];
+        // Words for the SHT_SYMTAB_SHNDX section, the section index of the symbols
+        // that have SHN_XINDEX
+        let mut local_indexes: Vec<u8> = vec![];
+        let mut global_indexes: Vec<u8> = vec![];
+        for symbol in &self.symbols {
+            let section_index = match symbol.symbol_type {
+                SymbolType::File => SectionIndex::Absolute,
+                _ => match sections
+                    .iter()
+                    .position(|s| s.contains_address(symbol.address))
+                {
+                    Some(v) => SectionIndex::Index(v as u32),
+                    None => SectionIndex::Absolute,
+                },
+            };
+            let mut extended_index = vec![0u8; 4];
+            if section_index.to_u16() == SHN_XINDEX {
+                encoding.put_u32(
+                    &mut extended_index,
+                    0,
+                    section_index.get_index().unwrap() as u32,
+                );
+            }
+            let name_offset = strings.len() as u32;
+            strings.extend_from_slice(symbol.name.as_bytes());
+            strings.push(0);
+
+            let mut raw = vec![0u8; entry_size];
+            let info = (symbol.bind.to_u8() << 4) | (symbol.symbol_type.to_u8() & 0xf);
+            let other = Visibility::Default.to_u8();
+            encoding.put_u32(&mut raw, 0, name_offset);
+            if encoding.is_64() {
+                raw[4] = info;
+                raw[5] = other;
+                encoding.put_u16(&mut raw, 6, section_index.to_u16());
+                encoding.put_u64(&mut raw, 8, symbol.address);
+                encoding.put_u64(&mut raw, 16, symbol.size);
+            } else {
+                encoding.put_u32(&mut raw, 4, symbol.address as u32);
+                encoding.put_u32(&mut raw, 8, symbol.size as u32);
+                raw[12] = info;
+                raw[13] = other;
+                encoding.put_u16(&mut raw, 14, section_index.to_u16());
+            }
+            match symbol.bind {
+                Bind::Local => {
+                    locals.extend_from_slice(&raw);
+                    local_indexes.extend_from_slice(&extended_index);
+                }
+                _ => {
+                    globals.extend_from_slice(&raw);
+                    global_indexes.extend_from_slice(&extended_index);
+                }
+            }
+        }
+
+        // Locals come before globals and sh_info is the index of the first global, so
+        // new locals move the globals along. Relocations refer to symbols by index.
+        if !locals.is_empty()
+            && sections.iter().any(|s| {
+                (s.section_type == SHT_REL || s.section_type == SHT_RELA)
+                    && s.link as usize == symbol_table_index
+            })
+        {
+            return Err(Error::unsupported(
+                sections[symbol_table_index].offset,
+                "adding local symbols to a symbol table that relocations refer to",
+            ));
+        }
+        let table = sections[symbol_table_index].get_data(&self.elf.data);
+        let first_global = std::cmp::min(
+            sections[symbol_table_index].info as usize * entry_size,
+            table.len() / entry_size * entry_size,
+        );
+        let mut new_table: Vec<u8> = table[..first_global].to_vec();
+        new_table.extend_from_slice(&locals);
+        new_table.extend_from_slice(&table[first_global..table.len() / entry_size * entry_size]);
+        new_table.extend_from_slice(&globals);
+
+        // The SHT_SYMTAB_SHNDX section has a word for every symbol, so the new symbols
+        // go in the same places. One is added when a new symbol needs it.
+        let symbol_count = table.len() / entry_size;
+        let extended_table_index = sections.iter().position(|s| {
+            s.section_type == SHT_SYMTAB_SHNDX && s.link as usize == symbol_table_index
+        });
+        let needs_extended_table = local_indexes
+            .iter()
+            .chain(global_indexes.iter())
+            .any(|v| *v != 0);
+        if extended_table_index.is_some() || needs_extended_table {
+            let mut indexes = match extended_table_index {
+                Some(v) => sections[v].get_data(&self.elf.data).to_vec(),
+                None => vec![],
+            };
+            indexes.resize(symbol_count * 4, 0);
+            let split = first_global / entry_size * 4;
+            let mut new_indexes: Vec<u8> = indexes[..split].to_vec();
+            new_indexes.extend_from_slice(&local_indexes);
+            new_indexes.extend_from_slice(&indexes[split..]);
+            new_indexes.extend_from_slice(&global_indexes);
+            match extended_table_index {
+                Some(v) => sections[v].data = Some(new_indexes),
+                None => sections.push(WriterSection {
+                    name: String::from(".symtab_shndx"),
+                    section_type: SHT_SYMTAB_SHNDX,
+                    flags: 0,
+                    address: 0,
+                    offset: 0,
+                    size: 0,
+                    link: symbol_table_index as u32,
+                    info: 0,
+                    addralign: 4,
+                    entsize: 4,
+                    data: Some(new_indexes),
+                    is_new: true,
+                }),
+            }
+        }
+
+        let symbol_table = &mut sections[symbol_table_index];
+        symbol_table.info = ((first_global + locals.len()) / entry_size) as u32;
+        symbol_table.entsize = entry_size as u64;
+        symbol_table.data = Some(new_table);
+        sections[string_table_index].data = Some(strings);
+        Ok(())
+    }
+
+    // The dynamic table is loaded, so it is rewritten in place and can't grow
+    fn write_dynamic_table(&self, out: &mut [u8]) -> Result<()> {
+        let entries = match &self.dynamic {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        let (offset, size) = match self
+            .elf
+            .program_headers
+            .iter()
+            .find(|p| p.header_type == ProgramHeaderType::Dynamic)
+        {
+            Some(p) => (p.offset, p.file_size),
+            None => match self.sections.iter().find(|s| s.name == ".dynamic") {
+                Some(s) => (s.offset, s.size),
+                None => return Err(Error::not_found("dynamic table")),
+            },
+        };
+        let entry_size = self.encoding.get_dynamic_entry_size();
+        let capacity = (size / entry_size as u64) as usize;
+        // There has to be room for the DT_NULL at the end
+        if entries.len() >= capacity {
+            return Err(Error::unsupported(
+                offset,
+                format!(
+                    "{} dynamic entries, the table only has room for {}",
+                    entries.len(),
+                    capacity.saturating_sub(1)
+                ),
+            ));
+        }
+        let start = offset as usize;
+        let end = start + capacity * entry_size;
+        if end > out.len() {
+            return Err(Error::truncated(offset, size, "dynamic table"));
+        }
+        let table = &mut out[start..end];
+        table.iter_mut().for_each(|b| *b = 0);
+        let word = entry_size / 2;
+        for (i, (tag, value)) in entries.iter().enumerate() {
+            self.encoding.put_word(table, i * entry_size, *tag);
+            self.encoding.put_word(table, i * entry_size + word, *value);
+        }
+        Ok(())
+    }
+
+    // New loaded sections go at the end of the file, at an offset that matches their
+    // address modulo the page size, with a PT_LOAD segment replacing a PT_NULL one
+    fn add_loaded_sections(&self, out: &mut Vec<u8>, sections: &mut [WriterSection]) -> Result<()> {
+        let header = &self.elf.elf_header;
+        let mut unused_program_headers = self
+            .elf
+            .program_headers
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.header_type == ProgramHeaderType::Null)
+            .map(|(i, _)| i);
+        for section in sections.iter_mut().filter(|s| s.is_new && s.is_alloc()) {
+            let index = match unused_program_headers.next() {
+                Some(v) => v,
+                None => {
+                    return Err(Error::unsupported(
+                        header.e_phoff,
+                        format!(
+                            "adding loaded section {}, there is no unused program header for it",
+                            section.name
+                        ),
+                    ))
+                }
+            };
+            let end = section.address.checked_add(section.size);
+            let overlaps = self.elf.program_headers.iter().any(|p| {
+                p.header_type == ProgramHeaderType::Load
+                    && match end {
+                        Some(end) => {
+                            section.address < p.virtual_address.saturating_add(p.memory_size)
+                                && p.virtual_address < end
+                        }
+                        None => true,
+                    }
+            });
+            if overlaps {
+                return Err(Error::unsupported(
+                    0,
+                    format!("section {} overlaps a loaded segment", section.name),
+                ));
+            }
+
+            let file_size = if section.has_file_data() {
+                section.size
+            } else {
+                0
+            };
+            let offset = align_up(out.len() as u64, PAGE_SIZE) + section.address % PAGE_SIZE;
+            out.resize(offset as usize, 0);
+            if let Some(contents) = &section.data {
+                if section.has_file_data() {
+                    out.extend_from_slice(contents);
+                }
+            }
+            section.offset = offset;
+
+            let mut flags = PF_R;
+            if section.flags & SHF_WRITE != 0 {
+                flags |= PF_W;
+            }
+            if section.flags & SHF_EXECINSTR != 0 {
+                flags |= PF_X;
+            }
+            let encoding = self.encoding;
+            let position = (header.e_phoff + index as u64 * u64::from(header.e_phentsize)) as usize;
+            let entry = &mut out[position..];
+            encoding.put_u32(entry, 0, PT_LOAD);
+            if encoding.is_64() {
+                encoding.put_u32(entry, 4, flags);
+                encoding.put_u64(entry, 8, offset);
+                encoding.put_u64(entry, 16, section.address);
+                encoding.put_u64(entry, 24, section.address);
+                encoding.put_u64(entry, 32, file_size);
+                encoding.put_u64(entry, 40, section.size);
+                encoding.put_u64(entry, 48, PAGE_SIZE);
+            } else {
+                encoding.put_u32(entry, 4, offset as u32);
+                encoding.put_u32(entry, 8, section.address as u32);
+                encoding.put_u32(entry, 12, section.address as u32);
+                encoding.put_u32(entry, 16, file_size as u32);
+                encoding.put_u32(entry, 20, section.size as u32);
+                encoding.put_u32(entry, 24, flags);
+                encoding.put_u32(entry, 28, PAGE_SIZE as u32);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn align_up(value: u64, align: u64) -> u64 {
+    match value % align {
+        0 => value,
+        remainder => value + (align - remainder),
+    }
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use super::super::section::SectionFlags;
+    use super::*;
+    use crate::elf::load_elf_from_vec;
+
+    // sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link, sh_info,
+    // sh_addralign, sh_entsize
+    fn push_section_header(raw: &mut Vec<u8>, fields: [u64; 10]) {
+        for (i, field) in fields.iter().enumerate() {
+            match i {
+                0 | 1 | 6 | 7 => raw.extend_from_slice(&(*field as u32).to_le_bytes()),
+                _ => raw.extend_from_slice(&field.to_le_bytes()),
+            }
+        }
+    }
+
+    const SHF_GNU_RETAIN: u64 = 1 << 21;
+
+    // A position independent x86_64 executable with .text, .dynamic needing
+    // libc.so.6, a .comment and a .symtab with main
+    fn load_test_binary() -> ELF {
+        let mut raw: Vec<u8> = vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x03, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_DYN, x86_64, version 1
+            0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0xf8, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x03, 0x00, 0x40, 0x00, 0x08, 0x00, 0x07, 0x00, // e_phnum to e_shstrndx
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let mut push_segment =
+            |header_type: u32, flags: u32, offset: u64, address: u64, size: u64| {
+                push_u32(&mut raw, header_type);
+                push_u32(&mut raw, flags);
+                push_u64(&mut raw, offset);
+                push_u64(&mut raw, address);
+                push_u64(&mut raw, address);
+                push_u64(&mut raw, size);
+                push_u64(&mut raw, size);
+                push_u64(&mut raw, if header_type == 1 { 0x1000 } else { 8 });
+            };
+        // Two PT_LOADs and PT_DYNAMIC
+        push_segment(1, 5, 0, 0, 0x100);
+        push_segment(1, 6, 0x100, 0x1100, 0x70);
+        push_segment(2, 6, 0x100, 0x1100, 0x70);
+
+        // .dynstr and .text
+        raw.extend_from_slice(b"\0libc.so.6\0");
+        raw.resize(0xf8, 0);
+        raw.extend_from_slice(&[0xf3, 0x0f, 0x1e, 0xfa, 0xc3]);
+        raw.resize(0x100, 0xcc);
+        // .dynamic: DT_NEEDED, DT_STRTAB, DT_STRSZ, DT_FLAGS with DF_BIND_NOW,
+        // DT_DEBUG and two DT_NULLs
+        for (tag, value) in [
+            (1, 1),
+            (5, 0xe8),
+            (10, 11),
+            (30, 8),
+            (21, 0),
+            (0, 0),
+            (0, 0),
+        ] {
+            push_u64(&mut raw, tag);
+            push_u64(&mut raw, value);
+        }
+        raw.extend_from_slice(b"GCC: (GNU) 13\0");
+        // .symtab and .strtab
+        raw.resize(0x198, 0);
+        raw.extend_from_slice(&[1, 0, 0, 0, 0x12, 0, 2, 0]);
+        push_u64(&mut raw, 0xf8);
+        push_u64(&mut raw, 5);
+        raw.extend_from_slice(b"\0main\0");
+        raw.extend_from_slice(
+            b"\0.dynstr\0.text\0.dynamic\0.comment\0.symtab\0.strtab\0.shstrtab\0",
+        );
+
+        raw.resize(0x1f8, 0);
+        push_section_header(&mut raw, [0; 10]);
+        push_section_header(&mut raw, [1, 3, 2, 0xe8, 0xe8, 11, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [9, 1, 6, 0xf8, 0xf8, 8, 0, 0, 8, 0]);
+        push_section_header(&mut raw, [15, 6, 3, 0x1100, 0x100, 0x70, 1, 0, 8, 16]);
+        push_section_header(&mut raw, [24, 1, 0x30, 0, 0x170, 14, 0, 0, 1, 1]);
+        push_section_header(&mut raw, [33, 2, 0, 0, 0x180, 0x30, 6, 1, 8, 24]);
+        push_section_header(&mut raw, [41, 3, 0, 0, 0x1b0, 6, 0, 0, 1, 0]);
+        push_section_header(&mut raw, [49, 3, 0, 0, 0x1b6, 0x3b, 0, 0, 1, 0]);
+        load_elf_from_vec(raw).unwrap()
+    }
+
+    #[test]
+    fn can_write_unmodified_elf() {
+        let elf = load_test_binary();
+        let written = load_elf_from_vec(ELFWriter::new(&elf).write().unwrap()).unwrap();
+
+        assert!(written.diagnostics.is_empty());
+        assert_eq!(written.section_headers.len(), 8);
+        for (before, after) in elf.section_headers.iter().zip(&written.section_headers) {
+            assert_eq!(before.name_string, after.name_string);
+            assert_eq!(before.address, after.address);
+            assert_eq!(before.get_data(&elf.data), after.get_data(&written.data));
+        }
+        assert_eq!(written.program_headers.len(), 3);
+        for (before, after) in elf.program_headers.iter().zip(&written.program_headers) {
+            assert_eq!(before.header_type, after.header_type);
+            assert_eq!(before.virtual_address, after.virtual_address);
+            assert_eq!(before.memory_size, after.memory_size);
+        }
+        assert_eq!(written.symbol_table.len(), 2);
+        let dynamic: Vec<(DynamicTag, u64)> =
+            written.dynamic.iter().map(|e| (e.tag, e.value)).collect();
+        assert_eq!(
+            dynamic,
+            vec![
+                (DynamicTag::Needed, 1),
+                (DynamicTag::StrTab, 0xe8),
+                (DynamicTag::StrSize, 11),
+                (DynamicTag::Flags, 8),
+                (DynamicTag::Debug, 0),
+                (DynamicTag::Null, 0),
+            ]
+        );
+        assert_eq!(
+            written.dynamic[0].string_value.as_deref(),
+            Some("libc.so.6")
+        );
+    }
+
+    #[test]
+    fn can_write_modified_elf() {
+        let elf = load_test_binary();
+        let text = &elf.section_headers[2];
+
+        let mut writer = ELFWriter::new(&elf);
+        writer.set_entry_point(text.address + 4);
+        writer
+            .add_section(
+                ".note.added",
+                SectionType::Note,
+                SHF_GNU_RETAIN,
+                0,
+                4,
+                vec![1, 2, 3, 4],
+            )
+            .unwrap();
+        writer
+            .set_section_data(".comment", b"a much longer comment than before\0".repeat(8))
+            .unwrap();
+        writer.add_symbol(
+            "added_function",
+            text.address + 4,
+            4,
+            SymbolType::Function,
+            Bind::Local,
+        );
+        writer.add_symbol(
+            "added_global",
+            text.address + 6,
+            4,
+            SymbolType::Function,
+            Bind::Global,
+        );
+        assert!(writer
+            .set_section_data(".text", vec![0; text.size as usize + 1])
+            .is_err());
+        let written = load_elf_from_vec(writer.write().unwrap()).unwrap();
+
+        assert!(written.diagnostics.is_empty());
+        assert_eq!(written.elf_header.e_entry, 0xfc);
+        assert_eq!(written.section_headers.len(), 9);
+        let added = written.section_headers.last().unwrap();
+        assert_eq!(added.name_string, ".note.added");
+        assert_eq!(added.get_data(&written.data), [1, 2, 3, 4]);
+        // An OS specific flag we don't have a name for is kept
+        assert!(added.flags == vec![SectionFlags::MaskOS]);
+        let comment = written
+            .section_headers
+            .iter()
+            .find(|s| s.name_string == ".comment")
+            .unwrap();
+        assert_eq!(comment.size, 34 * 8);
+
+        let written_text = written
+            .section_headers
+            .iter()
+            .find(|s| s.name_string == ".text")
+            .unwrap();
+        assert_eq!(
+            written_text.get_data(&written.data),
+            text.get_data(&elf.data)
+        );
+
+        assert_eq!(written.symbol_table.len(), 4);
+        let symbol = written
+            .symbol_table
+            .iter()
+            .find(|s| s.name_string == "added_function")
+            .unwrap();
+        assert_eq!(symbol.address, 0xfc);
+        assert_eq!(symbol.section_index.get_index(), Some(2));
+        assert!(written
+            .symbol_table
+            .iter()
+            .any(|s| s.name_string == "added_global"));
+    }
+
+    #[test]
+    fn can_reject_malformed_input() {
+        let elf = load_test_binary();
+        for addralign in &[3, PAGE_SIZE * 2] {
+            let mut writer = ELFWriter::new(&elf);
+            writer
+                .add_section(".added", SectionType::ProgBits, 0, 0, *addralign, vec![0])
+                .unwrap();
+            assert!(matches!(writer.write(), Err(Error::Malformed { .. })));
+        }
+
+        // An x86_64 executable with only the ELF header, cut short after loading so
+        // there is no header to patch
+        let mut truncated = load_elf_from_vec(vec![
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, // 64 bit, little endian
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // padding
+            0x02, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, // ET_EXEC, x86_64, version 1
+            0x00, 0x10, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_entry
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phoff
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // e_shoff
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00, // e_flags to e_phentsize
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, // e_phnum to e_shstrndx
+        ])
+        .unwrap();
+        assert!(ELFWriter::new(&truncated).write().is_ok());
+        truncated.data.truncate(0x10);
+        assert!(matches!(
+            ELFWriter::new(&truncated).write(),
+            Err(Error::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn can_write_extended_section_numbering() {
+        let elf = load_test_binary();
+        let mut writer = ELFWriter::new(&elf);
+        let count = usize::from(SHN_LORESERVE) + 0x10;
+        for i in elf.section_headers.len()..count {
+            writer
+                .add_section(&format!(".s{}", i), SectionType::ProgBits, 0, 0, 1, vec![])
+                .unwrap();
+        }
+        let written = load_elf_from_vec(writer.write().unwrap()).unwrap();
+
+        // The count doesn't fit in e_shnum so it is in section 0
+        assert_eq!(written.elf_header.e_shnum, 0);
+        assert_eq!(written.section_headers[0].size, count as u64);
+        assert!(written.diagnostics.is_empty());
+        assert_eq!(written.section_headers.len(), count);
+        assert_eq!(
+            written.section_headers[count - 1].name_string,
+            format!(".s{}", count - 1)
+        );
+
+        // Written again once loaded
+        let rewritten = ELFWriter::new(&written).write().unwrap();
+        assert_eq!(
+            load_elf_from_vec(rewritten).unwrap().section_headers.len(),
+            count
+        );
+    }
+
+    #[test]
+    fn can_edit_dynamic_entries() {
+        let elf = load_test_binary();
+        let mut writer = ELFWriter::new(&elf);
+        writer.set_dynamic_entry(DynamicTag::Debug, 0x1234).unwrap();
+        writer.remove_dynamic_entries(DynamicTag::Flags).unwrap();
+        assert!(writer.set_dynamic_entry(DynamicTag::Null, 0).is_err());
+        let written = load_elf_from_vec(writer.write().unwrap()).unwrap();
+
+        let debug: Vec<u64> = written
+            .dynamic
+            .iter()
+            .filter(|e| e.tag == DynamicTag::Debug)
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(debug, vec![0x1234]);
+        assert!(!written.dynamic.iter().any(|e| e.tag == DynamicTag::Flags));
+        assert_eq!(written.dynamic.last().unwrap().tag, DynamicTag::Null);
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::io;
+
+// Errors from loading a binary. Offsets are from the start of the file, except for
+// data that lives inside a section (e.g. DWARF) where they are from the start of
+// that section.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    // The magic bytes don't match the format we were asked to load
+    BadMagic {
+        format: &'static str,
+        magic: Vec<u8>,
+    },
+    // The data ended before a structure we need was fully read
+    Truncated {
+        offset: u64,
+        size: u64,
+        context: String,
+    },
+    // A field has a value we can't interpret
+    InvalidValue {
+        offset: u64,
+        value: u64,
+        context: String,
+    },
+    // The data is inconsistent in some other way
+    Malformed {
+        offset: u64,
+        context: String,
+    },
+    // Valid, but something we don't handle yet
+    Unsupported {
+        offset: u64,
+        context: String,
+    },
+    // Something optional, like a debug section, isn't in the file
+    NotFound {
+        context: String,
+    },
+}
+
+impl Error {
+    pub fn truncated<S: Into<String>>(offset: u64, size: u64, context: S) -> Error {
+        Error::Truncated {
+            offset,
+            size,
+            context: context.into(),
+        }
+    }
+
+    pub fn invalid_value<S: Into<String>>(offset: u64, value: u64, context: S) -> Error {
+        Error::InvalidValue {
+            offset,
+            value,
+            context: context.into(),
+        }
+    }
+
+    pub fn malformed<S: Into<String>>(offset: u64, context: S) -> Error {
+        Error::Malformed {
+            offset,
+            context: context.into(),
+        }
+    }
+
+    pub fn unsupported<S: Into<String>>(offset: u64, context: S) -> Error {
+        Error::Unsupported {
+            offset,
+            context: context.into(),
+        }
+    }
+
+    pub fn not_found<S: Into<String>>(context: S) -> Error {
+        Error::NotFound {
+            context: context.into(),
+        }
+    }
+
+    // The offset the error happened at, if there is one
+    pub fn get_offset(&self) -> Option<u64> {
+        match self {
+            Error::Io(_) | Error::NotFound { .. } => None,
+            Error::BadMagic { .. } => Some(0),
+            Error::Truncated { offset, .. }
+            | Error::InvalidValue { offset, .. }
+            | Error::Malformed { offset, .. }
+            | Error::Unsupported { offset, .. } => Some(*offset),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::BadMagic { format, magic } => {
+                write!(f, "bad {} magic bytes {:02x?}", format, magic)
+            }
+            Error::Truncated {
+                offset,
+                size,
+                context,
+            } => write!(
+                f,
+                "truncated at offset {:#x} while reading {} ({} bytes)",
+                offset, context, size
+            ),
+            Error::InvalidValue {
+                offset,
+                value,
+                context,
+            } => write!(
+                f,
+                "invalid value {:#x} for {} at offset {:#x}",
+                value, context, offset
+            ),
+            Error::Malformed { offset, context } => {
+                write!(f, "malformed data at offset {:#x}: {}", offset, context)
+            }
+            Error::Unsupported { offset, context } => {
+                write!(f, "unsupported at offset {:#x}: {}", offset, context)
+            }
+            Error::NotFound { context } => write!(f, "no {} in the file", context),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// A problem that was worked around while loading, e.g. a section running past
+// the end of the file that was truncated. Loading carries on with best effort data.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub offset: Option<u64>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(offset: u64, message: S) -> Diagnostic {
+        Diagnostic {
+            offset: Some(offset),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// For parts of the file that failed to parse but weren't needed to carry on
+impl From<Error> for Diagnostic {
+    fn from(e: Error) -> Diagnostic {
+        Diagnostic {
+            offset: e.get_offset(),
+            message: e.to_string(),
+        }
+    }
+}
+
+// Gets `size` bytes at `offset`, or a Truncated error naming what was being read
+pub fn get_bytes<F: FnOnce() -> String>(
+    data: &[u8],
+    offset: u64,
+    size: u64,
+    context: F,
+) -> Result<&[u8]> {
+    let end = offset.checked_add(size);
+    match end.and_then(|end| data.get(offset as usize..end as usize)) {
+        Some(v) => Ok(v),
+        None => Err(Error::truncated(offset, size, context())),
+    }
+}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    #[test]
+    fn can_report_truncated_reads() {
+        let data = [0u8; 8];
+        assert!(get_bytes(&data, 4, 4, || String::from("test")).is_ok());
+        let error = get_bytes(&data, 4, 8, || String::from("section header 3")).unwrap_err();
+        assert_eq!(error.get_offset(), Some(4));
+        assert_eq!(
+            error.to_string(),
+            "truncated at offset 0x4 while reading section header 3 (8 bytes)"
+        );
+        assert!(get_bytes(&data, u64::MAX, 2, String::new).is_err());
+    }
+}
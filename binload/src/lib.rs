@@ -1,15 +1,95 @@
-mod binary;
+pub mod archive;
+pub mod binary;
+pub mod dwarf;
 pub mod elf;
+pub mod error;
 pub mod macho;
+pub mod pe;
+
+pub use error::{Diagnostic, Error, Result};
 
 use std::fs::File;
-use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum FileFormat {
+    ELF,
+    MachO,
+    FatMachO,
+    PE,
+    Archive,
+    Raw, // anything that isn't recognised
+}
 
-pub fn load_from_file(path: &str) -> elf::ELF {
-    let f = File::open(path).unwrap();
-    let mut reader = BufReader::new(f);
+// Works out the format from the magic bytes at the start of the file
+pub fn detect_format(data: &[u8]) -> FileFormat {
+    if elf::is_elf(data) {
+        FileFormat::ELF
+    } else if macho::is_macho(data) {
+        FileFormat::MachO
+    } else if macho::fat::is_fat_binary(data) {
+        FileFormat::FatMachO
+    } else if pe::is_pe(data) {
+        FileFormat::PE
+    } else if archive::is_archive(data) {
+        FileFormat::Archive
+    } else {
+        FileFormat::Raw
+    }
+}
 
-    elf::load_elf_from_buffer(&mut reader).expect("failed to load ELF from file")
+// Only one of these is made per file, so the ELF variant isn't boxed
+#[allow(clippy::large_enum_variant)]
+pub enum LoadedFile {
+    ELF(elf::ELF),
+    MachO(macho::MACHO),
+    FatMachO(macho::FatMACHO),
+    PE(pe::PE),
+    Archive(archive::Archive),
+    Raw(Vec<u8>),
+}
+
+impl LoadedFile {
+    pub fn get_format(&self) -> FileFormat {
+        match self {
+            LoadedFile::ELF(_) => FileFormat::ELF,
+            LoadedFile::MachO(_) => FileFormat::MachO,
+            LoadedFile::FatMachO(_) => FileFormat::FatMachO,
+            LoadedFile::PE(_) => FileFormat::PE,
+            LoadedFile::Archive(_) => FileFormat::Archive,
+            LoadedFile::Raw(_) => FileFormat::Raw,
+        }
+    }
+
+    // The format independent model, for the formats that have a converter. Fat
+    // binaries give the first architecture, archives have no single binary.
+    pub fn to_binary(&self) -> Option<binary::Binary> {
+        match self {
+            LoadedFile::ELF(elf) => Some(binary::Binary::from_elf(elf)),
+            LoadedFile::MachO(macho) => Some(binary::Binary::from_macho(macho)),
+            LoadedFile::FatMachO(fat) => fat.binaries.first().map(binary::Binary::from_macho),
+            LoadedFile::PE(_) | LoadedFile::Archive(_) | LoadedFile::Raw(_) => None,
+        }
+    }
+}
+
+pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<LoadedFile> {
+    let mut data: Vec<u8> = vec![];
+    File::open(path)?.read_to_end(&mut data)?;
+
+    load_from_vec(data)
+}
+
+pub fn load_from_vec(data: Vec<u8>) -> Result<LoadedFile> {
+    Ok(match detect_format(&data) {
+        FileFormat::ELF => LoadedFile::ELF(elf::load_elf_from_vec(data)?),
+        FileFormat::MachO => LoadedFile::MachO(macho::load_macho_from_slice(&data)?),
+        FileFormat::FatMachO => LoadedFile::FatMachO(macho::load_fat_macho_from_slice(&data)?),
+        FileFormat::PE => LoadedFile::PE(pe::load_pe_from_vec(data)?),
+        FileFormat::Archive => LoadedFile::Archive(archive::load_archive_from_vec(data)?),
+        FileFormat::Raw => LoadedFile::Raw(data),
+    })
 }
 
 #[allow(unused_imports)]
@@ -26,4 +106,51 @@ mod tests {
     fn can_parse_elf_32() {
         // load_from_file("/home/mdelaney/infosec/reverse/crackmes/IOLI/bin-linux/crackme0x00");
     }
+
+    #[test]
+    fn can_detect_format() {
+        assert_eq!(
+            detect_format(&[0x7f, 0x45, 0x4c, 0x46, 0x02]),
+            FileFormat::ELF
+        );
+        assert_eq!(detect_format(&[0xcf, 0xfa, 0xed, 0xfe]), FileFormat::MachO);
+        assert_eq!(
+            detect_format(&[0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x02]),
+            FileFormat::FatMachO
+        );
+        // a Java class file, version 52
+        assert_eq!(
+            detect_format(&[0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x34]),
+            FileFormat::Raw
+        );
+        assert_eq!(detect_format(&[0x4d, 0x5a, 0x90, 0x00]), FileFormat::Raw);
+        assert_eq!(detect_format(b"!<arch>\n"), FileFormat::Archive);
+        assert_eq!(detect_format(&[]), FileFormat::Raw);
+    }
+
+    #[test]
+    fn can_load_from_file() {
+        // An x86_64 executable with only the ELF header
+        let raw = [
+            0x7f, 0x45, 0x4c, 0x46, 0x02, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x02, 0x00, 0x3e, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x10, 0x40, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x38, 0x00,
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let path = std::env::temp_dir().join(format!("binload-load-{}", std::process::id()));
+        std::fs::write(&path, raw).unwrap();
+        match load_from_file(&path).unwrap() {
+            LoadedFile::ELF(elf) => {
+                assert_eq!(elf.elf_header.e_entry, 0x40_1000);
+                assert!(elf.program_headers.is_empty());
+                assert_eq!(elf.data, raw);
+            }
+            other => panic!("expected an ELF file, got {:?}", other.get_format()),
+        }
+        std::fs::write(&path, b"not a binary").unwrap();
+        assert_eq!(load_from_file(&path).unwrap().get_format(), FileFormat::Raw);
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_from_file("/nonexistent/file").is_err());
+    }
 }
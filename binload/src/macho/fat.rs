@@ -4,6 +4,7 @@ extern crate enum_primitive;
 use std::convert::TryInto;
 use std::fmt;
 use enum_primitive::FromPrimitive;
+use crate::error::{get_bytes, Error, Result};
 use crate::macho::header::{CpuType, Endian};
 
 const MAGIC_BIG_ENDIAN: [u8; 4] = [0xca, 0xfe, 0xba, 0xbe];
@@ -19,27 +20,34 @@ pub struct FatArchitecture {
 }
 
 impl FatArchitecture {
-    pub fn parse_from_buffer(binary: &[u8]) -> Result<Vec<FatArchitecture>, &'static str> {
+    pub fn parse_from_buffer(binary: &[u8]) -> Result<Vec<FatArchitecture>> {
         let mut result: Vec<FatArchitecture> = Vec::new();
 
         // First we verify that this is in fact a fat file
-        let mut magic = [0; 4];
-        magic.copy_from_slice(&binary[0..4]);
-        let endian = match magic {
-            MAGIC_LITTLE_ENDIAN => Endian::LittleEndian,
-            MAGIC_BIG_ENDIAN => Endian::BigEndian,
-            _ => return Err("This is not a fat file!"),
+        let endian = match binary.get(0..4) {
+            Some(v) if v == MAGIC_LITTLE_ENDIAN => Endian::LittleEndian,
+            Some(v) if v == MAGIC_BIG_ENDIAN => Endian::BigEndian,
+            _ => return Err(Error::BadMagic {
+                format: "fat Mach-O",
+                magic: binary.iter().take(4).cloned().collect(),
+            }),
         };
 
         let u32_from_bytes = get_num_from_bytes!(u32, endian);
         // now we see how many architectures are present here and parse each one.
         const ARCH_STRUCT_SIZE: usize = 5 * 4; // 5 fields, each 4 bytes
-        let count: usize = u32_from_bytes(binary[4..8].try_into().unwrap()) as usize;
+        let raw_count = get_bytes(binary, 4, 4, || String::from("fat architecture count"))?;
+        let count: usize = u32_from_bytes(raw_count.try_into().unwrap()) as usize;
         for i in 0..count {
             let offset: usize = 8 + i * ARCH_STRUCT_SIZE;
-            let raw: &[u8] = &binary[offset..offset + ARCH_STRUCT_SIZE];
+            let raw: &[u8] = get_bytes(binary, offset as u64, ARCH_STRUCT_SIZE as u64, || {
+                format!("fat architecture {}", i)
+            })?;
+            let cpu_type = u32_from_bytes(raw[0..4].try_into().unwrap());
             result.push(FatArchitecture {
-                cpu_type: CpuType::from_u32(u32_from_bytes(raw[0..4].try_into().unwrap())).unwrap(),
+                cpu_type: CpuType::from_u32(cpu_type).ok_or_else(|| {
+                    Error::invalid_value(offset as u64, u64::from(cpu_type), "fat architecture CPU type")
+                })?,
                 cpu_subtype: u32_from_bytes(raw[4..8].try_into().unwrap()),
                 offset: u32_from_bytes(raw[8..12].try_into().unwrap()),
                 size: u32_from_bytes(raw[12..16].try_into().unwrap()),
@@ -49,8 +57,10 @@ impl FatArchitecture {
         Ok(result)
     }
 
-    pub fn get_binary<'a>(&self, binary: &'a [u8]) -> &'a [u8] {
-        &binary[self.offset as usize..(self.offset + self.size) as usize]
+    pub fn get_binary<'a>(&self, binary: &'a [u8]) -> Result<&'a [u8]> {
+        get_bytes(binary, u64::from(self.offset), u64::from(self.size), || {
+            format!("{:?} architecture", self.cpu_type)
+        })
     }
 
     fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -148,13 +158,25 @@ mod macho_fat_tests {
                 align: 0x0000000e,
             },
         ];
-        assert_eq!(&raw[64..96], arch[0].get_binary(&raw));
-        assert_eq!(&raw[96..128], arch[1].get_binary(&raw));
+        assert_eq!(&raw[64..96], arch[0].get_binary(&raw).unwrap());
+        assert_eq!(&raw[96..128], arch[1].get_binary(&raw).unwrap());
     }
 }
 
+// Java class files share the 0xcafebabe magic, where the next field is the class
+// file version rather than the number of architectures. Versions start at 45, so a
+// small count is taken to mean a fat binary.
+const MAX_ARCHITECTURES: u32 = 20;
+
 pub fn is_fat_binary(binary: &[u8]) -> bool {
-    let mut magic = [0; 4];
-    magic.copy_from_slice(&binary[0..4]);
-    magic == MAGIC_BIG_ENDIAN || magic == MAGIC_LITTLE_ENDIAN
+    let endian = match binary.get(0..4) {
+        Some(v) if v == MAGIC_LITTLE_ENDIAN => Endian::LittleEndian,
+        Some(v) if v == MAGIC_BIG_ENDIAN => Endian::BigEndian,
+        _ => return false,
+    };
+    let u32_from_bytes = get_num_from_bytes!(u32, endian);
+    match binary.get(4..8) {
+        Some(raw_count) => u32_from_bytes(raw_count.try_into().unwrap()) < MAX_ARCHITECTURES,
+        None => false,
+    }
 }
\ No newline at end of file
@@ -9,7 +9,7 @@ use enum_primitive::enum_from_primitive_impl_ty;
 use enum_primitive::FromPrimitive;
 use std::convert::TryInto;
 use std::fmt;
-use std::result::Result::Err;
+use crate::error::{get_bytes, Error, Result};
 
 // This makes it easy to get the function to convert from bytes to a number type
 // with a specified endianness
@@ -342,38 +342,43 @@ pub struct Header {
 }
 
 impl Header {
-    pub fn parse_from_buffer(binary: &[u8]) -> Result<Header, &'static str> {
+    pub fn parse_from_buffer(binary: &[u8]) -> Result<Header> {
         const SIZE: usize = 32;
-        let raw_header: &[u8] = &binary[0..SIZE];
 
         let mut magic = [0; 4];
-        magic.copy_from_slice(&raw_header[0..4]);
-        let (endian, arch_size) = match magic {
-            [0xfe, 0xed, 0xfa, 0xce] => (Endian::BigEndian, ArchSize::_32),
-            [0xce, 0xfa, 0xed, 0xfe] => (Endian::LittleEndian, ArchSize::_32),
-            [0xfe, 0xed, 0xfa, 0xcf] => (Endian::BigEndian, ArchSize::_64),
-            [0xcf, 0xfa, 0xed, 0xfe] => (Endian::LittleEndian, ArchSize::_64),
-            _ => panic!("Invalid magic bytes for Mach-O file!"),
+        let (endian, arch_size) = match binary.get(0..4) {
+            Some([0xfe, 0xed, 0xfa, 0xce]) => (Endian::BigEndian, ArchSize::_32),
+            Some([0xce, 0xfa, 0xed, 0xfe]) => (Endian::LittleEndian, ArchSize::_32),
+            Some([0xfe, 0xed, 0xfa, 0xcf]) => (Endian::BigEndian, ArchSize::_64),
+            Some([0xcf, 0xfa, 0xed, 0xfe]) => (Endian::LittleEndian, ArchSize::_64),
+            _ => return Err(Error::BadMagic {
+                format: "Mach-O",
+                magic: binary.iter().take(4).cloned().collect(),
+            }),
         };
+        let raw_header: &[u8] = get_bytes(binary, 0, SIZE as u64, || String::from("Mach-O header"))?;
+        magic.copy_from_slice(&raw_header[0..4]);
 
         let u32_from_bytes = get_num_from_bytes!(u32, endian);
+        let cpu_type = u32_from_bytes(raw_header[4..8].try_into().unwrap());
+        let file_type = u32_from_bytes(raw_header[12..16].try_into().unwrap());
 
         let result: Header = Header {
             magic,
             endian,
             arch_size,
-            cpu_type: CpuType::from_u32(u32_from_bytes(raw_header[4..8].try_into().unwrap()))
-                .unwrap(),
+            cpu_type: CpuType::from_u32(cpu_type)
+                .ok_or_else(|| Error::invalid_value(4, u64::from(cpu_type), "Mach-O CPU type"))?,
             // TODO: eventually should probably do something with cpu_subtype
             //       all the enums have been created but needs to be generic...
             //       not important yet, but may be useful eventually
             cpu_subtype: u32_from_bytes(raw_header[8..12].try_into().unwrap()),
-            file_type: FileType::from_u32(u32_from_bytes(raw_header[12..16].try_into().unwrap()))
-                .unwrap(),
+            file_type: FileType::from_u32(file_type)
+                .ok_or_else(|| Error::invalid_value(12, u64::from(file_type), "Mach-O file type"))?,
             number_of_commands: u32_from_bytes(raw_header[16..20].try_into().unwrap()),
             size_of_commands: u32_from_bytes(raw_header[20..24].try_into().unwrap()),
-            flags: Flags::from_bits(u32_from_bytes(raw_header[24..28].try_into().unwrap()))
-                .unwrap(),
+            // Flags newer than the ones we know about are dropped rather than rejected
+            flags: Flags::from_bits_truncate(u32_from_bytes(raw_header[24..28].try_into().unwrap())),
             reserved: 0, //u32_from_bytes(raw_header[28..32].try_into().unwrap()),
         };
         Ok(result)
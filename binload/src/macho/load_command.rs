@@ -0,0 +1,470 @@
+#![macro_use]
+extern crate bitflags;
+
+use bitflags::bitflags;
+use std::convert::TryInto;
+use std::fmt;
+use crate::error::{get_bytes, Error, Result};
+use crate::macho::header::{ArchSize, CpuType, Endian, Header};
+
+// Only the load commands that describe the memory layout, the symbols and the entry
+// point are parsed, everything else is skipped over using its size
+const LC_SEGMENT: u32 = 0x01;
+const LC_SYMTAB: u32 = 0x02;
+const LC_UNIXTHREAD: u32 = 0x05;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_MAIN: u32 = 0x80000028;
+
+// Thread state flavors for LC_UNIXTHREAD
+const X86_THREAD_STATE32: u32 = 1;
+const X86_THREAD_STATE64: u32 = 4;
+const ARM_THREAD_STATE64: u32 = 6;
+
+bitflags! {
+    pub struct VmProtection: u32 {
+        const READ = 0x01;
+        const WRITE = 0x02;
+        const EXECUTE = 0x04;
+    }
+}
+
+// The low byte of the section flags is the section type, the rest are attributes
+pub const SECTION_TYPE_MASK: u32 = 0x000000ff;
+pub const S_ZEROFILL: u32 = 0x01;
+pub const S_GB_ZEROFILL: u32 = 0x0c;
+pub const S_THREAD_LOCAL_ZEROFILL: u32 = 0x12;
+pub const S_ATTR_PURE_INSTRUCTIONS: u32 = 0x80000000;
+pub const S_ATTR_SOME_INSTRUCTIONS: u32 = 0x00000400;
+
+#[derive(Eq, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub segment_name: String,
+    pub address: u64,
+    pub size: u64,
+    pub offset: u32,
+    pub align: u32,
+    pub flags: u32,
+}
+
+impl Section {
+    // Zero fill sections take up memory but have nothing in the file
+    pub fn is_zero_fill(&self) -> bool {
+        matches!(
+            self.flags & SECTION_TYPE_MASK,
+            S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL
+        )
+    }
+
+    pub fn has_instructions(&self) -> bool {
+        self.flags & (S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS) != 0
+    }
+
+    pub fn get_data<'a>(&self, binary: &'a [u8]) -> &'a [u8] {
+        if self.is_zero_fill() {
+            return &[];
+        }
+        crate::elf::utils::get_clamped_slice(binary, u64::from(self.offset), self.size)
+    }
+
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}{},{}", "Name:", self.segment_name, self.name),
+            format!("{:15}{:#x}", "Address:", self.address),
+            format!("{:15}{:#x}", "Size:", self.size),
+            format!("{:15}{:#x}", "Offset:", self.offset),
+            format!("{:15}{}", "Align:", self.align),
+            format!("{:15}{:#x}", "Flags:", self.flags),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+impl fmt::Debug for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+#[derive(Eq, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    pub vm_address: u64,
+    pub vm_size: u64,
+    pub file_offset: u64,
+    pub file_size: u64,
+    pub max_protection: VmProtection,
+    pub initial_protection: VmProtection,
+    pub flags: u32,
+    pub sections: Vec<Section>,
+}
+
+impl Segment {
+    fn formatter(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings = [
+            format!("{:15}{}", "Name:", self.name),
+            format!("{:15}{:#x}", "VM Address:", self.vm_address),
+            format!("{:15}{:#x}", "VM Size:", self.vm_size),
+            format!("{:15}{:#x}", "File Offset:", self.file_offset),
+            format!("{:15}{:#x}", "File Size:", self.file_size),
+            format!("{:15}{:?}", "Max Prot:", self.max_protection),
+            format!("{:15}{:?}", "Init Prot:", self.initial_protection),
+            format!("{:15}{:#x}", "Flags:", self.flags),
+            format!("{:15}{}", "Sections:", self.sections.len()),
+        ];
+        writeln!(f, "{}", strings.join("\n"))
+    }
+}
+impl fmt::Debug for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.formatter(f)
+    }
+}
+
+// n_type masks from nlist.h
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_EXT: u8 = 0x01;
+const N_UNDF: u8 = 0x00;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub n_type: u8,
+    pub section_index: u8, // 1 based, 0 is NO_SECT
+    pub description: u16,
+    pub value: u64,
+}
+
+impl Symbol {
+    pub fn is_debug(&self) -> bool {
+        self.n_type & N_STAB != 0
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.n_type & N_EXT != 0
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        !self.is_debug() && self.n_type & N_TYPE == N_UNDF
+    }
+}
+
+// Everything taken from the load commands
+pub struct LoadCommands {
+    pub segments: Vec<Segment>,
+    pub symbols: Vec<Symbol>,
+    pub entry_point: Option<u64>,
+}
+
+// The null terminated name in a fixed size field
+fn get_fixed_string(raw: &[u8]) -> String {
+    let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+    String::from_utf8_lossy(&raw[..end]).into_owned()
+}
+
+fn get_null_terminated_string(data: &[u8], offset: u64) -> String {
+    match data.get(offset as usize..) {
+        Some(raw) => get_fixed_string(raw),
+        None => String::new(),
+    }
+}
+
+pub fn parse_load_commands(binary: &[u8], header: &Header) -> Result<LoadCommands> {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.endian);
+    let u64_from_bytes = get_num_from_bytes!(u64, header.endian);
+    let is_64 = header.arch_size == ArchSize::_64;
+    // The address sized fields of the 32 bit structures
+    let address_from_bytes = |raw: &[u8]| -> u64 {
+        if is_64 {
+            u64_from_bytes(raw[0..8].try_into().unwrap())
+        } else {
+            u64::from(u32_from_bytes(raw[0..4].try_into().unwrap()))
+        }
+    };
+    let address_size: usize = if is_64 { 8 } else { 4 };
+
+    let mut result = LoadCommands {
+        segments: vec![],
+        symbols: vec![],
+        entry_point: None,
+    };
+    let mut entry_offset: Option<u64> = None;
+    let mut offset: u64 = if is_64 { 32 } else { 28 };
+    for i in 0..header.number_of_commands {
+        let raw = get_bytes(binary, offset, 8, || format!("load command {}", i))?;
+        let command = u32_from_bytes(raw[0..4].try_into().unwrap());
+        let command_size = u32_from_bytes(raw[4..8].try_into().unwrap());
+        if command_size < 8 {
+            return Err(Error::malformed(
+                offset,
+                format!("load command {} has a size of {}", i, command_size),
+            ));
+        }
+        let raw = get_bytes(binary, offset, u64::from(command_size), || {
+            format!("load command {}", i)
+        })?;
+
+        match command {
+            LC_SEGMENT | LC_SEGMENT_64 => {
+                // segname is followed by vmaddr, vmsize, fileoff and filesize
+                let header_size = 24 + 4 * address_size + 16;
+                let section_size = 32 + 2 * address_size + 28;
+                if raw.len() < header_size {
+                    return Err(Error::truncated(offset, header_size as u64, "segment command"));
+                }
+                let mut position = 24;
+                let mut fields = [0u64; 4];
+                for field in fields.iter_mut() {
+                    *field = address_from_bytes(&raw[position..]);
+                    position += address_size;
+                }
+                let number_of_sections = u32_from_bytes(raw[position + 8..position + 12].try_into().unwrap());
+                let mut segment = Segment {
+                    name: get_fixed_string(&raw[8..24]),
+                    vm_address: fields[0],
+                    vm_size: fields[1],
+                    file_offset: fields[2],
+                    file_size: fields[3],
+                    max_protection: VmProtection::from_bits_truncate(
+                        u32_from_bytes(raw[position..position + 4].try_into().unwrap()),
+                    ),
+                    initial_protection: VmProtection::from_bits_truncate(
+                        u32_from_bytes(raw[position + 4..position + 8].try_into().unwrap()),
+                    ),
+                    flags: u32_from_bytes(raw[position + 12..position + 16].try_into().unwrap()),
+                    sections: vec![],
+                };
+                for j in 0..number_of_sections as usize {
+                    let start = header_size + j * section_size;
+                    let raw_section = match raw.get(start..start + section_size) {
+                        Some(v) => v,
+                        None => {
+                            return Err(Error::truncated(
+                                offset + start as u64,
+                                section_size as u64,
+                                format!("section {} of segment {}", j, segment.name),
+                            ))
+                        }
+                    };
+                    let position = 32 + 2 * address_size;
+                    segment.sections.push(Section {
+                        name: get_fixed_string(&raw_section[0..16]),
+                        segment_name: get_fixed_string(&raw_section[16..32]),
+                        address: address_from_bytes(&raw_section[32..]),
+                        size: address_from_bytes(&raw_section[32 + address_size..]),
+                        offset: u32_from_bytes(raw_section[position..position + 4].try_into().unwrap()),
+                        align: u32_from_bytes(raw_section[position + 4..position + 8].try_into().unwrap()),
+                        flags: u32_from_bytes(raw_section[position + 16..position + 20].try_into().unwrap()),
+                    });
+                }
+                result.segments.push(segment);
+            }
+            LC_SYMTAB => {
+                if raw.len() < 24 {
+                    return Err(Error::truncated(offset, 24, "symtab command"));
+                }
+                let symbol_offset = u32_from_bytes(raw[8..12].try_into().unwrap());
+                let number_of_symbols = u32_from_bytes(raw[12..16].try_into().unwrap());
+                let string_offset = u32_from_bytes(raw[16..20].try_into().unwrap());
+                let string_size = u32_from_bytes(raw[20..24].try_into().unwrap());
+                let strings = crate::elf::utils::get_clamped_slice(
+                    binary,
+                    u64::from(string_offset),
+                    u64::from(string_size),
+                );
+                let entry_size = 8 + address_size as u64;
+                let raw_symbols = get_bytes(
+                    binary,
+                    u64::from(symbol_offset),
+                    u64::from(number_of_symbols) * entry_size,
+                    || String::from("symbol table"),
+                )?;
+                for raw_symbol in raw_symbols.chunks_exact(entry_size as usize) {
+                    let name_offset = u32_from_bytes(raw_symbol[0..4].try_into().unwrap());
+                    result.symbols.push(Symbol {
+                        name: get_null_terminated_string(strings, u64::from(name_offset)),
+                        n_type: raw_symbol[4],
+                        section_index: raw_symbol[5],
+                        description: get_num_from_bytes!(u16, header.endian)(raw_symbol[6..8].try_into().unwrap()),
+                        value: address_from_bytes(&raw_symbol[8..]),
+                    });
+                }
+            }
+            LC_MAIN => {
+                if raw.len() < 16 {
+                    return Err(Error::truncated(offset, 16, "main command"));
+                }
+                // This is a file offset, it is turned into an address once the
+                // segments have all been seen
+                entry_offset = Some(u64_from_bytes(raw[8..16].try_into().unwrap()));
+            }
+            LC_UNIXTHREAD => {
+                result.entry_point = get_thread_entry_point(raw, header);
+            }
+            _ => {}
+        }
+        offset += u64::from(command_size);
+    }
+
+    if let Some(entry_offset) = entry_offset {
+        result.entry_point = result
+            .segments
+            .iter()
+            .find(|s| {
+                s.file_size != 0
+                    && entry_offset >= s.file_offset
+                    && entry_offset - s.file_offset < s.file_size
+            })
+            .map(|s| s.vm_address.wrapping_add(entry_offset - s.file_offset));
+    }
+    Ok(result)
+}
+
+// Older binaries start with the registers set by LC_UNIXTHREAD, the entry point is
+// the instruction pointer
+fn get_thread_entry_point(raw: &[u8], header: &Header) -> Option<u64> {
+    let u32_from_bytes = get_num_from_bytes!(u32, header.endian);
+    let u64_from_bytes = get_num_from_bytes!(u64, header.endian);
+    let flavor = u32_from_bytes(raw.get(8..12)?.try_into().unwrap());
+    let state = raw.get(16..)?;
+    match (&header.cpu_type, flavor) {
+        (CpuType::X86, X86_THREAD_STATE32) => {
+            // eip follows eax, ebx, ecx, edx, edi, esi, ebp, esp, ss and eflags
+            Some(u64::from(u32_from_bytes(state.get(40..44)?.try_into().unwrap())))
+        }
+        (CpuType::X86_64, X86_THREAD_STATE64) => {
+            // rip follows the 16 general purpose registers
+            Some(u64_from_bytes(state.get(128..136)?.try_into().unwrap()))
+        }
+        (CpuType::ARM64, ARM_THREAD_STATE64) => {
+            // pc follows x0 to x28, fp, lr and sp
+            Some(u64_from_bytes(state.get(256..264)?.try_into().unwrap()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod macho_load_command_tests {
+    use super::*;
+
+    // A 64 bit x86_64 executable with __PAGEZERO, __TEXT with a __text section,
+    // LC_MAIN and a symbol table with one defined and one undefined symbol
+    pub fn get_test_binary() -> Vec<u8> {
+        let mut raw: Vec<u8> = vec![
+            0xcf, 0xfa, 0xed, 0xfe, // magic bytes
+            0x07, 0x00, 0x00, 0x01, // cpu_type
+            0x03, 0x00, 0x00, 0x00, // cpu_subtype
+            0x02, 0x00, 0x00, 0x00, // file type
+            0x04, 0x00, 0x00, 0x00, // number of commands
+            0x00, 0x00, 0x00, 0x00, // size of commands
+            0x85, 0x00, 0x20, 0x00, // flags
+            0x00, 0x00, 0x00, 0x00, // reserved
+        ];
+        let push_u32 = |raw: &mut Vec<u8>, v: u32| raw.extend_from_slice(&v.to_le_bytes());
+        let push_u64 = |raw: &mut Vec<u8>, v: u64| raw.extend_from_slice(&v.to_le_bytes());
+        let push_name = |raw: &mut Vec<u8>, name: &str| {
+            let mut field = [0u8; 16];
+            field[..name.len()].copy_from_slice(name.as_bytes());
+            raw.extend_from_slice(&field);
+        };
+
+        // __PAGEZERO
+        push_u32(&mut raw, LC_SEGMENT_64);
+        push_u32(&mut raw, 72);
+        push_name(&mut raw, "__PAGEZERO");
+        push_u64(&mut raw, 0);
+        push_u64(&mut raw, 0x100000000);
+        push_u64(&mut raw, 0);
+        push_u64(&mut raw, 0);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+
+        // __TEXT, covering the whole file
+        push_u32(&mut raw, LC_SEGMENT_64);
+        push_u32(&mut raw, 72 + 80);
+        push_name(&mut raw, "__TEXT");
+        push_u64(&mut raw, 0x100000000);
+        push_u64(&mut raw, 0x1000);
+        push_u64(&mut raw, 0);
+        push_u64(&mut raw, 0x200);
+        push_u32(&mut raw, 5);
+        push_u32(&mut raw, 5);
+        push_u32(&mut raw, 1);
+        push_u32(&mut raw, 0);
+        push_name(&mut raw, "__text");
+        push_name(&mut raw, "__TEXT");
+        push_u64(&mut raw, 0x100000180);
+        push_u64(&mut raw, 0x10);
+        push_u32(&mut raw, 0x180);
+        push_u32(&mut raw, 4);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+        push_u32(&mut raw, 0);
+
+        // LC_MAIN
+        push_u32(&mut raw, LC_MAIN);
+        push_u32(&mut raw, 24);
+        push_u64(&mut raw, 0x180);
+        push_u64(&mut raw, 0);
+
+        // LC_SYMTAB
+        push_u32(&mut raw, LC_SYMTAB);
+        push_u32(&mut raw, 24);
+        push_u32(&mut raw, 0x1a0);
+        push_u32(&mut raw, 2);
+        push_u32(&mut raw, 0x1c0);
+        push_u32(&mut raw, 0x20);
+
+        raw.resize(0x180, 0);
+        raw.extend_from_slice(&[0x55, 0x48, 0x89, 0xe5, 0x31, 0xc0, 0x5d, 0xc3]);
+        raw.resize(0x1a0, 0);
+        // _main, defined and external in section 1
+        push_u32(&mut raw, 1);
+        raw.extend_from_slice(&[0x0f, 0x01, 0x00, 0x00]);
+        push_u64(&mut raw, 0x100000180);
+        // _puts, undefined and external
+        push_u32(&mut raw, 7);
+        raw.extend_from_slice(&[0x01, 0x00, 0x00, 0x01]);
+        push_u64(&mut raw, 0);
+        raw.extend_from_slice(b"\0_main\0_puts\0");
+        raw.resize(0x200, 0);
+        raw
+    }
+
+    #[test]
+    fn can_parse_segments_symbols_and_entry_point() {
+        let raw = get_test_binary();
+        let header = Header::parse_from_buffer(&raw).unwrap();
+        let commands = parse_load_commands(&raw, &header).unwrap();
+
+        assert_eq!(commands.segments.len(), 2);
+        assert_eq!(commands.segments[0].name, "__PAGEZERO");
+        let text = &commands.segments[1];
+        assert_eq!(text.initial_protection, VmProtection::READ | VmProtection::EXECUTE);
+        assert_eq!(text.sections.len(), 1);
+        assert_eq!(text.sections[0].name, "__text");
+        assert!(text.sections[0].has_instructions());
+        assert_eq!(text.sections[0].get_data(&raw)[0..4], [0x55, 0x48, 0x89, 0xe5]);
+        assert_eq!(commands.entry_point, Some(0x100000180));
+
+        assert_eq!(commands.symbols.len(), 2);
+        assert_eq!(commands.symbols[0].name, "_main");
+        assert!(!commands.symbols[0].is_undefined());
+        assert_eq!(commands.symbols[1].name, "_puts");
+        assert!(commands.symbols[1].is_undefined());
+    }
+
+    #[test]
+    fn can_reject_bad_command_size() {
+        let mut raw = get_test_binary();
+        raw[36..40].copy_from_slice(&[0x04, 0x00, 0x00, 0x00]);
+        let header = Header::parse_from_buffer(&raw).unwrap();
+        assert!(parse_load_commands(&raw, &header).is_err());
+    }
+}
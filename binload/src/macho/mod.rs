@@ -1,32 +1,76 @@
 pub mod header;
 pub mod fat;
+pub mod load_command;
+
+use crate::error::{Error, Result};
 
 pub struct MACHO {
     pub header: header::Header,
+    pub segments: Vec<load_command::Segment>,
+    pub symbols: Vec<load_command::Symbol>,
+    pub entry_point: Option<u64>,
+    pub data: Vec<u8>,
+}
+
+// A universal binary, with one Mach-O file per architecture
+pub struct FatMACHO {
+    pub architectures: Vec<fat::FatArchitecture>,
+    pub binaries: Vec<MACHO>,
 }
 
-pub fn load_macho_from_buffer<T: std::io::Read + std::io::Seek>(buffer: &mut T) -> Result<MACHO, &str> {
+pub fn is_macho(binary: &[u8]) -> bool {
+    matches!(
+        binary.get(0..4),
+        Some([0xfe, 0xed, 0xfa, 0xce])
+            | Some([0xce, 0xfa, 0xed, 0xfe])
+            | Some([0xfe, 0xed, 0xfa, 0xcf])
+            | Some([0xcf, 0xfa, 0xed, 0xfe])
+    )
+}
+
+pub fn load_macho_from_buffer<T: std::io::Read + std::io::Seek>(buffer: &mut T) -> Result<MACHO> {
     let mut data: Vec<u8> = vec![];
-    buffer.read_to_end(&mut data).unwrap();
+    buffer.read_to_end(&mut data)?;
 
-    let header = if fat::is_fat_binary(&data) {
+    if fat::is_fat_binary(&data) {
         // TODO: we need to decide what architecture we want to proceed with, for now we'll just use
         //       the first one
         let architectures = fat::FatArchitecture::parse_from_buffer(&data)?;
-        // TODO: probably want to handle the case where there are no architectures present
+        let architecture = match architectures.first() {
+            Some(v) => v,
+            None => return Err(Error::malformed(4, "fat file has no architectures")),
+        };
 
-        header::Header::parse_from_buffer(&architectures[0].get_binary(&data))
+        load_macho_from_slice(architecture.get_binary(&data)?)
     } else {
-        header::Header::parse_from_buffer(&data)
-    }?;
-
+        load_macho_from_slice(&data)
+    }
+}
 
-    // println!("Read struct: \n{:#?}", elf_ident);
-    // let elf_header = elf_header::ELFHeader::parse_from_buffer(&data, elf_ident);
-    println!("header32\n{:#?}", header);
+pub fn load_macho_from_slice(data: &[u8]) -> Result<MACHO> {
+    let header = header::Header::parse_from_buffer(data)?;
+    log::trace!("Mach-O header\n{:#?}", header);
+    let load_commands = load_command::parse_load_commands(data, &header)?;
 
     Ok(MACHO {
         header,
+        segments: load_commands.segments,
+        symbols: load_commands.symbols,
+        entry_point: load_commands.entry_point,
+        data: data.to_vec(),
+    })
+}
+
+pub fn load_fat_macho_from_slice(data: &[u8]) -> Result<FatMACHO> {
+    let architectures = fat::FatArchitecture::parse_from_buffer(data)?;
+    let mut binaries: Vec<MACHO> = Vec::with_capacity(architectures.len());
+    for architecture in &architectures {
+        binaries.push(load_macho_from_slice(architecture.get_binary(data)?)?);
+    }
+
+    Ok(FatMACHO {
+        architectures,
+        binaries,
     })
 }
 
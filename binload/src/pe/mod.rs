@@ -0,0 +1,196 @@
+use crate::error::{get_bytes, Error, Result};
+use std::convert::TryInto;
+
+// Only the headers needed to identify a PE file are parsed for now
+
+const DOS_MAGIC: [u8; 2] = [0x4d, 0x5a]; // MZ
+const PE_SIGNATURE: [u8; 4] = [0x50, 0x45, 0x00, 0x00]; // PE\0\0
+const E_LFANEW_OFFSET: u64 = 0x3c;
+const COFF_HEADER_SIZE: u64 = 20;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Eq, PartialEq)]
+pub enum Machine {
+    Unknown, // 0x0
+    I386,    // 0x14c
+    ARM,     // 0x1c0
+    ARMNT,   // 0x1c4
+    IA64,    // 0x200
+    AMD64,   // 0x8664
+    ARM64,   // 0xaa64
+    RISCV32, // 0x5032
+    RISCV64, // 0x5064
+    Other(u16),
+}
+impl Machine {
+    fn from_u16(value: u16) -> Machine {
+        match value {
+            0x0000 => Machine::Unknown,
+            0x014c => Machine::I386,
+            0x01c0 => Machine::ARM,
+            0x01c4 => Machine::ARMNT,
+            0x0200 => Machine::IA64,
+            0x8664 => Machine::AMD64,
+            0xaa64 => Machine::ARM64,
+            0x5032 => Machine::RISCV32,
+            0x5064 => Machine::RISCV64,
+            _ => Machine::Other(value),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum OptionalHeaderMagic {
+    PE32,     // 0x10b
+    PE32Plus, // 0x20b
+    ROM,      // 0x107
+}
+impl OptionalHeaderMagic {
+    fn from_u16(value: u16) -> Option<OptionalHeaderMagic> {
+        match value {
+            0x010b => Some(OptionalHeaderMagic::PE32),
+            0x020b => Some(OptionalHeaderMagic::PE32Plus),
+            0x0107 => Some(OptionalHeaderMagic::ROM),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CoffHeader {
+    pub machine: Machine,
+    pub number_of_sections: u16,
+    pub time_date_stamp: u32,
+    pub pointer_to_symbol_table: u32,
+    pub number_of_symbols: u32,
+    pub size_of_optional_header: u16,
+    pub characteristics: u16,
+}
+
+impl CoffHeader {
+    pub fn parse_from_buffer(binary: &[u8], offset: u64) -> Result<CoffHeader> {
+        let raw = get_bytes(binary, offset, COFF_HEADER_SIZE, || {
+            String::from("COFF file header")
+        })?;
+        let u16_at = |i: usize| u16::from_le_bytes(raw[i..i + 2].try_into().unwrap());
+        let u32_at = |i: usize| u32::from_le_bytes(raw[i..i + 4].try_into().unwrap());
+        Ok(CoffHeader {
+            machine: Machine::from_u16(u16_at(0)),
+            number_of_sections: u16_at(2),
+            time_date_stamp: u32_at(4),
+            pointer_to_symbol_table: u32_at(8),
+            number_of_symbols: u32_at(12),
+            size_of_optional_header: u16_at(16),
+            characteristics: u16_at(18),
+        })
+    }
+}
+
+pub struct PE {
+    pub pe_header_offset: u64,
+    pub coff_header: CoffHeader,
+    pub optional_header_magic: Option<OptionalHeaderMagic>,
+    pub data: Vec<u8>,
+}
+
+impl PE {
+    pub fn is_64_bit(&self) -> bool {
+        self.optional_header_magic == Some(OptionalHeaderMagic::PE32Plus)
+    }
+}
+
+// Finds the PE signature through e_lfanew in the DOS header
+fn get_pe_header_offset(binary: &[u8]) -> Option<u64> {
+    if binary.get(0..2)? != DOS_MAGIC {
+        return None;
+    }
+    let raw_lfanew = binary.get(E_LFANEW_OFFSET as usize..E_LFANEW_OFFSET as usize + 4)?;
+    let offset = u64::from(u32::from_le_bytes(raw_lfanew.try_into().unwrap()));
+    let signature = get_bytes(binary, offset, 4, String::new).ok()?;
+    if signature == PE_SIGNATURE {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+// A DOS stub on its own isn't enough, the PE signature has to be there too
+pub fn is_pe(binary: &[u8]) -> bool {
+    get_pe_header_offset(binary).is_some()
+}
+
+pub fn load_pe_from_vec(data: Vec<u8>) -> Result<PE> {
+    let pe_header_offset = match get_pe_header_offset(&data) {
+        Some(v) => v,
+        None => {
+            return Err(Error::BadMagic {
+                format: "PE",
+                magic: data.iter().take(4).cloned().collect(),
+            })
+        }
+    };
+    let coff_header = CoffHeader::parse_from_buffer(&data, pe_header_offset + 4)?;
+    let optional_header_magic = if coff_header.size_of_optional_header >= 2 {
+        let offset = pe_header_offset + 4 + COFF_HEADER_SIZE;
+        let raw = get_bytes(&data, offset, 2, || String::from("optional header magic"))?;
+        let magic = u16::from_le_bytes(raw.try_into().unwrap());
+        Some(OptionalHeaderMagic::from_u16(magic).ok_or_else(|| {
+            Error::invalid_value(offset, u64::from(magic), "optional header magic")
+        })?)
+    } else {
+        None
+    };
+    log::trace!("PE COFF header\n{:#?}", coff_header);
+
+    Ok(PE {
+        pe_header_offset,
+        coff_header,
+        optional_header_magic,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod pe_tests {
+    use super::*;
+
+    fn get_minimal_pe() -> Vec<u8> {
+        let mut raw = vec![0u8; 0x40];
+        raw[0..2].copy_from_slice(&DOS_MAGIC);
+        raw[0x3c] = 0x40;
+        raw.extend_from_slice(&PE_SIGNATURE);
+        raw.extend_from_slice(&[
+            0x64, 0x86, 0x03, 0x00, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0xf0, 0x00, 0x22, 0x00,
+        ]);
+        raw.extend_from_slice(&[0x0b, 0x02]);
+        raw
+    }
+
+    #[test]
+    fn can_parse_coff_header() {
+        let pe = load_pe_from_vec(get_minimal_pe()).unwrap();
+        assert_eq!(pe.pe_header_offset, 0x40);
+        assert_eq!(
+            pe.coff_header,
+            CoffHeader {
+                machine: Machine::AMD64,
+                number_of_sections: 3,
+                time_date_stamp: 0x12345678,
+                pointer_to_symbol_table: 0,
+                number_of_symbols: 0,
+                size_of_optional_header: 0xf0,
+                characteristics: 0x22,
+            }
+        );
+        assert!(pe.is_64_bit());
+    }
+
+    #[test]
+    fn can_reject_dos_stub_without_pe_header() {
+        let mut raw = get_minimal_pe();
+        raw[0x3c] = 0xff;
+        assert!(!is_pe(&raw));
+        assert!(load_pe_from_vec(raw).is_err());
+    }
+}
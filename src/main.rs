@@ -5,14 +5,24 @@ extern crate binload;
 extern crate capstone;
 
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 
 use capstone::*;
 
-use binload::elf::section::get_section_by_name;
-use binload::elf::ELF;
-use binload::load_from_file;
+use binload::binary::{AddressSpace, Binary};
+use binload::dwarf::frame::load_eh_frame_from_elf;
+use binload::dwarf::{load_dwarf_from_elf, Dwarf};
+use binload::elf::debuglink::DEFAULT_DEBUG_DIRECTORY;
+use binload::elf::plt::get_plt_entry_for_address;
+use binload::elf::{get_elf_print_string, ELF};
+use binload::{load_from_file, LoadedFile};
 
 fn get_instruction_string(cs: &Capstone, instruction: &Instruction) -> String {
+    get_instruction_string_with_target(instruction, instruction.op_str)
+}
+
+// Same as get_instruction_string but with the operands replaced, e.g. `call puts@plt`
+fn get_instruction_string_with_target(instruction: &Instruction, operands: &str) -> String {
     let mut byte_strings: Vec<String> = vec![];
     for byte in instruction.bytes {
         byte_strings.push(format!("{:02x}", byte));
@@ -22,20 +32,21 @@ fn get_instruction_string(cs: &Capstone, instruction: &Instruction) -> String {
         instruction.address,
         byte_strings.join(" "),
         instruction.mnemonic,
-        instruction.op_str,
+        operands,
     )
 }
 
 // This just dumps the disassembled instructions to stdout
-fn get_linear_disassembly(cs: &Capstone, elf: &ELF) {
-    let text_section = get_section_by_name(".text", &elf.section_headers)
+fn get_linear_disassembly(cs: &Capstone, binary: &Binary) {
+    let text_section = binary
+        .get_section_by_name(".text")
         .expect("there is no .text section in the executable");
-    let text_binary = text_section.get_data(&elf.data);
+    let text_binary = &text_section.bytes;
 
     // TODO REM START
     cs.set_option(OptionType::Detail, OptionValue::On);
     // TODO REM END
-    let instructions = cs.disassemble(text_binary, text_section.address, 0);
+    let instructions = cs.disassemble(text_binary, text_section.vm_address, 0);
     //        .disasm_all(text_binary, text_section.address)
     //        .expect("Failed to disassemeble");
 
@@ -54,20 +65,80 @@ fn get_linear_disassembly(cs: &Capstone, elf: &ELF) {
     }
 }
 
-fn get_basic_recurisive_disassembly(cs: &Capstone, elf: &ELF) {
-    let text_section = get_section_by_name(".text", &elf.section_headers)
+// Disassembles from the instruction that crashed in a core dump
+fn get_crash_disassembly(cs: &Capstone, elf: &ELF) {
+    let core = match elf.get_core() {
+        Some(v) => v,
+        None => return,
+    };
+    let pc = match core.get_crash_address() {
+        Some(v) => v,
+        None => {
+            eprintln!("the core has no registers for the crashing thread");
+            return;
+        }
+    };
+    let address_space = AddressSpace::from_elf(elf);
+    // Code is read from the mapped files when it wasn't dumped
+    let code = match core.read(&address_space, pc, 64) {
+        Some(v) => v,
+        None => {
+            eprintln!(
+                "the code at {:#x} isn't in the core or its mapped files",
+                pc
+            );
+            return;
+        }
+    };
+    for i in cs.disassemble(&code, pc, 0).iter() {
+        println!("{}", get_instruction_string(cs, &i));
+    }
+}
+
+// The PLT, unwind and debug info are still read from the ELF file itself
+fn get_basic_recurisive_disassembly(
+    cs: &Capstone,
+    binary: &Binary,
+    elf: &ELF,
+    dwarf: Option<&Dwarf>,
+) {
+    let text_section = binary
+        .get_section_by_name(".text")
         .expect("there is no .text section in the executable");
-    let text_bytes = text_section.get_data(&elf.data);
+    let address_space = AddressSpace::from_elf(elf);
     let mut queue: VecDeque<u64> = VecDeque::new();
 
-    if text_section.contains_address(elf.elf_header.e_entry) {
-        queue.push_back(elf.elf_header.e_entry);
+    if text_section.contains(binary.entry_point) {
+        queue.push_back(binary.entry_point);
     }
 
-    for address in get_symbols_in_text_section(&elf) {
+    for address in get_symbols_in_text_section(binary) {
         queue.push_back(address);
     }
 
+    // Every function with unwind info has an FDE, which survives stripping
+    if let Ok(eh_frame) = load_eh_frame_from_elf(elf) {
+        for address in eh_frame.get_function_starts() {
+            if text_section.contains(address) {
+                queue.push_back(address);
+            }
+        }
+    }
+
+    // Constructors and destructors run without being called from anywhere in the code
+    for address in elf.get_init_functions() {
+        if text_section.contains(address) {
+            queue.push_back(address);
+        }
+    }
+
+    // Debug info knows where every function starts, even the ones without symbols
+    if let Some(dwarf) = dwarf {
+        for address in get_subprograms_in_text_section(binary, dwarf) {
+            queue.push_back(address);
+        }
+    }
+
     // TODO: iterate over symbols and add those addresses if they are in the .text section too
 
     let mut seen: HashMap<u64, bool> = HashMap::new();
@@ -79,11 +150,21 @@ fn get_basic_recurisive_disassembly(cs: &Capstone, elf: &ELF) {
             continue;
         }
 
-        let mut pc = (address - text_section.address) as usize;
+        if let Some(subprogram) = dwarf.and_then(|d| d.find_subprogram(address)) {
+            if subprogram.get_low_pc() == Some(address) {
+                println!("<{}>:", subprogram.name);
+            }
+        }
+
+        let code = match address_space.get_file_data(address) {
+            Some(v) => v,
+            None => continue,
+        };
+        let mut pc: usize = 0;
         loop {
-            let insn = &cs.disassemble(&text_bytes[pc..], address + pc as u64, 1)[0];
+            let insn = &cs.disassemble(&code[pc..], address + pc as u64, 1)[0];
             pc += insn.size as usize;
-            if pc >= text_bytes.len() {
+            if pc >= code.len() {
                 break;
             }
             if insn.id == arch::x86::instruction::InstructionId::INVALID as u32 || insn.size == 0 {
@@ -91,24 +172,39 @@ fn get_basic_recurisive_disassembly(cs: &Capstone, elf: &ELF) {
             }
 
             seen.insert(insn.address, true);
-            println!("{}", get_instruction_string(cs, &insn));
+
+            // Show the source line when the instruction starts a new line table row
+            if let Some(location) = dwarf.and_then(|d| d.find_location_start(insn.address)) {
+                println!("; {}", location);
+            }
 
             if is_cs_cflow_ins(&insn) {
                 let target: u64 = get_cs_ins_immediate_target(cs, insn);
-                // FIXME: we jump to the .plt section for __libc_start_main but because this is not
-                // in the text section we just skip. If we followed, we'd get a simple jump
-                if target != 0
-                    && !seen.contains_key(&target)
-                    && text_section.contains_address(target)
-                {
-                    queue.push_back(target);
-                    println!(" -> new target {:#016x}", target);
+                // Calls through the PLT go to imported functions, we show the import name
+                // and treat them as leaf nodes rather than following the stub
+                if let Some(entry) = get_plt_entry_for_address(target, &elf.plt_entries) {
+                    println!(
+                        "{}",
+                        get_instruction_string_with_target(&insn, &entry.get_display_name())
+                    );
+                } else {
+                    println!("{}", get_instruction_string(cs, &insn));
+                    if target != 0
+                        && !seen.contains_key(&target)
+                        && address_space.is_executable(target)
+                    {
+                        queue.push_back(target);
+                        println!(" -> new target {:#016x}", target);
+                    }
                 }
                 if is_cs_unconditional_cflow_ins(&insn) {
                     break;
                 }
-            } else if insn.id == arch::x86::instruction::InstructionId::HLT as u32 {
-                break;
+            } else {
+                println!("{}", get_instruction_string(cs, &insn));
+                if insn.id == arch::x86::instruction::InstructionId::HLT as u32 {
+                    break;
+                }
             }
         }
         println!("-------------------");
@@ -177,22 +273,61 @@ fn get_cs_ins_immediate_target(cs: &Capstone, insn: &Instruction) -> u64 {
     0
 }
 
-fn get_symbols_in_text_section(elf: &ELF) -> Vec<u64> {
+fn get_symbols_in_text_section(binary: &Binary) -> Vec<u64> {
     let mut result: Vec<u64> = vec![];
-    let text_section = get_section_by_name(".text", &elf.section_headers)
+    let text_section = binary
+        .get_section_by_name(".text")
         .expect("there is no .text section in the executable");
 
-    for symbol in &elf.symbol_table {
-        if text_section.contains_address(symbol.address) {
+    for symbol in &binary.symbols {
+        if text_section.contains(symbol.address) {
             result.push(symbol.address);
         }
     }
     result
 }
 
+fn get_subprograms_in_text_section(binary: &Binary, dwarf: &Dwarf) -> Vec<u64> {
+    let mut result: Vec<u64> = vec![];
+    let text_section = binary
+        .get_section_by_name(".text")
+        .expect("there is no .text section in the executable");
+
+    for subprogram in &dwarf.subprograms {
+        for (start, _) in &subprogram.ranges {
+            if text_section.contains(*start) {
+                result.push(*start);
+            }
+        }
+    }
+    result
+}
+
 fn main() {
     // get raw binary
-    let elf = load_from_file("/Users/mdelaney/Development/binary-analysis/crackmes/IOLI/bin-linux/crackme0x00");
+    let path = "/Users/mdelaney/Development/binary-analysis/crackmes/IOLI/bin-linux/crackme0x00";
+    let mut elf = match load_from_file(path) {
+        Ok(LoadedFile::ELF(elf)) => elf,
+        Ok(other) => {
+            eprintln!(
+                "only ELF files can be disassembled, found {:?}",
+                other.get_format()
+            );
+            return;
+        }
+        Err(e) => {
+            eprintln!("failed to load file: {}", e);
+            return;
+        }
+    };
+    // stripped binaries may have their symbols and debug info in a separate file
+    if let Ok(debug_path) = elf.load_debug_file(
+        Some(Path::new(path)),
+        &[PathBuf::from(DEFAULT_DEBUG_DIRECTORY)],
+    ) {
+        println!("loaded debug file {}", debug_path.display());
+    }
+    println!("{}", get_elf_print_string(&elf));
 
     //    capstone::
     // init Capstone
@@ -204,7 +339,15 @@ fn main() {
     //        .build()
     //        .expect("Failed to create Capstone object");
 
+    if elf.is_core() {
+        get_crash_disassembly(&cs, &elf);
+        return;
+    }
+
     // do disassembly
-    //    get_linear_disassembly(&cs, &elf);
-    get_basic_recurisive_disassembly(&cs, &elf);
+    let binary = Binary::from_elf(&elf);
+    //    get_linear_disassembly(&cs, &binary);
+    // debug info is optional, without it we only have symbols to go on
+    let dwarf = load_dwarf_from_elf(&elf).ok();
+    get_basic_recurisive_disassembly(&cs, &binary, &elf, dwarf.as_ref());
 }